@@ -0,0 +1,51 @@
+// 音声解析のベンチマーク: cargo bench --bench audio
+// バイナリクレートなので、対象のモジュールをそのまま取り込む
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/resample.rs"]
+mod resample;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use resample::{Resampler, downmix, rms};
+use std::hint::black_box;
+
+// よくあるコールバックの長さ（フレーム数）
+const BLOCK_SIZES: [usize; 3] = [256, 480, 1024];
+
+// ステレオのインターリーブされた 440Hz のサイン波
+fn stereo_block(frames: usize, rate: u32) -> Vec<f32> {
+    (0..frames)
+        .flat_map(|i| {
+            let s = (i as f32 * 440.0 * std::f32::consts::TAU / rate as f32).sin() * 0.5;
+            [s, s]
+        })
+        .collect()
+}
+
+// コールバック1回分: ダウンミックス、解析レートへの変換、RMS
+fn level(c: &mut Criterion) {
+    let mut group = c.benchmark_group("level");
+    for frames in BLOCK_SIZES {
+        let block = stereo_block(frames, 44_100);
+        group.throughput(Throughput::Elements(frames as u64));
+
+        for (name, from) in [("48k", 48_000), ("44.1k_to_48k", 44_100)] {
+            group.bench_with_input(BenchmarkId::new(name, frames), &block, |b, block| {
+                let mut resampler = Resampler::new(from, 48_000);
+                let mut mono = Vec::new();
+                let mut analysis = Vec::new();
+                b.iter(|| {
+                    mono.clear();
+                    analysis.clear();
+                    downmix(block, 2, &mut mono);
+                    resampler.process(&mono, &mut analysis);
+                    black_box(rms(&analysis))
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, level);
+criterion_main!(benches);
@@ -0,0 +1,245 @@
+// 合成処理のベンチマーク: cargo bench --bench compose
+// バイナリクレートなので、対象のモジュールをそのまま取り込む
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/color.rs"]
+mod color;
+#[path = "../src/compose.rs"]
+mod compose;
+#[path = "../src/dirty.rs"]
+mod dirty;
+#[path = "../src/i18n.rs"]
+mod i18n;
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use std::hint::black_box;
+
+const SIZES: [(&str, usize, usize); 3] = [
+    ("720p", 1280, 720),
+    ("1080p", 1920, 1080),
+    ("1440p", 2560, 1440),
+];
+
+// 立ち絵に近いフレーム: 中央の楕円が不透明、縁がぼけていて外側は透明
+fn avatar_frame(width: usize, height: usize) -> Vec<u8> {
+    let mut frame = vec![0u8; width * height * 4];
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let (rx, ry) = (width as f32 * 0.3, height as f32 * 0.45);
+    for y in 0..height {
+        for x in 0..width {
+            let dx = (x as f32 - cx) / rx;
+            let dy = (y as f32 - cy) / ry;
+            let d = (dx * dx + dy * dy).sqrt();
+            // 縁の 5% をぼかす
+            let alpha = ((1.0 - d) / 0.05).clamp(0.0, 1.0);
+            let i = (y * width + x) * 4;
+            frame[i..i + 4].copy_from_slice(&[230, 180, 160, (alpha * 255.0) as u8]);
+        }
+    }
+    compose::premultiply(&mut frame);
+    frame
+}
+
+// 煙や光のエフェクトに近いフレーム: ほぼ全体が半透明で、完全に透明・不透明な所が無い
+fn haze_frame(width: usize, height: usize) -> Vec<u8> {
+    let mut frame = vec![0u8; width * height * 4];
+    for y in 0..height {
+        for x in 0..width {
+            let alpha = 20 + (x * 7 + y * 3) % 216;
+            let i = (y * width + x) * 4;
+            frame[i..i + 4].copy_from_slice(&[200, 220, 255, alpha as u8]);
+        }
+    }
+    compose::premultiply(&mut frame);
+    frame
+}
+
+fn background(width: usize, height: usize) -> Vec<u8> {
+    [40, 90, 60, 255].repeat(width * height)
+}
+
+type Frame = fn(usize, usize) -> Vec<u8>;
+
+fn blend(c: &mut Criterion) {
+    let mut group = c.benchmark_group("blend");
+    // 縁だけ半透明の立ち絵と、ほぼ全体が半透明のエフェクト
+    let frames: [(&str, Frame); 2] = [("avatar", avatar_frame), ("haze", haze_frame)];
+    for ((name, width, height), (frame, make)) in SIZES
+        .into_iter()
+        .flat_map(|size| frames.map(|frame| (size, frame)))
+    {
+        let src = make(width, height);
+        let dst = background(width, height);
+        let name = format!("{frame}/{name}");
+        let name = name.as_str();
+        group.throughput(Throughput::Elements((width * height) as u64));
+
+        group.bench_with_input(BenchmarkId::new("per_pixel", name), &src, |b, src| {
+            let mut out = dst.clone();
+            b.iter(|| {
+                for (d, s) in out.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+                    compose::blend(d, s);
+                }
+                black_box(&out);
+            });
+        });
+        group.bench_with_input(BenchmarkId::new("row", name), &src, |b, src| {
+            let mut out = dst.clone();
+            b.iter(|| {
+                for (d, s) in out
+                    .chunks_exact_mut(width * 4)
+                    .zip(src.chunks_exact(width * 4))
+                {
+                    compose::blend_row(d, s);
+                }
+                black_box(&out);
+            });
+        });
+    }
+    group.finish();
+}
+
+// 色味を掛ける（半透明の多いフレームで、1ピクセルずつと行ごとを比べる）
+fn tint(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tint");
+    let factors = [1.0, 0.9, 0.8];
+    for (name, width, height) in SIZES {
+        let mut frame = haze_frame(width, height);
+        group.throughput(Throughput::Elements((width * height) as u64));
+        group.bench_function(BenchmarkId::new("per_pixel", name), |b| {
+            b.iter(|| {
+                for pixel in frame.chunks_exact_mut(4) {
+                    for i in 0..3 {
+                        pixel[i] = color::to_srgb(color::to_linear(pixel[i]) * factors[i]);
+                    }
+                }
+                black_box(&frame);
+            });
+        });
+        let clip = dirty::Rect::full(width, height);
+        group.bench_function(BenchmarkId::new("row", name), |b| {
+            b.iter(|| {
+                compose::tint(&mut frame, width, clip, factors);
+                black_box(&frame);
+            });
+        });
+    }
+    group.finish();
+}
+
+fn draw_rect(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw_rect");
+    for (name, width, height) in SIZES {
+        let src = avatar_frame(width, height);
+        let dst = background(width, height);
+        let clip = dirty::Rect::full(width, height);
+        // 右下に縮小して重ねるスロット
+        let rect = [
+            (width / 2) as i32,
+            (height / 2) as i32,
+            (width / 2) as i32,
+            (height / 2) as i32,
+        ];
+        group.bench_with_input(BenchmarkId::new("slot", name), &src, |b, src| {
+            let mut out = dst.clone();
+            b.iter(|| {
+                compose::draw_rect(&mut out, src, width, height, rect, clip);
+                black_box(&out);
+            });
+        });
+    }
+    group.finish();
+}
+
+// メインのフレームをキャンバスに描く（そのまま、変形あり、変わった領域だけ）
+fn draw(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw");
+    for (name, width, height) in SIZES {
+        let src = avatar_frame(width, height);
+        let mut out = vec![0u8; width * height * 4];
+        group.throughput(Throughput::Elements((width * height) as u64));
+
+        group.bench_function(BenchmarkId::new("identity", name), |b| {
+            b.iter(|| {
+                compose::draw(&mut out, &src, width, height, compose::Transform::default());
+                black_box(&out);
+            });
+        });
+        let transform = compose::Transform {
+            offset: [12.0, -30.0],
+            scale: 1.1,
+            rotation: 0.0,
+            ..Default::default()
+        };
+        group.bench_function(BenchmarkId::new("transformed", name), |b| {
+            b.iter(|| {
+                compose::draw(&mut out, &src, width, height, transform);
+                black_box(&out);
+            });
+        });
+        // 口元だけが変わったときの領域
+        let mouth = dirty::Rect {
+            x: width * 2 / 5,
+            y: height / 2,
+            width: width / 5,
+            height: height / 8,
+        };
+        group.bench_function(BenchmarkId::new("region", name), |b| {
+            b.iter(|| {
+                compose::draw_region(
+                    &mut out,
+                    &src,
+                    width,
+                    height,
+                    compose::Transform::default(),
+                    mouth,
+                );
+                black_box(&out);
+            });
+        });
+    }
+    group.finish();
+}
+
+// 同じ合成を1スレッドと全スレッドで比べる（4K と、パーツを重ねた1フレーム分）
+fn threads(c: &mut Criterion) {
+    let mut group = c.benchmark_group("threads");
+    let single = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    for (name, width, height) in SIZES.into_iter().chain([("2160p", 3840, 2160)]) {
+        let src = avatar_frame(width, height);
+        let clip = dirty::Rect::full(width, height);
+        let mut out = vec![0u8; width * height * 4];
+        group.throughput(Throughput::Elements((width * height) as u64));
+        let frame = |out: &mut [u8]| {
+            compose::draw(out, &src, width, height, compose::Transform::default());
+            // 傾けたパーツと、縮めて半透明にしたパーツ
+            let tilted = compose::Transform {
+                rotation: 8.0,
+                ..Default::default()
+            };
+            compose::draw_over(out, &src, width, height, tilted, 1.0, clip);
+            let shrunk = compose::Transform {
+                offset: [0.0, -40.0],
+                scale: 0.6,
+                ..Default::default()
+            };
+            compose::draw_over(out, &src, width, height, shrunk, 0.7, clip);
+            compose::tint(out, width, clip, [1.0, 0.9, 0.8]);
+        };
+        group.bench_function(BenchmarkId::new("single", name), |b| {
+            b.iter(|| single.install(|| frame(&mut out)));
+            black_box(&out);
+        });
+        group.bench_function(BenchmarkId::new("multi", name), |b| {
+            b.iter(|| frame(&mut out));
+            black_box(&out);
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, draw, blend, tint, draw_rect, threads);
+criterion_main!(benches);
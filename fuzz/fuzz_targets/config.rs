@@ -0,0 +1,29 @@
+// 配布されたバンドルの darwin.toml を読み込む処理（不正な値でも落ちず、止まらないこと）
+// バイナリクレートなので、対象のモジュールをそのまま取り込む
+#![no_main]
+#![allow(dead_code)]
+
+#[path = "../../src/config.rs"]
+mod config;
+#[path = "../../src/i18n.rs"]
+mod i18n;
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(config) = toml::from_str::<config::Config>(text) else {
+        return;
+    };
+    // 保存するときと同じく書き戻せること
+    let _ = toml::to_string(&config);
+    for expression in config.expressions.values() {
+        for (_, frames) in expression.states() {
+            for frame in frames {
+                let _ = config.resolve(&frame.path);
+            }
+        }
+    }
+});
@@ -0,0 +1,15 @@
+// darwin import で読む PSD（壊れたファイルでも落ちず、巨大な確保もしないこと）
+// バイナリクレートなので、対象のモジュールをそのまま取り込む
+#![no_main]
+#![allow(dead_code)]
+
+#[path = "../../src/i18n.rs"]
+mod i18n;
+#[path = "../../src/psd.rs"]
+mod psd;
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = psd::Psd::parse(data);
+});
@@ -0,0 +1,71 @@
+// 部屋の雑音に合わせて閾値を動かす（直近の音量の下位パーセンタイルを雑音とみなす）
+use crate::config::AdaptiveConfig;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+// 音量を記録する間隔（ブロックごとに取ると窓が大きくなりすぎる）
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+// 閾値を動かし始めるまでに集める時間
+const WARM_UP: Duration = Duration::from_secs(3);
+
+/// Estimates the background noise as a low percentile of the recent levels and keeps the
+/// talking threshold a fixed margin above it.
+pub struct AdaptiveThreshold {
+    config: AdaptiveConfig,
+    window: Duration,
+    // 記録した時刻と音量 (RMS)
+    levels: VecDeque<(Instant, f32)>,
+    // 次の記録までの間で一番大きかった音量
+    pending: f32,
+    sorted: Vec<f32>,
+}
+
+impl AdaptiveThreshold {
+    pub fn new(config: &AdaptiveConfig) -> Self {
+        Self {
+            config: config.clone(),
+            window: Duration::try_from_secs_f32(config.window_secs.max(1.0))
+                .unwrap_or(Duration::MAX),
+            levels: VecDeque::new(),
+            pending: 0.0,
+            sorted: Vec::new(),
+        }
+    }
+
+    /// Feeds a level. Returns the new talking threshold whenever a level is recorded, once
+    /// enough of the window has been seen.
+    pub fn update(&mut self, level: f32, now: Instant) -> Option<f32> {
+        self.pending = self.pending.max(level);
+        if self
+            .levels
+            .back()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) < SAMPLE_INTERVAL)
+        {
+            return None;
+        }
+        self.levels
+            .push_back((now, std::mem::take(&mut self.pending)));
+        while self
+            .levels
+            .front()
+            .is_some_and(|(at, _)| now.saturating_duration_since(*at) > self.window)
+        {
+            self.levels.pop_front();
+        }
+        let (first, _) = *self.levels.front()?;
+        if now.saturating_duration_since(first) < WARM_UP {
+            return None;
+        }
+
+        self.sorted.clear();
+        self.sorted
+            .extend(self.levels.iter().map(|(_, level)| *level));
+        self.sorted.sort_by(f32::total_cmp);
+        let rank = self.config.percentile.clamp(0.0, 100.0) / 100.0;
+        let noise = self.sorted[((self.sorted.len() - 1) as f32 * rank).round() as usize];
+        let threshold = noise * 10f32.powf(self.config.margin_db / 20.0);
+        Some(threshold.clamp(self.config.min, self.config.max.max(self.config.min)))
+    }
+}
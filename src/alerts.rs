@@ -0,0 +1,251 @@
+// フォロー・サブスクの通知（Twitch EventSub または Streamlabs のソケット API）
+use crate::{
+    bus::{self, Bus},
+    config::{AlertSource, AlertsConfig},
+    t,
+};
+use anyhow::{Context, Result, bail};
+use serde_json::{Value, json};
+use std::{
+    net::TcpStream,
+    time::{Duration, Instant},
+};
+use tungstenite::{Message, WebSocket, stream::MaybeTlsStream};
+
+const TWITCH_EVENTSUB: &str = "wss://eventsub.wss.twitch.tv/ws";
+const TWITCH_API: &str = "https://api.twitch.tv/helix";
+const STREAMLABS_SOCKET: &str = "wss://sockets.streamlabs.com/socket.io/";
+
+// 切断されたときに接続し直すまでの時間
+const RETRY_INTERVAL: Duration = Duration::from_secs(10);
+
+// 受信待ちの合間に ping などを送る
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Follow,
+    Subscribe,
+    Raid,
+}
+
+/// Listens for alerts on a thread of its own and publishes the sequences to start to the bus.
+pub fn start(config: &AlertsConfig, bus: Bus) {
+    let config = config.clone();
+    std::thread::spawn(move || {
+        loop {
+            let result = match config.source {
+                AlertSource::Twitch => twitch(&config, &bus),
+                AlertSource::Streamlabs => streamlabs(&config, &bus),
+            };
+            if let Err(e) = result {
+                tracing::warn!("{}", t!("alerts.disconnected", e));
+            }
+            std::thread::sleep(RETRY_INTERVAL);
+        }
+    });
+}
+
+fn notify(config: &AlertsConfig, bus: &Bus, kind: Kind, user: &str) {
+    let (message, sequence) = match kind {
+        Kind::Follow => (t!("alerts.follow", user), &config.follow),
+        Kind::Subscribe => (t!("alerts.subscribe", user), &config.subscribe),
+        Kind::Raid => (t!("alerts.raid", user), &config.raid),
+    };
+    tracing::info!("{message}");
+    if let Some(sequence) = sequence {
+        bus.publish(bus::Event::Trigger(sequence.clone()));
+    }
+}
+
+fn connect(url: &str) -> Result<Socket> {
+    let (socket, _) =
+        tungstenite::connect(url).with_context(|| t!("alerts.connect_failed", url))?;
+    let stream = match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => stream,
+        MaybeTlsStream::Rustls(stream) => stream.get_ref(),
+        _ => bail!(t!("alerts.connect_failed", url)),
+    };
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    Ok(socket)
+}
+
+// 受信待ちがタイムアウトしたら None
+fn read(socket: &mut Socket) -> Result<Option<String>> {
+    match socket.read() {
+        Ok(Message::Text(text)) => Ok(Some(text)),
+        Ok(Message::Close(_)) => bail!(t!("alerts.closed")),
+        Ok(_) => Ok(None),
+        Err(tungstenite::Error::Io(e))
+            if matches!(
+                e.kind(),
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+            ) =>
+        {
+            Ok(None)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Twitch EventSub: 接続後に届くセッション ID で購読を登録する
+fn twitch(config: &AlertsConfig, bus: &Bus) -> Result<()> {
+    let client_id = config
+        .client_id
+        .as_deref()
+        .context(t!("alerts.no_client_id"))?;
+    let auth = format!("Bearer {}", config.token);
+    let user: Value = ureq::get(&format!("{TWITCH_API}/users"))
+        .set("Authorization", &auth)
+        .set("Client-Id", client_id)
+        .call()?
+        .into_json()?;
+    let broadcaster = user["data"][0]["id"]
+        .as_str()
+        .context(t!("alerts.no_user"))?
+        .to_string();
+
+    let mut url = TWITCH_EVENTSUB.to_string();
+    let mut keepalive = Duration::from_secs(10);
+    'connection: loop {
+        let mut socket = connect(&url)?;
+        let mut last_message = Instant::now();
+        loop {
+            let Some(text) = read(&mut socket)? else {
+                // キープアライブも届かなければ切れている
+                if last_message.elapsed() > keepalive * 2 {
+                    bail!(t!("alerts.timed_out"));
+                }
+                continue;
+            };
+            last_message = Instant::now();
+            let Ok(message) = serde_json::from_str::<Value>(&text) else {
+                continue;
+            };
+            let payload = &message["payload"];
+            match message["metadata"]["message_type"].as_str() {
+                Some("session_welcome") => {
+                    let session = payload["session"]["id"].as_str().unwrap_or_default();
+                    if let Some(seconds) = payload["session"]["keepalive_timeout_seconds"].as_u64()
+                    {
+                        keepalive = Duration::from_secs(seconds);
+                    }
+                    // 再接続先では購読が引き継がれる
+                    if url == TWITCH_EVENTSUB {
+                        subscribe(client_id, &auth, &broadcaster, session)?;
+                    }
+                    tracing::info!("{}", t!("alerts.connected", "Twitch"));
+                }
+                Some("session_reconnect") => {
+                    if let Some(reconnect) = payload["session"]["reconnect_url"].as_str() {
+                        url = reconnect.to_string();
+                        continue 'connection;
+                    }
+                }
+                Some("notification") => {
+                    let kind = match message["metadata"]["subscription_type"].as_str() {
+                        Some("channel.follow") => Kind::Follow,
+                        Some("channel.subscribe") => Kind::Subscribe,
+                        Some("channel.raid") => Kind::Raid,
+                        _ => continue,
+                    };
+                    let event = &payload["event"];
+                    let user = event["user_name"]
+                        .as_str()
+                        .or(event["from_broadcaster_user_name"].as_str())
+                        .unwrap_or_default();
+                    notify(config, bus, kind, user);
+                }
+                Some("revocation") => {
+                    let kind = payload["subscription"]["type"].as_str().unwrap_or_default();
+                    tracing::warn!("{}", t!("alerts.revoked", kind));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+fn subscribe(client_id: &str, auth: &str, broadcaster: &str, session: &str) -> Result<()> {
+    let subscriptions = [
+        (
+            "channel.follow",
+            "2",
+            json!({ "broadcaster_user_id": broadcaster, "moderator_user_id": broadcaster }),
+        ),
+        (
+            "channel.subscribe",
+            "1",
+            json!({ "broadcaster_user_id": broadcaster }),
+        ),
+        (
+            "channel.raid",
+            "1",
+            json!({ "to_broadcaster_user_id": broadcaster }),
+        ),
+    ];
+    for (kind, version, condition) in subscriptions {
+        let body = json!({
+            "type": kind,
+            "version": version,
+            "condition": condition,
+            "transport": { "method": "websocket", "session_id": session },
+        });
+        ureq::post(&format!("{TWITCH_API}/eventsub/subscriptions"))
+            .set("Authorization", auth)
+            .set("Client-Id", client_id)
+            .send_json(body)
+            .with_context(|| t!("alerts.subscribe_failed", kind))?;
+    }
+    Ok(())
+}
+
+// Streamlabs: Socket.IO (Engine.IO v3) の上でイベントが届く
+fn streamlabs(config: &AlertsConfig, bus: &Bus) -> Result<()> {
+    let url = format!(
+        "{STREAMLABS_SOCKET}?token={}&EIO=3&transport=websocket",
+        config.token
+    );
+    let mut socket = connect(&url)?;
+    let mut ping_interval = Duration::from_secs(25);
+    let mut last_ping = Instant::now();
+    loop {
+        // クライアントから ping を送らないと切断される
+        if last_ping.elapsed() >= ping_interval {
+            socket.send(Message::Text("2".to_string()))?;
+            last_ping = Instant::now();
+        }
+        let Some(text) = read(&mut socket)? else {
+            continue;
+        };
+        // 先頭の数字がパケットの種類（0: open, 40: 接続, 42: イベント）
+        if let Some(open) = text.strip_prefix('0') {
+            if let Some(ms) = serde_json::from_str::<Value>(open)
+                .ok()
+                .and_then(|v| v["pingInterval"].as_u64())
+            {
+                ping_interval = Duration::from_millis(ms);
+            }
+        } else if text == "40" {
+            tracing::info!("{}", t!("alerts.connected", "Streamlabs"));
+        } else if let Some(event) = text.strip_prefix("42") {
+            let Ok(event) = serde_json::from_str::<Value>(event) else {
+                continue;
+            };
+            let data = &event[1];
+            // YouTube の登録者は follow、メンバーは subscription として届く
+            let kind = match data["type"].as_str() {
+                Some("follow") => Kind::Follow,
+                Some("subscription" | "resub") => Kind::Subscribe,
+                Some("raid") => Kind::Raid,
+                _ => continue,
+            };
+            for message in data["message"].as_array().into_iter().flatten() {
+                let user = message["name"].as_str().unwrap_or_default();
+                notify(config, bus, kind, user);
+            }
+        }
+    }
+}
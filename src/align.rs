@@ -0,0 +1,228 @@
+use crate::{avatar::load_image, compose, config::Config, render::Renderer, t};
+use anyhow::{Context, Result, bail};
+use winit::{
+    dpi::LogicalSize,
+    event::{ElementState, Event, KeyEvent, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    window::WindowBuilder,
+};
+
+const CHECKER_SIZE: usize = 16;
+// Shift を押しながらの移動量
+const COARSE_STEP: i32 = 10;
+
+/// Frame reference written as `expression/state/index`, e.g. `default/talking/0`.
+#[derive(Debug, Clone)]
+pub struct FrameRef {
+    pub expression: String,
+    pub state: String,
+    pub index: usize,
+}
+
+impl std::str::FromStr for FrameRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split('/').collect();
+        let [expression, state, index] = parts[..] else {
+            return Err(t!("align.bad_ref", s));
+        };
+        if !matches!(state, "idle" | "whisper" | "talking" | "blink") {
+            return Err(t!("align.bad_ref", s));
+        }
+        Ok(Self {
+            expression: expression.to_string(),
+            state: state.to_string(),
+            index: index.parse().map_err(|_| t!("align.bad_ref", s))?,
+        })
+    }
+}
+
+impl std::fmt::Display for FrameRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}/{}", self.expression, self.state, self.index)
+    }
+}
+
+struct Layer {
+    frame: FrameRef,
+    image: Vec<u8>,
+    offset: [i32; 2],
+}
+
+/// Onion-skin editor: overlays two frames at half opacity and nudges their offsets.
+pub fn run(mut config: Config, first: Option<FrameRef>, second: Option<FrameRef>) -> Result<()> {
+    let default = |state: &str| FrameRef {
+        expression: config.default_expression.clone(),
+        state: state.to_string(),
+        index: 0,
+    };
+    let refs = [
+        first.unwrap_or_else(|| default("idle")),
+        second.unwrap_or_else(|| default("talking")),
+    ];
+
+    let width = config.canvas.width as usize;
+    let height = config.canvas.height as usize;
+
+    let mut layers = Vec::new();
+    for frame_ref in refs {
+        let frame = config
+            .frame(&frame_ref.expression, &frame_ref.state, frame_ref.index)
+            .with_context(|| t!("align.missing_frame", frame_ref))?;
+        let path = config.resolve(&frame.path);
+        // オフセットは合成時に適用するので、ここではずらさずに読み込む
+        let Some(image) = load_image(&path, width, height, [0, 0], frame.scale) else {
+            bail!(t!("image.not_found", path.display()));
+        };
+        layers.push(Layer {
+            frame: frame_ref,
+            image,
+            offset: frame.offset,
+        });
+    }
+
+    let event_loop = EventLoop::new()?;
+    let window = WindowBuilder::new()
+        .with_title(t!("align.title"))
+        .with_inner_size(LogicalSize::new(width as u32, height as u32))
+        .build(&event_loop)?;
+    let mut renderer = Renderer::new(&window, width as u32, height as u32)?;
+
+    let mut selected = 1;
+    let mut coarse = false;
+    let mut canvas = vec![0u8; width * height * 4];
+    window.set_title(&title(&layers, selected));
+
+    event_loop.run(move |event, elwt| {
+        elwt.set_control_flow(ControlFlow::Wait);
+
+        let Event::WindowEvent { event, .. } = event else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested => elwt.exit(),
+            WindowEvent::ModifiersChanged(modifiers) => coarse = modifiers.state().shift_key(),
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(keycode),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => {
+                let step = if coarse { COARSE_STEP } else { 1 };
+                let offset = &mut layers[selected].offset;
+                match keycode {
+                    KeyCode::Escape => elwt.exit(),
+                    KeyCode::Tab => selected = 1 - selected,
+                    KeyCode::ArrowLeft => offset[0] -= step,
+                    KeyCode::ArrowRight => offset[0] += step,
+                    KeyCode::ArrowUp => offset[1] -= step,
+                    KeyCode::ArrowDown => offset[1] += step,
+                    KeyCode::KeyR => *offset = [0, 0],
+                    KeyCode::KeyS => {
+                        for layer in &layers {
+                            if let Some(frame) = config.frame_mut(
+                                &layer.frame.expression,
+                                &layer.frame.state,
+                                layer.frame.index,
+                            ) {
+                                frame.offset = layer.offset;
+                            }
+                        }
+                        match config.save() {
+                            Ok(path) => tracing::info!("{}", t!("align.saved", path.display())),
+                            Err(e) => tracing::error!("{}", t!("align.save_failed", e)),
+                        }
+                    }
+                    _ => return,
+                }
+                window.set_title(&title(&layers, selected));
+                window.request_redraw();
+            }
+            WindowEvent::Resized(size) => {
+                if let Err(e) = renderer.resize_surface(size.width, size.height) {
+                    tracing::error!("{}", t!("render.resize_failed", e));
+                    elwt.exit();
+                }
+                window.request_redraw();
+            }
+            WindowEvent::RedrawRequested => {
+                draw_checker(&mut canvas, width, height);
+                for layer in &layers {
+                    blend_layer(&mut canvas, &layer.image, width, height, layer.offset, 0.5);
+                }
+                if let Some(frame) = renderer.frame_mut() {
+                    frame.copy_from_slice(&canvas);
+                }
+                if let Err(e) = renderer.render(&window) {
+                    tracing::error!("{}", t!("render.render_failed", e));
+                    elwt.exit();
+                }
+            }
+            _ => {}
+        }
+    })?;
+
+    Ok(())
+}
+
+// タイトルバーに選択中のレイヤーとオフセットを表示
+fn title(layers: &[Layer], selected: usize) -> String {
+    let parts: Vec<String> = layers
+        .iter()
+        .enumerate()
+        .map(|(i, layer)| {
+            format!(
+                "{}{} [{}, {}]",
+                if i == selected { "*" } else { "" },
+                layer.frame,
+                layer.offset[0],
+                layer.offset[1]
+            )
+        })
+        .collect();
+    format!("{} - {}", t!("align.title"), parts.join(" | "))
+}
+
+pub fn draw_checker(canvas: &mut [u8], width: usize, height: usize) {
+    for y in 0..height {
+        for x in 0..width {
+            let shade = if (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2) {
+                0x99
+            } else {
+                0x66
+            };
+            let idx = (y * width + x) * 4;
+            canvas[idx..idx + 4].copy_from_slice(&[shade, shade, shade, 0xff]);
+        }
+    }
+}
+
+fn blend_layer(
+    canvas: &mut [u8],
+    image: &[u8],
+    width: usize,
+    height: usize,
+    offset: [i32; 2],
+    opacity: f32,
+) {
+    for y in 0..height {
+        let sy = y as i64 - offset[1] as i64;
+        if sy < 0 || sy >= height as i64 {
+            continue;
+        }
+        for x in 0..width {
+            let sx = x as i64 - offset[0] as i64;
+            if sx < 0 || sx >= width as i64 {
+                continue;
+            }
+            let src = (sy as usize * width + sx as usize) * 4;
+            let dst = (y * width + x) * 4;
+            compose::blend_opacity(&mut canvas[dst..dst + 4], &image[src..src + 4], opacity);
+        }
+    }
+}
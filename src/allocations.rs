@@ -0,0 +1,188 @@
+// 描画と音声解析が、温まった後は1フレームごとにヒープを確保しないことの確認。
+// テスト用のアロケーターで、いまのスレッドが確保した回数を数える
+use crate::{
+    beat::BeatDetector,
+    compose::{self, ClipScratch, Transform},
+    config::{AudioConfig, AudioFeature, Curve, EmotionConfig, MappingConfig, MusicConfig},
+    dirty::{DirtyTracker, Rect},
+    emotion::EmotionDetector,
+    features::{Analyzer, Features},
+    mapping::{Mapper, Params},
+};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+    f32::consts::TAU,
+    time::{Duration, Instant},
+};
+
+// スレッドに分けない大きさにして、確保を数えるスレッドで合成させる
+const WIDTH: usize = 128;
+const HEIGHT: usize = 96;
+const RATE: u32 = 16_000;
+// 入力コールバック1回分のサンプル数
+const BLOCK: usize = 160;
+
+struct Counting;
+
+thread_local! {
+    static COUNT: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        COUNT.with(|count| count.set(count.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        COUNT.with(|count| count.set(count.get() + 1));
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+// `f` の中で確保した回数
+fn allocations(f: impl FnOnce()) -> usize {
+    let before = COUNT.with(Cell::get);
+    f();
+    COUNT.with(Cell::get) - before
+}
+
+// 乗算済みの単色の画像
+fn solid(color: [u8; 4]) -> Vec<u8> {
+    color.repeat(WIDTH * HEIGHT)
+}
+
+#[test]
+fn rendering_a_frame_does_not_allocate() {
+    let frame = solid([200, 120, 80, 255]);
+    let layer = solid([40, 40, 40, 128]);
+    let clipped = solid([0, 60, 0, 255]);
+    let slot = solid([255; 4]);
+    let names = ["hair", "hat"];
+    let mut mapper = Mapper::new(&[MappingConfig {
+        feature: AudioFeature::Level,
+        parameter: "layer.hat.rotation".into(),
+        input: [0.0, 1.0],
+        output: [0.0, 30.0],
+        curve: Curve::Linear,
+        smoothing_ms: 100,
+        color: None,
+    }]);
+    let mut dirty = DirtyTracker::new(WIDTH, HEIGHT);
+    let mut scratch = ClipScratch::default();
+    let mut output = vec![0u8; WIDTH * HEIGHT * 4];
+    let (mut params, mut layer_params) = (Params::default(), Vec::new());
+    let features = Features::default();
+    let start = Instant::now();
+
+    let mut render = |i: u32| {
+        let now = start + Duration::from_millis(16 * i as u64);
+        mapper.evaluate(
+            &features,
+            names.iter().copied(),
+            now,
+            &mut params,
+            &mut layer_params,
+        );
+        let transform = Transform {
+            scale: 0.9,
+            ..Default::default()
+        };
+        let slots = [(Some(slot.as_slice()), [4, 4, 32, 24])];
+        let layers = layer_params.iter().map(|p| {
+            (
+                Some(Rect::full(WIDTH, HEIGHT)),
+                p.apply(transform),
+                p.visibility(),
+            )
+        });
+        let region = dirty
+            .update(None, transform, true, layers, slots.iter().copied())
+            .unwrap_or(Rect::full(WIDTH, HEIGHT));
+        compose::draw_region(&mut output, &frame, WIDTH, HEIGHT, transform, region);
+        compose::draw_over(
+            &mut output,
+            &layer,
+            WIDTH,
+            HEIGHT,
+            layer_params[0].apply(transform),
+            layer_params[0].visibility(),
+            region,
+        );
+        let base = (
+            layer.as_slice(),
+            layer_params[1].apply(transform),
+            layer_params[1].visibility(),
+        );
+        let children = [(clipped.as_slice(), transform, 0.5)];
+        compose::draw_clipped(
+            &mut output,
+            base,
+            children,
+            WIDTH,
+            HEIGHT,
+            region,
+            &mut scratch,
+        );
+        compose::tint(&mut output, WIDTH, region, [1.0, 0.8, 0.8]);
+        for (frame, rect) in slots.into_iter() {
+            if let Some(frame) = frame {
+                compose::draw_rect(&mut output, frame, WIDTH, HEIGHT, rect, region);
+            }
+        }
+    };
+
+    // 最初のフレームで作業用のバッファが揃う
+    render(0);
+    render(1);
+    let count = allocations(|| (2..30).for_each(&mut render));
+    assert_eq!(count, 0, "{count} allocations while rendering");
+}
+
+#[test]
+fn analysing_audio_does_not_allocate() {
+    let audio = AudioConfig::default();
+    let mut analyzer = Analyzer::new(RATE, audio.threshold);
+    let mut beat = BeatDetector::new(&MusicConfig::default(), RATE);
+    let mut emotion = EmotionDetector::new(
+        &EmotionConfig::default(),
+        &AudioConfig {
+            analysis_rate: RATE,
+            ..audio
+        },
+    );
+    let mut features = Features::default();
+    let mut samples = [0.0f32; BLOCK];
+    let start = Instant::now();
+
+    // 220Hz の声に 120BPM のクリックを重ねる
+    let mut analyse = |block: usize| {
+        for (i, sample) in samples.iter_mut().enumerate() {
+            let n = block * BLOCK + i;
+            let t = n as f32 / RATE as f32;
+            let click = if n % (RATE as usize / 2) < 200 {
+                0.8
+            } else {
+                0.0
+            };
+            *sample = (t * 220.0 * TAU).sin() * 0.3 + click;
+        }
+        let now = start + Duration::from_millis(10 * block as u64);
+        analyzer.update(&samples, &mut features);
+        beat.update(&samples);
+        emotion.update(&samples, now);
+    };
+
+    // テンポの推定と感情の判定が一度は走るまで温める
+    (0..1000).for_each(&mut analyse);
+    let count = allocations(|| (1000..2000).for_each(&mut analyse));
+    assert_eq!(count, 0, "{count} allocations while analysing");
+}
@@ -0,0 +1,110 @@
+// 大きな状態の変化（ミュート、プロファイルの切り替え、入力デバイスが無くなった等）を OS の
+// 通知か読み上げで知らせる。ウィンドウを見ずに OBS の裏で動かしていても分かるように
+use crate::{config::AnnounceMethod, t};
+use std::{process::Command, sync::mpsc};
+
+/// Hands announcements to a thread of their own that runs the OS commands one at a time,
+/// so the drawing never waits and speech doesn't talk over itself.
+pub struct Announcer {
+    sender: mpsc::Sender<String>,
+}
+
+impl Announcer {
+    pub fn new(method: AnnounceMethod) -> Self {
+        let (sender, receiver) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            // 使えないコマンドは何度も警告しない
+            let mut warned = false;
+            for message in receiver {
+                let mut command = command(method, &message);
+                let error = match command.status() {
+                    Ok(status) if status.success() => continue,
+                    Ok(status) => status.to_string(),
+                    Err(e) => e.to_string(),
+                };
+                if !warned {
+                    let program = command.get_program().to_string_lossy().into_owned();
+                    tracing::warn!("{}", t!("announce.failed", program, error));
+                    warned = true;
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    pub fn say(&self, message: &str) {
+        tracing::debug!("{}", t!("announce.said", message));
+        let _ = self.sender.send(message.to_string());
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn command(method: AnnounceMethod, message: &str) -> Command {
+    // 読み上げは Speech Dispatcher（画面読み上げソフトと同じ声になる）
+    let mut command = Command::new(match method {
+        AnnounceMethod::Notification => "notify-send",
+        AnnounceMethod::Speech => "spd-say",
+    });
+    match method {
+        AnnounceMethod::Notification => command.args(["--app-name=Darwin", "Darwin", message]),
+        AnnounceMethod::Speech => command.args(["--wait", message]),
+    };
+    command
+}
+
+#[cfg(target_os = "macos")]
+fn command(method: AnnounceMethod, message: &str) -> Command {
+    let mut command = Command::new(match method {
+        AnnounceMethod::Notification => "osascript",
+        AnnounceMethod::Speech => "say",
+    });
+    match method {
+        // 文字列は AppleScript に埋め込まず、引数で渡す
+        AnnounceMethod::Notification => command.args([
+            "-e",
+            "on run argv",
+            "-e",
+            "display notification (item 1 of argv) with title \"Darwin\"",
+            "-e",
+            "end run",
+            message,
+        ]),
+        AnnounceMethod::Speech => command.arg(message),
+    };
+    command
+}
+
+#[cfg(windows)]
+fn command(method: AnnounceMethod, message: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    // コンソールのウィンドウを出さない
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    // 文字列はスクリプトに埋め込まず、環境変数で渡す
+    let script = match method {
+        AnnounceMethod::Notification => {
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $icon = New-Object System.Windows.Forms.NotifyIcon; \
+             $icon.Icon = [System.Drawing.SystemIcons]::Information; \
+             $icon.Visible = $true; \
+             $icon.ShowBalloonTip(5000, 'Darwin', $env:DARWIN_ANNOUNCE, 'Info'); \
+             Start-Sleep -Seconds 6; \
+             $icon.Dispose()"
+        }
+        AnnounceMethod::Speech => {
+            "Add-Type -AssemblyName System.Speech; \
+             (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:DARWIN_ANNOUNCE)"
+        }
+    };
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .env("DARWIN_ANNOUNCE", message)
+        .creation_flags(CREATE_NO_WINDOW);
+    command
+}
+
+#[cfg(not(any(unix, windows)))]
+fn command(_method: AnnounceMethod, _message: &str) -> Command {
+    Command::new("false")
+}
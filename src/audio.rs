@@ -0,0 +1,381 @@
+// 音声のキャプチャと解析（コールバックはサンプルを渡すだけで、解析は専用のスレッドで行う）
+use crate::{
+    adaptive, beat,
+    bus::Bus,
+    captions,
+    config::AudioConfig,
+    echo, emotion, features, filter,
+    health::{AudioWarning, HealthMonitor},
+    host, monitor, permission,
+    reactivity::{ReactivityStateMachine, Thresholds},
+    reference, replay,
+    resample::{Resampler, downmix, rms},
+    ring,
+    state::{self, RenderState},
+    t, test_signal, timings, voice,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+// 解析スレッドが新しいサンプルを待つ間隔（待ち受けでコールバック側にロックを取らせないため、ポーリングする）
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+// デバイスが無い・外れたときに探し直す間隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+// 解析するサンプルの出どころ
+enum Source {
+    // ストリームは止めないように持っておく
+    Device {
+        _stream: cpal::Stream,
+        consumer: ring::Consumer,
+    },
+    Signal(test_signal::Generator),
+}
+
+pub fn find_loopback_device() -> Option<cpal::Device> {
+    let host = host::host();
+
+    // 利用可能な入力デバイスを表示
+    tracing::info!("{}", t!("audio.devices"));
+    if let Ok(devices) = host.input_devices() {
+        for (i, device) in devices.enumerate() {
+            if let Ok(name) = device.name() {
+                tracing::info!(index = i, device = %name, "  {}: {}", i, name);
+            }
+        }
+    }
+
+    // BlackHole, Soundflower, Loopback, eqMacなどを探す
+    if let Ok(devices) = host.input_devices() {
+        for device in devices {
+            if let Ok(name) = device.name() {
+                let name_lower = name.to_lowercase();
+                if name_lower.contains("blackhole")
+                    || name_lower.contains("soundflower")
+                    || name_lower.contains("loopback")
+                    || name_lower.contains("eqmac")
+                    || name_lower.contains("multi-output")
+                {
+                    tracing::info!(device = %name, "{}", t!("audio.loopback_found", name));
+                    return Some(device);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+pub fn find_input_device(host: &cpal::Host, name: &str) -> Option<cpal::Device> {
+    let needle = name.to_lowercase();
+    host.input_devices().ok()?.find(|device| {
+        device
+            .name()
+            .is_ok_and(|n| n.to_lowercase().contains(&needle))
+    })
+}
+
+/// Settings the capture threads pick up while they run (`darwin ctl filter`, tuning mode).
+pub struct Controls {
+    pub cutoffs: filter::Cutoffs,
+    pub thresholds: Thresholds,
+}
+
+impl Controls {
+    pub fn new(audio: &AudioConfig) -> Self {
+        Self {
+            cutoffs: filter::Cutoffs::new(audio),
+            thresholds: Thresholds::new(audio),
+        }
+    }
+}
+
+/// Analysis that only the main capture runs, on the mono samples at the analysis rate.
+#[derive(Default)]
+pub struct Analysis {
+    pub beat: Option<beat::BeatDetector>,
+    pub captions: Option<captions::Feed>,
+    pub emotion: Option<emotion::EmotionDetector>,
+    pub features: Option<features::Analyzer>,
+}
+
+impl Analysis {
+    fn process(&mut self, samples: &[f32], now: Instant, state: &mut RenderState) {
+        if let Some(detector) = &mut self.beat {
+            state.beats = detector.update(samples);
+        }
+        if let Some(feed) = &self.captions {
+            feed.push(samples);
+        }
+        if let Some(detector) = &mut self.emotion {
+            state.emotion = detector.update(samples, now);
+        }
+        if let Some(analyzer) = &mut self.features {
+            analyzer.update(samples, &mut state.features);
+        }
+    }
+}
+
+/// Runs [`capture`] on the calling thread for the rest of the app's life. When no device
+/// can be opened, or the stream stops, the state goes back to idle and the device is looked
+/// up again every few seconds; the bus (main capture only) is told while there is none.
+/// On macOS, microphone access is requested first and checked again on every retry.
+pub fn run(
+    mut writer: state::Writer,
+    input: Option<String>,
+    audio: AudioConfig,
+    bus: Option<Bus>,
+    recorder: Option<replay::Recorder>,
+    controls: Arc<Controls>,
+    mut analysis_extra: Analysis,
+) {
+    let mut health_monitor = HealthMonitor::new(bus);
+    loop {
+        // 許可が無いまま開くと無音が届き続けるだけなので、開く前に確かめる
+        let (reason, e) = if audio.test_signal.is_some() || permission::microphone() {
+            let Err(e) = capture(
+                &mut writer,
+                input.as_deref(),
+                &audio,
+                &mut health_monitor,
+                recorder.as_ref(),
+                &controls,
+                &mut analysis_extra,
+            );
+            (AudioWarning::NoDevice, e)
+        } else {
+            (
+                AudioWarning::PermissionDenied,
+                anyhow!(t!("permission.denied")),
+            )
+        };
+        // 同じ理由で失敗し続けるときは、最初の一度だけ知らせる
+        if health_monitor.set_unavailable(Some(reason)) {
+            tracing::error!("{}", t!("audio.capture_error", e));
+            tracing::info!("{}", t!("audio.retrying", RETRY_INTERVAL.as_secs()));
+            writer.publish(RenderState::default());
+        } else {
+            tracing::debug!("{}", t!("audio.capture_error", e));
+        }
+        std::thread::sleep(RETRY_INTERVAL);
+    }
+}
+
+/// Captures `input` (or the loopback/default device) and publishes its state to `writer`.
+/// Only the main capture (`input` is `None`) feeds the monitor output, and it analyses the
+/// configured test signal instead of a device when there is one.
+///
+/// The cpal callback only queues the samples; everything else runs on the calling thread.
+/// Only returns when the device cannot be opened or the stream stops.
+fn capture(
+    writer: &mut state::Writer,
+    input: Option<&str>,
+    audio: &AudioConfig,
+    health_monitor: &mut HealthMonitor,
+    recorder: Option<&replay::Recorder>,
+    controls: &Controls,
+    analysis_extra: &mut Analysis,
+) -> Result<std::convert::Infallible> {
+    // テスト信号は解析のレートのモノラルで作るので、デバイスは開かない
+    let signal = audio.test_signal.filter(|_| input.is_none());
+    let device = match signal {
+        Some(_) => None,
+        None => timings::measure(timings::Phase::Devices, || -> Result<_> {
+            let host = host::host();
+
+            // ループバックデバイスを探すか、デフォルトの入力デバイスを使用
+            let device = match &input {
+                Some(name) => find_input_device(&host, name),
+                None => find_loopback_device().or_else(|| host.default_input_device()),
+            }
+            .context(t!("audio.no_device"))?;
+
+            let config = device.default_input_config()?;
+            tracing::debug!("{}", t!("audio.config", format!("{:?}", config)));
+            Ok(Some((device, config)))
+        })?,
+    };
+    let (sample_rate, channels) = match &device {
+        Some((_, config)) => (config.sample_rate().0, config.channels() as usize),
+        None => (audio.analysis_rate, 1),
+    };
+
+    let mut reactivity = ReactivityStateMachine::new(audio);
+
+    // 解析はデバイスのサンプルレートに関係なく一定のレートで行う
+    let mut resampler = Resampler::new(sample_rate, audio.analysis_rate);
+    if !resampler.is_passthrough() {
+        tracing::info!(
+            "{}",
+            t!("audio.resampling", sample_rate, audio.analysis_rate)
+        );
+    }
+    let mut state = RenderState::default();
+    // 解析したサンプル数（音声の時間軸）
+    let mut analysed: u64 = 0;
+    let mut data = Vec::new();
+    let mut mono = Vec::new();
+    let mut analysis = Vec::new();
+    let mut filters = filter::Filters::new(audio.analysis_rate);
+
+    // 解析している音声を聞けるように出力デバイスへ流す
+    let mut passthrough = None;
+    let mut monitor_tap = None;
+    if audio.monitor.enabled && input.is_none() {
+        match monitor::start(&audio.monitor, sample_rate, channels) {
+            Ok((stream, tap)) => {
+                passthrough = Some(stream);
+                monitor_tap = Some(tap);
+            }
+            Err(e) => tracing::warn!("{}", t!("monitor.failed", e)),
+        }
+    }
+
+    // マイクと照らし合わせて、混ざった音声のうち自分の声だけで口を動かす
+    let mut reference_stream = None;
+    let mut voice_gate = None;
+    let analyses_device = input.is_none() && signal.is_none();
+    // 閾値を動かすのはメインの入力だけ（スロットは同じ閾値を使う）
+    let mut adaptive = audio
+        .adaptive
+        .as_ref()
+        .filter(|_| analyses_device)
+        .map(adaptive::AdaptiveThreshold::new);
+    if let Some(voice) = audio.voice_reference.as_ref().filter(|_| analyses_device) {
+        match reference::start(voice.input.as_deref(), false, audio.analysis_rate) {
+            Ok((stream, reference)) => {
+                reference_stream = Some(stream);
+                voice_gate = Some(voice::VoiceGate::new(voice, reference, audio.analysis_rate));
+            }
+            Err(e) => tracing::warn!("{}", t!("reference.failed", e)),
+        }
+    }
+    // スピーカーから回り込んだ音を消してから音量を見る
+    let mut echo_stream = None;
+    let mut echo_canceller = None;
+    let mut cleaned = Vec::new();
+    if let Some(echo) = audio.echo_cancellation.as_ref().filter(|_| analyses_device) {
+        match reference::start(echo.reference.as_deref(), true, echo::RATE) {
+            Ok((stream, reference)) => {
+                echo_stream = Some(stream);
+                echo_canceller = Some(echo::EchoCanceller::new(
+                    echo,
+                    reference,
+                    audio.analysis_rate,
+                ));
+            }
+            Err(e) => tracing::warn!("{}", t!("reference.failed", e)),
+        }
+    }
+
+    let mut source = match (device, signal) {
+        (Some((device, config)), _) => {
+            let (producer, consumer) = ring::channel(sample_rate as usize * channels);
+            let stream = device.build_input_stream(
+                &config.into(),
+                move |data: &[f32], _: &cpal::InputCallbackInfo| producer.push(data),
+                |err| tracing::error!("{}", t!("audio.stream_error", err)),
+                None,
+            )?;
+            stream.play()?;
+            tracing::info!("{}", t!("audio.started"));
+            Source::Device {
+                _stream: stream,
+                consumer,
+            }
+        }
+        (None, signal) => {
+            let signal = signal.unwrap_or_default();
+            tracing::info!("{}", t!("test_signal.started", format!("{signal:?}")));
+            Source::Signal(test_signal::Generator::new(signal, audio, Instant::now()))
+        }
+    };
+    // デバイスが見つからなかった後なら、その表示を消す
+    health_monitor.set_unavailable(None);
+
+    // ストリームを維持したまま、届いたサンプルを解析する
+    let _passthrough = passthrough;
+    let _reference_stream = reference_stream;
+    let _echo_stream = echo_stream;
+    loop {
+        data.clear();
+        match &mut source {
+            Source::Device { consumer, .. } => {
+                if !consumer.read(&mut data) {
+                    bail!(t!("audio.stream_closed"));
+                }
+                let dropped = consumer.take_dropped();
+                if dropped > 0 {
+                    tracing::warn!("{}", t!("audio.overrun", dropped));
+                }
+            }
+            Source::Signal(generator) => generator.read(Instant::now(), &mut data),
+        }
+        if data.is_empty() {
+            std::thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+        let now = Instant::now();
+        health_monitor.update(&data, now);
+        if let Some(tap) = &mut monitor_tap {
+            tap.push(&data);
+        }
+
+        mono.clear();
+        analysis.clear();
+        downmix(&data, channels, &mut mono);
+        resampler.process(&mono, &mut analysis);
+        if analysis.is_empty() {
+            continue;
+        }
+        analysed += analysis.len() as u64;
+        state.position =
+            Duration::try_from_secs_f64(analysed as f64 / audio.analysis_rate as f64).ok();
+        filters.process(&controls.cutoffs, &mut analysis);
+        analysis_extra.process(&analysis, now, &mut state);
+
+        // RMS音量を計算（自分の声でなければ無音として扱う）
+        let mut rms = match &mut echo_canceller {
+            Some(canceller) => {
+                cleaned.clear();
+                canceller.process(&analysis, &mut cleaned);
+                rms(&cleaned)
+            }
+            None => rms(&analysis),
+        };
+        if let Some(gate) = &mut voice_gate
+            && !gate.update(&analysis)
+        {
+            rms = 0.0;
+        }
+
+        // 音量で待機・ささやき・発話を切り替える
+        state.level = rms;
+        if let Some(threshold) = adaptive.as_mut().and_then(|a| a.update(rms, now)) {
+            let (previous, whisper, hold_ms) = controls.thresholds.get();
+            let ratio = threshold / previous.max(f32::EPSILON);
+            controls
+                .thresholds
+                .set(threshold, whisper.map(|w| w * ratio), hold_ms);
+        }
+        reactivity.retune(&controls.thresholds);
+        if let Some(transition) = reactivity.update(rms, now) {
+            state.mouth = transition.to;
+            if let Some(recorder) = recorder {
+                recorder.record_mouth(transition.to, rms);
+            }
+            tracing::debug!(
+                from = transition.from.state(),
+                to = transition.to.state(),
+                rms,
+                "{}",
+                t!("state.transition")
+            );
+        }
+        writer.publish(state);
+    }
+}
@@ -0,0 +1,172 @@
+// ログイン時の自動起動（XDG autostart / LaunchAgent / レジストリの Run キー）
+use crate::{cli::AutostartAction, paths, t};
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+/// Registers, removes or reports launch-at-login. The registered command starts Darwin
+/// minimized with the given config.
+pub fn run(action: AutostartAction, config: Option<PathBuf>) -> Result<()> {
+    match action {
+        AutostartAction::Enable => {
+            let exe = std::env::current_exe()?;
+            let mut args = vec!["--minimized".to_string()];
+            if paths::portable().is_some() {
+                args.push("--portable".to_string());
+            }
+            if let Some(config) = config {
+                // 起動時のカレントディレクトリは決まっていないので絶対パスにする
+                let config = std::path::absolute(&config)?;
+                args.push("--config".to_string());
+                args.push(config.to_string_lossy().into_owned());
+            }
+            let location = enable(&exe, &args)?;
+            println!("{}", t!("autostart.enabled", location));
+        }
+        AutostartAction::Disable => {
+            disable()?;
+            println!("{}", t!("autostart.disabled"));
+        }
+        AutostartAction::Status => match status()? {
+            Some(location) => println!("{}", t!("autostart.enabled", location)),
+            None => println!("{}", t!("autostart.disabled")),
+        },
+    }
+    Ok(())
+}
+
+fn home() -> Result<PathBuf> {
+    std::env::var_os("HOME")
+        .map(PathBuf::from)
+        .context(t!("autostart.no_home"))
+}
+
+fn write(path: &Path, contents: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents).with_context(|| t!("autostart.write_failed", path.display()))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn entry_path() -> Result<PathBuf> {
+    let config = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => home()?.join(".config"),
+    };
+    Ok(config.join("autostart").join("darwin.desktop"))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn enable(exe: &Path, args: &[String]) -> Result<String> {
+    let path = entry_path()?;
+    let exec = std::iter::once(exe.to_string_lossy().into_owned())
+        .chain(args.iter().cloned())
+        .map(|a| format!("\"{}\"", a.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect::<Vec<_>>()
+        .join(" ");
+    write(
+        &path,
+        &format!(
+            "[Desktop Entry]\nType=Application\nName=Darwin\nExec={exec}\nX-GNOME-Autostart-enabled=true\n"
+        ),
+    )?;
+    Ok(path.display().to_string())
+}
+
+#[cfg(target_os = "macos")]
+const LABEL: &str = "com.potistudio.darwin";
+
+#[cfg(target_os = "macos")]
+fn entry_path() -> Result<PathBuf> {
+    Ok(home()?
+        .join("Library/LaunchAgents")
+        .join(format!("{LABEL}.plist")))
+}
+
+#[cfg(target_os = "macos")]
+fn enable(exe: &Path, args: &[String]) -> Result<String> {
+    let path = entry_path()?;
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    };
+    let arguments: String = std::iter::once(exe.to_string_lossy().into_owned())
+        .chain(args.iter().cloned())
+        .map(|a| format!("        <string>{}</string>\n", escape(&a)))
+        .collect();
+    write(
+        &path,
+        &format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{LABEL}</string>
+    <key>ProgramArguments</key>
+    <array>
+{arguments}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+</dict>
+</plist>
+"#
+        ),
+    )?;
+    Ok(path.display().to_string())
+}
+
+#[cfg(unix)]
+fn disable() -> Result<()> {
+    match std::fs::remove_file(entry_path()?) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn status() -> Result<Option<String>> {
+    let path = entry_path()?;
+    Ok(path.exists().then(|| path.display().to_string()))
+}
+
+#[cfg(windows)]
+const RUN_KEY: &str = r"HKCU\Software\Microsoft\Windows\CurrentVersion\Run";
+
+#[cfg(windows)]
+fn reg(args: &[&str]) -> Result<bool> {
+    let status = std::process::Command::new("reg")
+        .args(args)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()?;
+    Ok(status.success())
+}
+
+#[cfg(windows)]
+fn enable(exe: &Path, args: &[String]) -> Result<String> {
+    let command = std::iter::once(exe.to_string_lossy().into_owned())
+        .chain(args.iter().cloned())
+        .map(|a| format!("\"{a}\""))
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !reg(&[
+        "add", RUN_KEY, "/v", "Darwin", "/t", "REG_SZ", "/d", &command, "/f",
+    ])? {
+        anyhow::bail!(t!("autostart.write_failed", RUN_KEY));
+    }
+    Ok(format!(r"{RUN_KEY}\Darwin"))
+}
+
+#[cfg(windows)]
+fn disable() -> Result<()> {
+    // 登録されていなければ何もしない
+    reg(&["delete", RUN_KEY, "/v", "Darwin", "/f"])?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn status() -> Result<Option<String>> {
+    Ok(reg(&["query", RUN_KEY, "/v", "Darwin"])?.then(|| format!(r"{RUN_KEY}\Darwin")))
+}
@@ -0,0 +1,474 @@
+use crate::{
+    color, compose,
+    config::{AudioConfig, Config, TalkingConfig, TalkingMode},
+    dirty::{self, FrameInfo, Rect},
+    svg, t,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+// 組み込みのデモの立ち絵（設定した画像が1枚も読めなかったときに出す）
+const DEMO_IDLE: &[u8] = include_bytes!("../assets/demo/idle.png");
+const DEMO_TALKING: &[u8] = include_bytes!("../assets/demo/talking.png");
+const DEMO_BLINK: &[u8] = include_bytes!("../assets/demo/blink.png");
+
+// まばたきの長さと、まばたきの間隔の範囲
+const BLINK_DURATION: Duration = Duration::from_millis(150);
+const BLINK_INTERVAL_MS: std::ops::Range<u64> = 2000..6000;
+
+/// Opens a frame image at its native size. SVGs are rasterized at their document size.
+pub fn open_image(path: &Path) -> anyhow::Result<image::DynamicImage> {
+    if svg::is_svg(path) {
+        return Ok(svg::open(path)?.into());
+    }
+    color::open(path)
+}
+
+/// Loads a frame onto a transparent canvas-sized buffer (premultiplied alpha, see
+/// [`crate::compose`]), scaled around the canvas center and shifted by `offset`.
+pub fn load_image(
+    path: &Path,
+    target_width: usize,
+    target_height: usize,
+    offset: [i32; 2],
+    scale: f32,
+) -> Option<Vec<u8>> {
+    let scaled_width = ((target_width as f32 * scale).round() as u32).max(1);
+    let scaled_height = ((target_height as f32 * scale).round() as u32).max(1);
+    // SVG は縮小・拡大せず、最終的なサイズで直接ラスタライズする
+    let rgba = if svg::is_svg(path) {
+        let mut rgba = svg::rasterize(path, scaled_width, scaled_height).ok()?;
+        compose::premultiply(&mut rgba);
+        rgba
+    } else {
+        color::resize_premultiplied(
+            &color::open(path).ok()?.to_rgba8(),
+            scaled_width,
+            scaled_height,
+        )
+    };
+
+    Some(place(&rgba, target_width, target_height, offset))
+}
+
+/// Loads a border or frame image as a 9-slice (see [`color::nine_slice`]) stretched to the
+/// scaled canvas, placed like [`load_image`].
+pub fn load_sliced(
+    path: &Path,
+    target_width: usize,
+    target_height: usize,
+    offset: [i32; 2],
+    scale: f32,
+    insets: [u32; 4],
+) -> Option<Vec<u8>> {
+    let scaled_width = ((target_width as f32 * scale).round() as u32).max(1);
+    let scaled_height = ((target_height as f32 * scale).round() as u32).max(1);
+    // 角の形を保つため、SVG も元の大きさで読んでから切り分ける
+    let image = open_image(path).ok()?.to_rgba8();
+    let rgba = color::nine_slice(&image, insets, scaled_width, scaled_height);
+    Some(place(&rgba, target_width, target_height, offset))
+}
+
+// 透明なキャンバス大のバッファに画像を置く
+fn place(
+    rgba: &image::RgbaImage,
+    target_width: usize,
+    target_height: usize,
+    offset: [i32; 2],
+) -> Vec<u8> {
+    let (img_w, img_h) = rgba.dimensions();
+
+    // RGBAバッファを作成
+    let mut buffer = vec![0u8; target_width * target_height * 4];
+
+    // キャンバス中心に置いてからオフセット分ずらす（はみ出た部分は捨てる）
+    let left = (target_width as i64 - img_w as i64) / 2 + offset[0] as i64;
+    let top = (target_height as i64 - img_h as i64) / 2 + offset[1] as i64;
+    for y in 0..img_h as usize {
+        let ty = y as i64 + top;
+        if ty < 0 || ty >= target_height as i64 {
+            continue;
+        }
+        for x in 0..img_w as usize {
+            let tx = x as i64 + left;
+            if tx < 0 || tx >= target_width as i64 {
+                continue;
+            }
+            let pixel = rgba.get_pixel(x as u32, y as u32);
+            let idx = (ty as usize * target_width + tx as usize) * 4;
+            buffer[idx] = pixel[0];
+            buffer[idx + 1] = pixel[1];
+            buffer[idx + 2] = pixel[2];
+            buffer[idx + 3] = pixel[3];
+        }
+    }
+
+    buffer
+}
+
+/// Frames of one expression, already scaled to the canvas.
+#[derive(Default)]
+pub struct Expression {
+    pub idle: Vec<Vec<u8>>,
+    pub whisper: Vec<Vec<u8>>,
+    pub talking: Vec<Vec<u8>>,
+    pub blink: Vec<Vec<u8>>,
+    // フレーム（アドレス）ごとの、基準フレームと異なる領域
+    regions: HashMap<usize, Rect>,
+}
+
+impl Expression {
+    // 待機フレームの1枚目を基準にする
+    fn base(&self) -> Option<&[u8]> {
+        self.idle
+            .first()
+            .or(self.talking.first())
+            .or(self.whisper.first())
+            .map(Vec::as_slice)
+    }
+
+    fn compute_regions(&mut self, width: usize) {
+        let Some(base) = self.base() else {
+            return;
+        };
+        let regions = [&self.idle, &self.whisper, &self.talking, &self.blink]
+            .into_iter()
+            .flatten()
+            .filter_map(|frame| Some((frame.as_ptr() as usize, dirty::diff(base, frame, width)?)))
+            .collect();
+        self.regions = regions;
+    }
+}
+
+/// Mouth state driven by the input level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mouth {
+    Idle,
+    Whisper,
+    Talking,
+}
+
+impl Mouth {
+    /// Classifies a level without any hold time.
+    pub fn from_level(rms: f32, audio: &AudioConfig) -> Self {
+        if rms > audio.threshold {
+            Self::Talking
+        } else if audio.whisper_threshold.is_some_and(|w| rms > w) {
+            Self::Whisper
+        } else {
+            Self::Idle
+        }
+    }
+
+    // 対応表の mouth パラメーターの値（0: 待機、1: 発話、2: ささやき）
+    pub fn from_index(index: usize) -> Self {
+        match index {
+            1 => Self::Talking,
+            2 => Self::Whisper,
+            _ => Self::Idle,
+        }
+    }
+
+    /// State name as used in the config.
+    pub fn state(self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Whisper => "whisper",
+            Self::Talking => "talking",
+        }
+    }
+}
+
+/// A part drawn over the expressions, decoded at the canvas size.
+pub struct Layer {
+    pub name: String,
+    pub pixels: Vec<u8>,
+    // 切り抜く元のレイヤー（layers の中の位置）。その上に重ねて、不透明な所だけに出す
+    pub clip: Option<usize>,
+    // 透明でない所の範囲（動いたときに描き直す範囲を絞る）
+    pub bounds: Option<Rect>,
+}
+
+/// Every configured expression, decoded up front so switching is instant.
+pub struct Avatar {
+    pub expressions: BTreeMap<String, Expression>,
+    pub default: String,
+    // 表情の上に重ねるパーツ
+    pub layers: Vec<Layer>,
+}
+
+impl Avatar {
+    pub fn load(config: &Config) -> Self {
+        let width = config.canvas.width as usize;
+        let height = config.canvas.height as usize;
+
+        let mut expressions = BTreeMap::new();
+        for (name, expression) in &config.expressions {
+            let load = |frames: &[crate::config::FrameConfig]| -> Vec<Vec<u8>> {
+                frames
+                    .iter()
+                    .filter_map(|frame| {
+                        let path = config.resolve(&frame.path);
+                        tracing::debug!("{}", t!("image.loading", path.display()));
+                        if !path.exists() {
+                            tracing::warn!("{}", t!("image.not_found", path.display()));
+                            return None;
+                        }
+                        let buffer = load_image(&path, width, height, frame.offset, frame.scale)?;
+                        tracing::debug!("{}", t!("image.loaded"));
+                        Some(buffer)
+                    })
+                    .collect()
+            };
+            let mut expression = Expression {
+                idle: load(&expression.idle),
+                whisper: load(&expression.whisper),
+                talking: load(&expression.talking),
+                blink: load(&expression.blink),
+                ..Default::default()
+            };
+            expression.compute_regions(width);
+            expressions.insert(name.clone(), expression);
+        }
+
+        let loaded: Vec<(&String, Vec<u8>, Option<&String>)> = config
+            .layers
+            .iter()
+            .filter_map(|(name, frame)| {
+                let path = config.resolve(&frame.path);
+                let buffer = match frame.slice {
+                    Some(insets) => {
+                        load_sliced(&path, width, height, frame.offset, frame.scale, insets)
+                    }
+                    None => load_image(&path, width, height, frame.offset, frame.scale),
+                };
+                if buffer.is_none() {
+                    tracing::warn!("{}", t!("image.not_found", path.display()));
+                }
+                Some((name, buffer?, frame.clip.as_ref()))
+            })
+            .collect();
+        // 切り抜く元は、読み込めた、それ自身は切り抜かれていないレイヤーに限る。
+        // 元が無ければ出せる所も無いので、そのレイヤーは使わない
+        let is_base = |target: &String| {
+            loaded
+                .iter()
+                .any(|(name, _, clip)| *name == target && clip.is_none())
+        };
+        let usable: Vec<bool> = loaded
+            .iter()
+            .map(|(name, _, clip)| {
+                let usable = clip.is_none_or(is_base);
+                if !usable {
+                    tracing::warn!("{}", t!("image.clip_missing", name));
+                }
+                usable
+            })
+            .collect();
+        let loaded: Vec<_> = loaded
+            .into_iter()
+            .zip(usable)
+            .filter_map(|(layer, usable)| usable.then_some(layer))
+            .collect();
+        let clips: Vec<Option<usize>> = loaded
+            .iter()
+            .map(|(_, _, clip)| {
+                clip.and_then(|target| loaded.iter().position(|(name, ..)| *name == target))
+            })
+            .collect();
+        let layers = loaded
+            .into_iter()
+            .zip(clips)
+            .map(|((name, pixels, _), clip)| Layer {
+                name: name.clone(),
+                bounds: dirty::opaque(&pixels, width),
+                pixels,
+                clip,
+            })
+            .collect();
+
+        let avatar = Self {
+            expressions,
+            default: config.default_expression.clone(),
+            layers,
+        };
+        if avatar.frame(&avatar.default, Mouth::Idle, 0).is_none() {
+            tracing::info!("{}", t!("image.demo"));
+            return Self::demo(width, height);
+        }
+        avatar
+    }
+
+    /// The built-in demo avatar (idle, talking and blink frames), fitted to the canvas. It
+    /// goes through the same decoding, scaling and drawing as configured images, so seeing
+    /// it talk and blink shows the whole pipeline works.
+    pub fn demo(width: usize, height: usize) -> Self {
+        let load = |png: &[u8]| {
+            let rgba = image::load_from_memory(png)
+                .expect("embedded demo image")
+                .to_rgba8();
+            // 縦横比を保ってキャンバスに収める
+            let fit =
+                (width as f32 / rgba.width() as f32).min(height as f32 / rgba.height() as f32);
+            let scaled_width = ((rgba.width() as f32 * fit).round() as u32).max(1);
+            let scaled_height = ((rgba.height() as f32 * fit).round() as u32).max(1);
+            let rgba = color::resize_premultiplied(&rgba, scaled_width, scaled_height);
+            vec![place(&rgba, width, height, [0, 0])]
+        };
+
+        let mut expression = Expression {
+            idle: load(DEMO_IDLE),
+            talking: load(DEMO_TALKING),
+            blink: load(DEMO_BLINK),
+            ..Default::default()
+        };
+        expression.compute_regions(width);
+        let mut expressions = BTreeMap::new();
+        expressions.insert("default".to_string(), expression);
+        Self {
+            expressions,
+            default: "default".to_string(),
+            layers: Vec::new(),
+        }
+    }
+
+    fn expression(&self, name: &str) -> Option<&Expression> {
+        self.expressions
+            .get(name)
+            .or_else(|| self.expressions.get(&self.default))
+    }
+
+    pub fn talking_count(&self, expression: &str) -> usize {
+        self.expression(expression).map_or(0, |e| e.talking.len())
+    }
+
+    /// Picks the frame to show. Unknown expressions fall back to the default one, and a
+    /// missing talking or whisper frame falls back to idle. `index` selects among talking frames.
+    pub fn frame(&self, expression: &str, mouth: Mouth, index: usize) -> Option<&[u8]> {
+        let expression = self.expression(expression)?;
+        if mouth == Mouth::Whisper
+            && let Some(frame) = expression.whisper.first()
+        {
+            return Some(frame);
+        }
+        if mouth == Mouth::Talking && !expression.talking.is_empty() {
+            let frames = &expression.talking;
+            return Some(&frames[index % frames.len()]);
+        }
+        expression.idle.first().map(Vec::as_slice)
+    }
+
+    /// The eyes-closed frame of an expression, if it has one.
+    pub fn blink(&self, expression: &str) -> Option<&[u8]> {
+        self.expression(expression)?
+            .blink
+            .first()
+            .map(Vec::as_slice)
+    }
+
+    /// What the dirty-rect tracker needs to know about a frame of `expression` (from
+    /// [`Avatar::frame`] or [`Avatar::blink`]).
+    pub fn frame_info(&self, expression: &str, frame: &[u8]) -> Option<FrameInfo> {
+        let expression = self.expression(expression)?;
+        let key = frame.as_ptr() as usize;
+        Some(FrameInfo {
+            key,
+            group: expression.base()?.as_ptr() as usize,
+            region: expression.regions.get(&key).copied(),
+        })
+    }
+}
+
+/// Closes the eyes for a moment every few seconds, at random intervals.
+pub struct Blinks {
+    rng: fastrand::Rng,
+    // 次に目を閉じる時刻と、閉じている間は開ける時刻
+    next: Option<Instant>,
+    until: Option<Instant>,
+}
+
+impl Blinks {
+    /// `rng` picks the intervals.
+    pub fn new(rng: fastrand::Rng) -> Self {
+        Self {
+            rng,
+            next: None,
+            until: None,
+        }
+    }
+
+    /// Whether the eyes are closed at `now`.
+    pub fn update(&mut self, now: Instant) -> bool {
+        if let Some(until) = self.until {
+            if now < until {
+                return true;
+            }
+            self.until = None;
+        }
+        let next = *self
+            .next
+            .get_or_insert_with(|| now + Duration::from_millis(self.rng.u64(BLINK_INTERVAL_MS)));
+        if now < next {
+            return false;
+        }
+        self.next = None;
+        self.until = Some(now + BLINK_DURATION);
+        true
+    }
+}
+
+/// Chooses which talking frame to show while speech continues, holding each one for at
+/// least the configured duration.
+pub struct TalkingFrames {
+    mode: TalkingMode,
+    min_duration: Duration,
+    index: usize,
+    since: Instant,
+    talking: bool,
+    rng: fastrand::Rng,
+}
+
+impl TalkingFrames {
+    /// `rng` picks the frames in random mode.
+    pub fn new(config: &TalkingConfig, rng: fastrand::Rng) -> Self {
+        Self {
+            mode: config.mode,
+            min_duration: Duration::from_millis(config.min_frame_ms),
+            index: 0,
+            since: Instant::now(),
+            talking: false,
+            rng,
+        }
+    }
+
+    /// Returns the talking frame index for this redraw.
+    pub fn update(&mut self, talking: bool, count: usize, now: Instant) -> usize {
+        if count <= 1 {
+            self.talking = talking;
+            return 0;
+        }
+
+        if talking && !self.talking {
+            // 話し始めたら最初のフレームを選び直す
+            self.index = match self.mode {
+                TalkingMode::Cycle => 0,
+                TalkingMode::Random => self.rng.usize(..count),
+            };
+            self.since = now;
+        } else if talking && now.duration_since(self.since) >= self.min_duration {
+            self.index = match self.mode {
+                TalkingMode::Cycle => (self.index + 1) % count,
+                // 同じフレームが続くと口が止まって見えるので必ず別のものにする
+                TalkingMode::Random => (self.index + 1 + self.rng.usize(..count - 1)) % count,
+            };
+            self.since = now;
+        }
+        self.talking = talking;
+        self.index % count
+    }
+}
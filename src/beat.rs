@@ -0,0 +1,216 @@
+// 音楽のビート検出（音量の立ち上がりから拍を拾い、自己相関でテンポを推定する）
+use crate::{config::MusicConfig, features::OnePole, t};
+use std::{collections::VecDeque, time::Duration};
+
+// 立ち上がりを見る単位（1秒あたり）
+const HOPS_PER_SECOND: f32 = 100.0;
+// バスドラムを拾う帯域の上限 (Hz)
+const BASS_CUTOFF: f32 = 200.0;
+// 立ち上がりの閾値を決める履歴（1秒）と、テンポを推定する履歴（6秒）
+const THRESHOLD_HOPS: usize = 100;
+const TEMPO_HOPS: usize = 600;
+// テンポを推定し直す間隔
+const TEMPO_INTERVAL: usize = 50;
+// 自己相関のピークが 0 ずれの何割以上ならテンポとして採用するか
+const TEMPO_CONFIDENCE: f32 = 0.1;
+// 拍の予測とのずれを直す割合と、直す対象にするずれの大きさ（周期に対する比）
+const PHASE_CORRECTION: f32 = 0.5;
+const PHASE_WINDOW: f32 = 0.25;
+// これだけ立ち上がりが無ければ曲が止まったとみなす（秒）
+const LOST: f32 = 2.0;
+// 対数を取る前に足すエネルギー（無音時の雑音を立ち上がりと見ないように）
+const FLOOR: f32 = 1e-6;
+
+/// Onset and tempo tracker fed from the audio callback.
+pub struct BeatDetector {
+    sensitivity: f32,
+    // 拍の周期の範囲（hop 数）
+    min_period: f32,
+    max_period: f32,
+    hop: usize,
+    bass: OnePole,
+    // hop 内の音のエネルギー
+    energy: [f32; 2],
+    filled: usize,
+    previous: Option<[f32; 2]>,
+    // 立ち上がりの強さの履歴
+    onsets: VecDeque<f32>,
+    // テンポ推定で平均を引いた履歴（毎回確保しないよう使い回す）
+    centered: Vec<f32>,
+    hops: usize,
+    last_onset: Option<usize>,
+    period: Option<f32>,
+    next_beat: f32,
+    // これまでの拍の数（描画ループは変わるたびに跳ねる）
+    count: usize,
+}
+
+impl BeatDetector {
+    /// `rate` is the sample rate of the mono input.
+    pub fn new(config: &MusicConfig, rate: u32) -> Self {
+        Self {
+            sensitivity: config.sensitivity,
+            min_period: 60.0 * HOPS_PER_SECOND / config.max_bpm,
+            max_period: 60.0 * HOPS_PER_SECOND / config.min_bpm,
+            hop: (rate as f32 / HOPS_PER_SECOND).round().max(1.0) as usize,
+            bass: OnePole::new(BASS_CUTOFF, rate as f32),
+            energy: [0.0; 2],
+            filled: 0,
+            previous: None,
+            onsets: VecDeque::with_capacity(TEMPO_HOPS),
+            centered: Vec::with_capacity(TEMPO_HOPS),
+            hops: 0,
+            last_onset: None,
+            period: None,
+            next_beat: 0.0,
+            count: 0,
+        }
+    }
+
+    /// Takes mono samples and returns the number of beats so far.
+    pub fn update(&mut self, samples: &[f32]) -> usize {
+        for &x in samples {
+            let bass = self.bass.process(x);
+            self.energy[0] += bass * bass;
+            self.energy[1] += x * x;
+            self.filled += 1;
+            if self.filled == self.hop {
+                let energy =
+                    std::mem::take(&mut self.energy).map(|e| (e / self.hop as f32 + FLOOR).ln());
+                self.filled = 0;
+                self.step(energy);
+            }
+        }
+        self.count
+    }
+
+    // hop ごとの処理
+    fn step(&mut self, energy: [f32; 2]) {
+        // 対数エネルギーの増えた分を立ち上がりの強さにする（低音を重く見る）
+        let onset = match self.previous.replace(energy) {
+            Some([bass, full]) => (energy[0] - bass).max(0.0) * 2.0 + (energy[1] - full).max(0.0),
+            None => 0.0,
+        };
+        if self.onsets.len() == TEMPO_HOPS {
+            self.onsets.pop_front();
+        }
+        self.onsets.push_back(onset);
+        self.hops += 1;
+
+        if self.is_onset(onset) {
+            self.last_onset = Some(self.hops);
+            match self.period {
+                // テンポが分かっていれば予測した拍をずれの分だけ寄せる
+                Some(period) => {
+                    let now = self.hops as f32;
+                    let previous = self.next_beat - period;
+                    let nearest = if now - previous < self.next_beat - now {
+                        previous
+                    } else {
+                        self.next_beat
+                    };
+                    let error = now - nearest;
+                    if error.abs() < period * PHASE_WINDOW {
+                        self.next_beat += error * PHASE_CORRECTION;
+                    }
+                }
+                None => self.beat(),
+            }
+        }
+
+        let lost = self
+            .last_onset
+            .is_none_or(|at| (self.hops - at) as f32 > LOST * HOPS_PER_SECOND);
+        if !lost && self.hops.is_multiple_of(TEMPO_INTERVAL) {
+            self.estimate_tempo();
+        }
+        if lost && self.period.take().is_some() {
+            tracing::debug!("{}", t!("beat.lost"));
+        }
+        if let Some(period) = self.period
+            && self.hops as f32 >= self.next_beat
+        {
+            self.beat();
+            // 取りこぼした拍は飛ばす
+            while self.next_beat <= self.hops as f32 {
+                self.next_beat += period;
+            }
+        }
+    }
+
+    // 直近の平均 + 標準偏差 × 感度を超えたら立ち上がりとする
+    fn is_onset(&self, onset: f32) -> bool {
+        let recent = self.onsets.iter().rev().skip(1).take(THRESHOLD_HOPS);
+        let n = recent.len().max(1) as f32;
+        let mean = recent.clone().sum::<f32>() / n;
+        let variance = recent.map(|o| (o - mean).powi(2)).sum::<f32>() / n;
+        let threshold = mean + variance.sqrt() * self.sensitivity;
+        // 速すぎる連打は拍として数えない
+        let spaced = self
+            .last_onset
+            .is_none_or(|at| (self.hops - at) as f32 >= self.min_period * 0.5);
+        onset > threshold && onset > f32::EPSILON && spaced
+    }
+
+    fn beat(&mut self) {
+        self.count += 1;
+    }
+
+    // 立ち上がりの強さの自己相関から、BPM の範囲で一番強い周期を選ぶ
+    fn estimate_tempo(&mut self) {
+        if self.onsets.len() < TEMPO_HOPS / 2 {
+            return;
+        }
+        let onsets = self.onsets.make_contiguous();
+        let mean = onsets.iter().sum::<f32>() / onsets.len() as f32;
+        self.centered.clear();
+        self.centered.extend(onsets.iter().map(|o| o - mean));
+        let centered = &self.centered;
+        let correlation = |lag: usize| -> f32 {
+            centered
+                .iter()
+                .zip(&centered[lag..])
+                .map(|(a, b)| a * b)
+                .sum::<f32>()
+                / (centered.len() - lag) as f32
+        };
+        let zero = correlation(0);
+        if zero <= f32::EPSILON {
+            return;
+        }
+        let min_lag = self.min_period.floor().max(1.0) as usize;
+        let max_lag = (self.max_period.ceil() as usize).min(centered.len() / 2);
+        let Some((lag, peak)) = (min_lag..=max_lag)
+            .map(|lag| (lag, correlation(lag)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+        else {
+            return;
+        };
+        if peak < zero * TEMPO_CONFIDENCE {
+            return;
+        }
+        // 前後との放物線補間で周期を細かく求める
+        let (a, c) = (correlation(lag - 1), correlation(lag + 1));
+        let denominator = a + c - 2.0 * peak;
+        let shift = if denominator.abs() > f32::EPSILON {
+            ((a - c) / (2.0 * denominator)).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        };
+        let period = lag as f32 + shift;
+        if self.period.is_none() {
+            // 拍の位置は最後の立ち上がりに合わせて始める
+            let start = self.last_onset.unwrap_or(self.hops) as f32;
+            self.next_beat = start + period;
+            let bpm = 60.0 * HOPS_PER_SECOND / period;
+            tracing::debug!(bpm, "{}", t!("beat.locked"));
+        }
+        self.period = Some(period);
+    }
+}
+
+/// Extra scale for the `pulse` action, `elapsed` after the latest beat.
+pub fn pulse(config: &MusicConfig, elapsed: Duration) -> f32 {
+    let t = elapsed.as_secs_f32() * 1000.0 / config.pulse_ms.max(1) as f32;
+    (1.0 - config.pulse_easing.ease(t)) * config.pulse
+}
@@ -0,0 +1,98 @@
+// サブシステムをつなぐイベントバス。入力側（制御ソケット、リモコン、Stream Deck、MQTT、通知、音声など）は
+// publish するだけで、描画ループなどの購読側がまとめて受け取る
+use crate::{config::ExpressionSource, filter::Band, health::AudioWarning, ipc::ForwardedArgs};
+use crossbeam_channel::{Receiver, Sender, unbounded};
+use std::path::PathBuf;
+
+/// Something that happened in one subsystem that others may react to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// Ask for an expression on behalf of `source`, or withdraw its request with `None`.
+    Expression(ExpressionSource, Option<String>),
+    /// Manually select an expression, or withdraw it if it is already selected.
+    ToggleExpression(String),
+    Sequence(String),
+    /// A sequence asked for by an outside service; rate limited and queued before it plays.
+    Trigger(String),
+    /// Change a filter cutoff on the analysis path, or turn it off with `None`.
+    Filter(Band, Option<f32>),
+    /// Keep the mouth closed (`Some(true)`), open it again, or flip it with `None`.
+    Mute(Option<bool>),
+    /// Switch the overlay theme by name, or to the next one with `None`.
+    Theme(Option<String>),
+    /// Draw a random expression with the gacha.
+    Gacha,
+    Fullscreen,
+    Quit,
+    /// Command line of a second launch, forwarded instead of starting another instance.
+    Open(ForwardedArgs),
+    /// Switch to another profile (config file).
+    Profile(PathBuf),
+    /// Hide these layers whatever the mappings say, replacing the last list from the same
+    /// source.
+    HideLayers(LayerSource, Vec<String>),
+    /// The main capture's input warning changed.
+    AudioWarning(Option<AudioWarning>),
+}
+
+/// Who hides layers with [`Event::HideLayers`]; each keeps its own list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LayerSource {
+    /// Time-of-day and date rules.
+    Outfit,
+    /// Rules for the focused application.
+    Focus,
+}
+
+enum Message {
+    Event(Event),
+    Subscribe(Sender<Event>),
+}
+
+/// Handle to the bus; cheap to clone into every thread that publishes.
+#[derive(Clone)]
+pub struct Bus {
+    sender: Sender<Message>,
+}
+
+impl Bus {
+    /// Starts the hub thread that hands every event to every subscriber.
+    pub fn start() -> Self {
+        let (sender, receiver) = unbounded::<Message>();
+        std::thread::spawn(move || {
+            let mut subscribers: Vec<Sender<Event>> = Vec::new();
+            for message in receiver {
+                match message {
+                    Message::Subscribe(subscriber) => subscribers.push(subscriber),
+                    // 受け取り側が無くなった購読は外す
+                    Message::Event(event) => {
+                        subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok())
+                    }
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    pub fn publish(&self, event: Event) {
+        let _ = self.sender.send(Message::Event(event));
+    }
+
+    /// Receives every event published after this call.
+    pub fn subscribe(&self) -> Subscription {
+        let (sender, receiver) = unbounded();
+        let _ = self.sender.send(Message::Subscribe(sender));
+        Subscription { receiver }
+    }
+}
+
+pub struct Subscription {
+    receiver: Receiver<Event>,
+}
+
+impl Subscription {
+    /// Events received since the last call.
+    pub fn poll(&self) -> Vec<Event> {
+        self.receiver.try_iter().collect()
+    }
+}
@@ -0,0 +1,90 @@
+// Webカメラの枠のような見た目: アバターを角丸の四角か円で切り抜き、縁取りを付ける。
+// 顔出しカメラの代わりに置けるよう、形も色も設定だけで決める
+use crate::{
+    compose,
+    config::{CamFrameConfig, CamShape},
+    dirty::Rect,
+    panel,
+};
+
+/// The avatar cut out to a rounded box or a circle with a border around it.
+pub struct CamFrame {
+    width: usize,
+    // 枠の内側の割合（0〜255）
+    mask: Vec<u8>,
+    // 縁取り（乗算済み）
+    border: Vec<u8>,
+    background: [u8; 4],
+}
+
+impl CamFrame {
+    pub fn new(config: &CamFrameConfig, width: usize, height: usize) -> Self {
+        let margin = config.margin as usize;
+        let (mut box_w, mut box_h) = (
+            width.saturating_sub(margin * 2),
+            height.saturating_sub(margin * 2),
+        );
+        let radius = match config.shape {
+            CamShape::Rounded => config.radius,
+            // 円は短い辺に合わせた正方形の中に置く
+            CamShape::Circle => {
+                let side = box_w.min(box_h);
+                (box_w, box_h) = (side, side);
+                side as f32 / 2.0
+            }
+        };
+        let left = (width - box_w) / 2;
+        let top = (height - box_h) / 2;
+        let thickness = (config.border as usize).min(box_w / 2).min(box_h / 2);
+        let coverage = |x: usize, y: usize, inset: usize| {
+            let (w, h) = (box_w - inset * 2, box_h - inset * 2);
+            let (x0, y0) = (left + inset, top + inset);
+            if w == 0 || h == 0 || x < x0 || y < y0 || x >= x0 + w || y >= y0 + h {
+                return 0.0;
+            }
+            panel::rounded(x - x0, y - y0, w, h, (radius - inset as f32).max(0.0))
+        };
+
+        let color = panel::premultiplied(config.border_color);
+        let mut mask = vec![0; width * height];
+        let mut border = vec![0; width * height * 4];
+        for y in 0..height {
+            for x in 0..width {
+                let outer = coverage(x, y, 0);
+                let inner = if thickness > 0 {
+                    coverage(x, y, thickness)
+                } else {
+                    outer
+                };
+                let i = y * width + x;
+                mask[i] = (outer * 255.0).round() as u8;
+                compose::blend_opacity(&mut border[i * 4..i * 4 + 4], &color, outer - inner);
+            }
+        }
+        Self {
+            width,
+            mask,
+            border,
+            background: panel::premultiplied(config.background),
+        }
+    }
+
+    /// Cuts the avatar in `dst` to the frame, fills behind it and draws the border, where
+    /// it overlaps `clip`.
+    pub fn draw(&self, dst: &mut [u8], clip: Rect) {
+        for y in clip.y..clip.y + clip.height {
+            let row = clip.row(y, self.width);
+            let start = y * self.width + clip.x;
+            let pixels = dst[row.clone()].chunks_exact_mut(4);
+            let mask = &self.mask[start..start + clip.width];
+            for ((pixel, &mask), border) in pixels.zip(mask).zip(self.border[row].chunks_exact(4)) {
+                // 背景の上にアバターを重ねてから切り抜く
+                let mut inside = self.background;
+                compose::blend(&mut inside, pixel);
+                pixel.fill(0);
+                compose::blend_opacity(pixel, &inside, mask as f32 / 255.0);
+                compose::blend(pixel, border);
+            }
+        }
+    }
+}
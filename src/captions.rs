@@ -0,0 +1,363 @@
+// マイクの音声をローカルで文字起こしして、キャンバスの下に字幕として出す
+use crate::{color, compose, config::CaptionsConfig, dirty::Rect, t};
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont, point};
+use anyhow::{Context, Result};
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+// 音声認識が追いつかないときに溜めておく入力ブロック数（超えたら捨てる）
+const FEED_CAPACITY: usize = 256;
+
+/// Hands microphone samples (mono, analysis rate) to the transcriber without blocking.
+#[derive(Clone)]
+pub struct Feed(mpsc::SyncSender<Vec<f32>>);
+
+impl Feed {
+    pub fn push(&self, samples: &[f32]) {
+        let _ = self.0.try_send(samples.to_vec());
+    }
+}
+
+// 描画済みの字幕（文字の範囲だけの大きさ）
+struct Layer {
+    rect: Rect,
+    pixels: Vec<u8>,
+}
+
+/// The caption text layer: the latest lines, shown until a pause and then faded out.
+pub struct Captions {
+    config: CaptionsConfig,
+    font: FontVec,
+    width: usize,
+    height: usize,
+    texts: mpsc::Receiver<String>,
+    lines: Vec<String>,
+    layer: Option<Layer>,
+    last_text: Instant,
+    opacity: f32,
+}
+
+impl Captions {
+    /// Loads the font and starts the transcriber. `rate` is the sample rate of the feed, and
+    /// audio quieter than `threshold` counts as a pause between phrases.
+    pub fn start(
+        config: &CaptionsConfig,
+        width: usize,
+        height: usize,
+        rate: u32,
+        threshold: f32,
+    ) -> Result<(Self, Feed)> {
+        let data = std::fs::read(&config.font)
+            .with_context(|| t!("captions.font_failed", config.font.display()))?;
+        let font = FontVec::try_from_vec(data)
+            .with_context(|| t!("captions.font_failed", config.font.display()))?;
+
+        let (feed, audio) = mpsc::sync_channel(FEED_CAPACITY);
+        let (sender, texts) = mpsc::channel();
+        start_transcriber(config, rate, threshold, audio, sender)?;
+
+        let captions = Self {
+            config: config.clone(),
+            font,
+            width,
+            height,
+            texts,
+            lines: Vec::new(),
+            layer: None,
+            last_text: Instant::now(),
+            opacity: 0.0,
+        };
+        Ok((captions, Feed(feed)))
+    }
+
+    /// Takes in new text and advances the fade. Returns whether the layer looks different
+    /// from the last frame.
+    pub fn update(&mut self, now: Instant) -> bool {
+        let mut changed = false;
+        for text in self.texts.try_iter().collect::<Vec<_>>() {
+            // 消えた後の新しい発話は前の字幕を引き継がない
+            if self.opacity == 0.0 {
+                self.lines.clear();
+            }
+            let max_width = self.width.saturating_sub(self.config.margin as usize * 2);
+            self.lines.extend(self.wrap(&text, max_width as f32));
+            let excess = self
+                .lines
+                .len()
+                .saturating_sub(self.config.max_lines.max(1));
+            self.lines.drain(..excess);
+            self.last_text = now;
+            self.opacity = 1.0;
+            self.layer = self.render();
+            changed = true;
+        }
+
+        // 長すぎる表示時間は、消えないのと同じ
+        let hold = Duration::try_from_secs_f32(self.config.hold.max(0.0)).unwrap_or(Duration::MAX);
+        let since = self
+            .last_text
+            .checked_add(hold)
+            .map_or(0.0, |end| now.saturating_duration_since(end).as_secs_f32());
+        let opacity = if self.config.fade > 0.0 {
+            1.0 - self.config.fade_easing.ease(since / self.config.fade)
+        } else if since > 0.0 {
+            0.0
+        } else {
+            1.0
+        };
+        if self.layer.is_some() && opacity != self.opacity {
+            self.opacity = opacity;
+            changed = true;
+        }
+        changed
+    }
+
+    /// Draws the captions where they overlap `clip`.
+    pub fn draw(&self, dst: &mut [u8], clip: Rect) {
+        let Some(layer) = &self.layer else {
+            return;
+        };
+        if self.opacity == 0.0 {
+            return;
+        }
+        let rect = layer.rect;
+        let x0 = rect.x.max(clip.x);
+        let x1 = (rect.x + rect.width).min(clip.x + clip.width);
+        if x1 <= x0 {
+            return;
+        }
+        for y in rect.y.max(clip.y)..(rect.y + rect.height).min(clip.y + clip.height) {
+            let s = ((y - rect.y) * rect.width + (x0 - rect.x)) * 4;
+            let d = (y * self.width + x0) * 4;
+            let len = (x1 - x0) * 4;
+            let (dst, src) = (&mut dst[d..d + len], &layer.pixels[s..s + len]);
+            compose::blend_row_opacity(dst, src, self.opacity);
+        }
+    }
+
+    // 幅に収まるように折り返す（空白があればそこで、なければ文字単位で）
+    fn wrap(&self, text: &str, max_width: f32) -> Vec<String> {
+        let font = self.font.as_scaled(PxScale::from(self.config.size));
+        let advance =
+            |s: &str| -> f32 { s.chars().map(|c| font.h_advance(font.glyph_id(c))).sum() };
+        let mut lines = Vec::new();
+        let mut line = String::new();
+        for c in text.chars() {
+            if !line.is_empty() && advance(&line) + advance(&c.to_string()) > max_width {
+                if c == ' ' {
+                    lines.push(std::mem::take(&mut line));
+                    continue;
+                }
+                match line.rfind(' ') {
+                    Some(i) => {
+                        let rest = line[i + 1..].to_string();
+                        line.truncate(i);
+                        lines.push(std::mem::replace(&mut line, rest));
+                    }
+                    None => lines.push(std::mem::take(&mut line)),
+                }
+            }
+            line.push(c);
+        }
+        if !line.trim().is_empty() {
+            lines.push(line);
+        }
+        lines
+    }
+
+    // 行を中央揃えで描き、縁取りを付けた乗算済みの画像にする
+    fn render(&self) -> Option<Layer> {
+        let font = self.font.as_scaled(PxScale::from(self.config.size));
+        let outline = self.config.outline_width as usize;
+        let line_height = (font.height() + font.line_gap()).ceil() as usize;
+        let widths: Vec<f32> = self
+            .lines
+            .iter()
+            .map(|line| line.chars().map(|c| font.h_advance(font.glyph_id(c))).sum())
+            .collect();
+        let text_width = widths.iter().copied().fold(0.0, f32::max).ceil() as usize;
+        let width = (text_width + outline * 2).min(self.width);
+        let height = (line_height * self.lines.len() + outline * 2).min(self.height);
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        // 文字の被覆率
+        let mut coverage = vec![0.0f32; width * height];
+        for (row, (line, line_width)) in self.lines.iter().zip(&widths).enumerate() {
+            let mut x = outline as f32 + (text_width as f32 - line_width) / 2.0;
+            let baseline = (outline + row * line_height) as f32 + font.ascent();
+            for c in line.chars() {
+                let id = font.glyph_id(c);
+                let glyph = id.with_scale_and_position(font.scale(), point(x, baseline));
+                x += font.h_advance(id);
+                let Some(outlined) = self.font.outline_glyph(glyph) else {
+                    continue;
+                };
+                let bounds = outlined.px_bounds();
+                outlined.draw(|gx, gy, c| {
+                    let px = bounds.min.x as i32 + gx as i32;
+                    let py = bounds.min.y as i32 + gy as i32;
+                    if px >= 0 && py >= 0 && (px as usize) < width && (py as usize) < height {
+                        let i = py as usize * width + px as usize;
+                        coverage[i] = coverage[i].max(c);
+                    }
+                });
+            }
+        }
+
+        // 縁取りは被覆率を円形に広げたもの
+        let mut border = coverage.clone();
+        if outline > 0 {
+            let r = outline as i32;
+            for y in 0..height as i32 {
+                for x in 0..width as i32 {
+                    let mut value = 0.0f32;
+                    for dy in -r..=r {
+                        for dx in -r..=r {
+                            let (sx, sy) = (x + dx, y + dy);
+                            if dx * dx + dy * dy > r * r
+                                || sx < 0
+                                || sy < 0
+                                || sx >= width as i32
+                                || sy >= height as i32
+                            {
+                                continue;
+                            }
+                            value = value.max(coverage[sy as usize * width + sx as usize]);
+                        }
+                    }
+                    border[y as usize * width + x as usize] = value;
+                }
+            }
+        }
+
+        let fill = self.config.color;
+        let stroke = self.config.outline_color;
+        let mut pixels = vec![0u8; width * height * 4];
+        for ((pixel, &text), &edge) in pixels.chunks_exact_mut(4).zip(&coverage).zip(&border) {
+            let ta = text * fill[3] as f32 / 255.0;
+            let oa = edge * stroke[3] as f32 / 255.0 * (1.0 - ta);
+            for i in 0..3 {
+                let c = color::to_linear(fill[i]) * ta + color::to_linear(stroke[i]) * oa;
+                pixel[i] = color::to_srgb(c);
+            }
+            pixel[3] = ((ta + oa) * 255.0).round() as u8;
+        }
+
+        Some(Layer {
+            rect: Rect {
+                x: (self.width - width) / 2,
+                y: self
+                    .height
+                    .saturating_sub(height + self.config.margin as usize),
+                width,
+                height,
+            },
+            pixels,
+        })
+    }
+}
+
+#[cfg(feature = "stt")]
+fn start_transcriber(
+    config: &CaptionsConfig,
+    rate: u32,
+    threshold: f32,
+    audio: mpsc::Receiver<Vec<f32>>,
+    texts: mpsc::Sender<String>,
+) -> Result<()> {
+    use whisper_rs::{WhisperContext, WhisperContextParameters};
+
+    let model = config.model.to_string_lossy().into_owned();
+    let context = WhisperContext::new_with_params(&model, WhisperContextParameters::default())
+        .with_context(|| t!("captions.model_failed", config.model.display()))?;
+    let language = config.language.clone();
+    std::thread::spawn(move || {
+        if let Err(e) = transcribe(&context, language, rate, threshold, audio, texts) {
+            tracing::error!("{}", t!("captions.failed", e));
+        }
+    });
+    tracing::info!("{}", t!("captions.started", model));
+    Ok(())
+}
+
+#[cfg(not(feature = "stt"))]
+fn start_transcriber(
+    _config: &CaptionsConfig,
+    _rate: u32,
+    _threshold: f32,
+    _audio: mpsc::Receiver<Vec<f32>>,
+    _texts: mpsc::Sender<String>,
+) -> Result<()> {
+    anyhow::bail!(t!("captions.unavailable"))
+}
+
+// whisper.cpp の入力のサンプルレート
+#[cfg(feature = "stt")]
+const WHISPER_RATE: u32 = 16_000;
+
+// この長さ無音が続いたら1つの発話として文字起こしする
+#[cfg(feature = "stt")]
+const PAUSE: Duration = Duration::from_millis(600);
+
+// 話し続けていてもこの長さで区切る
+#[cfg(feature = "stt")]
+const MAX_PHRASE: Duration = Duration::from_secs(8);
+
+#[cfg(feature = "stt")]
+fn transcribe(
+    context: &whisper_rs::WhisperContext,
+    language: Option<String>,
+    rate: u32,
+    threshold: f32,
+    audio: mpsc::Receiver<Vec<f32>>,
+    texts: mpsc::Sender<String>,
+) -> Result<()> {
+    use crate::resample::{Resampler, rms};
+    use whisper_rs::{FullParams, SamplingStrategy};
+
+    let mut state = context.create_state()?;
+    let mut resampler = Resampler::new(rate, WHISPER_RATE);
+    let samples_of = |d: Duration| (d.as_secs_f32() * WHISPER_RATE as f32) as usize;
+    let mut phrase = Vec::new();
+    let mut silent = 0;
+    let mut chunk = Vec::new();
+    for samples in audio {
+        chunk.clear();
+        resampler.process(&samples, &mut chunk);
+        let loud = rms(&samples) >= threshold;
+        // 話し始めるまでは溜めない
+        if phrase.is_empty() && !loud {
+            continue;
+        }
+        phrase.extend_from_slice(&chunk);
+        silent = if loud { 0 } else { silent + chunk.len() };
+        if silent < samples_of(PAUSE) && phrase.len() < samples_of(MAX_PHRASE) {
+            continue;
+        }
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        params.set_language(Some(language.as_deref().unwrap_or("auto")));
+        params.set_no_context(true);
+        params.set_single_segment(true);
+        params.set_suppress_blank(true);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_special(false);
+        params.set_print_timestamps(false);
+        state.full(params, &phrase)?;
+        let text: String = (0..state.full_n_segments()?)
+            .filter_map(|i| state.full_get_segment_text(i).ok())
+            .collect();
+        let text = text.trim();
+        if !text.is_empty() && texts.send(text.to_string()).is_err() {
+            return Ok(());
+        }
+        phrase.clear();
+        silent = 0;
+    }
+    Ok(())
+}
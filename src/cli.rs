@@ -0,0 +1,187 @@
+use crate::align::FrameRef;
+use crate::config::{GfxBackend, TestSignal};
+use crate::logging::LogFormat;
+use crate::t;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(name = "darwin", version)]
+pub struct Cli {
+    #[arg(short, long, global = true)]
+    pub config: Option<PathBuf>,
+
+    #[arg(long, global = true, value_enum, default_value_t)]
+    pub log_format: LogFormat,
+
+    #[arg(long, global = true)]
+    pub log_dir: Option<PathBuf>,
+
+    #[arg(long, global = true)]
+    pub instance: Option<String>,
+
+    #[arg(long, global = true)]
+    pub portable: bool,
+
+    #[arg(long, global = true, value_enum, value_delimiter = ',')]
+    pub gfx_backend: Vec<GfxBackend>,
+
+    #[arg(long)]
+    pub watchdog: bool,
+
+    #[arg(long)]
+    pub preview: bool,
+
+    #[arg(long)]
+    pub host: Option<String>,
+
+    #[arg(long)]
+    pub gallery: bool,
+
+    #[arg(long)]
+    pub minimized: bool,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "tone")]
+    pub test_signal: Option<TestSignal>,
+
+    #[arg(long)]
+    pub timings: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    Validate {
+        path: PathBuf,
+    },
+    Align {
+        first: Option<FrameRef>,
+        second: Option<FrameRef>,
+    },
+    Edit,
+    Import {
+        psd: PathBuf,
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    Render {
+        #[arg(long)]
+        audio: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+        #[arg(long, default_value_t = 60)]
+        fps: u32,
+    },
+    Autostart {
+        #[arg(value_enum)]
+        action: AutostartAction,
+    },
+    Paths,
+    Gpus,
+    Preset {
+        #[command(subcommand)]
+        action: PresetAction,
+    },
+    Ctl {
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    #[command(name = "streamdeck")]
+    StreamDeck {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PresetAction {
+    Export { file: PathBuf },
+    Import { file: PathBuf },
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum AutostartAction {
+    Enable,
+    Disable,
+    Status,
+}
+
+// ヘルプ文は現在の言語で差し替える
+pub fn command() -> clap::Command {
+    Cli::command()
+        .about(t!("cli.about"))
+        .mut_arg("config", |a| a.help(t!("cli.config")))
+        .mut_arg("log_format", |a| a.help(t!("cli.log_format")))
+        .mut_arg("log_dir", |a| a.help(t!("cli.log_dir")))
+        .mut_arg("instance", |a| a.help(t!("cli.instance")))
+        .mut_arg("portable", |a| a.help(t!("cli.portable")))
+        .mut_arg("gfx_backend", |a| a.help(t!("cli.gfx_backend")))
+        .mut_arg("watchdog", |a| a.help(t!("cli.watchdog")))
+        .mut_arg("preview", |a| a.help(t!("cli.preview")))
+        .mut_arg("gallery", |a| a.help(t!("cli.gallery")))
+        .mut_arg("host", |a| a.help(t!("cli.host")))
+        .mut_arg("minimized", |a| a.help(t!("cli.minimized")))
+        .mut_arg("record", |a| a.help(t!("cli.record")))
+        .mut_arg("replay", |a| a.help(t!("cli.replay")))
+        .mut_arg("test_signal", |a| a.help(t!("cli.test_signal")))
+        .mut_arg("timings", |a| a.help(t!("cli.timings")))
+        .mut_subcommand("validate", |c| {
+            c.about(t!("cli.validate"))
+                .mut_arg("path", |a| a.help(t!("cli.config")))
+        })
+        .mut_subcommand("align", |c| {
+            c.about(t!("cli.align"))
+                .mut_arg("first", |a| a.help(t!("cli.align.first")))
+                .mut_arg("second", |a| a.help(t!("cli.align.second")))
+        })
+        .mut_subcommand("edit", |c| c.about(t!("cli.edit")))
+        .mut_subcommand("import", |c| {
+            c.about(t!("cli.import"))
+                .mut_arg("psd", |a| a.help(t!("cli.import.psd")))
+                .mut_arg("out", |a| a.help(t!("cli.import.out")))
+        })
+        .mut_subcommand("render", |c| {
+            c.about(t!("cli.render"))
+                .mut_arg("audio", |a| a.help(t!("cli.render.audio")))
+                .mut_arg("out", |a| a.help(t!("cli.render.out")))
+                .mut_arg("fps", |a| a.help(t!("cli.render.fps")))
+        })
+        .mut_subcommand("autostart", |c| {
+            c.about(t!("cli.autostart"))
+                .mut_arg("action", |a| a.help(t!("cli.autostart.action")))
+        })
+        .mut_subcommand("paths", |c| c.about(t!("cli.paths")))
+        .mut_subcommand("gpus", |c| c.about(t!("cli.gpus")))
+        .mut_subcommand("preset", |c| {
+            c.about(t!("cli.preset"))
+                .mut_subcommand("export", |c| {
+                    c.about(t!("cli.preset.export"))
+                        .mut_arg("file", |a| a.help(t!("cli.preset.file")))
+                })
+                .mut_subcommand("import", |c| {
+                    c.about(t!("cli.preset.import"))
+                        .mut_arg("file", |a| a.help(t!("cli.preset.file")))
+                })
+        })
+        .mut_subcommand("ctl", |c| {
+            c.about(t!("cli.ctl"))
+                .mut_arg("command", |a| a.help(t!("cli.ctl.command")))
+        })
+        .mut_subcommand("streamdeck", |c| {
+            c.about(t!("cli.streamdeck"))
+                .mut_arg("args", |a| a.help(t!("cli.streamdeck.args")))
+        })
+}
+
+pub fn parse() -> Cli {
+    let matches = command().get_matches();
+    Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
+}
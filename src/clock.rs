@@ -0,0 +1,17 @@
+// 地方時。time クレートは他のスレッドが動き出すと時差を読めなくなるので、起動直後に一度だけ読んでおく
+use std::sync::OnceLock;
+use time::{OffsetDateTime, UtcOffset};
+
+static OFFSET: OnceLock<UtcOffset> = OnceLock::new();
+
+/// Reads the local UTC offset. Call before any thread is started; until then (or if the
+/// offset cannot be read) local time is UTC.
+pub fn init() {
+    let _ = OFFSET.set(UtcOffset::current_local_offset().unwrap_or(UtcOffset::UTC));
+}
+
+/// The current local time, with the offset read at startup (daylight saving changes during
+/// a session are not followed).
+pub fn now() -> OffsetDateTime {
+    OffsetDateTime::now_utc().to_offset(OFFSET.get().copied().unwrap_or(UtcOffset::UTC))
+}
@@ -0,0 +1,190 @@
+// 色空間の変換（sRGB <-> リニア、埋め込み ICC プロファイル）
+use anyhow::Result;
+use image::{DynamicImage, ImageDecoder, ImageReader, Rgba, Rgba32FImage, RgbaImage};
+use std::{path::Path, sync::OnceLock};
+use wide::{CmpEq, f32x4, i32x4};
+
+// リニア -> sRGB の表の分解能
+const ENCODE_STEPS: usize = 4096;
+
+fn decode_table() -> &'static [f32; 256] {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        std::array::from_fn(|i| {
+            let c = i as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        })
+    })
+}
+
+fn encode_table() -> &'static [u8] {
+    static TABLE: OnceLock<Vec<u8>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        (0..=ENCODE_STEPS)
+            .map(|i| {
+                let l = i as f32 / ENCODE_STEPS as f32;
+                let c = if l <= 0.003_130_8 {
+                    l * 12.92
+                } else {
+                    1.055 * l.powf(1.0 / 2.4) - 0.055
+                };
+                (c * 255.0).round() as u8
+            })
+            .collect()
+    })
+}
+
+pub fn to_linear(value: u8) -> f32 {
+    decode_table()[value as usize]
+}
+
+pub fn to_srgb(value: f32) -> u8 {
+    encode_table()[(value.clamp(0.0, 1.0) * ENCODE_STEPS as f32).round() as usize]
+}
+
+/// Rounds four non-negative values like [`f32::round`] (halves away from zero), where the
+/// SIMD conversion rounds halves to even.
+pub fn round4(values: f32x4) -> i32x4 {
+    let rounded = values.round_int();
+    let half_down = (values - rounded.round_float()).cmp_eq(f32x4::splat(0.5));
+    rounded + half_down.blend(f32x4::ONE, f32x4::ZERO).round_int()
+}
+
+/// [`to_linear`] for four values at once, one per lane.
+pub fn to_linear4(values: [u8; 4]) -> f32x4 {
+    let table = decode_table();
+    f32x4::from(values.map(|value| table[value as usize]))
+}
+
+/// [`to_srgb`] for four values at once, one per lane.
+pub fn to_srgb4(values: f32x4) -> [u8; 4] {
+    let steps = f32x4::splat(ENCODE_STEPS as f32);
+    let index = round4(values.max(f32x4::ZERO).min(f32x4::ONE) * steps);
+    let table = encode_table();
+    index.to_array().map(|i| table[i as usize])
+}
+
+/// Decodes an image file, converting it to sRGB if it embeds an ICC profile.
+pub fn open(path: &Path) -> Result<DynamicImage> {
+    let mut decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    let icc = decoder.icc_profile()?;
+    let img = DynamicImage::from_decoder(decoder)?;
+    let Some(icc) = icc else {
+        return Ok(img);
+    };
+
+    match to_srgb_profile(&img, &icc) {
+        Ok(converted) => Ok(converted),
+        Err(e) => {
+            tracing::warn!("{}", crate::t!("color.icc_failed", path.display(), e));
+            Ok(img)
+        }
+    }
+}
+
+fn to_srgb_profile(img: &DynamicImage, icc: &[u8]) -> Result<DynamicImage> {
+    let source = moxcms::ColorProfile::new_from_slice(icc).map_err(|e| anyhow::anyhow!("{e:?}"))?;
+    let srgb = moxcms::ColorProfile::new_srgb();
+    let convert = |layout, src: &[u8], dst: &mut [u8]| -> Result<()> {
+        let transform = source
+            .create_transform_8bit(layout, &srgb, layout, Default::default())
+            .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        transform
+            .transform(src, dst)
+            .map_err(|e| anyhow::anyhow!("{e:?}"))
+    };
+
+    // アルファの有無は検証で使うので元の形式に合わせる
+    if img.color().has_alpha() {
+        let src = img.to_rgba8();
+        let mut dst = src.clone();
+        convert(moxcms::Layout::Rgba, &src, &mut dst)?;
+        Ok(dst.into())
+    } else {
+        let src = img.to_rgb8();
+        let mut dst = src.clone();
+        convert(moxcms::Layout::Rgb, &src, &mut dst)?;
+        Ok(dst.into())
+    }
+}
+
+/// Scales `img` to `width` × `height` as a 9-slice: the corners keep their size, the edges
+/// stretch along their length and the middle fills the rest. `insets` are the top, right,
+/// bottom and left borders in source pixels. The result is premultiplied, as with
+/// [`resize_premultiplied`].
+pub fn nine_slice(img: &RgbaImage, insets: [u32; 4], width: u32, height: u32) -> RgbaImage {
+    let [top, right, bottom, left] = insets;
+    // 枠が画像より太ければ画像に収める
+    let left = left.min(img.width());
+    let right = right.min(img.width() - left);
+    let top = top.min(img.height());
+    let bottom = bottom.min(img.height() - top);
+    // 出力が両側の枠より小さければ、比率を保って枠を細くする
+    let fit = |a: u32, b: u32, size: u32| {
+        if a + b <= size {
+            return (a, b);
+        }
+        let a = (a as f32 * size as f32 / (a + b) as f32).round() as u32;
+        (a, size - a)
+    };
+    let (to_left, to_right) = fit(left, right, width);
+    let (to_top, to_bottom) = fit(top, bottom, height);
+    let src_x = [0, left, img.width() - right, img.width()];
+    let src_y = [0, top, img.height() - bottom, img.height()];
+    let dst_x = [0, to_left, width - to_right, width];
+    let dst_y = [0, to_top, height - to_bottom, height];
+
+    let mut out = RgbaImage::new(width, height);
+    for row in 0..3 {
+        for col in 0..3 {
+            let (src_w, src_h) = (src_x[col + 1] - src_x[col], src_y[row + 1] - src_y[row]);
+            let (dst_w, dst_h) = (dst_x[col + 1] - dst_x[col], dst_y[row + 1] - dst_y[row]);
+            if src_w == 0 || src_h == 0 || dst_w == 0 || dst_h == 0 {
+                continue;
+            }
+            let piece = image::imageops::crop_imm(img, src_x[col], src_y[row], src_w, src_h);
+            let piece = resize_premultiplied(&piece.to_image(), dst_w, dst_h);
+            image::imageops::replace(&mut out, &piece, dst_x[col] as i64, dst_y[row] as i64);
+        }
+    }
+    out
+}
+
+/// Resizes in linear light with premultiplied alpha, so semi-transparent edges don't darken
+/// or pick up the color of fully transparent pixels. The result stays premultiplied.
+pub fn resize_premultiplied(img: &RgbaImage, width: u32, height: u32) -> RgbaImage {
+    let linear = Rgba32FImage::from_fn(img.width(), img.height(), |x, y| {
+        let p = img.get_pixel(x, y);
+        let a = p[3] as f32 / 255.0;
+        Rgba([
+            to_linear(p[0]) * a,
+            to_linear(p[1]) * a,
+            to_linear(p[2]) * a,
+            a,
+        ])
+    });
+    let resized = image::imageops::resize(
+        &linear,
+        width,
+        height,
+        image::imageops::FilterType::Lanczos3,
+    );
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let p = resized.get_pixel(x, y);
+        // Lanczos のリンギングで色がアルファを超えないようにする
+        let a = p[3].clamp(0.0, 1.0);
+        Rgba([
+            to_srgb(p[0].min(a)),
+            to_srgb(p[1].min(a)),
+            to_srgb(p[2].min(a)),
+            (a * 255.0).round() as u8,
+        ])
+    })
+}
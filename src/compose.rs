@@ -0,0 +1,765 @@
+// 合成処理（キャンバスサイズの RGBA バッファ同士）
+// バッファはすべて乗算済みアルファ: RGB は「リニアの色 × アルファ」を sRGB で符号化した値
+use crate::{
+    color,
+    dirty::{self, Rect},
+};
+use rayon::prelude::*;
+use std::{cell::RefCell, ops::Range};
+use wide::f32x4;
+
+// この画素数より小さい範囲は、スレッドに分けるより1本で回す方が速い
+const PARALLEL_PIXELS: usize = 256 * 256;
+
+thread_local! {
+    // 行の作業用バッファ。フレームごとに確保しないよう、スレッドごとに1つを使い回す
+    static SCRATCH: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+// `dst` の `rows` の行を1行ずつ `draw` に渡す（行番号、その行、使い回せる作業用バッファ）。
+// 大きな範囲は行のまとまりごとにスレッドに分ける
+fn for_rows<F>(dst: &mut [u8], width: usize, rows: Range<usize>, draw: F)
+where
+    F: Fn(usize, &mut [u8], &mut Vec<u8>) + Sync + Send,
+{
+    let stride = width * 4;
+    if rows.is_empty() || stride == 0 {
+        return;
+    }
+    let first = rows.start;
+    let dst = &mut dst[rows.start * stride..rows.end * stride];
+    if rows.len() * width < PARALLEL_PIXELS {
+        SCRATCH.with_borrow_mut(|scratch| {
+            for (i, row) in dst.chunks_exact_mut(stride).enumerate() {
+                draw(first + i, row, scratch);
+            }
+        });
+    } else {
+        dst.par_chunks_exact_mut(stride)
+            .enumerate()
+            .for_each(|(i, row)| SCRATCH.with_borrow_mut(|scratch| draw(first + i, row, scratch)));
+    }
+}
+
+/// Offset/scale/rotation (degrees, clockwise) applied to a whole frame, around the canvas
+/// center. `stretch` scales each axis on top of `scale`, for squash and stretch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub offset: [f32; 2],
+    pub scale: f32,
+    pub rotation: f32,
+    pub stretch: [f32; 2],
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            offset: [0.0, 0.0],
+            scale: 1.0,
+            rotation: 0.0,
+            stretch: [1.0, 1.0],
+        }
+    }
+}
+
+impl Transform {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            offset: [
+                self.offset[0] + (other.offset[0] - self.offset[0]) * t,
+                self.offset[1] + (other.offset[1] - self.offset[1]) * t,
+            ],
+            scale: self.scale + (other.scale - self.scale) * t,
+            rotation: self.rotation + (other.rotation - self.rotation) * t,
+            stretch: [
+                self.stretch[0] + (other.stretch[0] - self.stretch[0]) * t,
+                self.stretch[1] + (other.stretch[1] - self.stretch[1]) * t,
+            ],
+        }
+    }
+
+    // 横と縦の拡大率
+    fn axis_scale(&self) -> [f32; 2] {
+        [
+            (self.scale * self.stretch[0]).max(f32::EPSILON),
+            (self.scale * self.stretch[1]).max(f32::EPSILON),
+        ]
+    }
+
+    /// Canvas area that `rect` of a `width` x `height` source covers once transformed, padded
+    /// for rounding. `None` if none of it lands on the canvas.
+    pub fn bounds(&self, rect: Rect, width: usize, height: usize) -> Option<Rect> {
+        let [scale_x, scale_y] = self.axis_scale();
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+        let (right, bottom) = (rect.x + rect.width, rect.y + rect.height);
+        // source の逆: 中心からの位置を拡大して回し、中心と offset の分を戻す
+        let corners = [
+            (rect.x, rect.y),
+            (right, rect.y),
+            (rect.x, bottom),
+            (right, bottom),
+        ]
+        .map(|(x, y)| {
+            let dx = (x as f32 - cx) * scale_x;
+            let dy = (y as f32 - cy) * scale_y;
+            (
+                dx * cos - dy * sin + cx + self.offset[0],
+                dx * sin + dy * cos + cy + self.offset[1],
+            )
+        });
+        let (mut left, mut top) = (f32::INFINITY, f32::INFINITY);
+        let (mut right, mut bottom) = (f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for (x, y) in corners {
+            (left, top) = (left.min(x), top.min(y));
+            (right, bottom) = (right.max(x), bottom.max(y));
+        }
+        let (left, top) = (left.floor() as i32, top.floor() as i32);
+        let (right, bottom) = (right.ceil() as i32, bottom.ceil() as i32);
+        let rect = [
+            left.saturating_sub(2),
+            top.saturating_sub(2),
+            right.saturating_sub(left).saturating_add(4),
+            bottom.saturating_sub(top).saturating_add(4),
+        ];
+        Rect::clipped(rect, width, height)
+    }
+
+    // 出力ピクセルの中心から元画像のピクセルへの逆変換（範囲外なら None）
+    fn source(&self, x: usize, y: usize, width: usize, height: usize) -> Option<usize> {
+        let [scale_x, scale_y] = self.axis_scale();
+        let (sin, cos) = (-self.rotation.to_radians()).sin_cos();
+        let dx = x as f32 + 0.5 - (width as f32 / 2.0 + self.offset[0]);
+        let dy = y as f32 + 0.5 - (height as f32 / 2.0 + self.offset[1]);
+        let sx = ((dx * cos - dy * sin) / scale_x + width as f32 / 2.0).floor();
+        let sy = ((dx * sin + dy * cos) / scale_y + height as f32 / 2.0).floor();
+        if sx < 0.0 || sy < 0.0 || sx >= width as f32 || sy >= height as f32 {
+            return None;
+        }
+        Some(sy as usize * width + sx as usize)
+    }
+}
+
+/// Draws `src` into `dst` (both `width` x `height` RGBA) with the transform applied.
+pub fn draw(dst: &mut [u8], src: &[u8], width: usize, height: usize, transform: Transform) {
+    if transform.is_identity() {
+        dst.copy_from_slice(src);
+        return;
+    }
+
+    // 出力ピクセルから元画像の位置を逆算（最近傍）
+    for_rows(dst, width, 0..height, |y, row, _| {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            match transform.source(x, y, width, height) {
+                Some(s) => pixel.copy_from_slice(&src[s * 4..s * 4 + 4]),
+                None => pixel.fill(0),
+            }
+        }
+    });
+}
+
+/// Redraws only `region` of the output; outside it `dst` is left as it was.
+pub fn draw_region(
+    dst: &mut [u8],
+    src: &[u8],
+    width: usize,
+    height: usize,
+    transform: Transform,
+    region: Rect,
+) {
+    if !transform.is_identity() {
+        draw(dst, src, width, height, transform);
+        return;
+    }
+    dirty::copy_rect(dst, src, width, region);
+}
+
+/// Draws `src` (`width` x `height` RGBA) scaled into `rect` = `[x, y, w, h]` of `dst`,
+/// alpha-blended over what is already there. Pixels outside `clip` are not touched.
+pub fn draw_rect(
+    dst: &mut [u8],
+    src: &[u8],
+    width: usize,
+    height: usize,
+    rect: [i32; 4],
+    clip: Rect,
+) {
+    draw_rect_faded(dst, src, width, height, rect, clip, 1.0);
+}
+
+/// Blends `src` over `dst` with the transform applied and faded by `opacity`, touching only
+/// pixels inside `clip`. Unlike [`draw`], what is already in `dst` shows through.
+pub fn draw_over(
+    dst: &mut [u8],
+    src: &[u8],
+    width: usize,
+    height: usize,
+    transform: Transform,
+    opacity: f32,
+    clip: Rect,
+) {
+    if transform.rotation != 0.0 {
+        if opacity <= 0.0 {
+            return;
+        }
+        let (x1, y1) = (
+            (clip.x + clip.width).min(width),
+            (clip.y + clip.height).min(height),
+        );
+        for_rows(dst, width, clip.y..y1, |y, row, _| {
+            for x in clip.x..x1 {
+                if let Some(s) = transform.source(x, y, width, height)
+                    && src[s * 4 + 3] != 0
+                {
+                    let d = x * 4;
+                    blend_opacity(&mut row[d..d + 4], &src[s * 4..s * 4 + 4], opacity);
+                }
+            }
+        });
+        return;
+    }
+    let [scale_x, scale_y] = transform.axis_scale();
+    let (w, h) = (width as f32 * scale_x, height as f32 * scale_y);
+    let rect = [
+        ((width as f32 - w) / 2.0 + transform.offset[0]).round() as i32,
+        ((height as f32 - h) / 2.0 + transform.offset[1]).round() as i32,
+        w.round() as i32,
+        h.round() as i32,
+    ];
+    draw_rect_faded(dst, src, width, height, rect, clip, opacity);
+}
+
+/// One image of a clipping group with its transform and opacity, as for [`draw_over`].
+pub type GroupLayer<'a> = (&'a [u8], Transform, f32);
+
+/// Canvas-sized buffers reused by [`draw_clipped`] from frame to frame.
+#[derive(Default)]
+pub struct ClipScratch {
+    group: Vec<u8>,
+    layer: Vec<u8>,
+    // 元のレイヤーのアルファ
+    mask: Vec<u8>,
+}
+
+/// Draws `base`, then each of `clipped` only where `base` is opaque (a clipping group, as in
+/// art programs), and blends the group over `dst` inside `clip`.
+pub fn draw_clipped<'a>(
+    dst: &mut [u8],
+    base: GroupLayer,
+    clipped: impl IntoIterator<Item = GroupLayer<'a>>,
+    width: usize,
+    height: usize,
+    clip: Rect,
+    scratch: &mut ClipScratch,
+) {
+    let clip = Rect {
+        width: clip.width.min(width.saturating_sub(clip.x)),
+        height: clip.height.min(height.saturating_sub(clip.y)),
+        ..clip
+    };
+    let size = width * height * 4;
+    scratch.group.resize(size, 0);
+    scratch.layer.resize(size, 0);
+    scratch.mask.resize(width * height, 0);
+    let rows = clip.y..clip.y + clip.height;
+
+    for y in rows.clone() {
+        scratch.group[clip.row(y, width)].fill(0);
+    }
+    let (pixels, transform, opacity) = base;
+    draw_over(
+        &mut scratch.group,
+        pixels,
+        width,
+        height,
+        transform,
+        opacity,
+        clip,
+    );
+    for y in rows.clone() {
+        for x in clip.x..clip.x + clip.width {
+            let i = y * width + x;
+            scratch.mask[i] = scratch.group[i * 4 + 3];
+        }
+    }
+
+    for (pixels, transform, opacity) in clipped {
+        for y in rows.clone() {
+            scratch.layer[clip.row(y, width)].fill(0);
+        }
+        draw_over(
+            &mut scratch.layer,
+            pixels,
+            width,
+            height,
+            transform,
+            opacity,
+            clip,
+        );
+        // 元のレイヤーのアルファを不透明度として重ねる
+        for y in rows.clone() {
+            for x in clip.x..clip.x + clip.width {
+                let i = y * width + x;
+                let (mask, d) = (scratch.mask[i], i * 4);
+                if mask == 0 || scratch.layer[d + 3] == 0 {
+                    continue;
+                }
+                blend_opacity(
+                    &mut scratch.group[d..d + 4],
+                    &scratch.layer[d..d + 4],
+                    mask as f32 / 255.0,
+                );
+            }
+        }
+    }
+
+    for y in rows {
+        let row = clip.row(y, width);
+        blend_row(&mut dst[row.clone()], &scratch.group[row]);
+    }
+}
+
+fn draw_rect_faded(
+    dst: &mut [u8],
+    src: &[u8],
+    width: usize,
+    height: usize,
+    rect: [i32; 4],
+    clip: Rect,
+    opacity: f32,
+) {
+    if opacity <= 0.0 {
+        return;
+    }
+    let [left, top, rect_w, rect_h] = rect;
+    if rect_w <= 0 || rect_h <= 0 {
+        return;
+    }
+    let (rect_w, rect_h) = (rect_w as usize, rect_h as usize);
+
+    // 描く列の範囲（キャンバスとクリップ範囲の内側）
+    let x0 = left.max(clip.x as i32);
+    let x1 = (left + rect_w as i32).min((clip.x + clip.width).min(width) as i32);
+    if x1 <= x0 {
+        return;
+    }
+    // 描く行の範囲（矩形とクリップ範囲とキャンバスの重なり）
+    let y0 = top.max(clip.y as i32);
+    let y1 = (top + rect_h as i32).min((clip.y + clip.height).min(height) as i32);
+    if y1 <= y0 {
+        return;
+    }
+
+    for_rows(dst, width, y0 as usize..y1 as usize, |y, dst, row| {
+        let sy = (y as i32 - top) as usize * height / rect_h;
+        // 1行分を拡大縮小して集めてから、行単位でまとめて合成する
+        row.clear();
+        for x in x0..x1 {
+            let sx = (x - left) as usize * width / rect_w;
+            let s = (sy * width + sx) * 4;
+            row.extend_from_slice(&src[s..s + 4]);
+        }
+        blend_row_opacity(&mut dst[x0 as usize * 4..x1 as usize * 4], row, opacity);
+    });
+}
+
+// 4ピクセル分のアルファのバイト（リトルエンディアンで読んだとき）
+const ALPHA_MASK: u128 = 0xff00_0000_ff00_0000_ff00_0000_ff00_0000;
+
+/// [`blend`] over a run of pixels. Four pixels at a time are checked for being fully
+/// transparent or fully opaque, which is most of an avatar frame; the other groups (soft
+/// edges) are blended four pixels at once in SIMD lanes.
+pub fn blend_row(dst: &mut [u8], src: &[u8]) {
+    let mut dst_chunks = dst.chunks_exact_mut(16);
+    let mut src_chunks = src.chunks_exact(16);
+    for (d, s) in (&mut dst_chunks).zip(&mut src_chunks) {
+        let alpha = u128::from_le_bytes(s.try_into().unwrap()) & ALPHA_MASK;
+        if alpha == 0 {
+            continue;
+        }
+        if alpha == ALPHA_MASK {
+            d.copy_from_slice(s);
+            continue;
+        }
+        // 全透明のピクセルはそのまま、不透明はコピーと、1ピクセルずつの blend に合わせる
+        let mut blended: [u8; 16] = d.try_into().unwrap();
+        blend_opacity4(&mut blended, s, 1.0);
+        for (p, (d, s)) in d.chunks_exact_mut(4).zip(s.chunks_exact(4)).enumerate() {
+            match s[3] {
+                0 => {}
+                255 => d.copy_from_slice(s),
+                _ => d.copy_from_slice(&blended[p * 4..p * 4 + 4]),
+            }
+        }
+    }
+    let rest = dst_chunks.into_remainder();
+    for (d, s) in rest
+        .chunks_exact_mut(4)
+        .zip(src_chunks.remainder().chunks_exact(4))
+    {
+        blend(d, s);
+    }
+}
+
+/// [`blend_opacity`] over a run of pixels, four at a time in SIMD lanes; with `opacity` 1.0
+/// it is [`blend_row`].
+pub fn blend_row_opacity(dst: &mut [u8], src: &[u8], opacity: f32) {
+    if opacity >= 1.0 {
+        return blend_row(dst, src);
+    }
+    let mut dst_chunks = dst.chunks_exact_mut(16);
+    let mut src_chunks = src.chunks_exact(16);
+    for (d, s) in (&mut dst_chunks).zip(&mut src_chunks) {
+        blend_opacity4(d, s, opacity);
+    }
+    let rest = dst_chunks.into_remainder();
+    for (d, s) in rest
+        .chunks_exact_mut(4)
+        .zip(src_chunks.remainder().chunks_exact(4))
+    {
+        blend_opacity(d, s, opacity);
+    }
+}
+
+// 4ピクセルの成分 `i` を並べたもの
+fn lanes(pixels: &[u8], i: usize) -> [u8; 4] {
+    std::array::from_fn(|p| pixels[p * 4 + i])
+}
+
+// blend_opacity を4ピクセルまとめて（各成分を4つのレーンに並べて）計算する。
+// 演算の順序を揃えてあるので、結果は1ピクセルずつと同じ
+fn blend_opacity4(dst: &mut [u8], src: &[u8], opacity: f32) {
+    let opacity = f32x4::splat(opacity);
+    let max = f32x4::splat(255.0);
+    let alpha = |pixels: &[u8]| f32x4::from(lanes(pixels, 3).map(f32::from)) / max;
+    let sa = alpha(src) * opacity;
+    let da = alpha(dst);
+    let keep = f32x4::ONE - sa;
+    let colors: [[u8; 4]; 3] = std::array::from_fn(|i| {
+        let s = color::to_linear4(lanes(src, i));
+        let d = color::to_linear4(lanes(dst, i));
+        color::to_srgb4(s * opacity + d * keep)
+    });
+    let alpha = color::round4((sa + da * keep) * max).to_array();
+    for (p, pixel) in dst.chunks_exact_mut(4).enumerate() {
+        pixel.copy_from_slice(&[colors[0][p], colors[1][p], colors[2][p], alpha[p] as u8]);
+    }
+}
+
+/// Blends `pixels` (`rect.width` x `rect.height`, not scaled) onto a `width` x `height`
+/// canvas at `rect`. Pixels outside `clip` and the canvas are not touched.
+pub fn draw_layer(
+    dst: &mut [u8],
+    width: usize,
+    height: usize,
+    rect: Rect,
+    pixels: &[u8],
+    clip: Rect,
+) {
+    let x0 = rect.x.max(clip.x);
+    let x1 = (rect.x + rect.width).min(clip.x + clip.width).min(width);
+    if x1 <= x0 {
+        return;
+    }
+    let y1 = (rect.y + rect.height).min(clip.y + clip.height).min(height);
+    for y in rect.y.max(clip.y)..y1 {
+        let s = ((y - rect.y) * rect.width + (x0 - rect.x)) * 4;
+        let d = (y * width + x0) * 4;
+        let len = (x1 - x0) * 4;
+        blend_row(&mut dst[d..d + len], &pixels[s..s + len]);
+    }
+}
+
+/// Premultiplied "over" in linear light.
+pub fn blend(dst: &mut [u8], src: &[u8]) {
+    match src[3] {
+        0 => {}
+        255 => dst.copy_from_slice(src),
+        _ => blend_opacity(dst, src, 1.0),
+    }
+}
+
+/// Like [`blend`], with the source faded by `opacity` (0.0..=1.0).
+pub fn blend_opacity(dst: &mut [u8], src: &[u8], opacity: f32) {
+    let sa = src[3] as f32 / 255.0 * opacity;
+    let da = dst[3] as f32 / 255.0;
+    for i in 0..3 {
+        let c = color::to_linear(src[i]) * opacity + color::to_linear(dst[i]) * (1.0 - sa);
+        dst[i] = color::to_srgb(c);
+    }
+    dst[3] = ((sa + da * (1.0 - sa)) * 255.0).round() as u8;
+}
+
+/// Multiplies the colors inside `region` by `tint` (linear RGB factors).
+pub fn tint(dst: &mut [u8], width: usize, region: Rect, tint: [f32; 3]) {
+    if tint == [1.0; 3] {
+        return;
+    }
+    for_rows(
+        dst,
+        width,
+        region.y..region.y + region.height,
+        |_, row, _| {
+            let row = &mut row[region.x * 4..(region.x + region.width) * 4];
+            let mut chunks = row.chunks_exact_mut(16);
+            // 4ピクセルずつ、成分ごとに4つのレーンに並べて掛ける
+            for pixels in &mut chunks {
+                for (i, factor) in tint.into_iter().enumerate() {
+                    let tinted =
+                        color::to_srgb4(color::to_linear4(lanes(pixels, i)) * f32x4::splat(factor));
+                    for (pixel, value) in pixels.chunks_exact_mut(4).zip(tinted) {
+                        if pixel[3] != 0 {
+                            pixel[i] = value;
+                        }
+                    }
+                }
+            }
+            for pixel in chunks.into_remainder().chunks_exact_mut(4) {
+                if pixel[3] == 0 {
+                    continue;
+                }
+                for i in 0..3 {
+                    pixel[i] = color::to_srgb(color::to_linear(pixel[i]) * tint[i]);
+                }
+            }
+        },
+    );
+}
+
+/// Converts straight-alpha RGBA to the premultiplied form used by every frame buffer.
+pub fn premultiply(buffer: &mut [u8]) {
+    for pixel in buffer.chunks_exact_mut(4) {
+        if pixel[3] == 255 {
+            continue;
+        }
+        let a = pixel[3] as f32 / 255.0;
+        for c in &mut pixel[..3] {
+            *c = color::to_srgb(color::to_linear(*c) * a);
+        }
+    }
+}
+
+/// Inverse of [`premultiply`], for writing images out to files.
+pub fn unpremultiply(buffer: &mut [u8]) {
+    for pixel in buffer.chunks_exact_mut(4) {
+        match pixel[3] {
+            255 => {}
+            0 => pixel[..3].fill(0),
+            a => {
+                let a = a as f32 / 255.0;
+                for c in &mut pixel[..3] {
+                    *c = color::to_srgb(color::to_linear(*c) / a);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 参照用: 浮動小数点でそのまま計算した sRGB <-> リニア
+    fn linear(c: u8) -> f32 {
+        let c = c as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    fn encode(l: f32) -> u8 {
+        let c = if l <= 0.003_130_8 {
+            l * 12.92
+        } else {
+            1.055 * l.powf(1.0 / 2.4) - 0.055
+        };
+        (c * 255.0).round() as u8
+    }
+
+    // ストレートアルファの画像をリニアで "over" 合成した参照結果（不透明な背景）
+    fn reference_over(src: [u8; 4], dst: [u8; 3]) -> [u8; 3] {
+        let a = src[3] as f32 / 255.0;
+        std::array::from_fn(|i| encode(linear(src[i]) * a + linear(dst[i]) * (1.0 - a)))
+    }
+
+    fn assert_close(actual: &[u8], expected: &[u8], tolerance: u8) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                a.abs_diff(*e) <= tolerance,
+                "{actual:?} differs from {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn opaque_source_replaces_destination() {
+        let mut dst = [10, 20, 30, 255];
+        blend(&mut dst, &[200, 100, 50, 255]);
+        assert_eq!(dst, [200, 100, 50, 255]);
+    }
+
+    #[test]
+    fn transparent_source_keeps_destination() {
+        let mut dst = [10, 20, 30, 255];
+        blend(&mut dst, &[0, 0, 0, 0]);
+        assert_eq!(dst, [10, 20, 30, 255]);
+    }
+
+    #[test]
+    fn half_transparent_over_opaque_matches_reference() {
+        for (src, bg) in [
+            ([255, 255, 255, 128], [0, 0, 0]),
+            ([255, 0, 0, 128], [0, 0, 255]),
+            ([30, 200, 90, 64], [240, 240, 240]),
+            ([120, 60, 250, 200], [20, 180, 40]),
+        ] {
+            let mut pixel = src;
+            premultiply(&mut pixel);
+            let mut dst = [bg[0], bg[1], bg[2], 255];
+            blend(&mut dst, &pixel);
+            assert_close(&dst[..3], &reference_over(src, bg), 1);
+            assert_eq!(dst[3], 255);
+        }
+    }
+
+    #[test]
+    fn stacked_layers_match_reference() {
+        let bg = [50, 80, 110];
+        let layers = [[255, 220, 0, 100], [0, 90, 255, 160]];
+
+        let mut dst = [bg[0], bg[1], bg[2], 255];
+        for layer in layers {
+            let mut pixel = layer;
+            premultiply(&mut pixel);
+            blend(&mut dst, &pixel);
+        }
+
+        let expected = layers
+            .iter()
+            .fold(bg, |acc, layer| reference_over(*layer, acc));
+        assert_close(&dst[..3], &expected, 1);
+    }
+
+    #[test]
+    fn premultiply_round_trips() {
+        let mut pixels = vec![
+            200, 100, 50, 255, //
+            200, 100, 50, 128, //
+            10, 250, 130, 200, //
+            99, 99, 99, 0,
+        ];
+        let original = pixels.clone();
+        premultiply(&mut pixels);
+        unpremultiply(&mut pixels);
+        assert_close(&pixels[..12], &original[..12], 2);
+        assert_eq!(&pixels[12..], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn downscaled_edges_have_no_dark_fringe() {
+        // 不透明な白と、色が黒の完全に透明なピクセル
+        let img = image::RgbaImage::from_raw(2, 1, vec![255, 255, 255, 255, 0, 0, 0, 0]).unwrap();
+        let mut resized = color::resize_premultiplied(&img, 1, 1).into_raw();
+        unpremultiply(&mut resized);
+        // ストレートアルファのまま縮小すると灰色 (約 128) になる
+        assert!(resized[0] >= 250, "edge color darkened: {resized:?}");
+        assert!(
+            (100..=160).contains(&resized[3]),
+            "unexpected alpha: {resized:?}"
+        );
+    }
+
+    #[test]
+    fn row_blend_matches_per_pixel_blend() {
+        // 全透明・全不透明・半透明が混ざった、4 の倍数でない長さの行
+        let alphas = [
+            0, 0, 0, 0, 255, 255, 255, 255, 0, 128, 255, 64, 255, 0, 200, 255, 10,
+        ];
+        let src: Vec<u8> = alphas
+            .iter()
+            .flat_map(|&a| {
+                let mut pixel = [200, 100, 50, a];
+                premultiply(&mut pixel);
+                pixel
+            })
+            .collect();
+        let background: Vec<u8> = (0..src.len()).map(|i| (i * 37 % 256) as u8).collect();
+
+        let mut expected = background.clone();
+        for (d, s) in expected.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+            blend(d, s);
+        }
+        let mut actual = background;
+        blend_row(&mut actual, &src);
+        assert_eq!(actual, expected);
+    }
+
+    // 半透明ばかりの行（4 の倍数でない長さ）と、その下地
+    fn haze_row() -> (Vec<u8>, Vec<u8>) {
+        let src: Vec<u8> = (0..67u32)
+            .flat_map(|i| {
+                let mut pixel = [
+                    (i * 53 % 256) as u8,
+                    180,
+                    (i * 11 % 256) as u8,
+                    (i * 29 % 256) as u8,
+                ];
+                premultiply(&mut pixel);
+                pixel
+            })
+            .collect();
+        let background = (0..src.len()).map(|i| (i * 37 % 256) as u8).collect();
+        (src, background)
+    }
+
+    #[test]
+    fn faded_row_blend_matches_per_pixel_blend() {
+        let (src, background) = haze_row();
+        for opacity in [0.0, 0.25, 0.5, 0.7, 1.0] {
+            let mut expected = background.clone();
+            for (d, s) in expected.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+                if opacity >= 1.0 {
+                    blend(d, s);
+                } else {
+                    blend_opacity(d, s, opacity);
+                }
+            }
+            let mut actual = background.clone();
+            blend_row_opacity(&mut actual, &src, opacity);
+            assert_eq!(actual, expected, "opacity {opacity}");
+        }
+    }
+
+    #[test]
+    fn row_tint_matches_per_pixel_tint() {
+        let (mut pixels, _) = haze_row();
+        let factors = [1.2, 0.9, 0.5];
+        let mut expected = pixels.clone();
+        for pixel in expected.chunks_exact_mut(4) {
+            if pixel[3] != 0 {
+                for i in 0..3 {
+                    pixel[i] = color::to_srgb(color::to_linear(pixel[i]) * factors[i]);
+                }
+            }
+        }
+        let width = pixels.len() / 4;
+        tint(&mut pixels, width, Rect::full(width, 1), factors);
+        assert_eq!(pixels, expected);
+    }
+
+    #[test]
+    fn simd_rounding_matches_scalar() {
+        let values = [0.0, 0.5, 1.5, 2.5, 127.5, 254.5, 0.49999997, 3.2];
+        for chunk in values.chunks_exact(4) {
+            let rounded = color::round4(f32x4::from(<[f32; 4]>::try_from(chunk).unwrap()));
+            let expected = chunk.iter().map(|v| v.round() as i32).collect::<Vec<_>>();
+            assert_eq!(rounded.to_array().to_vec(), expected);
+        }
+    }
+}
@@ -0,0 +1,1901 @@
+use crate::t;
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+// バンドル（ディレクトリ）内の設定ファイル名
+pub const CONFIG_FILE_NAME: &str = "darwin.toml";
+// 解析に使えるサンプルレート (Hz)
+pub const ANALYSIS_RATES: RangeInclusive<u32> = 8_000..=192_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    // UI・ログの言語 ("en", "ja")。未指定ならシステムのロケール
+    pub language: Option<String>,
+    pub canvas: CanvasConfig,
+    pub audio: AudioConfig,
+    pub preview: PreviewConfig,
+    pub talking: TalkingConfig,
+    pub default_expression: String,
+    pub expressions: BTreeMap<String, ExpressionConfig>,
+    pub priority: PriorityConfig,
+    pub sequences: BTreeMap<String, SequenceConfig>,
+    pub rate_limit: RateLimitConfig,
+    pub slots: Vec<SlotConfig>,
+    // 共演者と口の動きだけを UDP でやり取りする
+    pub feed: Option<FeedConfig>,
+    // 表情の上に重ねるパーツ（眉など）。mappings で動かす
+    pub layers: BTreeMap<String, FrameConfig>,
+    pub mappings: Vec<MappingConfig>,
+    // 瞳のパーツをマウスの方へ寄せる
+    pub eyes: Option<EyesConfig>,
+    // キーを打っている間だけ出す手のパーツや揺れ（押した回数だけを見る）
+    pub typing: Option<TypingConfig>,
+    // 対応表に入る前に、特徴量ごとにかける範囲の制限・不感帯・平滑化
+    pub smoothing: BTreeMap<AudioFeature, SmoothingConfig>,
+    // フェイストラッキングのアプリから VMC で届くブレンドシェイプ
+    pub vmc: Option<VmcConfig>,
+    pub video: Option<VideoConfig>,
+    pub mqtt: Option<MqttConfig>,
+    pub alerts: Option<AlertsConfig>,
+    pub captions: Option<CaptionsConfig>,
+    pub emotion: Option<EmotionConfig>,
+    pub music: Option<MusicConfig>,
+    pub layer_shell: Option<LayerShellConfig>,
+    pub mascot: Option<MascotConfig>,
+    // ウィンドウ以外に同時に送る出力
+    pub outputs: Vec<OutputConfig>,
+    pub stats: Option<StatsConfig>,
+    pub talk_time: Option<TalkTimeConfig>,
+    // 決まった時刻や間隔で始めるシーケンス
+    pub schedules: Vec<ScheduleConfig>,
+    // 時刻や季節で着替える（夜はパジャマ、冬は帽子など）
+    pub outfits: Vec<OutfitConfig>,
+    // 前面のアプリに合わせて表情やパーツを変える（ゲーム中、コードを書いている等）
+    pub focus: Vec<FocusConfig>,
+    pub pomodoro: Option<PomodoroConfig>,
+    // ランダムな表情を引く（視聴者参加の演出）
+    pub gacha: Option<GachaConfig>,
+    // 外部のデータと、それを埋め込んだ文字のオーバーレイ
+    pub data: DataConfig,
+    pub texts: Vec<TextConfig>,
+    // 重ねて表示するものの見た目
+    pub themes: ThemesConfig,
+    // スマートフォンから操作するリモコン
+    pub remote: Option<RemoteConfig>,
+    // アバターを Webカメラの枠のように切り抜く
+    pub cam_frame: Option<CamFrameConfig>,
+    // アニメーションの乱数の種。指定すると書き出しやリプレイが毎回同じになる
+    pub seed: Option<u64>,
+    // 描画が重いときに見た目を落とす
+    pub quality: Option<QualityConfig>,
+    // しゃべり始めにつぶれて弾む動き
+    pub squash: Option<SquashConfig>,
+    // 状態の変化を OS の通知か読み上げで知らせる
+    pub accessibility: Option<AccessibilityConfig>,
+    // 重大なことをデスクトップ通知で知らせる
+    pub notifications: NotificationsConfig,
+    // 描画に使う GPU（ノート PC で内蔵 GPU に寄せる等）
+    pub gpu: Option<GpuConfig>,
+    // ウィンドウのタイトルとアイコン
+    pub window: WindowConfig,
+
+    // 相対パスの基準ディレクトリ（設定ファイルの場所）
+    #[serde(skip)]
+    pub base_dir: PathBuf,
+    // 読み込んだ設定ファイル（デフォルト設定なら None）
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CanvasConfig {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    // オーディオホスト名 ("ALSA", "JACK" など)。--host が優先
+    pub host: Option<String>,
+    // 音量閾値 (RMS)
+    pub threshold: f32,
+    // 解析に使うサンプルレート（入力はこのレートに変換される）
+    pub analysis_rate: u32,
+    // これ以上 threshold 未満ならささやき状態（未指定なら無効）
+    pub whisper_threshold: Option<f32>,
+    // 音量が下がってもささやき状態を保つ時間（ミリ秒）
+    pub whisper_hold_ms: u64,
+    // 音量を見る前にかけるハイパス・ローパスのカットオフ (Hz)。`darwin ctl filter` で変えられる
+    pub highpass: Option<f32>,
+    pub lowpass: Option<f32>,
+    pub monitor: MonitorConfig,
+    // ループバックに自分の声とゲーム音が混ざっているとき、マイクと照らし合わせて声だけで口を動かす
+    pub voice_reference: Option<VoiceReferenceConfig>,
+    // スピーカーから回り込んだゲーム音をループバックを参照にして消してから音量を見る
+    pub echo_cancellation: Option<EchoConfig>,
+    // 入力の代わりに解析へ流すテスト信号（--test-signal でも指定できる）
+    pub test_signal: Option<TestSignal>,
+    // 部屋の雑音に合わせて threshold を動かす（whisper_threshold は同じ比率で付いていく）
+    pub adaptive: Option<AdaptiveConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveConfig {
+    // 直近の音量のうち、この百分位を雑音の大きさとみなす
+    pub percentile: f32,
+    // 雑音を見積もる期間（秒）
+    pub window_secs: f32,
+    // 閾値を雑音よりどれだけ上に置くか (dB)
+    pub margin_db: f32,
+    // 閾値が動ける範囲 (RMS)
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        Self {
+            percentile: 20.0,
+            window_secs: 60.0,
+            margin_db: 10.0,
+            min: 0.0002,
+            max: 0.05,
+        }
+    }
+}
+
+/// Built-in signal for checking the setup without talking.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TestSignal {
+    #[default]
+    Tone,
+    Pink,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VoiceReferenceConfig {
+    // マイクのデバイス名の一部（未指定ならデフォルト入力）
+    pub input: Option<String>,
+    // マイクがこの音量 (RMS) 未満なら話していないとみなす
+    pub threshold: f32,
+    // 音量の動きの相関がこれ以上なら自分の声とみなす
+    pub correlation: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EchoConfig {
+    // 参照にする入力のデバイス名の一部（未指定ならループバックデバイス）
+    pub reference: Option<String>,
+    // スピーカーからマイクまでの反響を消す長さ（ミリ秒）
+    pub tail_ms: u64,
+    // 適応の速さ（0〜1）。大きいほど早く馴染むが、消し方が荒くなる
+    pub step: f32,
+}
+
+impl Default for EchoConfig {
+    fn default() -> Self {
+        Self {
+            reference: None,
+            tail_ms: 200,
+            step: 0.2,
+        }
+    }
+}
+
+impl Default for VoiceReferenceConfig {
+    fn default() -> Self {
+        Self {
+            input: None,
+            threshold: 0.01,
+            correlation: 0.6,
+        }
+    }
+}
+
+// 入力をそのまま出力デバイスで鳴らす（仮想ケーブル経由でも音を聞けるように）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorConfig {
+    pub enabled: bool,
+    // 出力デバイス名の一部（未指定ならデフォルト出力）
+    pub device: Option<String>,
+    pub volume: f32,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            device: None,
+            volume: 1.0,
+        }
+    }
+}
+
+// 出力を縮小表示する常に最前面のプレビューウィンドウ
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreviewConfig {
+    pub enabled: bool,
+    pub scale: f32,
+}
+
+/// An output the composed canvas is sent to besides the window; any number can run at once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum OutputConfig {
+    /// Numbered PNGs in a directory, or a video file through `ffmpeg` (WebM and MOV keep
+    /// the alpha channel).
+    Record {
+        path: PathBuf,
+        #[serde(default = "default_fps")]
+        fps: u32,
+    },
+    /// RGBA frames with alpha in a shared memory file, for a companion OBS source plugin.
+    Shm {
+        #[serde(default = "default_shm_name")]
+        name: String,
+    },
+    /// A local HTTP server streaming the frames to browsers (OBS browser sources, other
+    /// devices on the LAN). Listens on `127.0.0.1` unless `bind` says otherwise.
+    Http {
+        #[serde(default = "default_bind")]
+        bind: String,
+        #[serde(default = "default_stream_fps")]
+        fps: u32,
+        #[serde(default)]
+        format: StreamFormat,
+        /// JPEG quality, 1–100.
+        #[serde(default = "default_quality")]
+        quality: u8,
+    },
+}
+
+/// Image format of the HTTP stream.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamFormat {
+    /// Keeps the alpha channel, so the avatar stays transparent in browser sources.
+    #[default]
+    Png,
+    /// Smaller and cheaper to encode, but over a black background (MJPEG).
+    Jpeg,
+}
+
+fn default_shm_name() -> String {
+    "darwin".to_string()
+}
+
+fn default_fps() -> u32 {
+    30
+}
+
+fn default_bind() -> String {
+    "127.0.0.1:8090".to_string()
+}
+
+fn default_stream_fps() -> u32 {
+    15
+}
+
+fn default_quality() -> u8 {
+    80
+}
+
+/// A summary of each session (talk time, expression switches, peak levels) written to a
+/// local file on exit. Nothing is sent anywhere.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StatsConfig {
+    // 設定ファイルからの相対パス
+    pub dir: PathBuf,
+    pub format: StatsFormat,
+}
+
+impl Default for StatsConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("stats"),
+            format: StatsFormat::Json,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsFormat {
+    Json,
+    Csv,
+}
+
+/// Starts `sequence` at the times matching `cron` (minute hour day month weekday, as in
+/// crontab) or every `every` minutes after launch. Exactly one of the two is given.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    pub sequence: String,
+    #[serde(default)]
+    pub cron: Option<String>,
+    #[serde(default)]
+    pub every: Option<f32>,
+}
+
+/// While the local time is within `time` ("22:00..06:00") and the date within `dates`
+/// ("12-01..01-06", month-day, both ends included), switches to `profile`, shows the
+/// layers in `show` (hidden otherwise) and hides those in `hide`. Either range may wrap
+/// around; a rule without one holds all day or all year. Leaving the rule switches back to
+/// the profile Darwin was started with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutfitConfig {
+    #[serde(default)]
+    pub time: Option<String>,
+    #[serde(default)]
+    pub dates: Option<String>,
+    #[serde(default)]
+    pub profile: Option<PathBuf>,
+    #[serde(default)]
+    pub show: Vec<String>,
+    #[serde(default)]
+    pub hide: Vec<String>,
+}
+
+/// While the focused window's application name contains `app` and its title contains
+/// `title` (ignoring case; a rule may give either or both), asks for `expression` and shows
+/// or hides layers like an outfit rule. The first matching rule wins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FocusConfig {
+    #[serde(default)]
+    pub app: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub expression: Option<String>,
+    #[serde(default)]
+    pub show: Vec<String>,
+    #[serde(default)]
+    pub hide: Vec<String>,
+}
+
+/// Work and break periods repeated from launch. During a break the avatar shows
+/// `expression` and a timer; `hotkey` ends the break early.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PomodoroConfig {
+    pub work_minutes: f32,
+    pub break_minutes: f32,
+    // この数の作業ごとに長い休憩（0 なら長い休憩なし）
+    pub long_break_minutes: f32,
+    pub long_break_every: u32,
+    pub expression: Option<String>,
+    pub hotkey: Option<String>,
+    // 作業中も残り時間を出すか
+    pub show_during_work: bool,
+    // 枠の左上の位置と、棒の大きさ（ピクセル）
+    pub position: [u32; 2],
+    pub width: u32,
+    pub height: u32,
+    // 棒の残り時間の側と、過ぎた側
+    pub fill_color: [u8; 4],
+    pub empty_color: [u8; 4],
+    pub background: [u8; 4],
+    pub font: Option<PathBuf>,
+    pub size: f32,
+    pub text_color: [u8; 4],
+}
+
+/// Draws a random expression, weighted by `weights`, when `hotkey` is pressed or a
+/// trigger named `trigger` comes in (stream alerts, MQTT, the remote, `darwin ctl gacha`),
+/// and shows it for `duration_secs` before going back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GachaConfig {
+    pub hotkey: Option<String>,
+    pub trigger: String,
+    pub duration_secs: f32,
+    // 表情ごとの重み。空ならデフォルト以外のすべての表情を同じ重みで引く
+    pub weights: BTreeMap<String, f32>,
+}
+
+impl Default for GachaConfig {
+    fn default() -> Self {
+        Self {
+            hotkey: None,
+            trigger: "gacha".to_string(),
+            duration_secs: 10.0,
+            weights: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for PomodoroConfig {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25.0,
+            break_minutes: 5.0,
+            long_break_minutes: 15.0,
+            long_break_every: 4,
+            expression: None,
+            hotkey: None,
+            show_during_work: false,
+            position: [16, 48],
+            width: 160,
+            height: 10,
+            fill_color: [240, 180, 80, 255],
+            empty_color: [90, 90, 90, 200],
+            background: [0, 0, 0, 140],
+            font: None,
+            size: 18.0,
+            text_color: [255, 255, 255, 255],
+        }
+    }
+}
+
+/// Where the values of the text overlays come from. The clock is always available; the
+/// other providers are read only when enabled here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DataConfig {
+    // 再生中の曲を読み直す間隔（秒）
+    pub refresh_secs: f32,
+    pub now_playing: bool,
+    pub weather: Option<WeatherConfig>,
+}
+
+impl Default for DataConfig {
+    fn default() -> Self {
+        Self {
+            refresh_secs: 5.0,
+            now_playing: false,
+            weather: None,
+        }
+    }
+}
+
+/// Current weather at a place, from Open-Meteo (no API key needed).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WeatherConfig {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub unit: TemperatureUnit,
+    pub refresh_minutes: f32,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        Self {
+            latitude: 35.68,
+            longitude: 139.77,
+            unit: TemperatureUnit::Celsius,
+            refresh_minutes: 15.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureUnit {
+    Celsius,
+    Fahrenheit,
+}
+
+/// A line of text on the canvas. `{name}` in the template is replaced with a data value
+/// (`clock.time`, `music.title`, `weather.temperature`, ...); while one of them has no
+/// value the line is hidden.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextConfig {
+    pub template: String,
+    pub font: PathBuf,
+    // 枠の左上の位置（ピクセル）
+    pub position: [u32; 2],
+    pub size: f32,
+    pub color: [u8; 4],
+    pub background: [u8; 4],
+}
+
+impl Default for TextConfig {
+    fn default() -> Self {
+        Self {
+            template: String::new(),
+            font: PathBuf::new(),
+            position: [16, 16],
+            size: 24.0,
+            color: [255, 255, 255, 255],
+            background: [0, 0, 0, 0],
+        }
+    }
+}
+
+/// A web page with big buttons for the expressions, the sequences and mute, served on
+/// `bind` for a phone on the same network. `qr_hotkey` shows its address as a QR code.
+/// Every request must carry `token`; without one a new token is made at each launch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    pub bind: String,
+    pub qr_hotkey: Option<String>,
+    pub token: Option<String>,
+    // 指定すると HTTPS で待ち受ける
+    pub tls: Option<TlsConfig>,
+    // 視聴者のリアクションを受け付ける入口（/viewer/）
+    pub viewers: Option<ViewerConfig>,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            bind: "0.0.0.0:8091".to_string(),
+            qr_hotkey: Some("KeyQ".to_string()),
+            token: None,
+            tls: None,
+            viewers: None,
+        }
+    }
+}
+
+/// Reactions viewers may trigger through a bot bridge, apart from the full control API:
+/// only the `reactions` sequences, at most once per `user_cooldown` seconds per viewer and
+/// `per_minute` in total. Requests need `token` when one is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewerConfig {
+    pub token: Option<String>,
+    pub reactions: Vec<String>,
+    pub user_cooldown: f32,
+    pub per_minute: u32,
+    // 起動したときに受け付けるか（リモコンで切り替えられる）
+    pub enabled: bool,
+    pub blocked: Vec<String>,
+}
+
+impl Default for ViewerConfig {
+    fn default() -> Self {
+        Self {
+            token: None,
+            reactions: Vec::new(),
+            user_cooldown: 60.0,
+            per_minute: 4,
+            enabled: true,
+            blocked: Vec::new(),
+        }
+    }
+}
+
+/// PEM files of the certificate chain and its private key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
+/// Shows the avatar cut out to a rounded box or a circle with a border, like a webcam
+/// frame, over `background`. The overlays are drawn outside the frame as usual.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CamFrameConfig {
+    pub shape: CamShape,
+    // 角の丸み（ピクセル、円では使わない）
+    pub radius: f32,
+    // キャンバスの端から枠までの間（ピクセル）
+    pub margin: u32,
+    // 縁取りの太さ（ピクセル）と色
+    pub border: u32,
+    pub border_color: [u8; 4],
+    // 枠の内側、アバターの後ろを塗る色
+    pub background: [u8; 4],
+}
+
+impl Default for CamFrameConfig {
+    fn default() -> Self {
+        Self {
+            shape: CamShape::default(),
+            radius: 24.0,
+            margin: 8,
+            border: 4,
+            border_color: [255, 255, 255, 255],
+            background: [0, 0, 0, 0],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CamShape {
+    /// A box with rounded corners.
+    #[default]
+    Rounded,
+    /// The largest circle that fits, centered.
+    Circle,
+}
+
+/// Squashes the avatar (shorter and wider by `intensity`) when speech starts and lets it
+/// spring back over `duration_ms`, the bounce dying out along `easing`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SquashConfig {
+    pub intensity: f32,
+    pub duration_ms: u64,
+    pub easing: Easing,
+    // 下端を動かさない（足元を固定する）
+    pub anchor_bottom: bool,
+}
+
+impl Default for SquashConfig {
+    fn default() -> Self {
+        Self {
+            intensity: 0.12,
+            duration_ms: 350,
+            easing: Easing::EaseOut,
+            anchor_bottom: true,
+        }
+    }
+}
+
+/// Announces mute, profile and theme switches and a lost or returning input device
+/// through the OS, for streamers who can't see the window.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessibilityConfig {
+    pub announce: AnnounceMethod,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnounceMethod {
+    /// A desktop notification, which screen readers also read out.
+    #[default]
+    Notification,
+    /// Spoken with the system's text-to-speech.
+    Speech,
+}
+
+/// Desktop notifications for events that need attention: the input device going away, a
+/// config that could not be opened, or less than `min_free_mb` left where a recording goes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationsConfig {
+    pub enabled: bool,
+    pub min_free_mb: u64,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_free_mb: 1024,
+        }
+    }
+}
+
+/// The main window's title and icon (an image such as a thumbnail of the avatar), to tell
+/// several instances apart in OBS's window capture list and the task switcher.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WindowConfig {
+    pub title: Option<String>,
+    pub icon: Option<PathBuf>,
+}
+
+/// Which GPU draws the windows. `adapter` is part of an adapter's name as listed by `darwin
+/// gpus` and wins over `power_preference`; without either, wgpu picks (or the
+/// `WGPU_ADAPTER_NAME` / `WGPU_POWER_PREF` environment variables). `backends` are tried in
+/// order before letting wgpu choose, to get around driver bugs in one of them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GpuConfig {
+    pub power_preference: Option<PowerPreference>,
+    pub adapter: Option<String>,
+    // 順に試すグラフィックス API（--gfx-backend が優先）
+    pub backends: Vec<GfxBackend>,
+}
+
+/// A graphics API wgpu can draw through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GfxBackend {
+    Vulkan,
+    Metal,
+    Dx12,
+    /// OpenGL (or OpenGL ES), for old drivers.
+    Gl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerPreference {
+    /// Usually the integrated GPU, leaving the discrete one to the game.
+    LowPower,
+    /// Usually the discrete GPU.
+    HighPerformance,
+}
+
+/// How an animation moves from start to end: a name such as `"cubic_out"`, or
+/// `{ bezier = [x1, y1, x2, y2] }` with control points as in CSS `cubic-bezier()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Starts slowly (quadratic).
+    EaseIn,
+    /// Ends slowly (quadratic).
+    EaseOut,
+    /// Starts and ends slowly (smoothstep).
+    EaseInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    /// Overshoots and wobbles before settling.
+    Elastic,
+    /// Bounces to a stop like a dropped ball.
+    Bounce,
+    Bezier([f32; 4]),
+}
+
+/// Lowers the drawing quality (fewer particles, then no tint) while composing a frame
+/// takes longer than `budget_ms` on average for `degrade_after` seconds, and raises it
+/// again once it has stayed under `budget_ms × recover_ratio` for `recover_after` seconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QualityConfig {
+    pub budget_ms: f32,
+    pub degrade_after: f32,
+    pub recover_after: f32,
+    pub recover_ratio: f32,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            budget_ms: 12.0,
+            degrade_after: 2.0,
+            recover_after: 10.0,
+            recover_ratio: 0.5,
+        }
+    }
+}
+
+/// Theme files (TOML with `background`, `text`, `accent`, `track`, `font` and `radius`)
+/// laid over the overlays' own colors. The first is used at launch; `hotkey` moves on to
+/// the next.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemesConfig {
+    pub files: Vec<PathBuf>,
+    pub hotkey: Option<String>,
+}
+
+/// Which input wins when several ask for an expression at once. Sources missing from
+/// `order` are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriorityConfig {
+    // 優先度の高い順
+    pub order: Vec<ExpressionSource>,
+    // 入力ごとに、要求してからこの秒数で取り下げる（未指定なら取り下げない）
+    pub timeout: BTreeMap<ExpressionSource, f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpressionSource {
+    /// A running sequence (hotkeys, stream alerts, MQTT and control socket triggers).
+    Sequence,
+    /// The control socket, the Stream Deck, and other instances of the same profile.
+    Manual,
+    /// The expression drawn by the gacha.
+    Gacha,
+    /// Timers (pomodoro breaks).
+    Timer,
+    /// Rules for the focused application.
+    Focus,
+    /// MQTT triggers.
+    Remote,
+    /// The expression estimated from the voice.
+    Audio,
+}
+
+impl ExpressionSource {
+    /// The name used in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sequence => "sequence",
+            Self::Manual => "manual",
+            Self::Gacha => "gacha",
+            Self::Timer => "timer",
+            Self::Focus => "focus",
+            Self::Remote => "remote",
+            Self::Audio => "audio",
+        }
+    }
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        Self {
+            order: vec![
+                ExpressionSource::Sequence,
+                ExpressionSource::Manual,
+                ExpressionSource::Gacha,
+                ExpressionSource::Timer,
+                ExpressionSource::Focus,
+                ExpressionSource::Remote,
+                ExpressionSource::Audio,
+            ],
+            timeout: BTreeMap::new(),
+        }
+    }
+}
+
+/// Limits on sequences started by outside services (stream alerts, MQTT), so a flood of
+/// triggers cannot flip the avatar between expressions. Accepted triggers wait in a queue
+/// and play one after another.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    // 同じシーケンスを再び受け付けるまでの秒数
+    pub cooldown: f32,
+    // シーケンスごとの cooldown（未指定なら上の値）
+    pub cooldowns: BTreeMap<String, f32>,
+    // 1分間に始めるシーケンスの上限（未指定なら無制限）
+    pub per_minute: Option<u32>,
+    // 再生を待たせておける数。あふれた分は捨てる
+    pub queue: usize,
+    // 再生中のものと待っているものをすべて取りやめるキー（winit の KeyCode 名）
+    pub skip_hotkey: Option<String>,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            cooldown: 0.0,
+            cooldowns: BTreeMap::new(),
+            per_minute: None,
+            queue: 10,
+            skip_hotkey: None,
+        }
+    }
+}
+
+// 発話フレームが複数あるときの切り替え方
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TalkingConfig {
+    pub mode: TalkingMode,
+    // 1フレームを表示し続ける最短時間（ミリ秒）
+    pub min_frame_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TalkingMode {
+    /// Talking frames in order, wrapping around.
+    Cycle,
+    /// A random talking frame, never the same one twice in a row.
+    #[default]
+    Random,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExpressionConfig {
+    pub idle: Vec<FrameConfig>,
+    // 小声のときの半開きの口（なければ待機フレーム）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub whisper: Vec<FrameConfig>,
+    pub talking: Vec<FrameConfig>,
+    // 待機中にときどき出す目を閉じたフレーム（なければまばたきしない）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub blink: Vec<FrameConfig>,
+}
+
+impl ExpressionConfig {
+    /// Frame lists by state name, in display order.
+    pub fn states(&self) -> [(&'static str, &Vec<FrameConfig>); 4] {
+        [
+            ("idle", &self.idle),
+            ("whisper", &self.whisper),
+            ("talking", &self.talking),
+            ("blink", &self.blink),
+        ]
+    }
+
+    pub fn state(&self, state: &str) -> Option<&Vec<FrameConfig>> {
+        self.states()
+            .into_iter()
+            .find(|(name, _)| *name == state)
+            .map(|(_, frames)| frames)
+    }
+
+    pub fn state_mut(&mut self, state: &str) -> Option<&mut Vec<FrameConfig>> {
+        match state {
+            "idle" => Some(&mut self.idle),
+            "whisper" => Some(&mut self.whisper),
+            "talking" => Some(&mut self.talking),
+            "blink" => Some(&mut self.blink),
+            _ => None,
+        }
+    }
+}
+
+/// A single image frame. Written either as a plain path or as
+/// `{ path = "...", offset = [x, y], scale = 1.0 }`. A layer may add `clip = "<layer>"` to
+/// show only where that layer is opaque, like a clipping group in an art program, and
+/// `slice = [top, right, bottom, left]` to stretch a border image to the canvas as a 9-slice.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "FrameRepr", into = "FrameRepr")]
+pub struct FrameConfig {
+    pub path: PathBuf,
+    // キャンバス上のずれ補正（ピクセル）
+    pub offset: [i32; 2],
+    // キャンバス中心を基準にした拡大率
+    pub scale: f32,
+    // このレイヤーを切り抜く元のレイヤー（layers の中だけで使う）
+    pub clip: Option<String>,
+    // 9スライスの枠の太さ（上・右・下・左、元画像のピクセル）。layers の中だけで使う
+    pub slice: Option<[u32; 4]>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum FrameRepr {
+    Path(PathBuf),
+    Detailed {
+        path: PathBuf,
+        #[serde(default)]
+        offset: [i32; 2],
+        #[serde(default = "default_scale")]
+        scale: f32,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        clip: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        slice: Option<[u32; 4]>,
+    },
+}
+
+fn default_scale() -> f32 {
+    1.0
+}
+
+impl From<FrameRepr> for FrameConfig {
+    fn from(repr: FrameRepr) -> Self {
+        match repr {
+            FrameRepr::Path(path) => Self::new(path),
+            FrameRepr::Detailed {
+                path,
+                offset,
+                scale,
+                clip,
+                slice,
+            } => Self {
+                path,
+                offset,
+                scale,
+                clip,
+                slice,
+            },
+        }
+    }
+}
+
+impl From<FrameConfig> for FrameRepr {
+    fn from(frame: FrameConfig) -> Self {
+        if frame.is_plain() {
+            FrameRepr::Path(frame.path)
+        } else {
+            FrameRepr::Detailed {
+                path: frame.path,
+                offset: frame.offset,
+                scale: frame.scale,
+                clip: frame.clip,
+                slice: frame.slice,
+            }
+        }
+    }
+}
+
+impl FrameConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            offset: [0, 0],
+            scale: 1.0,
+            clip: None,
+            slice: None,
+        }
+    }
+
+    // オフセットも拡大率も切り抜きも9スライスもなければパスだけで書ける
+    fn is_plain(&self) -> bool {
+        self.offset == [0, 0] && self.scale == 1.0 && self.clip.is_none() && self.slice.is_none()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let mut expressions = BTreeMap::new();
+        expressions.insert(
+            "default".to_string(),
+            ExpressionConfig {
+                idle: vec![FrameConfig::new("image1.jpg")],
+                talking: vec![FrameConfig::new("image2.jpg")],
+                ..Default::default()
+            },
+        );
+
+        Self {
+            language: None,
+            canvas: CanvasConfig::default(),
+            audio: AudioConfig::default(),
+            preview: PreviewConfig::default(),
+            talking: TalkingConfig::default(),
+            default_expression: "default".to_string(),
+            expressions,
+            sequences: BTreeMap::new(),
+            priority: PriorityConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            slots: Vec::new(),
+            feed: None,
+            layers: BTreeMap::new(),
+            mappings: Vec::new(),
+            eyes: None,
+            typing: None,
+            smoothing: BTreeMap::new(),
+            vmc: None,
+            video: None,
+            mqtt: None,
+            alerts: None,
+            captions: None,
+            emotion: None,
+            music: None,
+            layer_shell: None,
+            mascot: None,
+            outputs: Vec::new(),
+            stats: None,
+            talk_time: None,
+            schedules: Vec::new(),
+            outfits: Vec::new(),
+            focus: Vec::new(),
+            pomodoro: None,
+            gacha: None,
+            data: DataConfig::default(),
+            texts: Vec::new(),
+            themes: ThemesConfig::default(),
+            remote: None,
+            cam_frame: None,
+            seed: None,
+            quality: None,
+            squash: None,
+            accessibility: None,
+            notifications: NotificationsConfig::default(),
+            gpu: None,
+            window: WindowConfig::default(),
+            base_dir: PathBuf::from("."),
+            source: None,
+        }
+    }
+}
+
+impl Default for CanvasConfig {
+    fn default() -> Self {
+        Self {
+            width: 1664,
+            height: 1080,
+        }
+    }
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            host: None,
+            threshold: 0.001,
+            analysis_rate: 48_000,
+            whisper_threshold: None,
+            whisper_hold_ms: 150,
+            highpass: None,
+            lowpass: None,
+            monitor: MonitorConfig::default(),
+            voice_reference: None,
+            echo_cancellation: None,
+            test_signal: None,
+            adaptive: None,
+        }
+    }
+}
+
+/// Additional avatar drawn into a region of the canvas on top of the main one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlotConfig {
+    // [x, y, 幅, 高さ]（キャンバス上のピクセル）
+    pub rect: [i32; 4],
+    pub expression: String,
+    // 入力デバイス名の一部（未指定ならメインと同じ音声に反応）
+    #[serde(default)]
+    pub input: Option<String>,
+    // 共演者から届く口の動きの名前（feed.listen で受け取る）。input の代わりに使う
+    #[serde(default)]
+    pub feed: Option<String>,
+}
+
+/// A level feed: the mouth state and level of a co-host's Darwin (never the audio itself),
+/// sent over UDP so it can animate a slot in the host's scene. `send` sends this instance's
+/// mouth as `name`; `listen` receives feeds for slots with `feed` set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeedConfig {
+    // 送り先（"host:port"）
+    pub send: Option<String>,
+    pub name: String,
+    // 受け取るアドレス（"0.0.0.0:9010"）
+    pub listen: Option<String>,
+    // 1秒に送る回数
+    pub rate: f32,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            send: None,
+            name: "cohost".to_string(),
+            listen: None,
+            rate: 30.0,
+        }
+    }
+}
+
+/// Green-screen video (file or capture device) used instead of the image frames.
+/// Decoded by an external `ffmpeg`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VideoConfig {
+    // 動画ファイル（設定ファイルからの相対パス）またはデバイス名
+    pub input: String,
+    // ffmpeg の入力形式 ("v4l2", "dshow", "avfoundation")。指定するとデバイスとして扱う
+    pub format: Option<String>,
+    #[serde(rename = "loop")]
+    pub looped: bool,
+    pub key_color: [u8; 3],
+    // キー色からの距離 (0.0〜1.0) がこれ以下なら透明
+    pub tolerance: f32,
+    // 透明から不透明までのフェード幅
+    pub softness: f32,
+    // 動画を表示する状態。それ以外の状態では通常の画像を使う
+    pub states: Vec<String>,
+}
+
+impl Default for VideoConfig {
+    fn default() -> Self {
+        Self {
+            input: String::new(),
+            format: None,
+            looped: true,
+            key_color: [0, 255, 0],
+            tolerance: 0.3,
+            softness: 0.1,
+            states: vec!["talking".to_string()],
+        }
+    }
+}
+
+/// MQTT broker to take triggers from (e.g. a doorbell) and to publish state changes to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    pub host: String,
+    pub port: u16,
+    pub client_id: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // 表情と口の状態を JSON で publish するトピック（未指定なら publish しない）
+    pub state_topic: Option<String>,
+    pub triggers: Vec<MqttTrigger>,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 1883,
+            client_id: "darwin".to_string(),
+            username: None,
+            password: None,
+            state_topic: None,
+            triggers: Vec::new(),
+        }
+    }
+}
+
+/// A message on `topic` (with exactly `payload`, if given) selects an expression and/or
+/// starts a sequence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MqttTrigger {
+    pub topic: String,
+    #[serde(default)]
+    pub payload: Option<String>,
+    #[serde(default)]
+    pub expression: Option<String>,
+    #[serde(default)]
+    pub sequence: Option<String>,
+}
+
+/// One route of the modulation matrix: `feature` is normalized over `input`, shaped by
+/// `curve`, scaled onto `output` and smoothed. Avatar parameters are `offset_x`, `offset_y`,
+/// `scale`, `rotation`, `tint` (with `color`) and `mouth` (replaces the level thresholds);
+/// layer parameters are `layer.<name>.<offset_x|offset_y|scale|rotation|opacity|visible>`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MappingConfig {
+    pub feature: AudioFeature,
+    pub parameter: String,
+    pub input: [f32; 2],
+    pub output: [f32; 2],
+    #[serde(default)]
+    pub curve: Curve,
+    // 出力の追従の時定数（ミリ秒、0 で即時）
+    #[serde(default)]
+    pub smoothing_ms: u64,
+    // tint で近づける色
+    #[serde(default)]
+    pub color: Option<[u8; 3]>,
+}
+
+/// Blendshapes received over the VMC protocol (OSC over UDP) from face-tracking apps such
+/// as iFacialMocap or VSeeFace. They become the `mouth_open` and `blink` mapping features
+/// (the largest of the listed blendshapes, matched ignoring case), and while they arrive
+/// the blink frame follows the tracked eyes instead of blinking at random.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VmcConfig {
+    pub listen: String,
+    pub mouth_open: Vec<String>,
+    pub blink: Vec<String>,
+    // この値を超えたら目を閉じたフレームにする
+    pub blink_threshold: f32,
+}
+
+impl Default for VmcConfig {
+    fn default() -> Self {
+        // VRM 0.x・VRM 1.0・ARKit の名前
+        let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        Self {
+            listen: "0.0.0.0:39539".to_string(),
+            mouth_open: names(&["A", "aa", "jawOpen"]),
+            blink: names(&[
+                "Blink",
+                "Blink_L",
+                "Blink_R",
+                "blinkLeft",
+                "blinkRight",
+                "eyeBlinkLeft",
+                "eyeBlinkRight",
+            ]),
+            blink_threshold: 0.5,
+        }
+    }
+}
+
+/// Moves the pupil `layers` toward the system mouse cursor, at most `range` canvas pixels
+/// (x, y). The cursor moves them all the way once it is `reach` screen pixels away from the
+/// window's center.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EyesConfig {
+    pub layers: Vec<String>,
+    pub range: [f32; 2],
+    pub reach: f32,
+    pub smoothing_ms: u64,
+}
+
+impl Default for EyesConfig {
+    fn default() -> Self {
+        Self {
+            layers: Vec::new(),
+            range: [6.0, 4.0],
+            reach: 600.0,
+            smoothing_ms: 80,
+        }
+    }
+}
+
+/// Reacts to typing on any keyboard: `layers` (typing hands, say) show only within `hold_ms`
+/// of the last key press, and each press bumps the whole avatar down by `shake` canvas
+/// pixels. Only the number of presses is read, never which keys.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TypingConfig {
+    pub layers: Vec<String>,
+    pub shake: f32,
+    pub hold_ms: u64,
+}
+
+impl Default for TypingConfig {
+    fn default() -> Self {
+        Self {
+            layers: Vec::new(),
+            shake: 2.0,
+            hold_ms: 400,
+        }
+    }
+}
+
+/// Conditioning of one feature before any mapping sees it, whatever its source (audio
+/// analysis or tracking): clamped to `min`..`max`, changes smaller than `deadzone` ignored,
+/// then smoothed with a time constant of `smoothing_ms`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SmoothingConfig {
+    pub smoothing_ms: u64,
+    pub min: Option<f32>,
+    pub max: Option<f32>,
+    pub deadzone: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFeature {
+    // 入力の RMS
+    Level,
+    // 帯域ごとの RMS（〜250Hz, 250Hz〜2kHz, 2kHz〜）
+    Low,
+    Mid,
+    High,
+    // 声の高さ (Hz)。話していないときは 0
+    Pitch,
+    // 発話らしさ (0〜1)
+    Vad,
+    // 顔のトラッキング（VMC）の口の開きとまばたき (0〜1)。届いていなければ 0
+    #[serde(rename = "mouth_open")]
+    MouthOpen,
+    Blink,
+}
+
+impl AudioFeature {
+    /// The name used in the config file.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Level => "level",
+            Self::Low => "low",
+            Self::Mid => "mid",
+            Self::High => "high",
+            Self::Pitch => "pitch",
+            Self::Vad => "vad",
+            Self::MouthOpen => "mouth_open",
+            Self::Blink => "blink",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Curve {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    Smooth,
+    // 半分を超えたら出力の最大
+    Step,
+}
+
+/// Picks expressions from the tone of voice. Manual selections (hotkeys, Stream Deck, `darwin
+/// ctl`, MQTT) take priority, and sequences take priority over both.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EmotionConfig {
+    // 感情ごとの表情（未指定の感情ではデフォルトのまま）
+    pub calm: Option<String>,
+    pub excited: Option<String>,
+    pub angry: Option<String>,
+    // 判定に使う音声の長さ（ミリ秒）
+    pub window_ms: u64,
+    // 黙ってから推定を外すまでの時間（ミリ秒）
+    pub hold_ms: u64,
+    // audio.threshold の何倍の音量から興奮・怒りとみなすか
+    pub excited_level: f32,
+    pub angry_level: f32,
+    // 音量の揺れ（標準偏差 / 平均）がこれ以上なら興奮、未満で大きな声なら怒り
+    pub excited_variability: f32,
+    // 怒りとみなす声の明るさ（1秒あたりのゼロ交差数）
+    pub angry_brightness: f32,
+}
+
+impl Default for EmotionConfig {
+    fn default() -> Self {
+        Self {
+            calm: None,
+            excited: None,
+            angry: None,
+            window_ms: 1500,
+            hold_ms: 3000,
+            excited_level: 2.5,
+            angry_level: 4.0,
+            excited_variability: 0.5,
+            angry_brightness: 1800.0,
+        }
+    }
+}
+
+/// Music-reactive mode: the avatar bops on the beat of the captured audio (usually a loopback
+/// device) instead of following its raw level.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MusicConfig {
+    pub action: BeatAction,
+    // 立ち上がりの閾値（直近の平均から標準偏差の何倍か）。小さいほど敏感
+    pub sensitivity: f32,
+    // 推定するテンポの範囲
+    pub min_bpm: f32,
+    pub max_bpm: f32,
+    // pulse で拍ごとに大きくする割合と、戻るまでの時間（ミリ秒）
+    pub pulse: f32,
+    pub pulse_ms: u64,
+    // 大きくなった状態から戻る進み方
+    pub pulse_easing: Easing,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BeatAction {
+    /// Briefly scale the avatar up on each beat.
+    #[default]
+    Pulse,
+    /// Show the next talking frame on each beat.
+    Advance,
+}
+
+impl Default for MusicConfig {
+    fn default() -> Self {
+        Self {
+            action: BeatAction::default(),
+            sensitivity: 1.5,
+            min_bpm: 70.0,
+            max_bpm: 180.0,
+            pulse: 0.06,
+            pulse_ms: 150,
+            pulse_easing: Easing::EaseOut,
+        }
+    }
+}
+
+/// Shows the canvas as a wlr-layer-shell surface on Linux/Wayland, like a desktop mascot:
+/// no decorations, above (or below) other windows, and optionally letting clicks through.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayerShellConfig {
+    pub layer: ShellLayer,
+    // 画面のどこに寄せるか
+    pub anchor: ShellAnchor,
+    // 画面の端からの距離（ピクセル）
+    pub margin: i32,
+    // クリックを下のウィンドウに通す
+    pub passthrough: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShellLayer {
+    Background,
+    Bottom,
+    Top,
+    /// Above everything, including fullscreen windows.
+    #[default]
+    Overlay,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShellAnchor {
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl Default for LayerShellConfig {
+    fn default() -> Self {
+        Self {
+            layer: ShellLayer::default(),
+            anchor: ShellAnchor::default(),
+            margin: 0,
+            passthrough: true,
+        }
+    }
+}
+
+/// Desktop mascot mode: the window becomes a borderless, always-on-top sprite that sits on
+/// a screen edge and wanders along it between rests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MascotConfig {
+    pub edge: MascotEdge,
+    // 画面の端からの距離（タスクバーの高さなど、ピクセル）
+    pub margin: u32,
+    // false ならその場に座ったまま
+    pub wander: bool,
+    // 歩く速さ（ピクセル/秒）と、歩いている間に跳ねる高さ（ピクセル）
+    pub speed: f32,
+    pub hop: f32,
+    // 一歩の中で跳ね上がる進み方
+    pub hop_easing: Easing,
+    // 次に歩き出すまでの休憩（秒）。この範囲からランダムに選ぶ
+    pub min_rest: f32,
+    pub max_rest: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MascotEdge {
+    Top,
+    #[default]
+    Bottom,
+}
+
+impl Default for MascotConfig {
+    fn default() -> Self {
+        Self {
+            edge: MascotEdge::default(),
+            margin: 48,
+            wander: true,
+            speed: 80.0,
+            hop: 6.0,
+            hop_easing: Easing::SineOut,
+            min_rest: 4.0,
+            max_rest: 12.0,
+        }
+    }
+}
+
+/// Live captions of the microphone, transcribed locally with whisper.cpp (`stt` feature) and
+/// drawn at the bottom of the canvas.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptionsConfig {
+    // whisper.cpp の ggml モデル
+    pub model: PathBuf,
+    // "ja" など。未指定なら自動判定
+    pub language: Option<String>,
+    pub font: PathBuf,
+    // 文字の高さ（ピクセル）
+    pub size: f32,
+    pub color: [u8; 4],
+    pub outline_color: [u8; 4],
+    pub outline_width: u32,
+    pub max_lines: usize,
+    // キャンバスの下端からの距離（ピクセル）
+    pub margin: u32,
+    // 最後に話してから消え始めるまでの秒数と、消えるのにかける秒数
+    pub hold: f32,
+    pub fade: f32,
+    pub fade_easing: Easing,
+}
+
+impl Default for CaptionsConfig {
+    fn default() -> Self {
+        Self {
+            model: PathBuf::new(),
+            language: None,
+            font: PathBuf::new(),
+            size: 36.0,
+            color: [255, 255, 255, 255],
+            outline_color: [0, 0, 0, 255],
+            outline_width: 3,
+            max_lines: 2,
+            margin: 24,
+            hold: 4.0,
+            fade: 0.5,
+            fade_easing: Easing::Linear,
+        }
+    }
+}
+
+/// On-canvas panel with the talk time so far and its share of the session, for co-hosts
+/// keeping an eye on airtime balance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TalkTimeConfig {
+    // 起動時に出しておくか（hotkey で切り替えられる）
+    pub visible: bool,
+    pub hotkey: Option<String>,
+    // 枠の左上の位置と、棒の大きさ（ピクセル）
+    pub position: [u32; 2],
+    pub width: u32,
+    pub height: u32,
+    pub talk_color: [u8; 4],
+    pub silence_color: [u8; 4],
+    pub background: [u8; 4],
+    // 時間と割合を文字でも出すときのフォント
+    pub font: Option<PathBuf>,
+    pub size: f32,
+    pub text_color: [u8; 4],
+}
+
+impl Default for TalkTimeConfig {
+    fn default() -> Self {
+        Self {
+            visible: true,
+            hotkey: None,
+            position: [16, 16],
+            width: 160,
+            height: 10,
+            talk_color: [90, 200, 120, 255],
+            silence_color: [90, 90, 90, 200],
+            background: [0, 0, 0, 140],
+            font: None,
+            size: 18.0,
+            text_color: [255, 255, 255, 255],
+        }
+    }
+}
+
+/// Follower and subscriber alerts, each starting a sequence. Twitch is read directly from
+/// EventSub; Streamlabs also relays YouTube subscribers and members.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertsConfig {
+    pub source: AlertSource,
+    // Twitch はユーザーアクセストークン、Streamlabs はソケット API トークン
+    pub token: String,
+    // Twitch のみ（アプリの Client ID）
+    #[serde(default)]
+    pub client_id: Option<String>,
+    // 新しいフォロワー（YouTube は登録者）で再生するシーケンス
+    #[serde(default)]
+    pub follow: Option<String>,
+    // 新しいサブスク（YouTube はメンバー）で再生するシーケンス
+    #[serde(default)]
+    pub subscribe: Option<String>,
+    // レイドを受けたときに再生するシーケンス
+    #[serde(default)]
+    pub raid: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertSource {
+    Twitch,
+    Streamlabs,
+}
+
+/// Named timeline (e.g. a "rage quit" emote) triggered by a hotkey.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SequenceConfig {
+    // winit の KeyCode 名 ("Digit1", "F5", "KeyQ" など)
+    pub hotkey: Option<String>,
+    pub keyframes: Vec<KeyframeConfig>,
+}
+
+/// Values not given in a keyframe carry over from the previous one; offset, scale and
+/// rotation move towards the next keyframe along `easing` (linear unless set), expression
+/// switches and sounds fire when the keyframe is reached.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyframeConfig {
+    // シーケンス開始からの秒数
+    pub time: f32,
+    pub expression: Option<String>,
+    pub offset: Option<[f32; 2]>,
+    pub scale: Option<f32>,
+    // 時計回りの角度（度）
+    pub rotation: Option<f32>,
+    // 次のキーフレームへの進み方
+    pub easing: Option<Easing>,
+    pub sound: Option<PathBuf>,
+    pub particles: Option<ParticlesConfig>,
+}
+
+/// A burst of sprites thrown up from the bottom of the canvas, falling back under gravity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticlesConfig {
+    pub image: PathBuf,
+    pub count: u32,
+    // 画像の長辺のピクセル数
+    pub size: u32,
+    // 1粒が消えるまでの秒数
+    pub lifetime: f32,
+}
+
+impl Default for ParticlesConfig {
+    fn default() -> Self {
+        Self {
+            image: PathBuf::new(),
+            count: 30,
+            size: 48,
+            lifetime: 2.5,
+        }
+    }
+}
+
+impl Default for TalkingConfig {
+    fn default() -> Self {
+        Self {
+            mode: TalkingMode::default(),
+            min_frame_ms: 90,
+        }
+    }
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scale: 0.25,
+        }
+    }
+}
+
+impl Config {
+    /// Loads a config file, or `darwin.toml` inside it when `path` is a bundle directory.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = config_file_path(path);
+        let text = std::fs::read_to_string(&file)
+            .with_context(|| t!("config.read_failed", file.display()))?;
+        let mut config: Config =
+            toml::from_str(&text).with_context(|| t!("config.parse_failed", file.display()))?;
+        // 解析のレートで時間を割り出すので、0 などは読み込む時点で断る
+        if !ANALYSIS_RATES.contains(&config.audio.analysis_rate) {
+            bail!(t!(
+                "config.bad_analysis_rate",
+                config.audio.analysis_rate,
+                file.display()
+            ));
+        }
+        // 起動したディレクトリによらないよう、設定ファイルの場所は絶対パスで持つ
+        let file = std::path::absolute(&file)?;
+        config.base_dir = file
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        config.source = Some(file);
+        Ok(config)
+    }
+
+    // 設定ファイルからの相対パスを解決
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.base_dir.join(path)
+        }
+    }
+
+    pub fn frame(&self, expression: &str, state: &str, index: usize) -> Option<&FrameConfig> {
+        self.expressions.get(expression)?.state(state)?.get(index)
+    }
+
+    pub fn frame_mut(
+        &mut self,
+        expression: &str,
+        state: &str,
+        index: usize,
+    ) -> Option<&mut FrameConfig> {
+        self.expressions
+            .get_mut(expression)?
+            .state_mut(state)?
+            .get_mut(index)
+    }
+
+    /// Writes the expression frame lists back to the config file, keeping the rest of the file
+    /// (and comments) untouched. Without a source file, the whole config is written to `darwin.toml`.
+    pub fn save(&self) -> Result<PathBuf> {
+        self.edit(|doc| {
+            for (name, expression) in &self.expressions {
+                // 新しい表情は [expressions.<名前>] のテーブルとして末尾に追加
+                if doc.get("expressions").is_none() {
+                    let mut table = toml_edit::Table::new();
+                    table.set_implicit(true);
+                    doc["expressions"] = toml_edit::Item::Table(table);
+                }
+                if doc["expressions"].get(name.as_str()).is_none() {
+                    doc["expressions"][name.as_str()] = toml_edit::table();
+                }
+                for (state, frames) in expression.states() {
+                    // 使っていないささやき・まばたきフレームのキーは増やさない
+                    let existing = doc
+                        .get("expressions")
+                        .and_then(|e| e.get(name.as_str()))
+                        .and_then(|e| e.get(state));
+                    if frames.is_empty()
+                        && matches!(state, "whisper" | "blink")
+                        && existing.is_none()
+                    {
+                        continue;
+                    }
+                    let array: toml_edit::Array = frames.iter().map(frame_value).collect();
+                    doc["expressions"][name.as_str()][state] = toml_edit::value(array);
+                }
+            }
+        })
+    }
+
+    /// Writes the audio thresholds back to the config file, like [`Config::save`].
+    pub fn save_thresholds(&self) -> Result<PathBuf> {
+        // f32 のままだと 0.0010000000474974513 のような値になるので丸める
+        let round = |value: f32| (value as f64 * 1e6).round() / 1e6;
+        self.edit(|doc| {
+            if doc.get("audio").is_none() {
+                doc["audio"] = toml_edit::table();
+            }
+            let audio = &mut doc["audio"];
+            audio["threshold"] = toml_edit::value(round(self.audio.threshold));
+            match self.audio.whisper_threshold {
+                Some(whisper) => audio["whisper_threshold"] = toml_edit::value(round(whisper)),
+                None => {
+                    if let Some(table) = audio.as_table_like_mut() {
+                        table.remove("whisper_threshold");
+                    }
+                }
+            }
+            audio["whisper_hold_ms"] = toml_edit::value(self.audio.whisper_hold_ms as i64);
+        })
+    }
+
+    /// Changes part of the config file and leaves the rest (and comments) as it is. Without a
+    /// source file, the whole config is written to `darwin.toml` with the change applied.
+    pub fn edit(&self, change: impl FnOnce(&mut toml_edit::DocumentMut)) -> Result<PathBuf> {
+        let Some(file) = &self.source else {
+            let file = self.base_dir.join(CONFIG_FILE_NAME);
+            let mut doc: toml_edit::DocumentMut = toml::to_string_pretty(self)?.parse()?;
+            change(&mut doc);
+            std::fs::write(&file, doc.to_string())?;
+            return Ok(file);
+        };
+
+        let text = std::fs::read_to_string(file)
+            .with_context(|| t!("config.read_failed", file.display()))?;
+        let mut doc: toml_edit::DocumentMut = text
+            .parse()
+            .with_context(|| t!("config.parse_failed", file.display()))?;
+        change(&mut doc);
+        std::fs::write(file, doc.to_string())?;
+        Ok(file.clone())
+    }
+}
+
+fn frame_value(frame: &FrameConfig) -> toml_edit::Value {
+    let path = frame.path.to_string_lossy().to_string();
+    if frame.is_plain() {
+        return path.into();
+    }
+    let mut table = toml_edit::InlineTable::new();
+    table.insert("path", path.into());
+    if frame.offset != [0, 0] {
+        table.insert(
+            "offset",
+            toml_edit::Array::from_iter(frame.offset.iter().map(|&v| v as i64)).into(),
+        );
+    }
+    if frame.scale != 1.0 {
+        table.insert("scale", (frame.scale as f64).into());
+    }
+    table.into()
+}
+
+pub fn config_file_path(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        path.join(CONFIG_FILE_NAME)
+    } else {
+        path.to_path_buf()
+    }
+}
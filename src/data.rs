@@ -0,0 +1,273 @@
+// 文字のオーバーレイに埋め込む外部のデータ（時刻、再生中の曲、天気）
+// 提供元はそれぞれ自分のスレッドで決まった間隔に読み直し、"music.title" のような名前で値を置く。
+use crate::{
+    clock,
+    config::{DataConfig, TemperatureUnit, WeatherConfig},
+    t,
+};
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const OPEN_METEO: &str = "https://api.open-meteo.com/v1/forecast";
+
+/// The latest values of every provider, by name ("music.title").
+#[derive(Clone, Default)]
+pub struct Variables {
+    values: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl Variables {
+    // 提供元の値をまとめて置き換える（無くなった値は消す）
+    fn replace(&self, prefix: &str, values: Vec<(&str, String)>) {
+        let mut all = self.values.lock().unwrap();
+        all.retain(|name, _| {
+            !name
+                .strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with('.'))
+        });
+        for (name, value) in values {
+            all.insert(format!("{prefix}.{name}"), value);
+        }
+    }
+
+    /// Fills the `{name}` placeholders of `template`. `None` when one of them has no value
+    /// (nothing is playing, the weather has not arrived yet), so the overlay can hide.
+    pub fn render(&self, template: &str) -> Option<String> {
+        let values = self.values.lock().unwrap();
+        let mut out = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let end = rest[start..].find('}')? + start;
+            let value = values.get(rest[start + 1..end].trim())?;
+            if value.is_empty() {
+                return None;
+            }
+            out.push_str(value);
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+        Some(out)
+    }
+}
+
+// データの提供元
+trait Provider: Send {
+    // 値の名前の前につける名前
+    fn prefix(&self) -> &'static str;
+    fn interval(&self) -> Duration;
+    fn fetch(&mut self) -> Result<Vec<(&'static str, String)>>;
+}
+
+/// Starts the configured providers, each on a thread of its own.
+pub fn start(config: &DataConfig) -> Variables {
+    let variables = Variables::default();
+    // NaN などは既定の間隔にする
+    let interval = Duration::try_from_secs_f32(config.refresh_secs.clamp(0.5, 3600.0))
+        .unwrap_or(Duration::from_secs(5));
+    let mut providers: Vec<Box<dyn Provider>> = vec![Box::new(Clock)];
+    if config.now_playing {
+        providers.push(Box::new(NowPlaying { interval }));
+    }
+    if let Some(weather) = &config.weather {
+        providers.push(Box::new(Weather {
+            config: weather.clone(),
+        }));
+    }
+    for mut provider in providers {
+        let variables = variables.clone();
+        std::thread::spawn(move || {
+            let mut failing = false;
+            loop {
+                match provider.fetch() {
+                    Ok(values) => {
+                        failing = false;
+                        variables.replace(provider.prefix(), values);
+                    }
+                    Err(e) => {
+                        // 失敗し続けている間は1度だけ知らせる
+                        if !failing {
+                            tracing::warn!("{}", t!("data.failed", provider.prefix(), e));
+                        }
+                        failing = true;
+                        variables.replace(provider.prefix(), Vec::new());
+                    }
+                }
+                std::thread::sleep(provider.interval());
+            }
+        });
+    }
+    variables
+}
+
+// clock.time（時:分）と clock.date（年-月-日）
+struct Clock;
+
+impl Provider for Clock {
+    fn prefix(&self) -> &'static str {
+        "clock"
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
+    fn fetch(&mut self) -> Result<Vec<(&'static str, String)>> {
+        let now = clock::now();
+        Ok(vec![
+            ("time", format!("{:02}:{:02}", now.hour(), now.minute())),
+            (
+                "date",
+                format!("{}-{:02}-{:02}", now.year(), now.month() as u8, now.day()),
+            ),
+        ])
+    }
+}
+
+// music.title・music.artist・music.album。再生中のときだけ値がある
+struct NowPlaying {
+    interval: Duration,
+}
+
+struct Track {
+    title: String,
+    artist: String,
+    album: String,
+}
+
+impl Provider for NowPlaying {
+    fn prefix(&self) -> &'static str {
+        "music"
+    }
+
+    fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn fetch(&mut self) -> Result<Vec<(&'static str, String)>> {
+        Ok(playing()?
+            .map(|track| {
+                vec![
+                    ("title", track.title),
+                    ("artist", track.artist),
+                    ("album", track.album),
+                ]
+            })
+            .unwrap_or_default())
+    }
+}
+
+// MPRIS に対応したプレーヤーを playerctl で読む
+#[cfg(target_os = "linux")]
+fn playing() -> Result<Option<Track>> {
+    let output = std::process::Command::new("playerctl")
+        .args([
+            "metadata",
+            "--format",
+            "{{status}}\t{{title}}\t{{artist}}\t{{album}}",
+        ])
+        .output()
+        .context(t!("data.no_playerctl"))?;
+    // プレーヤーが無ければ失敗で終わる
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut fields = text.trim_end_matches('\n').split('\t');
+    if fields.next() != Some("Playing") {
+        return Ok(None);
+    }
+    let mut field = || fields.next().unwrap_or_default().to_string();
+    Ok(Some(Track {
+        title: field(),
+        artist: field(),
+        album: field(),
+    }))
+}
+
+// システムのメディアセッション（再生中のアプリが Windows に知らせている曲）
+#[cfg(windows)]
+fn playing() -> Result<Option<Track>> {
+    use windows::Media::Control::{
+        GlobalSystemMediaTransportControlsSessionManager as Manager,
+        GlobalSystemMediaTransportControlsSessionPlaybackStatus as Status,
+    };
+    let manager = Manager::RequestAsync()?.get()?;
+    let Ok(session) = manager.GetCurrentSession() else {
+        return Ok(None);
+    };
+    if session.GetPlaybackInfo()?.PlaybackStatus()? != Status::Playing {
+        return Ok(None);
+    }
+    let properties = session.TryGetMediaPropertiesAsync()?.get()?;
+    Ok(Some(Track {
+        title: properties.Title()?.to_string(),
+        artist: properties.Artist()?.to_string(),
+        album: properties.AlbumTitle()?.to_string(),
+    }))
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn playing() -> Result<Option<Track>> {
+    anyhow::bail!(t!("data.now_playing_unsupported"))
+}
+
+// weather.temperature と weather.condition（Open-Meteo、API キーは要らない）
+struct Weather {
+    config: WeatherConfig,
+}
+
+impl Provider for Weather {
+    fn prefix(&self) -> &'static str {
+        "weather"
+    }
+
+    fn interval(&self) -> Duration {
+        let minutes = self.config.refresh_minutes.clamp(1.0, 1440.0);
+        Duration::try_from_secs_f32(minutes * 60.0).unwrap_or(Duration::from_secs(15 * 60))
+    }
+
+    fn fetch(&mut self) -> Result<Vec<(&'static str, String)>> {
+        let (unit, symbol) = match self.config.unit {
+            TemperatureUnit::Celsius => ("celsius", "°C"),
+            TemperatureUnit::Fahrenheit => ("fahrenheit", "°F"),
+        };
+        let response: Value = ureq::get(OPEN_METEO)
+            .query("latitude", &self.config.latitude.to_string())
+            .query("longitude", &self.config.longitude.to_string())
+            .query("current", "temperature_2m,weather_code")
+            .query("temperature_unit", unit)
+            .timeout(Duration::from_secs(10))
+            .call()?
+            .into_json()?;
+        let current = &response["current"];
+        let temperature = current["temperature_2m"]
+            .as_f64()
+            .context(t!("data.bad_weather"))?;
+        let code = current["weather_code"]
+            .as_u64()
+            .context(t!("data.bad_weather"))?;
+        Ok(vec![
+            ("temperature", format!("{}{symbol}", temperature.round())),
+            ("condition", condition(code).to_string()),
+        ])
+    }
+}
+
+// WMO の天気コードを短い言葉に
+fn condition(code: u64) -> &'static str {
+    match code {
+        0 => t!("weather.clear"),
+        1..=3 => t!("weather.cloudy"),
+        45 | 48 => t!("weather.fog"),
+        51..=67 | 80..=82 => t!("weather.rain"),
+        71..=77 | 85 | 86 => t!("weather.snow"),
+        95..=99 => t!("weather.thunder"),
+        _ => "",
+    }
+}
@@ -0,0 +1,348 @@
+// 自分のウィンドウの外の、デスクトップ全体の様子（マウスの位置、前面のアプリ、キーを押した回数）を OS に問い合わせる。
+// Wayland ではほかのアプリのことを教えてもらえないので、X11（XWayland を含む）・macOS・Windows だけ
+use anyhow::Result;
+
+/// Whether [`Desktop::cursor`] is in points (physical pixels divided by the scale factor)
+/// rather than physical pixels.
+pub const CURSOR_IN_POINTS: bool = cfg!(target_os = "macos");
+
+/// The application whose window has the keyboard focus.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Focused {
+    // アプリの名前（X11 は WM_CLASS、Windows は実行ファイル名、macOS は表示名）
+    pub app: String,
+    // ウィンドウのタイトル（macOS では分からないので空）
+    pub title: String,
+}
+
+/// A connection to the desktop, owned by the thread that polls it.
+pub struct Desktop(platform::Desktop);
+
+impl Desktop {
+    pub fn connect() -> Result<Self> {
+        platform::Desktop::connect().map(Self)
+    }
+
+    /// Where the mouse cursor is on the whole desktop.
+    pub fn cursor(&self) -> Option<[f64; 2]> {
+        self.0.cursor()
+    }
+
+    /// The focused application, if any window has the focus.
+    pub fn focused(&self) -> Option<Focused> {
+        self.0.focused()
+    }
+
+    /// A running count of key presses on any keyboard. Only the count is kept, never which
+    /// keys were pressed; it may wrap and only its changes mean anything.
+    pub fn key_presses(&self) -> Option<u64> {
+        self.0.key_presses()
+    }
+}
+
+// 押されているキーの一覧を前の一覧と比べて、新しく押されたキーの数を数える
+// （どのキーかは数えたら捨てる）
+#[cfg(any(target_os = "linux", windows))]
+fn count_presses<const N: usize>(
+    previous: &std::cell::Cell<[u8; N]>,
+    presses: &std::cell::Cell<u64>,
+    keys: [u8; N],
+) -> u64 {
+    let pressed: u32 = keys
+        .iter()
+        .zip(previous.replace(keys))
+        .map(|(now, before)| (now & !before).count_ones())
+        .sum();
+    presses.set(presses.get().wrapping_add(pressed as u64));
+    presses.get()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::Focused;
+    use crate::t;
+    use anyhow::{Context, Result};
+    use std::cell::Cell;
+    use x11rb::{
+        connection::Connection,
+        protocol::xproto::{Atom, AtomEnum, ConnectionExt, Window},
+        rust_connection::RustConnection,
+    };
+
+    pub struct Desktop {
+        connection: RustConnection,
+        root: Window,
+        // 前に見たときに押されていたキー（キーコードごとの1ビット）
+        keys: Cell<[u8; 32]>,
+        presses: Cell<u64>,
+    }
+
+    impl Desktop {
+        fn atom(&self, name: &[u8]) -> Option<Atom> {
+            Some(
+                self.connection
+                    .intern_atom(false, name)
+                    .ok()?
+                    .reply()
+                    .ok()?
+                    .atom,
+            )
+        }
+
+        fn property(&self, window: Window, property: Atom, type_: Atom) -> Option<Vec<u8>> {
+            let reply = self
+                .connection
+                .get_property(false, window, property, type_, 0, 1024)
+                .ok()?
+                .reply()
+                .ok()?;
+            Some(reply.value)
+        }
+    }
+
+    impl Desktop {
+        pub fn connect() -> Result<Self> {
+            let (connection, screen) = x11rb::connect(None).context(t!("desktop.no_x11"))?;
+            let root = connection.setup().roots[screen].root;
+            Ok(Self {
+                connection,
+                root,
+                keys: Cell::new([0; 32]),
+                presses: Cell::new(0),
+            })
+        }
+
+        pub fn cursor(&self) -> Option<[f64; 2]> {
+            let pointer = self
+                .connection
+                .query_pointer(self.root)
+                .ok()?
+                .reply()
+                .ok()?;
+            Some([pointer.root_x as f64, pointer.root_y as f64])
+        }
+
+        pub fn focused(&self) -> Option<Focused> {
+            let active = self.atom(b"_NET_ACTIVE_WINDOW")?;
+            let window = self
+                .connection
+                .get_property(false, self.root, active, AtomEnum::WINDOW, 0, 1)
+                .ok()?
+                .reply()
+                .ok()?
+                .value32()?
+                .next()
+                .filter(|&window| window != 0)?;
+            // WM_CLASS は「インスタンス名\0クラス名\0」。クラス名の方がアプリの名前らしい
+            let class =
+                self.property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())?;
+            let mut names = class.split(|&b| b == 0).filter(|name| !name.is_empty());
+            let instance = names.next().unwrap_or_default();
+            let app = String::from_utf8_lossy(names.next().unwrap_or(instance)).into_owned();
+            let title = self
+                .atom(b"UTF8_STRING")
+                .zip(self.atom(b"_NET_WM_NAME"))
+                .and_then(|(utf8, name)| self.property(window, name, utf8))
+                .filter(|title| !title.is_empty())
+                .or_else(|| {
+                    self.property(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into())
+                })
+                .unwrap_or_default();
+            Some(Focused {
+                app,
+                title: String::from_utf8_lossy(&title).into_owned(),
+            })
+        }
+
+        pub fn key_presses(&self) -> Option<u64> {
+            let keymap = self.connection.query_keymap().ok()?.reply().ok()?;
+            Some(super::count_presses(&self.keys, &self.presses, keymap.keys))
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::Focused;
+    use anyhow::Result;
+    use core_graphics::{
+        event::CGEvent,
+        event_source::{CGEventSource, CGEventSourceStateID},
+    };
+    use objc::{class, msg_send, runtime::Object, sel, sel_impl};
+    use std::ffi::{CStr, c_char};
+
+    // kCGEventSourceStateCombinedSessionState と kCGEventKeyDown
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const KEY_DOWN: u32 = 10;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    unsafe extern "C" {
+        fn CGEventSourceCounterForEventType(state: i32, event_type: u32) -> u32;
+    }
+
+    pub struct Desktop;
+
+    impl Desktop {
+        pub fn connect() -> Result<Self> {
+            Ok(Self)
+        }
+
+        pub fn cursor(&self) -> Option<[f64; 2]> {
+            // 空のイベントの位置が今のマウスの位置（左上が原点）
+            let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState).ok()?;
+            let point = CGEvent::new(source).ok()?.location();
+            Some([point.x, point.y])
+        }
+
+        pub fn focused(&self) -> Option<Focused> {
+            // 別のスレッドから呼ぶので、返ってくるオブジェクトはここで解放する
+            objc::rc::autoreleasepool(|| {
+                // SAFETY: NSWorkspace と NSRunningApplication のプロパティを読むだけで、
+                // 文字列は解放される前に複製する
+                unsafe {
+                    let workspace: *mut Object = msg_send![class!(NSWorkspace), sharedWorkspace];
+                    let app: *mut Object = msg_send![workspace, frontmostApplication];
+                    if app.is_null() {
+                        return None;
+                    }
+                    let name: *mut Object = msg_send![app, localizedName];
+                    if name.is_null() {
+                        return None;
+                    }
+                    let utf8: *const c_char = msg_send![name, UTF8String];
+                    // ウィンドウのタイトルはアクセシビリティの許可が無いと読めない
+                    Some(Focused {
+                        app: CStr::from_ptr(utf8).to_string_lossy().into_owned(),
+                        title: String::new(),
+                    })
+                }
+            })
+        }
+
+        pub fn key_presses(&self) -> Option<u64> {
+            // OS が数えているキーを押した回数をそのまま使う
+            // SAFETY: 引数は値だけで、何も書き込まない
+            let count =
+                unsafe { CGEventSourceCounterForEventType(COMBINED_SESSION_STATE, KEY_DOWN) };
+            Some(count as u64)
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::Focused;
+    use anyhow::Result;
+    use std::cell::Cell;
+    use windows::{
+        Win32::{
+            Foundation::{CloseHandle, POINT},
+            System::Threading::{
+                OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
+                QueryFullProcessImageNameW,
+            },
+            UI::Input::KeyboardAndMouse::GetAsyncKeyState,
+            UI::WindowsAndMessaging::{
+                GetCursorPos, GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId,
+            },
+        },
+        core::PWSTR,
+    };
+
+    pub struct Desktop {
+        // 前に見たときに押されていたキー（仮想キーコードごとの1ビット）
+        keys: Cell<[u8; 32]>,
+        presses: Cell<u64>,
+    }
+
+    impl Desktop {
+        pub fn connect() -> Result<Self> {
+            Ok(Self {
+                keys: Cell::new([0; 32]),
+                presses: Cell::new(0),
+            })
+        }
+
+        pub fn cursor(&self) -> Option<[f64; 2]> {
+            let mut point = POINT::default();
+            // SAFETY: 書き込み先は point だけ
+            unsafe { GetCursorPos(&mut point) }.ok()?;
+            Some([point.x as f64, point.y as f64])
+        }
+
+        pub fn focused(&self) -> Option<Focused> {
+            // SAFETY: ウィンドウのハンドルは使うだけで、プロセスのハンドルは読んだら閉じる。
+            // 書き込み先はそれぞれの長さを渡したバッファだけ
+            unsafe {
+                let window = GetForegroundWindow();
+                if window.0 == 0 {
+                    return None;
+                }
+                let mut title = [0u16; 512];
+                let length = GetWindowTextW(window, &mut title).max(0) as usize;
+                let mut pid = 0u32;
+                GetWindowThreadProcessId(window, Some(&mut pid as *mut u32));
+                let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+                let mut path = [0u16; 1024];
+                let mut size = path.len() as u32;
+                let named = QueryFullProcessImageNameW(
+                    process,
+                    PROCESS_NAME_WIN32,
+                    PWSTR(path.as_mut_ptr()),
+                    &mut size,
+                );
+                let _ = CloseHandle(process);
+                named.ok()?;
+                // 実行ファイル名から .exe を除いたもの
+                let path = String::from_utf16_lossy(&path[..size as usize]);
+                let app = std::path::Path::new(&path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or(path);
+                Some(Focused {
+                    app,
+                    title: String::from_utf16_lossy(&title[..length]),
+                })
+            }
+        }
+
+        pub fn key_presses(&self) -> Option<u64> {
+            let mut keys = [0u8; 32];
+            // マウスのボタン（0x01〜0x06）は数えない
+            for key in 0x08..=0xfe {
+                // SAFETY: 引数は値だけで、何も書き込まない。最上位ビットが今押されているか
+                if unsafe { GetAsyncKeyState(key) } < 0 {
+                    keys[key as usize / 8] |= 1 << (key % 8);
+                }
+            }
+            Some(super::count_presses(&self.keys, &self.presses, keys))
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use crate::t;
+    use anyhow::{Result, bail};
+
+    pub struct Desktop;
+
+    impl Desktop {
+        pub fn connect() -> Result<Self> {
+            bail!(t!("desktop.unsupported"))
+        }
+
+        pub fn cursor(&self) -> Option<[f64; 2]> {
+            None
+        }
+
+        pub fn focused(&self) -> Option<super::Focused> {
+            None
+        }
+
+        pub fn key_presses(&self) -> Option<u64> {
+            None
+        }
+    }
+}
@@ -0,0 +1,255 @@
+// 前回の合成から変わった領域だけを描き直すための管理
+use crate::compose::Transform;
+
+/// Pixel rectangle on the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn full(width: usize, height: usize) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    /// `[x, y, w, h]` clipped to the canvas; `None` if nothing is left.
+    pub fn clipped(rect: [i32; 4], width: usize, height: usize) -> Option<Self> {
+        let [x, y, w, h] = rect;
+        let left = x.max(0) as usize;
+        let top = y.max(0) as usize;
+        let right = (x.saturating_add(w)).clamp(0, width as i32) as usize;
+        let bottom = (y.saturating_add(h)).clamp(0, height as i32) as usize;
+        (right > left && bottom > top).then(|| Self {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+        })
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Self {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+
+    pub fn intersects(&self, other: &Self) -> bool {
+        self.x < other.x + other.width
+            && other.x < self.x + self.width
+            && self.y < other.y + other.height
+            && other.y < self.y + self.height
+    }
+
+    /// Byte range of row `y` inside a `width`-wide RGBA buffer.
+    pub fn row(&self, y: usize, width: usize) -> std::ops::Range<usize> {
+        let start = (y * width + self.x) * 4;
+        start..start + self.width * 4
+    }
+}
+
+/// Bounding box of the pixels that differ between two equally sized RGBA buffers.
+pub fn diff(a: &[u8], b: &[u8], width: usize) -> Option<Rect> {
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+    for (y, (row_a, row_b)) in a
+        .chunks_exact(width * 4)
+        .zip(b.chunks_exact(width * 4))
+        .enumerate()
+    {
+        if row_a == row_b {
+            continue;
+        }
+        let first = row_a
+            .chunks_exact(4)
+            .zip(row_b.chunks_exact(4))
+            .position(|(p, q)| p != q)
+            .unwrap_or(0);
+        let last = row_a
+            .chunks_exact(4)
+            .zip(row_b.chunks_exact(4))
+            .rposition(|(p, q)| p != q)
+            .unwrap_or(width - 1);
+        bounds = Some(match bounds {
+            None => (first, y, last, y),
+            Some((l, t, r, _)) => (l.min(first), t, r.max(last), y),
+        });
+    }
+    bounds.map(|(l, t, r, b)| Rect {
+        x: l,
+        y: t,
+        width: r - l + 1,
+        height: b - t + 1,
+    })
+}
+
+/// Bounding box of the pixels that aren't fully transparent in a `width`-wide RGBA buffer.
+pub fn opaque(pixels: &[u8], width: usize) -> Option<Rect> {
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+    for (y, row) in pixels.chunks_exact(width * 4).enumerate() {
+        let Some(first) = row.chunks_exact(4).position(|p| p[3] != 0) else {
+            continue;
+        };
+        let last = row
+            .chunks_exact(4)
+            .rposition(|p| p[3] != 0)
+            .unwrap_or(first);
+        bounds = Some(match bounds {
+            None => (first, y, last, y),
+            Some((l, t, r, _)) => (l.min(first), t, r.max(last), y),
+        });
+    }
+    bounds.map(|(l, t, r, b)| Rect {
+        x: l,
+        y: t,
+        width: r - l + 1,
+        height: b - t + 1,
+    })
+}
+
+/// A part drawn over the frame, as the tracker sees it: the opaque bounds of its image, and
+/// the transform and opacity it is drawn with.
+pub type LayerState = (Option<Rect>, Transform, f32);
+
+/// What the tracker needs to know about a composited frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameInfo {
+    // フレームのアドレス（読み込んだ画像は動かない）
+    pub key: usize,
+    // 同じ基準フレームを持つフレームの組（表情ごと）
+    pub group: usize,
+    // 基準フレームと異なる領域
+    pub region: Option<Rect>,
+}
+
+impl FrameInfo {
+    /// A frame with no known relation to any other, e.g. a video frame.
+    pub fn standalone(frame: &[u8]) -> Self {
+        Self {
+            key: frame.as_ptr() as usize,
+            group: usize::MAX,
+            region: None,
+        }
+    }
+}
+
+/// Remembers what was composited last time and works out which region must be redrawn.
+pub struct DirtyTracker {
+    width: usize,
+    height: usize,
+    // None なら全体を描き直す
+    last: Option<(Option<FrameInfo>, Transform)>,
+    // 前回と今回のスロットのフレームとパーツの状態（入れ替えて使い回す）
+    last_slots: Vec<Option<usize>>,
+    slots: Vec<Option<usize>>,
+    last_layers: Vec<LayerState>,
+    layers: Vec<LayerState>,
+}
+
+impl DirtyTracker {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            last: None,
+            last_slots: Vec::new(),
+            slots: Vec::new(),
+            last_layers: Vec::new(),
+            layers: Vec::new(),
+        }
+    }
+
+    /// Forces the next update to redraw everything (e.g. after the surface was recreated).
+    pub fn invalidate(&mut self) {
+        self.last = None;
+    }
+
+    /// Returns the region to redraw, or `None` if the frame is unchanged. `animated` marks a
+    /// main source whose contents change in place (video).
+    pub fn update<'a>(
+        &mut self,
+        main: Option<FrameInfo>,
+        transform: Transform,
+        animated: bool,
+        layers: impl Iterator<Item = LayerState>,
+        slots: impl Iterator<Item = (Option<&'a [u8]>, [i32; 4])> + Clone,
+    ) -> Option<Rect> {
+        let full = Rect::full(self.width, self.height);
+        std::mem::swap(&mut self.slots, &mut self.last_slots);
+        self.slots.clear();
+        self.slots.extend(
+            slots
+                .clone()
+                .map(|(frame, _)| frame.map(|f| f.as_ptr() as usize)),
+        );
+        std::mem::swap(&mut self.layers, &mut self.last_layers);
+        self.layers.clear();
+        self.layers.extend(layers);
+        let last = self.last.replace((main, transform));
+        let Some((last_main, last_transform)) = last else {
+            return Some(full);
+        };
+        if self.slots.len() != self.last_slots.len() || self.layers.len() != self.last_layers.len()
+        {
+            return Some(full);
+        }
+
+        let mut dirty = match (last_main, main) {
+            _ if animated || transform != last_transform => Some(full),
+            (Some(a), Some(b)) if a.key == b.key => None,
+            // 同じ表情の中での切り替えは、両方が基準と異なる領域だけ
+            (Some(a), Some(b)) if a.group == b.group && transform.is_identity() => {
+                match (a.region, b.region) {
+                    (Some(r), Some(s)) => Some(r.union(s)),
+                    (r, s) => r.or(s),
+                }
+            }
+            (None, None) => None,
+            _ => Some(full),
+        };
+
+        // 動いたパーツは、前と今の位置を合わせた範囲だけ
+        for (layer, last_layer) in self.layers.iter().zip(&self.last_layers) {
+            if layer == last_layer {
+                continue;
+            }
+            for (bounds, transform, _) in [layer, last_layer] {
+                let covered = bounds.and_then(|b| transform.bounds(b, self.width, self.height));
+                if let Some(rect) = covered {
+                    dirty = Some(dirty.map_or(rect, |d| d.union(rect)));
+                }
+            }
+        }
+
+        for ((_, rect), (key, last_key)) in slots.zip(self.slots.iter().zip(&self.last_slots)) {
+            let Some(rect) = Rect::clipped(rect, self.width, self.height) else {
+                continue;
+            };
+            // スロットは下の領域が描き直されたときも重ね直す
+            let overlapped = dirty.is_some_and(|d| d.intersects(&rect));
+            if key != last_key || overlapped {
+                dirty = Some(dirty.map_or(rect, |d| d.union(rect)));
+            }
+        }
+        dirty
+    }
+}
+
+/// Copies the rows of `rect` from `src` to `dst` (both `width`-wide RGBA).
+pub fn copy_rect(dst: &mut [u8], src: &[u8], width: usize, rect: Rect) {
+    for y in rect.y..rect.y + rect.height {
+        let row = rect.row(y, width);
+        dst[row.clone()].copy_from_slice(&src[row]);
+    }
+}
@@ -0,0 +1,96 @@
+// アニメーションの進み方（イージング）。設定では名前か、CSS と同じ 3 次ベジェの制御点で指定する
+use crate::config::Easing;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+// ベジェの x から媒介変数を解くときの精度と、ニュートン法の回数
+const EPSILON: f32 = 1e-5;
+const NEWTON_STEPS: usize = 8;
+
+impl Easing {
+    /// Progress along the curve at `t` (0.0..=1.0, clamped). Starts at 0 and ends at 1;
+    /// elastic and some Bézier curves overshoot on the way.
+    pub fn ease(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseIn => t * t,
+            Self::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Self::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Self::CubicIn => t * t * t,
+            Self::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Self::CubicInOut if t < 0.5 => 4.0 * t * t * t,
+            Self::CubicInOut => 1.0 - (2.0 - 2.0 * t).powi(3) / 2.0,
+            Self::SineIn => 1.0 - (t * FRAC_PI_2).cos(),
+            Self::SineOut => (t * FRAC_PI_2).sin(),
+            Self::SineInOut => (1.0 - (t * PI).cos()) / 2.0,
+            Self::Elastic => elastic(t),
+            Self::Bounce => bounce(t),
+            Self::Bezier(points) => bezier(points, t),
+        }
+    }
+}
+
+// 行き過ぎて揺れながら止まる
+fn elastic(t: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t;
+    }
+    2f32.powf(-10.0 * t) * ((t * 10.0 - 0.75) * TAU / 3.0).sin() + 1.0
+}
+
+// 床で跳ねながら止まる
+fn bounce(t: f32) -> f32 {
+    const N: f32 = 7.5625;
+    const D: f32 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+// 両端が (0, 0) と (1, 1) の 3 次ベジェ。x が t になる点の y を返す
+fn bezier([x1, y1, x2, y2]: [f32; 4], t: f32) -> f32 {
+    // x が行き来しないよう、制御点の x は 0〜1 に収める
+    let (x1, x2) = (x1.clamp(0.0, 1.0), x2.clamp(0.0, 1.0));
+    let curve = |a: f32, b: f32, s: f32| {
+        let u = 1.0 - s;
+        3.0 * u * u * s * a + 3.0 * u * s * s * b + s * s * s
+    };
+    let slope = |a: f32, b: f32, s: f32| {
+        let u = 1.0 - s;
+        3.0 * u * u * a + 6.0 * u * s * (b - a) + 3.0 * s * s * (1.0 - b)
+    };
+
+    // ニュートン法で解き、傾きが平らで収まらなければ二分法に切り替える
+    let mut s = t;
+    for _ in 0..NEWTON_STEPS {
+        let error = curve(x1, x2, s) - t;
+        if error.abs() < EPSILON {
+            return curve(y1, y2, s);
+        }
+        let d = slope(x1, x2, s);
+        if d.abs() < EPSILON {
+            break;
+        }
+        s = (s - error / d).clamp(0.0, 1.0);
+    }
+    let (mut low, mut high) = (0.0, 1.0);
+    s = t;
+    while high - low > EPSILON {
+        if curve(x1, x2, s) < t {
+            low = s;
+        } else {
+            high = s;
+        }
+        s = (low + high) / 2.0;
+    }
+    curve(y1, y2, s)
+}
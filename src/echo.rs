@@ -0,0 +1,114 @@
+// スピーカーから回り込んだゲーム音をマイクから取り除く（NLMS 適応フィルタによるエコーキャンセル）
+use crate::{config::EchoConfig, reference::Reference, resample::Resampler};
+use std::collections::VecDeque;
+
+// エコーキャンセルは 16kHz で行う（口の判定に使う音量には十分）
+pub const RATE: u32 = 16_000;
+// 参照が遅れて届いたときに溜めておく上限（0.5 秒）
+const MAX_PENDING: usize = RATE as usize / 2;
+// ダブルトーク判定（Geigel）: マイクが参照の最大値のこの割合を超えたら適応を止める
+const DOUBLE_TALK: f32 = 0.5;
+// 参照の最大値を見る区間（10ms ごとの最大値を尾の長さ分だけ持つ）
+const PEAK_HOP: usize = RATE as usize / 100;
+
+/// Subtracts the part of the microphone that is predicted from the loopback reference.
+pub struct EchoCanceller {
+    reference: Reference,
+    resampler: Resampler,
+    step: f32,
+    mic: Vec<f32>,
+    incoming: Vec<f32>,
+    pending: VecDeque<f32>,
+    // 参照の履歴（折り返さずに切り出せるよう2周分持つ）
+    history: Vec<f32>,
+    position: usize,
+    energy: f32,
+    weights: Vec<f32>,
+    peaks: VecDeque<f32>,
+    peak: f32,
+    peak_count: usize,
+}
+
+impl EchoCanceller {
+    /// `rate` is the analysis rate of the microphone; the reference must already be at
+    /// [`RATE`].
+    pub fn new(config: &EchoConfig, reference: Reference, rate: u32) -> Self {
+        let taps = (RATE as u64 * config.tail_ms / 1000).max(1) as usize;
+        Self {
+            reference,
+            resampler: Resampler::new(rate, RATE),
+            step: config.step,
+            mic: Vec::new(),
+            incoming: Vec::new(),
+            pending: VecDeque::new(),
+            history: vec![0.0; taps * 2],
+            position: 0,
+            energy: 0.0,
+            weights: vec![0.0; taps],
+            peaks: VecDeque::new(),
+            peak: 0.0,
+            peak_count: 0,
+        }
+    }
+
+    /// Takes the microphone at the analysis rate and appends the echo-free signal at
+    /// [`RATE`] to `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        self.incoming.clear();
+        self.reference.read(&mut self.incoming);
+        self.pending.extend(&self.incoming);
+        // マイクより参照が進みすぎたら古いものを捨てる
+        if self.pending.len() > MAX_PENDING {
+            self.pending.drain(..self.pending.len() - MAX_PENDING);
+        }
+
+        self.mic.clear();
+        self.resampler.process(input, &mut self.mic);
+        let taps = self.weights.len();
+        for i in 0..self.mic.len() {
+            let d = self.mic[i];
+            // 参照が途切れたら無音として扱う
+            let x = self.pending.pop_front().unwrap_or(0.0);
+            self.push_reference(x);
+
+            let window = &self.history[self.position..self.position + taps];
+            let estimate: f32 = window.iter().zip(&self.weights).map(|(x, w)| x * w).sum();
+            let error = d - estimate;
+            output.push(error);
+
+            // 話している間に学習するとフィルタが自分の声を消しにかかるので止める
+            let max_reference = self.peaks.iter().copied().fold(self.peak, f32::max);
+            if d.abs() > DOUBLE_TALK * max_reference || self.energy <= f32::EPSILON {
+                continue;
+            }
+            let k = self.step * error / (self.energy + 1e-6);
+            for (w, x) in self.weights.iter_mut().zip(window) {
+                *w += k * x;
+            }
+        }
+    }
+
+    fn push_reference(&mut self, x: f32) {
+        let taps = self.weights.len();
+        // 一番古いサンプルを新しいサンプルで置き換え、先頭を新しい側にする
+        self.position = (self.position + taps - 1) % taps;
+        let oldest = self.history[self.position];
+        self.energy = (self.energy + x * x - oldest * oldest).max(0.0);
+        self.history[self.position] = x;
+        self.history[self.position + taps] = x;
+
+        self.peak = self.peak.max(x.abs());
+        self.peak_count += 1;
+        if self.peak_count == PEAK_HOP {
+            self.peaks.push_back(self.peak);
+            if self.peaks.len() > taps.div_ceil(PEAK_HOP) {
+                self.peaks.pop_front();
+            }
+            self.peak = 0.0;
+            self.peak_count = 0;
+            // 足し引きの誤差が溜まらないように計算し直す
+            let window = &self.history[self.position..self.position + taps];
+            self.energy = window.iter().map(|x| x * x).sum();
+        }
+    }
+}
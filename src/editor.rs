@@ -0,0 +1,399 @@
+use crate::{
+    align::draw_checker,
+    avatar::{Mouth, open_image},
+    config::{Config, FrameConfig},
+    render::Renderer,
+    t,
+};
+use anyhow::Result;
+use std::{
+    collections::HashMap,
+    f32::consts::PI,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+use winit::{
+    dpi::LogicalSize,
+    event::{ElementState, Event, KeyEvent, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    window::WindowBuilder,
+};
+
+const COARSE_STEP: i32 = 10;
+const SCALE_STEP: f32 = 0.05;
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp", "bmp", "gif"];
+
+// 編集対象のフレームの位置
+#[derive(Debug, Clone)]
+struct Slot {
+    expression: String,
+    state: &'static str,
+    index: usize,
+}
+
+fn slots(config: &Config) -> Vec<Slot> {
+    let mut slots = Vec::new();
+    for (name, expression) in &config.expressions {
+        for (state, frames) in expression.states() {
+            for index in 0..frames.len() {
+                slots.push(Slot {
+                    expression: name.clone(),
+                    state,
+                    index,
+                });
+            }
+        }
+    }
+    slots
+}
+
+fn frames_mut<'a>(config: &'a mut Config, slot: &Slot) -> Option<&'a mut Vec<FrameConfig>> {
+    config
+        .expressions
+        .get_mut(&slot.expression)?
+        .state_mut(slot.state)
+}
+
+/// Synthetic speech-like signal (syllable bursts with pauses) used to preview the reaction
+/// without a microphone.
+struct TestTone {
+    started: Instant,
+}
+
+impl TestTone {
+    const SAMPLE_RATE: f32 = 48_000.0;
+    const BLOCK: usize = 480;
+
+    fn sample(t: f32) -> f32 {
+        // 2.5秒話して1.5秒黙る、の繰り返し
+        let phrase = if t % 4.0 < 2.5 { 1.0 } else { 0.0 };
+        let syllable = (PI * 4.0 * t).sin().abs();
+        0.3 * phrase * syllable * (2.0 * PI * 220.0 * t).sin()
+    }
+
+    // キャプチャ側と同じく、ブロックごとの RMS
+    fn level(&self) -> f32 {
+        let start = self.started.elapsed().as_secs_f32();
+        let sum: f32 = (0..Self::BLOCK)
+            .map(|i| Self::sample(start + i as f32 / Self::SAMPLE_RATE).powi(2))
+            .sum();
+        (sum / Self::BLOCK as f32).sqrt()
+    }
+}
+
+struct Editor {
+    config: Config,
+    selected: usize,
+    coarse: bool,
+    tone: Option<TestTone>,
+    sources: HashMap<PathBuf, Option<image::RgbaImage>>,
+    canvas: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+impl Editor {
+    fn slot(&self) -> Option<Slot> {
+        slots(&self.config).get(self.selected).cloned()
+    }
+
+    fn frame_mut(&mut self) -> Option<&mut FrameConfig> {
+        let slot = self.slot()?;
+        self.config
+            .frame_mut(&slot.expression, slot.state, slot.index)
+    }
+
+    fn title(&self) -> String {
+        let Some(slot) = self.slot() else {
+            return format!("{} - {}", t!("editor.title"), t!("editor.empty"));
+        };
+        let Some(frame) = self.config.frame(&slot.expression, slot.state, slot.index) else {
+            return t!("editor.title").to_string();
+        };
+        let mut title = format!(
+            "{} - {}/{}/{} {} [{}, {}] x{:.2}",
+            t!("editor.title"),
+            slot.expression,
+            slot.state,
+            slot.index,
+            frame.path.display(),
+            frame.offset[0],
+            frame.offset[1],
+            frame.scale
+        );
+        if let Some(tone) = &self.tone {
+            title.push_str(&format!(
+                " | {} {:.4}",
+                t!("editor.test_tone"),
+                tone.level()
+            ));
+        }
+        title
+    }
+
+    // ファイル選択ダイアログ。設定ファイルからの相対パスにできればそうする
+    fn pick_image(&self) -> Option<PathBuf> {
+        let base = std::fs::canonicalize(&self.config.base_dir).ok();
+        let mut dialog = rfd::FileDialog::new().add_filter("image", IMAGE_EXTENSIONS);
+        if let Some(base) = &base {
+            dialog = dialog.set_directory(base);
+        }
+        let path = dialog.pick_file()?;
+        Some(
+            base.and_then(|b| path.strip_prefix(b).ok().map(Path::to_path_buf))
+                .unwrap_or(path),
+        )
+    }
+
+    fn handle_key(&mut self, keycode: KeyCode) -> bool {
+        let step = if self.coarse { COARSE_STEP } else { 1 };
+        let count = slots(&self.config).len();
+        match keycode {
+            KeyCode::Tab if count > 0 => {
+                self.selected = if self.coarse {
+                    (self.selected + count - 1) % count
+                } else {
+                    (self.selected + 1) % count
+                };
+            }
+            KeyCode::ArrowLeft | KeyCode::ArrowRight | KeyCode::ArrowUp | KeyCode::ArrowDown => {
+                if let Some(frame) = self.frame_mut() {
+                    match keycode {
+                        KeyCode::ArrowLeft => frame.offset[0] -= step,
+                        KeyCode::ArrowRight => frame.offset[0] += step,
+                        KeyCode::ArrowUp => frame.offset[1] -= step,
+                        _ => frame.offset[1] += step,
+                    }
+                }
+            }
+            KeyCode::Equal | KeyCode::Minus => {
+                if let Some(frame) = self.frame_mut() {
+                    let delta = if keycode == KeyCode::Equal {
+                        SCALE_STEP
+                    } else {
+                        -SCALE_STEP
+                    };
+                    frame.scale = (frame.scale + delta).max(SCALE_STEP);
+                }
+            }
+            // 同じ状態の中でフレームの順番を入れ替える
+            KeyCode::BracketLeft | KeyCode::BracketRight => {
+                let Some(slot) = self.slot() else {
+                    return false;
+                };
+                let Some(frames) = frames_mut(&mut self.config, &slot) else {
+                    return false;
+                };
+                let target = if keycode == KeyCode::BracketLeft {
+                    slot.index.checked_sub(1)
+                } else {
+                    Some(slot.index + 1).filter(|&i| i < frames.len())
+                };
+                if let Some(target) = target {
+                    frames.swap(slot.index, target);
+                    self.selected = self.selected + target - slot.index;
+                }
+            }
+            KeyCode::KeyO => {
+                if let Some(path) = self.pick_image()
+                    && let Some(frame) = self.frame_mut()
+                {
+                    frame.path = path;
+                }
+            }
+            KeyCode::KeyN => {
+                if let Some(slot) = self.slot()
+                    && let Some(path) = self.pick_image()
+                    && let Some(frames) = frames_mut(&mut self.config, &slot)
+                {
+                    frames.insert(slot.index + 1, FrameConfig::new(path));
+                    self.selected += 1;
+                }
+            }
+            KeyCode::Delete | KeyCode::Backspace => {
+                if let Some(slot) = self.slot()
+                    && let Some(frames) = frames_mut(&mut self.config, &slot)
+                {
+                    frames.remove(slot.index);
+                    self.selected = self.selected.min(count.saturating_sub(2));
+                }
+            }
+            KeyCode::KeyT => {
+                self.tone = match self.tone {
+                    Some(_) => None,
+                    None => Some(TestTone {
+                        started: Instant::now(),
+                    }),
+                };
+            }
+            KeyCode::KeyS => match self.config.save() {
+                Ok(path) => tracing::info!("{}", t!("align.saved", path.display())),
+                Err(e) => tracing::error!("{}", t!("align.save_failed", e)),
+            },
+            _ => return false,
+        }
+        true
+    }
+
+    fn draw(&mut self) {
+        draw_checker(&mut self.canvas, self.width, self.height);
+        let Some(slot) = self.slot() else {
+            return;
+        };
+
+        // テストトーン中は閾値に応じて待機/発話の先頭フレームを表示
+        let frame = match &self.tone {
+            Some(tone) => {
+                let mouth = Mouth::from_level(tone.level(), &self.config.audio);
+                self.config
+                    .frame(&slot.expression, mouth.state(), 0)
+                    .or_else(|| self.config.frame(&slot.expression, "idle", 0))
+            }
+            None => self.config.frame(&slot.expression, slot.state, slot.index),
+        };
+        let Some(frame) = frame.cloned() else {
+            return;
+        };
+
+        let path = self.config.resolve(&frame.path);
+        let source = self
+            .sources
+            .entry(path.clone())
+            .or_insert_with(|| open_image(&path).ok().map(|img| img.to_rgba8()));
+        if let Some(source) = source {
+            blit(
+                &mut self.canvas,
+                self.width,
+                self.height,
+                source,
+                frame.offset,
+                frame.scale,
+            );
+        }
+    }
+}
+
+// 最近傍でキャンバスに拡大配置してアルファ合成（編集プレビュー用なので速さ優先）
+fn blit(
+    canvas: &mut [u8],
+    width: usize,
+    height: usize,
+    source: &image::RgbaImage,
+    offset: [i32; 2],
+    scale: f32,
+) {
+    let scaled_w = width as f32 * scale;
+    let scaled_h = height as f32 * scale;
+    let left = (width as f32 - scaled_w) / 2.0 + offset[0] as f32;
+    let top = (height as f32 - scaled_h) / 2.0 + offset[1] as f32;
+    let (src_w, src_h) = source.dimensions();
+
+    for y in 0..height {
+        let v = (y as f32 - top) / scaled_h;
+        if !(0.0..1.0).contains(&v) {
+            continue;
+        }
+        let sy = (v * src_h as f32) as u32;
+        for x in 0..width {
+            let u = (x as f32 - left) / scaled_w;
+            if !(0.0..1.0).contains(&u) {
+                continue;
+            }
+            let pixel = source.get_pixel((u * src_w as f32) as u32, sy);
+            let dst = (y * width + x) * 4;
+            let alpha = pixel[3] as u32;
+            for c in 0..3 {
+                let fg = pixel[c] as u32;
+                let bg = canvas[dst + c] as u32;
+                canvas[dst + c] = ((fg * alpha + bg * (255 - alpha)) / 255) as u8;
+            }
+        }
+    }
+}
+
+/// Keyboard-driven avatar editor: pick images for frames, reorder them, adjust offset/scale,
+/// preview the reaction with a test tone and save back to the config.
+pub fn run(config: Config) -> Result<()> {
+    let width = config.canvas.width as usize;
+    let height = config.canvas.height as usize;
+
+    let event_loop = EventLoop::new()?;
+    let window = WindowBuilder::new()
+        .with_title(t!("editor.title"))
+        .with_inner_size(LogicalSize::new(width as u32, height as u32))
+        .build(&event_loop)?;
+    let mut renderer = Renderer::new(&window, width as u32, height as u32)?;
+
+    let mut editor = Editor {
+        config,
+        selected: 0,
+        coarse: false,
+        tone: None,
+        sources: HashMap::new(),
+        canvas: vec![0u8; width * height * 4],
+        width,
+        height,
+    };
+    window.set_title(&editor.title());
+    tracing::info!("{}", t!("editor.help"));
+
+    event_loop.run(move |event, elwt| {
+        // テストトーン再生中だけ連続描画
+        elwt.set_control_flow(if editor.tone.is_some() {
+            ControlFlow::Poll
+        } else {
+            ControlFlow::Wait
+        });
+
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CloseRequested => elwt.exit(),
+                WindowEvent::ModifiersChanged(modifiers) => {
+                    editor.coarse = modifiers.state().shift_key();
+                }
+                WindowEvent::KeyboardInput {
+                    event:
+                        KeyEvent {
+                            physical_key: PhysicalKey::Code(keycode),
+                            state: ElementState::Pressed,
+                            ..
+                        },
+                    ..
+                } => {
+                    if keycode == KeyCode::Escape {
+                        elwt.exit();
+                    } else if editor.handle_key(keycode) {
+                        window.set_title(&editor.title());
+                        window.request_redraw();
+                    }
+                }
+                WindowEvent::Resized(size) => {
+                    if let Err(e) = renderer.resize_surface(size.width, size.height) {
+                        tracing::error!("{}", t!("render.resize_failed", e));
+                        elwt.exit();
+                    }
+                    window.request_redraw();
+                }
+                WindowEvent::RedrawRequested => {
+                    editor.draw();
+                    if let Some(frame) = renderer.frame_mut() {
+                        frame.copy_from_slice(&editor.canvas);
+                    }
+                    if let Err(e) = renderer.render(&window) {
+                        tracing::error!("{}", t!("render.render_failed", e));
+                        elwt.exit();
+                    }
+                }
+                _ => {}
+            },
+            Event::AboutToWait if editor.tone.is_some() => {
+                window.set_title(&editor.title());
+                window.request_redraw();
+            }
+            _ => {}
+        }
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+// 声の大きさ・抑揚・明るさから感情を推定して、表情の候補にする
+use crate::{
+    config::{AudioConfig, EmotionConfig},
+    resample::rms,
+    t,
+};
+use std::time::{Duration, Instant};
+
+// 窓の中でこの割合以上が発話なら判定する
+const VOICED_RATIO: f32 = 0.3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emotion {
+    Calm,
+    Excited,
+    Angry,
+}
+
+impl Emotion {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Calm => "calm",
+            Self::Excited => "excited",
+            Self::Angry => "angry",
+        }
+    }
+
+    /// The expression configured for this emotion, if any.
+    pub fn expression(self, config: &EmotionConfig) -> Option<&str> {
+        match self {
+            Self::Calm => config.calm.as_deref(),
+            Self::Excited => config.excited.as_deref(),
+            Self::Angry => config.angry.as_deref(),
+        }
+    }
+}
+
+// 入力コールバック1回分の特徴量
+struct Block {
+    level: f32,
+    // 1秒あたりのゼロ交差数（高い声・荒い声ほど大きい）
+    crossings: f32,
+    duration: f32,
+}
+
+/// Classifies the voice over a sliding window on the analysis thread.
+pub struct EmotionDetector {
+    config: EmotionConfig,
+    threshold: f32,
+    rate: f32,
+    blocks: Vec<Block>,
+    window: f32,
+    detected: Option<(Emotion, Instant)>,
+}
+
+impl EmotionDetector {
+    pub fn new(config: &EmotionConfig, audio: &AudioConfig) -> Self {
+        Self {
+            config: config.clone(),
+            threshold: audio.threshold,
+            rate: audio.analysis_rate as f32,
+            blocks: Vec::new(),
+            window: 0.0,
+            detected: None,
+        }
+    }
+
+    /// Takes one block of mono samples at the analysis rate and returns the current emotion.
+    pub fn update(&mut self, samples: &[f32], now: Instant) -> Option<Emotion> {
+        if samples.is_empty() {
+            return self.detected.map(|(e, _)| e);
+        }
+        let crossings = samples
+            .windows(2)
+            .filter(|w| (w[0] < 0.0) != (w[1] < 0.0))
+            .count();
+        let duration = samples.len() as f32 / self.rate;
+        self.blocks.push(Block {
+            level: rms(samples),
+            crossings: crossings as f32 / duration,
+            duration,
+        });
+        self.window += duration;
+
+        if self.window >= self.config.window_ms as f32 / 1000.0 {
+            if let Some(emotion) = self.classify() {
+                if self.detected.map(|(e, _)| e) != Some(emotion) {
+                    tracing::debug!(emotion = emotion.name(), "{}", t!("emotion.detected"));
+                }
+                self.detected = Some((emotion, now));
+            }
+            self.blocks.clear();
+            self.window = 0.0;
+        }
+
+        // 黙ってからしばらくしたら推定を外す
+        if let Some((_, at)) = self.detected
+            && now.duration_since(at) > Duration::from_millis(self.config.hold_ms)
+        {
+            self.detected = None;
+        }
+        self.detected.map(|(e, _)| e)
+    }
+
+    fn classify(&self) -> Option<Emotion> {
+        let voiced = || self.blocks.iter().filter(|b| b.level >= self.threshold);
+        let voiced_time: f32 = voiced().map(|b| b.duration).sum();
+        let n = voiced().count() as f32;
+        if n == 0.0 || voiced_time < self.window * VOICED_RATIO {
+            return None;
+        }
+
+        let mean = voiced().map(|b| b.level).sum::<f32>() / n;
+        let variance = voiced().map(|b| (b.level - mean).powi(2)).sum::<f32>() / n;
+        // 音量の揺れ（抑揚）は平均に対する比で見る
+        let variability = variance.sqrt() / mean.max(f32::EPSILON);
+        let loudness = mean / self.threshold.max(f32::EPSILON);
+        let brightness = voiced().map(|b| b.crossings).sum::<f32>() / n;
+
+        Some(
+            if loudness >= self.config.angry_level
+                && brightness >= self.config.angry_brightness
+                && variability < self.config.excited_variability
+            {
+                Emotion::Angry
+            } else if loudness >= self.config.excited_level
+                && variability >= self.config.excited_variability
+            {
+                Emotion::Excited
+            } else {
+                Emotion::Calm
+            },
+        )
+    }
+}
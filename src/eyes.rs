@@ -0,0 +1,87 @@
+// 瞳のパーツをマウスの方へ少し寄せて、配信者が画面で操作しているものを目で追っているように見せる
+use crate::{
+    config::EyesConfig,
+    desktop::{self, Desktop},
+    mapping::Params,
+};
+use anyhow::Result;
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// マウスの位置を見る間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(33);
+
+/// Follows the system cursor with the pupil layers.
+pub struct Eyes {
+    config: EyesConfig,
+    // 別のスレッドが読んだマウスの位置
+    cursor: Arc<Mutex<Option<[f64; 2]>>>,
+    // 平滑化した瞳のずれ（キャンバスのピクセル）
+    offset: [f32; 2],
+    last: Option<Instant>,
+}
+
+impl Eyes {
+    pub fn new(config: &EyesConfig) -> Result<Self> {
+        let desktop = Desktop::connect()?;
+        let cursor = Arc::new(Mutex::new(None));
+        let shared = cursor.clone();
+        std::thread::spawn(move || {
+            loop {
+                let position = desktop.cursor();
+                *shared.lock().unwrap_or_else(|e| e.into_inner()) = position;
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        });
+        Ok(Self {
+            config: config.clone(),
+            cursor,
+            offset: [0.0; 2],
+            last: None,
+        })
+    }
+
+    /// Eases the pupils toward the cursor. `center` is the window's center on the desktop in
+    /// physical pixels, if known, and `scale` its scale factor.
+    pub fn update(&mut self, center: Option<[f64; 2]>, scale: f64, now: Instant) {
+        let dt = self
+            .last
+            .replace(now)
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+        let cursor = *self.cursor.lock().unwrap_or_else(|e| e.into_inner());
+        // どちらかが分からなければ正面を見る
+        let target = match (cursor, center) {
+            (Some(cursor), Some(center)) => {
+                let cursor = match desktop::CURSOR_IN_POINTS {
+                    true => cursor.map(|v| v * scale),
+                    false => cursor,
+                };
+                let reach = (self.config.reach as f64).max(1.0);
+                [0, 1].map(|i| {
+                    let t = ((cursor[i] - center[i]) / reach).clamp(-1.0, 1.0) as f32;
+                    t * self.config.range[i]
+                })
+            }
+            _ => [0.0; 2],
+        };
+        let k = match self.config.smoothing_ms {
+            0 => 1.0,
+            ms => 1.0 - (-dt * 1000.0 / ms as f32).exp(),
+        };
+        for (offset, target) in self.offset.iter_mut().zip(target) {
+            *offset += (target - *offset) * k;
+        }
+    }
+
+    /// Adds the pupils' offset to the parameters of `layers` (in the order of `params`).
+    pub fn apply<'a>(&self, layers: impl Iterator<Item = &'a str>, params: &mut [Params]) {
+        for (name, params) in layers.zip(params) {
+            if self.config.layers.iter().any(|layer| layer == name) {
+                params.offset[0] += self.offset[0];
+                params.offset[1] += self.offset[1];
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+// モジュレーションの入力になる特徴量（音量・帯域ごとの音量・声の高さ・発話らしさ）。VMC のトラッキングの値は描画ループで入れる
+use crate::{config::AudioFeature, pitch::PitchTracker, resample::rms};
+use std::f32::consts::TAU;
+
+// 帯域の境目 (Hz)
+const LOW_CUTOFF: f32 = 250.0;
+const HIGH_CUTOFF: f32 = 2000.0;
+
+// 発話らしさが 1 になる音量（threshold の何倍か）
+const VAD_FULL: f32 = 4.0;
+
+const COUNT: usize = 8;
+
+fn slot(feature: AudioFeature) -> usize {
+    match feature {
+        AudioFeature::Level => 0,
+        AudioFeature::Low => 1,
+        AudioFeature::Mid => 2,
+        AudioFeature::High => 3,
+        AudioFeature::Pitch => 4,
+        AudioFeature::Vad => 5,
+        AudioFeature::MouthOpen => 6,
+        AudioFeature::Blink => 7,
+    }
+}
+
+/// Latest value of every audio feature.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Features {
+    values: [f32; COUNT],
+}
+
+impl Features {
+    pub fn get(&self, feature: AudioFeature) -> f32 {
+        self.values[slot(feature)]
+    }
+
+    pub fn set(&mut self, feature: AudioFeature, value: f32) {
+        self.values[slot(feature)] = value;
+    }
+}
+
+/// One-pole low-pass filter; subtract its output from the input for a high-pass.
+pub struct OnePole {
+    coefficient: f32,
+    state: f32,
+}
+
+impl OnePole {
+    pub fn new(cutoff: f32, rate: f32) -> Self {
+        Self {
+            coefficient: 1.0 - (-TAU * cutoff / rate).exp(),
+            state: 0.0,
+        }
+    }
+
+    pub fn process(&mut self, x: f32) -> f32 {
+        self.state += self.coefficient * (x - self.state);
+        self.state
+    }
+}
+
+/// Computes every [`AudioFeature`] from the main capture in the audio callback.
+pub struct Analyzer {
+    threshold: f32,
+    pitch: PitchTracker,
+    low: OnePole,
+    high: OnePole,
+    bands: [Vec<f32>; 3],
+}
+
+impl Analyzer {
+    /// `rate` is the sample rate of the mono input, `threshold` the speaking level.
+    pub fn new(rate: u32, threshold: f32) -> Self {
+        Self {
+            threshold,
+            pitch: PitchTracker::new(rate, threshold),
+            low: OnePole::new(LOW_CUTOFF, rate as f32),
+            high: OnePole::new(HIGH_CUTOFF, rate as f32),
+            bands: Default::default(),
+        }
+    }
+
+    pub fn update(&mut self, samples: &[f32], features: &mut Features) {
+        let [low, mid, high] = &mut self.bands;
+        low.clear();
+        mid.clear();
+        high.clear();
+        for &x in samples {
+            let l = self.low.process(x);
+            let h = x - self.high.process(x);
+            low.push(l);
+            mid.push(x - l - h);
+            high.push(h);
+        }
+        let level = rms(samples);
+        let pitch = self.pitch.update(samples);
+        features.set(AudioFeature::Level, level);
+        features.set(AudioFeature::Low, rms(low));
+        features.set(AudioFeature::Mid, rms(mid));
+        features.set(AudioFeature::High, rms(high));
+        features.set(AudioFeature::Pitch, pitch);
+
+        // 音量が閾値を超えた分に応じて上がり、声の高さが取れないときは半分にする
+        let loudness = level / self.threshold.max(f32::EPSILON);
+        let vad = ((loudness - 1.0) / (VAD_FULL - 1.0)).clamp(0.0, 1.0);
+        let vad = if pitch > 0.0 { vad } else { vad * 0.5 };
+        features.set(AudioFeature::Vad, vad);
+    }
+}
@@ -0,0 +1,144 @@
+// 共演者の口の動きを UDP でやり取りする。音声そのものではなく口の状態と音量だけを送るので、
+// 1パケット十数バイトで済み、細い回線でもホスト側の Darwin でスロットを動かせる
+use crate::{
+    avatar::Mouth,
+    config::FeedConfig,
+    state::{self, RenderState},
+    t,
+};
+use anyhow::{Context, Result};
+use std::{
+    io::ErrorKind,
+    net::UdpSocket,
+    time::{Duration, Instant},
+};
+
+// パケットの先頭（形式が変わったら末尾の数字を上げる）
+const MAGIC: &[u8; 4] = b"DRF1";
+// これだけ届かなければ口を閉じる（相手が落ちた、回線が切れた）
+const STALE: Duration = Duration::from_secs(1);
+// 名前の長さの上限（パケットを小さく保つ）
+const MAX_NAME: usize = 64;
+
+// MAGIC、口の状態、音量 (f32 LE)、名前
+fn encode(packet: &mut Vec<u8>, name: &str, mouth: Mouth, level: f32) {
+    packet.clear();
+    packet.extend_from_slice(MAGIC);
+    packet.push(match mouth {
+        Mouth::Idle => 0,
+        Mouth::Talking => 1,
+        Mouth::Whisper => 2,
+    });
+    packet.extend_from_slice(&level.to_le_bytes());
+    packet.extend_from_slice(name.as_bytes());
+}
+
+fn decode(packet: &[u8]) -> Option<(&str, Mouth, f32)> {
+    let rest = packet.strip_prefix(MAGIC)?;
+    let (&mouth, rest) = rest.split_first()?;
+    let (level, name) = rest.split_first_chunk::<4>()?;
+    let level = f32::from_le_bytes(*level);
+    let name = std::str::from_utf8(name).ok()?;
+    Some((name, Mouth::from_index(mouth as usize), level))
+}
+
+/// Sends this instance's mouth to a co-host's Darwin a few dozen times a second.
+pub struct Sender {
+    socket: UdpSocket,
+    name: String,
+    interval: Duration,
+    next: Instant,
+    // 送るパケット（毎回確保しない）
+    packet: Vec<u8>,
+}
+
+impl Sender {
+    pub fn new(config: &FeedConfig, target: &str) -> Result<Self> {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .and_then(|socket| socket.connect(target).map(|()| socket))
+            .or_else(|_| {
+                let socket = UdpSocket::bind("[::]:0")?;
+                socket.connect(target).map(|()| socket)
+            })
+            .with_context(|| t!("feed.send_failed", target))?;
+        socket.set_nonblocking(true)?;
+        let mut name = config.name.clone();
+        if name.len() > MAX_NAME {
+            let end = (0..=MAX_NAME)
+                .rev()
+                .find(|&i| name.is_char_boundary(i))
+                .unwrap_or(0);
+            name.truncate(end);
+        }
+        tracing::info!("{}", t!("feed.sending", name, target));
+        Ok(Self {
+            socket,
+            name,
+            interval: Duration::try_from_secs_f32(1.0 / config.rate)
+                .unwrap_or(Duration::from_millis(33)),
+            next: Instant::now(),
+            packet: Vec::with_capacity(MAGIC.len() + 5 + MAX_NAME),
+        })
+    }
+
+    /// Sends `mouth` and `level` unless the last packet went out less than an interval ago.
+    /// A packet that can't be sent right away is dropped; the next one replaces it.
+    pub fn update(&mut self, mouth: Mouth, level: f32, now: Instant) {
+        if now < self.next {
+            return;
+        }
+        self.next = now + self.interval;
+        encode(&mut self.packet, &self.name, mouth, level);
+        let _ = self.socket.send(&self.packet);
+    }
+}
+
+/// Receives feeds on `address` from a thread of its own and publishes each one to the
+/// slots waiting for its name. Feeds for other names are ignored.
+pub fn listen(address: &str, mut slots: Vec<(String, state::Writer)>) -> Result<()> {
+    let socket = UdpSocket::bind(address).with_context(|| t!("feed.listen_failed", address))?;
+    socket.set_read_timeout(Some(STALE / 4))?;
+    tracing::info!("{}", t!("feed.listening", address));
+    std::thread::spawn(move || {
+        // スロットごとに最後に届いた時刻
+        let mut seen: Vec<Option<Instant>> = vec![None; slots.len()];
+        let mut buffer = [0u8; 512];
+        loop {
+            match socket.recv_from(&mut buffer) {
+                Ok((length, from)) => {
+                    if let Some((name, mouth, level)) = decode(&buffer[..length]) {
+                        let now = Instant::now();
+                        for ((slot, writer), seen) in slots.iter_mut().zip(&mut seen) {
+                            if slot != name {
+                                continue;
+                            }
+                            if seen.is_none() {
+                                tracing::info!("{}", t!("feed.connected", name, from));
+                            }
+                            *seen = Some(now);
+                            writer.publish(RenderState {
+                                mouth,
+                                level,
+                                ..Default::default()
+                            });
+                        }
+                    }
+                }
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(e) => {
+                    tracing::warn!("{}", t!("feed.receive_failed", e));
+                    std::thread::sleep(STALE);
+                }
+            }
+            // 途切れたら口を閉じる
+            for ((name, writer), seen) in slots.iter_mut().zip(&mut seen) {
+                if seen.is_some_and(|seen| seen.elapsed() >= STALE) {
+                    tracing::info!("{}", t!("feed.lost", name));
+                    *seen = None;
+                    writer.publish(RenderState::default());
+                }
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,117 @@
+// 音量を見る前にかけるハイパス（机の振動などの低音）とローパス（ヒスノイズ）
+use crate::config::AudioConfig;
+use std::{
+    f32::consts::{FRAC_1_SQRT_2, TAU},
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+/// Cutoff frequencies shared between the capture threads and whoever adjusts them at run time.
+/// Zero disables a filter.
+#[derive(Debug, Default)]
+pub struct Cutoffs {
+    highpass: AtomicU32,
+    lowpass: AtomicU32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Band {
+    Highpass,
+    Lowpass,
+}
+
+impl Cutoffs {
+    pub fn new(config: &AudioConfig) -> Self {
+        let cutoffs = Self::default();
+        cutoffs.set(Band::Highpass, config.highpass);
+        cutoffs.set(Band::Lowpass, config.lowpass);
+        cutoffs
+    }
+
+    pub fn set(&self, band: Band, hz: Option<f32>) {
+        let value = hz.unwrap_or(0.0).max(0.0).to_bits();
+        match band {
+            Band::Highpass => self.highpass.store(value, Ordering::Relaxed),
+            Band::Lowpass => self.lowpass.store(value, Ordering::Relaxed),
+        }
+    }
+
+    fn get(&self, band: Band) -> f32 {
+        let bits = match band {
+            Band::Highpass => self.highpass.load(Ordering::Relaxed),
+            Band::Lowpass => self.lowpass.load(Ordering::Relaxed),
+        };
+        f32::from_bits(bits)
+    }
+}
+
+// RBJ のクックブックによる2次のフィルタ（Q = 1/√2 のバターワース特性）
+#[derive(Debug, Clone, Copy, Default)]
+struct Biquad {
+    b: [f32; 3],
+    a: [f32; 2],
+    x: [f32; 2],
+    y: [f32; 2],
+}
+
+impl Biquad {
+    fn new(band: Band, cutoff: f32, rate: f32) -> Self {
+        let w = TAU * cutoff / rate;
+        let (sin, cos) = w.sin_cos();
+        let alpha = sin / (2.0 * FRAC_1_SQRT_2);
+        let a0 = 1.0 + alpha;
+        let b = match band {
+            Band::Highpass => [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            Band::Lowpass => [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+        };
+        Self {
+            b: b.map(|b| b / a0),
+            a: [-2.0 * cos / a0, (1.0 - alpha) / a0],
+            x: [0.0; 2],
+            y: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x, self.x[0]];
+        self.y = [y, self.y[0]];
+        y
+    }
+}
+
+/// Per-capture filter state; picks up cutoff changes between callbacks.
+pub struct Filters {
+    rate: f32,
+    // 今使っているカットオフと、そのフィルタ
+    stages: [(Band, f32, Option<Biquad>); 2],
+}
+
+impl Filters {
+    /// `rate` is the analysis rate of the samples passed to [`Filters::process`].
+    pub fn new(rate: u32) -> Self {
+        Self {
+            rate: rate as f32,
+            stages: [(Band::Highpass, 0.0, None), (Band::Lowpass, 0.0, None)],
+        }
+    }
+
+    /// Filters `samples` in place with the current cutoffs.
+    pub fn process(&mut self, cutoffs: &Cutoffs, samples: &mut [f32]) {
+        for (band, current, biquad) in &mut self.stages {
+            let cutoff = cutoffs.get(*band);
+            if cutoff != *current {
+                *current = cutoff;
+                // ナイキスト周波数に近すぎると不安定になるので、かけない
+                *biquad = (cutoff > 0.0 && cutoff < self.rate * 0.45)
+                    .then(|| Biquad::new(*band, cutoff, self.rate));
+            }
+            if let Some(biquad) = biquad {
+                for sample in samples.iter_mut() {
+                    *sample = biquad.process(*sample);
+                }
+            }
+        }
+    }
+}
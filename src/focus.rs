@@ -0,0 +1,68 @@
+// 前面のアプリに合わせて表情やパーツを変える（ゲームならゲーム用の表情、エディタならタイピング用の小物）。
+// 見るのはアプリの名前とウィンドウのタイトルだけで、記録もせずどこにも送らない
+use crate::{
+    bus::{self, Bus, LayerSource},
+    config::{ExpressionSource, FocusConfig},
+    desktop::{Desktop, Focused},
+    schedule, t,
+};
+use anyhow::Result;
+use std::time::Duration;
+
+// 前面のアプリを見る間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches the focused application from a thread of its own and, when the matching rule
+/// changes, publishes its expression and the layers to hide.
+pub fn start(rules: &[FocusConfig], bus: Bus) -> Result<()> {
+    if rules.is_empty() {
+        return Ok(());
+    }
+    let desktop = Desktop::connect()?;
+    let rules = rules.to_vec();
+    std::thread::spawn(move || {
+        // 前に当てはまった規則（まだ一度も見ていなければ None）
+        let mut matched: Option<Option<usize>> = None;
+        loop {
+            let focused = desktop.focused();
+            let rule = focused
+                .as_ref()
+                .and_then(|focused| rules.iter().position(|rule| matches(rule, focused)));
+            if matched != Some(rule) {
+                match (rule, &focused) {
+                    (Some(_), Some(focused)) => {
+                        tracing::info!("{}", t!("focus.matched", focused.app))
+                    }
+                    _ => tracing::info!("{}", t!("focus.none")),
+                }
+                let expression = rule.and_then(|i| rules[i].expression.clone());
+                bus.publish(bus::Event::Expression(ExpressionSource::Focus, expression));
+                let layers = rules.iter().enumerate().map(|(i, config)| {
+                    (
+                        Some(i) == rule,
+                        config.show.as_slice(),
+                        config.hide.as_slice(),
+                    )
+                });
+                bus.publish(bus::Event::HideLayers(
+                    LayerSource::Focus,
+                    schedule::hidden_layers(layers),
+                ));
+                matched = Some(rule);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+    Ok(())
+}
+
+// 名前とタイトルに、規則の文字列が（大文字小文字を区別せず）含まれるか
+fn matches(rule: &FocusConfig, focused: &Focused) -> bool {
+    let contains = |text: &str, part: &Option<String>| {
+        part.as_ref()
+            .is_none_or(|part| text.to_lowercase().contains(&part.to_lowercase()))
+    };
+    (rule.app.is_some() || rule.title.is_some())
+        && contains(&focused.app, &rule.app)
+        && contains(&focused.title, &rule.title)
+}
@@ -0,0 +1,99 @@
+// 表情ガチャ: ホットキーや外からのトリガー（チャットボット、通知、MQTT など）で表情を
+// 重み付きのくじで引き、決まった時間だけ見せてから元に戻す
+use crate::{config::GachaConfig, t};
+use fastrand::Rng;
+use std::time::{Duration, Instant};
+use winit::keyboard::KeyCode;
+
+/// Draws a random expression and holds it for a while.
+pub struct Gacha {
+    config: GachaConfig,
+    rng: Rng,
+    // 引いた表情と、戻す時刻（長すぎて Instant に入らなければ None で、次に引くまで出しておく）
+    drawn: Option<(String, Option<Instant>)>,
+}
+
+impl Gacha {
+    pub fn new(config: &GachaConfig, rng: Rng) -> Self {
+        Self {
+            config: config.clone(),
+            rng,
+            drawn: None,
+        }
+    }
+
+    pub fn is_hotkey(&self, keycode: KeyCode) -> bool {
+        self.config.hotkey.as_deref() == Some(format!("{keycode:?}").as_str())
+    }
+
+    /// Whether a trigger from outside named `name` draws.
+    pub fn is_trigger(&self, name: &str) -> bool {
+        self.config.trigger == name
+    }
+
+    /// Draws one of `expressions` (all but `default` have the same chance when no weights
+    /// are configured). Ignored while the last draw is still showing, so a flood of
+    /// triggers doesn't flip the avatar around; a draw too long to time out is replaced.
+    pub fn draw<'a>(
+        &mut self,
+        expressions: impl Iterator<Item = &'a String>,
+        default: &str,
+        now: Instant,
+    ) {
+        if self
+            .drawn
+            .as_ref()
+            .is_some_and(|(_, until)| until.is_some_and(|until| now < until))
+        {
+            tracing::info!("{}", t!("gacha.busy"));
+            return;
+        }
+        let pool: Vec<(&String, f32)> = expressions
+            .filter_map(|name| match self.config.weights.is_empty() {
+                true => (name != default).then_some((name, 1.0)),
+                false => self.config.weights.get(name).map(|weight| (name, *weight)),
+            })
+            .filter(|(_, weight)| *weight > 0.0 && weight.is_finite())
+            .collect();
+        let total: f32 = pool.iter().map(|(_, weight)| weight).sum();
+        let Some(last) = pool.last() else {
+            tracing::warn!("{}", t!("gacha.empty"));
+            return;
+        };
+        // 丸めの誤差で最後まで届かなかったら最後の表情
+        let mut point = self.rng.f32() * total;
+        let name = pool
+            .iter()
+            .find(|(_, weight)| {
+                point -= weight;
+                point < 0.0
+            })
+            .unwrap_or(last)
+            .0;
+        let until = Duration::try_from_secs_f32(self.config.duration_secs)
+            .ok()
+            .and_then(|duration| now.checked_add(duration));
+        tracing::info!("{}", t!("gacha.drawn", name, self.config.duration_secs));
+        self.drawn = Some((name.clone(), until));
+    }
+
+    /// The drawn expression while it is showing.
+    pub fn expression(&self, now: Instant) -> Option<&str> {
+        self.drawn
+            .as_ref()
+            .filter(|(_, until)| until.is_none_or(|until| now < until))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Forgets the draw once its time is up.
+    pub fn update(&mut self, now: Instant) {
+        if self
+            .drawn
+            .as_ref()
+            .is_some_and(|(_, until)| until.is_some_and(|until| now >= until))
+        {
+            self.drawn = None;
+            tracing::info!("{}", t!("gacha.ended"));
+        }
+    }
+}
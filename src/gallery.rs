@@ -0,0 +1,147 @@
+use crate::{avatar::load_image, compose, config::Config, render::Renderer, t};
+use anyhow::Result;
+use winit::{
+    dpi::LogicalSize,
+    event::{ElementState, Event, KeyEvent, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    window::WindowBuilder,
+};
+
+// セル間の余白（ピクセル）
+const GAP: usize = 4;
+const CHECKER_SIZE: usize = 16;
+
+struct Cell<'a> {
+    expression: &'a str,
+    state: &'static str,
+    path: std::path::PathBuf,
+    offset: [i32; 2],
+    scale: f32,
+}
+
+/// Shows every configured expression/state frame side by side in a grid.
+pub fn run(config: &Config) -> Result<()> {
+    let mut cells = Vec::new();
+    for (name, expression) in &config.expressions {
+        for (state, frames) in expression.states() {
+            for frame in frames {
+                cells.push(Cell {
+                    expression: name,
+                    state,
+                    path: config.resolve(&frame.path),
+                    offset: frame.offset,
+                    scale: frame.scale,
+                });
+            }
+        }
+    }
+
+    let width = config.canvas.width as usize;
+    let height = config.canvas.height as usize;
+    let count = cells.len().max(1);
+    let cols = (count as f32).sqrt().ceil() as usize;
+    let rows = count.div_ceil(cols);
+    let cell_w = (width / cols).saturating_sub(GAP).max(1);
+    let cell_h = (height / rows).saturating_sub(GAP).max(1);
+
+    // 透過部分が分かるように市松模様の背景
+    let mut buffer = vec![0u8; width * height * 4];
+    for y in 0..height {
+        for x in 0..width {
+            let shade = if (x / CHECKER_SIZE + y / CHECKER_SIZE).is_multiple_of(2) {
+                0x99
+            } else {
+                0x66
+            };
+            let idx = (y * width + x) * 4;
+            buffer[idx..idx + 4].copy_from_slice(&[shade, shade, shade, 0xff]);
+        }
+    }
+
+    for (i, cell) in cells.iter().enumerate() {
+        let (row, col) = (i / cols, i % cols);
+        tracing::info!(
+            row,
+            col,
+            expression = cell.expression,
+            state = cell.state,
+            "{}",
+            t!(
+                "gallery.cell",
+                row + 1,
+                col + 1,
+                cell.expression,
+                cell.state,
+                cell.path.display()
+            )
+        );
+
+        // オフセットもセルの縮尺に合わせる
+        let offset = [
+            (cell.offset[0] as f32 * cell_w as f32 / width as f32).round() as i32,
+            (cell.offset[1] as f32 * cell_h as f32 / height as f32).round() as i32,
+        ];
+        let Some(image) = load_image(&cell.path, cell_w, cell_h, offset, cell.scale) else {
+            tracing::warn!("{}", t!("image.not_found", cell.path.display()));
+            continue;
+        };
+
+        let origin_x = col * (cell_w + GAP) + GAP / 2;
+        let origin_y = row * (cell_h + GAP) + GAP / 2;
+        for y in 0..cell_h {
+            let src = y * cell_w * 4;
+            let dst = ((origin_y + y) * width + origin_x) * 4;
+            compose::blend_row(
+                &mut buffer[dst..dst + cell_w * 4],
+                &image[src..src + cell_w * 4],
+            );
+        }
+    }
+
+    let event_loop = EventLoop::new()?;
+    let window = WindowBuilder::new()
+        .with_title(t!("gallery.title"))
+        .with_inner_size(LogicalSize::new(width as u32, height as u32))
+        .build(&event_loop)?;
+    let mut renderer = Renderer::new(&window, width as u32, height as u32)?;
+
+    event_loop.run(move |event, elwt| {
+        elwt.set_control_flow(ControlFlow::Wait);
+
+        let Event::WindowEvent { event, .. } = event else {
+            return;
+        };
+        match event {
+            WindowEvent::CloseRequested
+            | WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::Escape),
+                        state: ElementState::Pressed,
+                        ..
+                    },
+                ..
+            } => elwt.exit(),
+            WindowEvent::Resized(size) => {
+                if let Err(e) = renderer.resize_surface(size.width, size.height) {
+                    tracing::error!("{}", t!("render.resize_failed", e));
+                    elwt.exit();
+                }
+                window.request_redraw();
+            }
+            WindowEvent::RedrawRequested => {
+                if let Some(frame) = renderer.frame_mut() {
+                    frame.copy_from_slice(&buffer);
+                }
+                if let Err(e) = renderer.render(&window) {
+                    tracing::error!("{}", t!("render.render_failed", e));
+                    elwt.exit();
+                }
+            }
+            _ => {}
+        }
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,249 @@
+// 合成結果の回帰テスト: 決まった素材と状態で画面外に合成し、tests/golden の参照 PNG と比べる
+// 合成を意図して変えたときは DARWIN_BLESS=1 cargo test golden で参照を書き直す。
+// 一致しなかったときの結果は target/golden に書き出す。
+use crate::{
+    avatar::{self, Avatar, Mouth},
+    compose::{self, Transform},
+    dirty::Rect,
+};
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+
+const WIDTH: usize = 96;
+const HEIGHT: usize = 96;
+// sRGB の変換や拡大縮小の丸めの差は許す
+const TOLERANCE: u8 = 2;
+
+// 重ねるパーツと、その変換・不透明度
+struct Layer<'a> {
+    image: &'a [u8],
+    transform: Transform,
+    opacity: f32,
+}
+
+// 描画ループと同じ順に合成する: 本体、パーツ、色味、スロット
+fn render(
+    frame: &[u8],
+    transform: Transform,
+    layers: &[Layer],
+    tint: [f32; 3],
+    slots: &[(&[u8], [i32; 4])],
+) -> Vec<u8> {
+    let full = Rect::full(WIDTH, HEIGHT);
+    let mut output = vec![0u8; WIDTH * HEIGHT * 4];
+    compose::draw_region(&mut output, frame, WIDTH, HEIGHT, transform, full);
+    for layer in layers {
+        compose::draw_over(
+            &mut output,
+            layer.image,
+            WIDTH,
+            HEIGHT,
+            layer.transform,
+            layer.opacity,
+            full,
+        );
+    }
+    compose::tint(&mut output, WIDTH, full, tint);
+    for (frame, rect) in slots {
+        compose::draw_rect(&mut output, frame, WIDTH, HEIGHT, *rect, full);
+    }
+    output
+}
+
+// 縁のぼけた楕円（ストレートアルファ）。中は縦のグラデーション
+fn ellipse(width: u32, height: u32, top: [u8; 3], bottom: [u8; 3], alpha: u8) -> RgbaImage {
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    RgbaImage::from_fn(width, height, |x, y| {
+        let dx = (x as f32 + 0.5 - cx) / cx;
+        let dy = (y as f32 + 0.5 - cy) / cy;
+        let edge = ((1.0 - (dx * dx + dy * dy).sqrt()) * 8.0).clamp(0.0, 1.0);
+        let t = y as f32 / height as f32;
+        let mix = |i: usize| (top[i] as f32 * (1.0 - t) + bottom[i] as f32 * t).round() as u8;
+        Rgba([mix(0), mix(1), mix(2), (alpha as f32 * edge).round() as u8])
+    })
+}
+
+// 素材を PNG に書き出し、設定から読み込むときと同じ経路（拡大縮小・乗算済みへの変換）で読む
+struct Fixtures {
+    dir: PathBuf,
+}
+
+impl Fixtures {
+    // テストは並んで走るので、それぞれ別のディレクトリを使う
+    fn new(test: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("darwin-golden-{}-{test}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        Self { dir }
+    }
+
+    fn load(&self, name: &str, image: RgbaImage, offset: [i32; 2], scale: f32) -> Vec<u8> {
+        let path = self.dir.join(format!("{name}.png"));
+        image.save(&path).unwrap();
+        avatar::load_image(&path, WIDTH, HEIGHT, offset, scale).unwrap()
+    }
+
+    // 待機と発話の2枚を持つ立ち絵と、上に重ねる半透明のパーツ
+    fn avatar(&self) -> (Avatar, Vec<u8>) {
+        let body = ellipse(60, 90, [250, 200, 170], [120, 60, 160], 255);
+        let mut talking = body.clone();
+        // 発話の口
+        for (x, y, pixel) in talking.enumerate_pixels_mut() {
+            let (dx, dy) = (x as f32 - 30.0, y as f32 - 55.0);
+            if (dx / 10.0).powi(2) + (dy / 6.0).powi(2) <= 1.0 {
+                *pixel = Rgba([90, 10, 30, 255]);
+            }
+        }
+        // デモの立ち絵の画像を差し替える
+        let mut avatar = Avatar::demo(WIDTH, HEIGHT);
+        let expression = avatar.expressions.get_mut(&avatar.default).unwrap();
+        expression.idle = vec![self.load("idle", body, [0, 4], 0.8)];
+        expression.talking = vec![self.load("talking", talking, [0, 4], 0.8)];
+        let glasses = self.load(
+            "glasses",
+            ellipse(40, 12, [40, 200, 255], [40, 120, 255], 160),
+            [0, -14],
+            0.5,
+        );
+        (avatar, glasses)
+    }
+}
+
+impl Drop for Fixtures {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+// 乗算済みの結果をストレートアルファの PNG として参照と比べる
+fn check(name: &str, output: &[u8]) {
+    let mut rgba = output.to_vec();
+    compose::unpremultiply(&mut rgba);
+    let actual = RgbaImage::from_raw(WIDTH as u32, HEIGHT as u32, rgba).unwrap();
+    let reference = golden_dir().join(format!("{name}.png"));
+    if std::env::var_os("DARWIN_BLESS").is_some() {
+        std::fs::create_dir_all(golden_dir()).unwrap();
+        actual.save(&reference).unwrap();
+        return;
+    }
+
+    let expected = image::open(&reference)
+        .unwrap_or_else(|e| panic!("{}: {e} (DARWIN_BLESS=1 で作る)", reference.display()))
+        .to_rgba8();
+    assert_eq!(expected.dimensions(), actual.dimensions(), "{name}");
+    let (mut differing, mut worst) = (0, 0);
+    for (a, e) in actual.pixels().zip(expected.pixels()) {
+        let diff = (0..4).map(|i| a[i].abs_diff(e[i])).max().unwrap();
+        // 完全に透明なら色は意味を持たない
+        if diff > TOLERANCE && (a[3] != 0 || e[3] != 0) {
+            differing += 1;
+            worst = worst.max(diff);
+        }
+    }
+    if differing > 0 {
+        let out = Path::new(env!("CARGO_MANIFEST_DIR")).join("target/golden");
+        std::fs::create_dir_all(&out).unwrap();
+        actual.save(out.join(format!("{name}.png"))).unwrap();
+        panic!(
+            "{name}: {differing} pixels differ from {} by up to {worst} (result in {})",
+            reference.display(),
+            out.display()
+        );
+    }
+}
+
+#[test]
+fn mouth_states() {
+    let fixtures = Fixtures::new("mouth_states");
+    let (avatar, _) = fixtures.avatar();
+    for (name, mouth) in [("idle", Mouth::Idle), ("talking", Mouth::Talking)] {
+        let frame = avatar.frame("default", mouth, 0).unwrap();
+        check(
+            name,
+            &render(frame, Transform::default(), &[], [1.0; 3], &[]),
+        );
+    }
+    // ささやきの画像がなければ待機と同じ
+    let whisper = avatar.frame("default", Mouth::Whisper, 0).unwrap();
+    let idle = avatar.frame("default", Mouth::Idle, 0).unwrap();
+    assert_eq!(whisper, idle);
+}
+
+#[test]
+fn transformed() {
+    let fixtures = Fixtures::new("transformed");
+    let (avatar, _) = fixtures.avatar();
+    let frame = avatar.frame("default", Mouth::Idle, 0).unwrap();
+    let transform = Transform {
+        offset: [6.0, -4.0],
+        scale: 0.85,
+        rotation: 12.0,
+        ..Transform::default()
+    };
+    check("transformed", &render(frame, transform, &[], [1.0; 3], &[]));
+}
+
+#[test]
+fn layers_and_tint() {
+    let fixtures = Fixtures::new("layers_and_tint");
+    let (avatar, glasses) = fixtures.avatar();
+    let frame = avatar.frame("default", Mouth::Talking, 0).unwrap();
+    let layers = [
+        Layer {
+            image: &glasses,
+            transform: Transform::default(),
+            opacity: 1.0,
+        },
+        // 回転した半透明のパーツ
+        Layer {
+            image: &glasses,
+            transform: Transform {
+                offset: [0.0, 30.0],
+                scale: 1.2,
+                rotation: -20.0,
+                ..Transform::default()
+            },
+            opacity: 0.5,
+        },
+    ];
+    check(
+        "layers",
+        &render(frame, Transform::default(), &layers, [1.0; 3], &[]),
+    );
+    check(
+        "tinted",
+        &render(frame, Transform::default(), &layers, [1.1, 0.8, 0.6], &[]),
+    );
+}
+
+#[test]
+fn slots() {
+    let fixtures = Fixtures::new("slots");
+    let (avatar, _) = fixtures.avatar();
+    let idle = avatar.frame("default", Mouth::Idle, 0).unwrap();
+    let talking = avatar.frame("default", Mouth::Talking, 0).unwrap();
+    // 縮小したもう1体と、キャンバスからはみ出す1体
+    let slots: [(&[u8], [i32; 4]); 2] = [(talking, [4, 52, 40, 40]), (idle, [70, -10, 48, 48])];
+    check(
+        "slots",
+        &render(idle, Transform::default(), &[], [1.0; 3], &slots),
+    );
+}
+
+#[test]
+fn demo_avatar() {
+    // 組み込みのデモは、口と目がそれぞれ別のフレームで動く
+    let avatar = Avatar::demo(WIDTH, HEIGHT);
+    let idle = avatar.frame("default", Mouth::Idle, 0).unwrap();
+    let talking = avatar.frame("default", Mouth::Talking, 0).unwrap();
+    let blink = avatar.blink("default").unwrap();
+    assert_ne!(idle, talking);
+    assert_ne!(idle, blink);
+    check(
+        "demo",
+        &render(idle, Transform::default(), &[], [1.0; 3], &[]),
+    );
+}
@@ -0,0 +1,134 @@
+use crate::{
+    bus::{self, Bus},
+    t,
+};
+use std::time::{Duration, Instant};
+
+// この値以上のサンプルはクリップしているとみなす
+const CLIP_LEVEL: f32 = 0.99;
+// バッファ内でクリップしたサンプルの割合がこれを超えたら「クリップ中」
+const CLIP_RATIO: f32 = 0.01;
+const CLIP_AFTER: Duration = Duration::from_millis(500);
+const CLIP_CLEAR_AFTER: Duration = Duration::from_secs(1);
+// 仮想デバイスが止まっていると完全な 0 が届き続ける
+const SILENCE_AFTER: Duration = Duration::from_secs(3);
+
+/// Problem with the captured input that the user should know about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioWarning {
+    Clipping,
+    Silence,
+    /// No input device could be opened; the capture keeps retrying.
+    NoDevice,
+    /// The system denied microphone access (macOS); the capture keeps retrying.
+    PermissionDenied,
+}
+
+impl AudioWarning {
+    pub fn message(self) -> &'static str {
+        match self {
+            Self::Clipping => t!("health.clipping"),
+            Self::Silence => t!("health.silence"),
+            Self::NoDevice => t!("health.no_device"),
+            Self::PermissionDenied => t!("health.permission_denied"),
+        }
+    }
+
+    /// Whether there is no input at all, rather than a bad one.
+    pub fn is_unavailable(self) -> bool {
+        matches!(self, Self::NoDevice | Self::PermissionDenied)
+    }
+}
+
+/// Watches input buffers for sustained clipping or digital silence, and publishes changes
+/// to the bus (only for the main capture, which is what the UI shows).
+pub struct HealthMonitor {
+    bus: Option<Bus>,
+    warning: Option<AudioWarning>,
+    clipping_since: Option<Instant>,
+    last_clip: Instant,
+    silent_since: Option<Instant>,
+}
+
+impl HealthMonitor {
+    pub fn new(bus: Option<Bus>) -> Self {
+        Self {
+            bus,
+            warning: None,
+            clipping_since: None,
+            last_clip: Instant::now(),
+            silent_since: Some(Instant::now()),
+        }
+    }
+
+    /// Reports why the capture could not open its device ([`AudioWarning::NoDevice`] or
+    /// [`AudioWarning::PermissionDenied`]), or `None` once it did. Returns `true` when
+    /// this changed the warning.
+    pub fn set_unavailable(&mut self, reason: Option<AudioWarning>) -> bool {
+        let changed = match reason {
+            Some(_) => reason != self.warning,
+            None => self.warning.is_some_and(AudioWarning::is_unavailable),
+        };
+        if !changed {
+            return false;
+        }
+        if reason.is_none() {
+            // 開き直したデバイスは最初から様子を見る
+            let now = Instant::now();
+            self.clipping_since = None;
+            self.last_clip = now;
+            self.silent_since = Some(now);
+        }
+        self.set(reason);
+        true
+    }
+
+    pub fn update(&mut self, data: &[f32], now: Instant) {
+        if data.is_empty() {
+            return;
+        }
+
+        let clipped = data.iter().filter(|s| s.abs() >= CLIP_LEVEL).count();
+        if clipped as f32 / data.len() as f32 > CLIP_RATIO {
+            self.clipping_since.get_or_insert(now);
+            self.last_clip = now;
+        } else if now.duration_since(self.last_clip) >= CLIP_CLEAR_AFTER {
+            self.clipping_since = None;
+        }
+
+        if data.iter().all(|&s| s == 0.0) {
+            self.silent_since.get_or_insert(now);
+        } else {
+            self.silent_since = None;
+        }
+
+        let warning = if self
+            .clipping_since
+            .is_some_and(|since| now.duration_since(since) >= CLIP_AFTER)
+        {
+            Some(AudioWarning::Clipping)
+        } else if self
+            .silent_since
+            .is_some_and(|since| now.duration_since(since) >= SILENCE_AFTER)
+        {
+            Some(AudioWarning::Silence)
+        } else {
+            None
+        };
+
+        if warning != self.warning {
+            self.set(warning);
+        }
+    }
+
+    fn set(&mut self, warning: Option<AudioWarning>) {
+        match warning {
+            Some(w) => tracing::warn!("{}", w.message()),
+            None => tracing::info!("{}", t!("health.recovered")),
+        }
+        self.warning = warning;
+        if let Some(bus) = &self.bus {
+            bus.publish(bus::Event::AudioWarning(warning));
+        }
+    }
+}
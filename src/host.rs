@@ -0,0 +1,52 @@
+use crate::t;
+use anyhow::{Result, bail};
+use std::sync::OnceLock;
+
+// 選択したオーディオホスト（未選択なら cpal のデフォルト）
+static HOST: OnceLock<cpal::HostId> = OnceLock::new();
+
+// 機能フラグを付けてビルドしたときだけ使えるホストと、そのフラグ（ASIO は Windows だけ）
+const FEATURE_HOSTS: &[(&str, &str)] = if cfg!(windows) {
+    &[("JACK", "jack"), ("ASIO", "asio")]
+} else {
+    &[("JACK", "jack")]
+};
+
+/// Selects the audio host by name ("ALSA", "JACK", "ASIO", "WASAPI", ...), case-insensitively.
+/// Must be called before any audio stream is opened.
+pub fn select(name: Option<&str>) -> Result<()> {
+    let Some(name) = name else {
+        return Ok(());
+    };
+    let available = cpal::available_hosts();
+    let Some(id) = available
+        .iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+    else {
+        let names: Vec<&str> = cpal::ALL_HOSTS.iter().map(|id| id.name()).collect();
+        if let Some((host, feature)) = FEATURE_HOSTS
+            .iter()
+            .find(|(host, _)| host.eq_ignore_ascii_case(name))
+            && !names.contains(host)
+        {
+            bail!(t!("host.needs_feature", host, feature));
+        }
+        bail!(t!("host.unknown", name, names.join(", ")));
+    };
+    tracing::info!(host = id.name(), "{}", t!("host.selected", id.name()));
+    let _ = HOST.set(*id);
+    Ok(())
+}
+
+/// The host every audio stream should be opened on.
+pub fn host() -> cpal::Host {
+    HOST.get()
+        .and_then(|id| match cpal::host_from_id(*id) {
+            Ok(host) => Some(host),
+            Err(e) => {
+                tracing::warn!("{}", t!("host.unavailable", id.name(), e));
+                None
+            }
+        })
+        .unwrap_or_else(cpal::default_host)
+}
@@ -0,0 +1,331 @@
+// 組み込みの HTTP サーバー（フレームの配信、リモコン）で共通の小さな部分。
+// 1接続に1リクエストで、本文は読まない。応答したら閉じる。
+use crate::t;
+use anyhow::{Context, Result};
+use rustls::{
+    ServerConfig,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+};
+use std::{
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    path::Path,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+// リクエスト行とヘッダーの1行、ヘッダー全体の長さの上限（越えたら 431 を返して切る）
+const MAX_LINE: usize = 8 * 1024;
+const MAX_HEADERS: u64 = 32 * 1024;
+// 少しずつ送ってきて居座る接続も、これだけ経ったら切る
+const REQUEST_DEADLINE: Duration = Duration::from_secs(10);
+
+// 上限を越えたことを示す印。ほかの壊れた入力（UTF-8 でない行など）と分けて 431 を返す
+#[derive(Debug)]
+struct TooLarge;
+
+impl std::fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str("headers too large")
+    }
+}
+
+impl std::error::Error for TooLarge {}
+
+// 期限を過ぎたら読むのをやめる
+struct Deadline<R> {
+    inner: R,
+    until: Instant,
+}
+
+impl<R: Read> Read for Deadline<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if Instant::now() >= self.until {
+            return Err(std::io::Error::new(
+                ErrorKind::TimedOut,
+                "request took too long",
+            ));
+        }
+        self.inner.read(buf)
+    }
+}
+
+/// The request line and headers of an HTTP request.
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    query: String,
+    // 名前は小文字にしておく
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    /// Reads the request line and headers, answering 431 and failing when they are too long,
+    /// or 400 when they are malformed.
+    pub fn read(stream: &mut (impl Read + Write)) -> std::io::Result<Self> {
+        let request = Self::read_limited(&mut *stream);
+        if let Err(e) = &request {
+            if e.get_ref().is_some_and(|inner| inner.is::<TooLarge>()) {
+                respond(
+                    stream,
+                    "431 Request Header Fields Too Large",
+                    "text/plain; charset=utf-8",
+                    b"request header fields too large",
+                )?;
+            } else if e.kind() == ErrorKind::InvalidData {
+                respond(
+                    stream,
+                    "400 Bad Request",
+                    "text/plain; charset=utf-8",
+                    b"bad request",
+                )?;
+            }
+        }
+        request
+    }
+
+    fn read_limited(stream: &mut impl Read) -> std::io::Result<Self> {
+        let deadline = Deadline {
+            inner: stream,
+            until: Instant::now() + REQUEST_DEADLINE,
+        };
+        let mut reader = BufReader::new(deadline.take(MAX_HEADERS));
+        let too_large = || std::io::Error::new(ErrorKind::InvalidData, TooLarge);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if line.len() > MAX_LINE {
+            return Err(too_large());
+        }
+        let mut words = line.split_whitespace();
+        let method = words.next().unwrap_or("GET").to_string();
+        let target = words.next().unwrap_or("/");
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let (path, query) = (path.to_string(), query.to_string());
+        let mut headers = Vec::new();
+        let mut header = String::new();
+        loop {
+            if reader.read_line(&mut header)? == 0 {
+                // 空行の前に上限に届いたか、相手が送るのをやめた
+                if reader.get_ref().limit() == 0 {
+                    return Err(too_large());
+                }
+                break;
+            }
+            if header.len() > MAX_LINE {
+                return Err(too_large());
+            }
+            if header.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+            }
+            header.clear();
+        }
+        Ok(Self {
+            method,
+            path,
+            query,
+            headers,
+        })
+    }
+
+    /// The value of the header `name` (lowercase).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// A query parameter, percent-decoded.
+    pub fn param(&self, name: &str) -> Option<String> {
+        self.query.split('&').find_map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(key) == name).then(|| decode(value))
+        })
+    }
+}
+
+// %XX と + をもとの文字に
+fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = |i: usize| {
+            text.get(i..i + 2)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        };
+        match (bytes[i], hex(i + 1)) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                out.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Caps the connections served at once across the threads that serve them.
+#[derive(Clone)]
+pub struct Connections {
+    active: Arc<AtomicUsize>,
+    limit: usize,
+}
+
+/// One connection being served; frees its place when dropped.
+pub struct Connection(Arc<AtomicUsize>);
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl Connections {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            active: Arc::new(AtomicUsize::new(0)),
+            limit,
+        }
+    }
+
+    /// A place for a new connection, unless `limit` are already being served.
+    pub fn enter(&self) -> Option<Connection> {
+        self.active
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |active| {
+                (active < self.limit).then_some(active + 1)
+            })
+            .ok()
+            .map(|_| Connection(self.active.clone()))
+    }
+}
+
+/// Server settings for HTTPS from a PEM certificate chain and private key.
+pub fn tls_config(cert: &Path, key: &Path) -> Result<Arc<ServerConfig>> {
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| t!("http.bad_cert", cert.display()))?;
+    let key =
+        PrivateKeyDer::from_pem_file(key).with_context(|| t!("http.bad_key", key.display()))?;
+    let config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_no_client_auth()
+            .with_single_cert(certs, key)?;
+    Ok(Arc::new(config))
+}
+
+/// Whether two secrets are equal, taking the same time wherever they differ.
+pub fn same_secret(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+pub fn respond(
+    stream: &mut impl Write,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\n\
+         Cache-Control: no-cache\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // 読むのはリクエスト、書くのは応答
+    struct Exchange {
+        request: Cursor<Vec<u8>>,
+        response: Vec<u8>,
+    }
+
+    impl Exchange {
+        fn new(request: impl Into<Vec<u8>>) -> Self {
+            Self {
+                request: Cursor::new(request.into()),
+                response: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Exchange {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.request.read(buf)
+        }
+    }
+
+    impl Write for Exchange {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.response.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn reads_request_line_and_headers() {
+        let mut exchange = Exchange::new("GET /api/status?token=a%20b HTTP/1.1\r\nHost: x\r\n\r\n");
+        let request = Request::read(&mut exchange).unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("GET", "/api/status")
+        );
+        assert_eq!(request.param("token").as_deref(), Some("a b"));
+        assert_eq!(request.header("host"), Some("x"));
+        assert!(exchange.response.is_empty());
+    }
+
+    #[test]
+    fn rejects_oversized_header_line() {
+        let header = format!("X-Long: {}\r\n", "a".repeat(MAX_LINE));
+        let mut exchange = Exchange::new(format!("GET / HTTP/1.1\r\n{header}\r\n"));
+        let error = Request::read(&mut exchange).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(exchange.response.starts_with(b"HTTP/1.1 431 "));
+    }
+
+    #[test]
+    fn rejects_endless_headers() {
+        // 1行ずつは短くても、合わせて上限を越えれば切る
+        let mut request = b"GET / HTTP/1.1\r\n".to_vec();
+        request.extend(b"X-A: b\r\n".repeat(MAX_HEADERS as usize));
+        let mut exchange = Exchange::new(request);
+        let error = Request::read(&mut exchange).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(exchange.response.starts_with(b"HTTP/1.1 431 "));
+    }
+
+    #[test]
+    fn rejects_malformed_headers_as_bad_request() {
+        let mut exchange = Exchange::new(b"GET / HTTP/1.1\r\nX-A: \xff\r\n\r\n".to_vec());
+        let error = Request::read(&mut exchange).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(exchange.response.starts_with(b"HTTP/1.1 400 "));
+    }
+}
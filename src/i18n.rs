@@ -0,0 +1,2302 @@
+use std::{
+    fmt::{Display, Write},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Ja,
+}
+
+impl Lang {
+    // "ja", "ja-JP", "ja_JP.UTF-8" などを受け付ける
+    pub fn parse(code: &str) -> Option<Self> {
+        let code = code.to_lowercase();
+        if code.starts_with("ja") {
+            Some(Lang::Ja)
+        } else if code.starts_with("en") {
+            Some(Lang::En)
+        } else {
+            None
+        }
+    }
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_lang(lang: Lang) {
+    CURRENT.store(lang as u8, Ordering::Relaxed);
+}
+
+pub fn lang() -> Lang {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Lang::Ja,
+        _ => Lang::En,
+    }
+}
+
+/// Picks the UI language: explicit setting first, then `DARWIN_LANG`, then the system locale.
+pub fn detect(setting: Option<&str>) -> Lang {
+    setting
+        .and_then(Lang::parse)
+        .or_else(|| {
+            std::env::var("DARWIN_LANG")
+                .ok()
+                .as_deref()
+                .and_then(Lang::parse)
+        })
+        .or_else(|| sys_locale::get_locale().as_deref().and_then(Lang::parse))
+        .unwrap_or(Lang::En)
+}
+
+// キー → (英語, 日本語)
+const MESSAGES: &[(&str, &str, &str)] = &[
+    // CLI ヘルプ
+    (
+        "cli.about",
+        "Audio-reactive avatar viewer",
+        "音声に反応するアバタービューア",
+    ),
+    (
+        "cli.config",
+        "Config file or avatar bundle directory",
+        "設定ファイルまたはアバターバンドルのディレクトリ",
+    ),
+    ("cli.log_format", "Log output format", "ログの出力形式"),
+    (
+        "cli.log_dir",
+        "Also write daily-rotated logs to this directory",
+        "日ごとにローテーションするログをこのディレクトリにも書き出す",
+    ),
+    (
+        "cli.validate",
+        "Check an avatar bundle or config for problems before publishing",
+        "公開前にアバターバンドルや設定の問題をチェックする",
+    ),
+    // ウィンドウ
+    (
+        "window.title",
+        "Image Viewer - ESC to exit, F to toggle fullscreen",
+        "Image Viewer - ESC で終了、F でフルスクリーン切り替え",
+    ),
+    (
+        "window.icon_failed",
+        "Cannot use {0} as the window icon: {1}",
+        "{0} をウィンドウのアイコンにできません: {1}",
+    ),
+    // 画像読み込み
+    (
+        "image.loading",
+        "Loading image from {0}...",
+        "画像を読み込み中: {0}",
+    ),
+    (
+        "image.loaded",
+        "Loaded image successfully",
+        "画像を読み込みました",
+    ),
+    (
+        "image.not_found",
+        "Cannot find image at {0}",
+        "画像が見つかりません: {0}",
+    ),
+    (
+        "quality.reduced",
+        "Frames are taking {1} ms to draw; lowering the quality to {0}",
+        "フレームの描画に {1} ms かかっています。画質を {0} に下げます",
+    ),
+    (
+        "quality.restored",
+        "Drawing has caught up; raising the quality to {0}",
+        "描画に余裕ができました。画質を {0} に戻します",
+    ),
+    (
+        "image.clip_missing",
+        "Layer \"{0}\" is not drawn: the layer it clips to is missing or clipped itself",
+        "パーツ \"{0}\" は表示しません: 切り抜く元のパーツが無いか、それ自身が切り抜かれています",
+    ),
+    (
+        "image.demo",
+        "No images found. Showing the built-in demo avatar",
+        "画像が見つかりません。組み込みのデモの立ち絵を表示します",
+    ),
+    // オーディオ
+    (
+        "audio.devices",
+        "Available input devices:",
+        "利用可能な入力デバイス:",
+    ),
+    (
+        "audio.loopback_found",
+        "Found loopback device: {0}",
+        "ループバックデバイスを検出: {0}",
+    ),
+    (
+        "audio.no_device",
+        "No input device available",
+        "入力デバイスがありません",
+    ),
+    ("audio.config", "Input config: {0}", "入力設定: {0}"),
+    (
+        "audio.resampling",
+        "Resampling input from {0} Hz to {1} Hz for analysis",
+        "解析のため入力を {0} Hz から {1} Hz に変換します",
+    ),
+    (
+        "audio.started",
+        "Audio capture started. Listening...",
+        "オーディオキャプチャを開始しました。待機中...",
+    ),
+    (
+        "audio.stream_error",
+        "Audio stream error: {0}",
+        "オーディオストリームエラー: {0}",
+    ),
+    (
+        "audio.overrun",
+        "Analysis fell behind; dropped {0} samples",
+        "解析が追いつかず、{0} サンプルを捨てました",
+    ),
+    (
+        "audio.stream_closed",
+        "The audio stream stopped",
+        "オーディオストリームが止まりました",
+    ),
+    (
+        "audio.capture_error",
+        "Audio capture error: {0}",
+        "オーディオキャプチャエラー: {0}",
+    ),
+    (
+        "audio.manual_control",
+        "Hold Space to move the mouth until an input device is found",
+        "入力デバイスが見つかるまで、Space を押している間は口が動きます",
+    ),
+    (
+        "audio.retrying",
+        "Looking for an input device again in {0} s",
+        "{0} 秒後に入力デバイスを探し直します",
+    ),
+    ("state.transition", "State transition", "状態遷移"),
+    (
+        "health.clipping",
+        "input is clipping, lower the input gain",
+        "入力がクリップしています。入力ゲインを下げてください",
+    ),
+    (
+        "health.silence",
+        "input is silent, check the audio device and routing",
+        "入力が無音です。オーディオデバイスと経路を確認してください",
+    ),
+    (
+        "health.no_device",
+        "no audio input device, looking for one again",
+        "入力デバイスがありません。探し直しています",
+    ),
+    (
+        "health.permission_denied",
+        "microphone access is denied, allow it in System Settings",
+        "マイクへのアクセスが許可されていません。システム設定で許可してください",
+    ),
+    (
+        "health.recovered",
+        "input level is back to normal",
+        "入力レベルが正常に戻りました",
+    ),
+    // マイクの許可
+    (
+        "permission.prompting",
+        "Asking for microphone access",
+        "マイクへのアクセスを求めています",
+    ),
+    (
+        "permission.denied",
+        "Microphone access is denied",
+        "マイクへのアクセスが許可されていません",
+    ),
+    (
+        "permission.open_failed",
+        "Failed to open System Settings: {0}",
+        "システム設定を開けませんでした: {0}",
+    ),
+    (
+        "permission.overlay_title",
+        "Darwin cannot hear the microphone",
+        "マイクの音声を受け取れません",
+    ),
+    (
+        "permission.overlay_body",
+        "Allow Darwin (or your terminal) under Privacy & Security > Microphone",
+        "プライバシーとセキュリティ > マイク で Darwin（またはターミナル）を許可してください",
+    ),
+    (
+        "permission.overlay_action",
+        "Click to open System Settings",
+        "クリックでシステム設定を開きます",
+    ),
+    // レイヤーシェル
+    (
+        "layer_shell.started",
+        "Showing the avatar on a Wayland layer-shell surface",
+        "Wayland のレイヤーシェルにアバターを表示します",
+    ),
+    (
+        "layer_shell.unavailable",
+        "Layer-shell output needs Linux with a Wayland compositor",
+        "レイヤーシェルでの表示には Linux と Wayland コンポジターが必要です",
+    ),
+    (
+        "layer_shell.no_wayland",
+        "Could not connect to a Wayland compositor",
+        "Wayland コンポジターに接続できませんでした",
+    ),
+    (
+        "layer_shell.unsupported",
+        "The compositor does not support wlr-layer-shell",
+        "コンポジターが wlr-layer-shell に対応していません",
+    ),
+    (
+        "layer_shell.failed",
+        "Layer-shell output failed: {0}",
+        "レイヤーシェルでの表示に失敗しました: {0}",
+    ),
+    (
+        "layer_shell.closed",
+        "The layer-shell surface was closed",
+        "レイヤーシェルの表示が閉じられました",
+    ),
+    // デスクトップマスコット
+    (
+        "mascot.no_positioning",
+        "This platform does not let the mascot move its window; it will stay in place",
+        "このプラットフォームではウィンドウを動かせないため、マスコットはその場に留まります",
+    ),
+    // インスタンス間の同期
+    (
+        "sync.hosting",
+        "Relaying expression changes between instances of this profile",
+        "このプロファイルのインスタンス間で表情の変更を中継します",
+    ),
+    (
+        "sync.joined",
+        "Keeping the expression in step with other instances of this profile",
+        "このプロファイルの他のインスタンスと表情を揃えます",
+    ),
+    (
+        "sync.retrying",
+        "Lost the other instances, looking for them again: {0}",
+        "他のインスタンスとの接続が切れたので探し直します: {0}",
+    ),
+    // 閾値の調整モード
+    (
+        "tuning.title",
+        "Tuning - talking {0}, whisper {1}, hold {2} ms (↑↓ talking, ←→ whisper, [ ] hold, Enter save, Esc cancel)",
+        "閾値の調整 - 発話 {0}、ささやき {1}、保持 {2} ms（↑↓ 発話、←→ ささやき、[ ] 保持、Enter で保存、Esc で取り消し）",
+    ),
+    ("tuning.off", "off", "なし"),
+    (
+        "tuning.started",
+        "Threshold tuning: adjust with the arrow keys and press Enter to save",
+        "閾値の調整: 矢印キーで動かし、Enter で保存します",
+    ),
+    (
+        "tuning.saved",
+        "Saved the thresholds to {0}",
+        "閾値を {0} に保存しました",
+    ),
+    (
+        "tuning.save_failed",
+        "Could not save the thresholds: {0}",
+        "閾値を保存できませんでした: {0}",
+    ),
+    (
+        "tuning.cancelled",
+        "Threshold tuning cancelled",
+        "閾値の調整を取り消しました",
+    ),
+    // 出力
+    (
+        "sink.start_failed",
+        "Could not start an output: {0}",
+        "出力を開始できませんでした: {0}",
+    ),
+    (
+        "sink.recording",
+        "Recording the output to {0}",
+        "出力を {0} に録画しています",
+    ),
+    (
+        "sink.recorded",
+        "Recorded {0} frames to {1}",
+        "{0} フレームを {1} に録画しました",
+    ),
+    (
+        "sink.failed",
+        "Recording to {0} stopped: {1}",
+        "{0} への録画が止まりました: {1}",
+    ),
+    (
+        "sink.disk_low",
+        "Only {1} MB left on the disk recording to {0}",
+        "{0} の録画先のディスクの空きが残り {1} MB です",
+    ),
+    (
+        "shm.started",
+        "Sharing frames through {0}",
+        "フレームを {0} で共有しています",
+    ),
+    (
+        "shm.open_failed",
+        "Could not create the shared memory file {0}",
+        "共有メモリのファイル {0} を作れませんでした",
+    ),
+    (
+        "web.started",
+        "Streaming the output at http://{0}/",
+        "出力を http://{0}/ で配信しています",
+    ),
+    (
+        "http.bad_cert",
+        "Could not read the certificate {0}",
+        "証明書 {0} を読めませんでした",
+    ),
+    (
+        "http.bad_key",
+        "Could not read the private key {0}",
+        "秘密鍵 {0} を読めませんでした",
+    ),
+    (
+        "web.bind_failed",
+        "Could not listen on {0}",
+        "{0} で待ち受けできませんでした",
+    ),
+    (
+        "web.encode_failed",
+        "Could not encode a frame for the HTTP stream: {0}",
+        "HTTP 配信用にフレームをエンコードできませんでした: {0}",
+    ),
+    // セッションの統計
+    (
+        "stats.written",
+        "Session statistics written to {0}",
+        "セッションの統計を {0} に書き出しました",
+    ),
+    (
+        "stats.write_failed",
+        "Could not write the session statistics to {0}",
+        "セッションの統計を {0} に書き出せませんでした",
+    ),
+    // 文字と棒グラフの枠
+    (
+        "panel.font_failed",
+        "Could not load the font {0}",
+        "フォント {0} を読み込めませんでした",
+    ),
+    // 発話時間
+    ("talk_time.label", "Talk {0} ({1}%)", "発話 {0} ({1}%)"),
+    (
+        "talk_time.failed",
+        "Talk time display disabled: {0}",
+        "発話時間の表示を無効にしました: {0}",
+    ),
+    // 予定
+    (
+        "schedule.bad_cron",
+        "Invalid cron expression \"{0}\" (minute hour day month weekday)",
+        "cron 式 \"{0}\" が正しくありません（分 時 日 月 曜日）",
+    ),
+    (
+        "schedule.bad_every",
+        "Invalid interval {0} minutes (must be at least one second)",
+        "間隔 {0} 分が正しくありません（1秒以上にしてください）",
+    ),
+    (
+        "schedule.cron_or_every",
+        "Schedule for \"{0}\" needs exactly one of cron and every",
+        "\"{0}\" の予定には cron と every のどちらか一方を指定してください",
+    ),
+    (
+        "schedule.triggered",
+        "Scheduled sequence: {0}",
+        "予定のシーケンス: {0}",
+    ),
+    (
+        "schedule.bad_time",
+        "Invalid time range \"{0}\" (for example \"22:00..06:00\")",
+        "時刻の範囲 \"{0}\" が正しくありません（例: \"22:00..06:00\"）",
+    ),
+    (
+        "schedule.bad_dates",
+        "Invalid date range \"{0}\" (month-day, for example \"12-01..01-06\")",
+        "日付の範囲 \"{0}\" が正しくありません（月-日。例: \"12-01..01-06\"）",
+    ),
+    (
+        "schedule.outfit",
+        "Outfit changed ({0} rules apply)",
+        "着替えました（当てはまる規則 {0} 件）",
+    ),
+    (
+        "schedule.failed",
+        "Schedules disabled: {0}",
+        "予定を無効にしました: {0}",
+    ),
+    // ポモドーロ
+    (
+        "pomodoro.work",
+        "Pomodoro: back to work",
+        "ポモドーロ: 作業に戻ります",
+    ),
+    (
+        "pomodoro.break",
+        "Pomodoro: break time ({0})",
+        "ポモドーロ: 休憩です（{0}）",
+    ),
+    (
+        "pomodoro.dismissed",
+        "Pomodoro: break ended early",
+        "ポモドーロ: 休憩を早めに切り上げました",
+    ),
+    ("pomodoro.work_label", "Work {0}", "作業 {0}"),
+    ("pomodoro.break_label", "Break {0}", "休憩 {0}"),
+    (
+        "pomodoro.failed",
+        "Pomodoro timer disabled: {0}",
+        "ポモドーロを無効にしました: {0}",
+    ),
+    // マウスを目で追う
+    (
+        "eyes.failed",
+        "Eye tracking of the mouse disabled: {0}",
+        "マウスを目で追う動きを無効にしました: {0}",
+    ),
+    // キーボードを打つ動き
+    (
+        "typing.failed",
+        "Typing reaction disabled: {0}",
+        "キーボードを打つ動きを無効にしました: {0}",
+    ),
+    (
+        "desktop.no_x11",
+        "could not connect to the X server (the cursor and focused window are not available under Wayland)",
+        "X サーバーに接続できません（Wayland ではマウスの位置や前面のウィンドウが分かりません）",
+    ),
+    (
+        "desktop.unsupported",
+        "not supported on this platform",
+        "この OS では使えません",
+    ),
+    // VMC のフェイストラッキング
+    (
+        "vmc.listening",
+        "Waiting for VMC tracking on {0}",
+        "{0} で VMC のトラッキングを待っています",
+    ),
+    (
+        "vmc.listen_failed",
+        "could not receive VMC on {0}",
+        "{0} で VMC を受け取れません",
+    ),
+    (
+        "vmc.receive_failed",
+        "Receiving VMC failed: {0}",
+        "VMC を受け取れませんでした: {0}",
+    ),
+    (
+        "vmc.connected",
+        "VMC tracking started",
+        "VMC のトラッキングが届きました",
+    ),
+    (
+        "vmc.lost",
+        "VMC tracking stopped; blinking at random again",
+        "VMC のトラッキングが途切れました。まばたきはランダムに戻ります",
+    ),
+    (
+        "vmc.failed",
+        "VMC tracking disabled: {0}",
+        "VMC のトラッキングを無効にしました: {0}",
+    ),
+    // 共演者の口の動き
+    (
+        "feed.sending",
+        "Sending the mouth as \"{0}\" to {1}",
+        "口の動きを \"{0}\" として {1} に送ります",
+    ),
+    (
+        "feed.send_failed",
+        "could not send the feed to {0}",
+        "{0} に口の動きを送れません",
+    ),
+    (
+        "feed.listening",
+        "Waiting for co-host feeds on {0}",
+        "{0} で共演者の口の動きを待っています",
+    ),
+    (
+        "feed.listen_failed",
+        "could not receive feeds on {0}",
+        "{0} で口の動きを受け取れません",
+    ),
+    (
+        "feed.receive_failed",
+        "Receiving a feed failed: {0}",
+        "口の動きを受け取れませんでした: {0}",
+    ),
+    (
+        "feed.connected",
+        "Feed \"{0}\" connected from {1}",
+        "\"{0}\" の口の動きが {1} から届きました",
+    ),
+    (
+        "feed.lost",
+        "Feed \"{0}\" stopped; closing its mouth",
+        "\"{0}\" の口の動きが途切れたので口を閉じます",
+    ),
+    (
+        "feed.failed",
+        "Level feed disabled: {0}",
+        "口の動きのやり取りを無効にしました: {0}",
+    ),
+    // 前面のアプリ
+    (
+        "focus.matched",
+        "Focused app \"{0}\" matches a focus rule",
+        "前面のアプリ \"{0}\" が規則に当てはまりました",
+    ),
+    (
+        "focus.none",
+        "Focused app matches no focus rule",
+        "前面のアプリはどの規則にも当てはまりません",
+    ),
+    (
+        "focus.failed",
+        "Focus rules disabled: {0}",
+        "前面のアプリの規則を無効にしました: {0}",
+    ),
+    // 表情ガチャ
+    (
+        "gacha.drawn",
+        "Gacha: drew \"{0}\" for {1} s",
+        "ガチャ: \"{0}\" を引きました（{1} 秒）",
+    ),
+    (
+        "gacha.busy",
+        "Gacha: still showing the last draw",
+        "ガチャ: 前に引いた表情の表示中です",
+    ),
+    (
+        "gacha.empty",
+        "Gacha: no expression to draw",
+        "ガチャ: 引ける表情がありません",
+    ),
+    (
+        "gacha.ended",
+        "Gacha: back to the usual expression",
+        "ガチャ: いつもの表情に戻ります",
+    ),
+    // 文字のオーバーレイのデータ
+    (
+        "data.failed",
+        "Could not read the {0} data: {1}",
+        "{0} のデータを読めませんでした: {1}",
+    ),
+    (
+        "data.no_playerctl",
+        "playerctl is needed to read the song that is playing",
+        "再生中の曲を読むには playerctl が必要です",
+    ),
+    (
+        "data.now_playing_unsupported",
+        "Reading the song that is playing is not supported on this platform",
+        "このプラットフォームでは再生中の曲を読めません",
+    ),
+    (
+        "data.bad_weather",
+        "Unexpected weather response",
+        "天気の応答の形式が想定と違います",
+    ),
+    ("weather.clear", "Clear", "晴れ"),
+    ("weather.cloudy", "Cloudy", "くもり"),
+    ("weather.fog", "Fog", "霧"),
+    ("weather.rain", "Rain", "雨"),
+    ("weather.snow", "Snow", "雪"),
+    ("weather.thunder", "Thunderstorm", "雷雨"),
+    (
+        "text.failed",
+        "Text overlays disabled: {0}",
+        "文字のオーバーレイを無効にしました: {0}",
+    ),
+    // テーマ
+    (
+        "theme.read_failed",
+        "Could not read the theme {0}",
+        "テーマ {0} を読めませんでした",
+    ),
+    (
+        "theme.none",
+        "No themes are configured",
+        "テーマが設定されていません",
+    ),
+    (
+        "theme.unknown",
+        "Unknown theme \"{0}\"",
+        "テーマ \"{0}\" はありません",
+    ),
+    ("theme.selected", "Theme: {0}", "テーマ: {0}"),
+    (
+        "theme.failed",
+        "Could not switch the theme: {0}",
+        "テーマを切り替えられませんでした: {0}",
+    ),
+    // スマートフォンのリモコン
+    (
+        "remote.started",
+        "Phone remote at {0} (press the QR hotkey to pair a phone)",
+        "スマートフォンのリモコン: {0}（QR コードのホットキーでスマートフォンとつなぐ）",
+    ),
+    (
+        "remote.unauthorized",
+        "Refused a remote request without the right token",
+        "トークンが合わないリモコンのリクエストを断りました",
+    ),
+    (
+        "remote.no_random",
+        "Could not make a token for the remote",
+        "リモコンのトークンを作れませんでした",
+    ),
+    (
+        "remote.failed",
+        "Phone remote disabled: {0}",
+        "スマートフォンのリモコンを無効にしました: {0}",
+    ),
+    ("remote.mute", "Mute", "ミュート"),
+    (
+        "remote.unmute",
+        "Muted — tap to unmute",
+        "ミュート中（タップで解除）",
+    ),
+    ("remote.expressions", "Expressions", "表情"),
+    ("remote.sequences", "Sequences", "シーケンス"),
+    (
+        "remote.offline",
+        "Cannot reach Darwin",
+        "Darwin に接続できません",
+    ),
+    (
+        "remote.viewers_on",
+        "Viewer reactions: on",
+        "視聴者のリアクション: 受付中",
+    ),
+    (
+        "remote.viewers_off",
+        "Viewer reactions: off",
+        "視聴者のリアクション: 停止中",
+    ),
+    (
+        "viewers.reacted",
+        "Viewer reaction from {0}: {1}",
+        "{0} さんからのリアクション: {1}",
+    ),
+    (
+        "viewers.refused",
+        "Refused a viewer reaction",
+        "視聴者のリアクションを断りました",
+    ),
+    (
+        "viewers.enabled",
+        "Viewer reactions turned on",
+        "視聴者のリアクションの受け付けを始めました",
+    ),
+    (
+        "viewers.paused",
+        "Viewer reactions turned off",
+        "視聴者のリアクションの受け付けを止めました",
+    ),
+    (
+        "remote.unpaired",
+        "Scan the QR code again to pair this phone",
+        "QR コードを読み直して、このスマートフォンをつないでください",
+    ),
+    // テスト信号
+    (
+        "test_signal.started",
+        "Analysing the test signal ({0}) instead of an input device",
+        "入力デバイスの代わりにテスト信号 ({0}) を解析しています",
+    ),
+    ("test_signal.phase", "Test signal: {0}", "テスト信号: {0}"),
+    // 流量制限
+    (
+        "rate_limit.cooling_down",
+        "Ignored trigger for sequence \"{0}\": still cooling down",
+        "シーケンス \"{0}\" のトリガーを無視しました（クールダウン中）",
+    ),
+    (
+        "rate_limit.skipped",
+        "Skipped the playing sequence and {0} waiting",
+        "再生中のシーケンスと、待っていた {0} 件を取りやめました",
+    ),
+    (
+        "rate_limit.queue_full",
+        "Dropped trigger for sequence \"{0}\": too many are waiting",
+        "シーケンス \"{0}\" のトリガーを捨てました（待ちが多すぎます）",
+    ),
+    // ウォッチドッグ
+    (
+        "watchdog.starting",
+        "Starting supervised process",
+        "監視対象のプロセスを起動します",
+    ),
+    (
+        "watchdog.crashed",
+        "Process exited abnormally ({0}), restarting",
+        "プロセスが異常終了しました ({0})。再起動します",
+    ),
+    (
+        "watchdog.giving_up",
+        "Crashed more than {0} times in {1}s, giving up",
+        "{1} 秒以内に {0} 回を超えて異常終了したため、再起動を中止します",
+    ),
+    (
+        "cli.watchdog",
+        "Restart automatically after a crash or GPU loss",
+        "クラッシュや GPU の喪失時に自動で再起動する",
+    ),
+    // プレビュー
+    (
+        "cli.preview",
+        "Open a small always-on-top preview window",
+        "常に最前面の小さなプレビューウィンドウを開く",
+    ),
+    ("preview.title", "Darwin Preview", "Darwin プレビュー"),
+    (
+        "preview.failed",
+        "Preview window failed: {0}",
+        "プレビューウィンドウでエラーが発生しました: {0}",
+    ),
+    // ギャラリー
+    (
+        "cli.gallery",
+        "Show every expression and state side by side",
+        "すべての表情と状態を並べて表示する",
+    ),
+    (
+        "cli.host",
+        "Audio host to use, e.g. ALSA, WASAPI or JACK (JACK requires the jack build feature)",
+        "使用するオーディオホスト（ALSA、WASAPI、JACK など。JACK は jack 機能を有効にしたビルドが必要）",
+    ),
+    ("gallery.title", "Darwin Gallery", "Darwin ギャラリー"),
+    (
+        "gallery.cell",
+        "Row {0}, column {1}: {2} / {3} ({4})",
+        "{0} 行 {1} 列: {2} / {3} ({4})",
+    ),
+    // 位置合わせ
+    (
+        "cli.align",
+        "Overlay two frames and nudge their offsets (arrows: move, Shift: x10, Tab: switch, R: reset, S: save)",
+        "2つのフレームを重ねてずれを調整する（矢印: 移動、Shift: 10倍、Tab: 切り替え、R: リセット、S: 保存）",
+    ),
+    (
+        "cli.align.first",
+        "First frame as expression/state/index (default: <default>/idle/0)",
+        "1つ目のフレーム（表情/状態/番号、既定: <既定の表情>/idle/0）",
+    ),
+    (
+        "cli.align.second",
+        "Second frame as expression/state/index (default: <default>/talking/0)",
+        "2つ目のフレーム（表情/状態/番号、既定: <既定の表情>/talking/0）",
+    ),
+    ("align.title", "Darwin Align", "Darwin 位置合わせ"),
+    (
+        "align.bad_ref",
+        "\"{0}\" is not expression/(idle|whisper|talking)/index",
+        "\"{0}\" は 表情/(idle|whisper|talking)/番号 の形式ではありません",
+    ),
+    (
+        "align.missing_frame",
+        "Frame {0} is not defined in the config",
+        "フレーム {0} は設定に定義されていません",
+    ),
+    (
+        "align.saved",
+        "Saved offsets to {0}",
+        "オフセットを {0} に保存しました",
+    ),
+    (
+        "align.save_failed",
+        "Failed to save offsets: {0}",
+        "オフセットを保存できません: {0}",
+    ),
+    // エディタ
+    (
+        "cli.edit",
+        "Edit frames, offsets and scales, preview with a test tone and save",
+        "フレーム・オフセット・拡大率を編集し、テストトーンで確認して保存する",
+    ),
+    (
+        "cli.import",
+        "Import a layered PSD, mapping mouth_*/eyes_* layers to states",
+        "レイヤー付き PSD を読み込み、mouth_*/eyes_* レイヤーを状態に割り当てる",
+    ),
+    (
+        "cli.import.psd",
+        "PSD file to import",
+        "読み込む PSD ファイル",
+    ),
+    (
+        "cli.import.out",
+        "Directory for the generated PNGs (default: next to the config, named after the PSD)",
+        "生成した PNG の保存先（既定: 設定ファイルと同じ場所の PSD 名のフォルダ）",
+    ),
+    ("editor.title", "Darwin Editor", "Darwin エディタ"),
+    ("editor.empty", "no frames", "フレームがありません"),
+    ("editor.test_tone", "test tone", "テストトーン"),
+    (
+        "editor.help",
+        "Tab/Shift+Tab: select frame, arrows: move (Shift: x10), +/-: scale, [/]: reorder, O: replace image, N: add frame, Delete: remove, T: test tone, S: save, Esc: quit",
+        "Tab/Shift+Tab: フレーム選択、矢印: 移動（Shift: 10倍）、+/-: 拡大縮小、[/]: 並べ替え、O: 画像を差し替え、N: フレーム追加、Delete: 削除、T: テストトーン、S: 保存、Esc: 終了",
+    ),
+    // オーディオホスト
+    (
+        "host.unknown",
+        "audio host \"{0}\" is not available in this build (hosts: {1})",
+        "オーディオホスト \"{0}\" はこのビルドでは使えません（ホスト: {1}）",
+    ),
+    (
+        "host.needs_feature",
+        "audio host {0} is not built in; rebuild with --features {1}",
+        "オーディオホスト {0} は組み込まれていません。--features {1} を付けてビルドしてください",
+    ),
+    ("host.selected", "Audio host: {0}", "オーディオホスト: {0}"),
+    (
+        "host.unavailable",
+        "audio host {0} could not be opened, using the default: {1}",
+        "オーディオホスト {0} を開けないため、デフォルトを使います: {1}",
+    ),
+    // モニター出力
+    (
+        "monitor.started",
+        "Monitoring input on {0}",
+        "{0} で入力をモニターしています",
+    ),
+    (
+        "monitor.device_not_found",
+        "monitor output device \"{0}\" not found, using the default output",
+        "モニター出力デバイス \"{0}\" が見つからないため、デフォルト出力を使います",
+    ),
+    (
+        "monitor.rate_mismatch",
+        "output device does not support {0} Hz, converting the monitor to {1} Hz",
+        "出力デバイスが {0} Hz に対応していないため、{1} Hz に変換してモニターします",
+    ),
+    (
+        "monitor.failed",
+        "monitor output disabled: {0}",
+        "モニター出力を無効にしました: {0}",
+    ),
+    (
+        "monitor.stream_error",
+        "monitor output error: {0}",
+        "モニター出力エラー: {0}",
+    ),
+    // 動画ソース
+    (
+        "video.spawn_failed",
+        "could not start ffmpeg, is it installed and on PATH?",
+        "ffmpeg を起動できません。インストールされ PATH が通っているか確認してください",
+    ),
+    ("video.started", "Video source: {0}", "動画ソース: {0}"),
+    (
+        "video.ended",
+        "Video source ended",
+        "動画ソースが終了しました",
+    ),
+    (
+        "video.failed",
+        "video source disabled: {0}",
+        "動画ソースを無効にしました: {0}",
+    ),
+    // シーケンス・効果音
+    (
+        "sequence.started",
+        "sequence started: {0}",
+        "シーケンス開始: {0}",
+    ),
+    (
+        "sequence.finished",
+        "sequence finished: {0}",
+        "シーケンス終了: {0}",
+    ),
+    (
+        "sound.no_device",
+        "no audio output device found",
+        "音声出力デバイスが見つかりません",
+    ),
+    (
+        "sound.unsupported_format",
+        "unsupported output sample format: {0}",
+        "未対応の出力サンプル形式です: {0}",
+    ),
+    (
+        "sound.unavailable",
+        "sound effects disabled: {0}",
+        "効果音を無効にしました: {0}",
+    ),
+    (
+        "sound.load_failed",
+        "failed to load sound {0}: {1}",
+        "効果音 {0} の読み込みに失敗しました: {1}",
+    ),
+    (
+        "sound.stream_error",
+        "sound output error: {0}",
+        "音声出力エラー: {0}",
+    ),
+    // PSD 読み込み
+    (
+        "psd.parse_failed",
+        "failed to read PSD {0}",
+        "PSD {0} の読み込みに失敗しました",
+    ),
+    (
+        "psd.unsupported",
+        "only 8-bit RGB PSDs are supported (depth {0}, color mode {1})",
+        "8bit RGB の PSD のみ対応しています（深度 {0}、カラーモード {1}）",
+    ),
+    (
+        "psd.compression",
+        "unsupported PSD channel compression {0} (save without ZIP compression)",
+        "未対応の PSD チャンネル圧縮 {0} です（ZIP 圧縮なしで保存してください）",
+    ),
+    (
+        "import.no_mouth",
+        "{0} has no mouth_open layer; name mouth layers mouth_closed, mouth_half and mouth_open",
+        "{0} に mouth_open レイヤーがありません。口のレイヤーを mouth_closed、mouth_half、mouth_open と名付けてください",
+    ),
+    (
+        "import.write_failed",
+        "failed to write {0}",
+        "{0} の書き出しに失敗しました",
+    ),
+    (
+        "import.mapped",
+        "{0}/{1}: {2} frame(s)",
+        "{0}/{1}: {2} フレーム",
+    ),
+    ("import.saved", "Saved {0}", "{0} に保存しました"),
+    // 色空間
+    (
+        "color.icc_failed",
+        "could not apply the ICC profile of {0}, using it as sRGB: {1}",
+        "{0} の ICC プロファイルを適用できないため、sRGB として扱います: {1}",
+    ),
+    // オフラインレンダリング
+    (
+        "cli.render",
+        "Render the avatar reacting to an audio file, without a window",
+        "音声ファイルに反応するアバターをウィンドウなしで書き出す",
+    ),
+    (
+        "cli.render.audio",
+        "WAV file to react to",
+        "反応させる WAV ファイル",
+    ),
+    (
+        "cli.render.out",
+        "Directory for numbered PNG frames, or a video file (.mp4, .mov, .webm, .mkv) encoded with ffmpeg",
+        "連番 PNG を書き出すディレクトリ、または ffmpeg で作る動画ファイル（.mp4, .mov, .webm, .mkv）",
+    ),
+    (
+        "cli.render.fps",
+        "Frames per second",
+        "1秒あたりのフレーム数",
+    ),
+    (
+        "offline.bad_fps",
+        "fps must be at least 1",
+        "fps は 1 以上にしてください",
+    ),
+    (
+        "offline.read_failed",
+        "failed to read audio file {0}",
+        "音声ファイル {0} を読み込めませんでした",
+    ),
+    (
+        "offline.write_failed",
+        "failed to write {0}",
+        "{0} の書き出しに失敗しました",
+    ),
+    (
+        "offline.ffmpeg_failed",
+        "encoding with ffmpeg failed (is ffmpeg installed?)",
+        "ffmpeg でのエンコードに失敗しました（ffmpeg はインストールされていますか？）",
+    ),
+    (
+        "offline.started",
+        "Rendering {0}: {1} frames",
+        "{0} を書き出しています: {1} フレーム",
+    ),
+    (
+        "offline.finished",
+        "Wrote {0} frames to {1}",
+        "{0} フレームを {1} に書き出しました",
+    ),
+    // 自動起動
+    (
+        "cli.instance",
+        "Run (or control) a named instance; instances sharing a config directory keep the same expression",
+        "名前を付けたインスタンスとして起動（または操作）する。設定ファイルのディレクトリが同じインスタンスは表情が揃う",
+    ),
+    (
+        "cli.portable",
+        "Keep the config, state and logs next to the executable, e.g. to run from a USB stick",
+        "設定・状態・ログを実行ファイルの隣に置く（USB メモリから起動するときなど）",
+    ),
+    (
+        "cli.gfx_backend",
+        "Graphics APIs to try in order (e.g. dx12,gl) before letting wgpu pick, to avoid driver bugs",
+        "順に試すグラフィックス API（dx12,gl など）。ドライバーの不具合を避けるときに。どれも駄目なら wgpu に任せる",
+    ),
+    (
+        "cli.gpus",
+        "List the GPUs that can draw the avatar, for [gpu] adapter",
+        "アバターを描画できる GPU を一覧する（[gpu] adapter 用）",
+    ),
+    (
+        "cli.paths",
+        "Show where the config, state, cache and logs are kept",
+        "設定・状態・キャッシュ・ログの置き場所を表示する",
+    ),
+    (
+        "cli.preset",
+        "Share thresholds, filters and animation settings without the images",
+        "閾値・フィルタ・アニメーションの設定を、画像を含めずに共有する",
+    ),
+    (
+        "cli.preset.export",
+        "Write the tuning settings of the config to a preset file",
+        "設定の調整値をプリセットのファイルに書き出す",
+    ),
+    (
+        "cli.preset.import",
+        "Replace the tuning settings of the config with a preset",
+        "設定の調整値をプリセットの値で置き換える",
+    ),
+    ("cli.preset.file", "Preset file", "プリセットのファイル"),
+    // プリセット
+    (
+        "preset.exported",
+        "Saved the preset to {0}",
+        "プリセットを {0} に保存しました",
+    ),
+    (
+        "preset.imported",
+        "Applied {0} to {1}",
+        "{0} を {1} に反映しました",
+    ),
+    (
+        "preset.read_failed",
+        "Cannot read the preset {0}",
+        "プリセット {0} を読み込めません",
+    ),
+    (
+        "preset.invalid",
+        "{0} is not a valid preset",
+        "{0} はプリセットとして正しくありません",
+    ),
+    // 置き場所
+    (
+        "paths.no_exe_dir",
+        "Cannot find the directory of the executable",
+        "実行ファイルのディレクトリが分かりません",
+    ),
+    (
+        "paths.portable",
+        "Portable mode: everything is kept in {0}",
+        "ポータブルモード: すべて {0} に置きます",
+    ),
+    ("paths.config", "Config: {0}", "設定ファイル: {0}"),
+    (
+        "paths.config_missing",
+        "Config: none (a new one is saved as {0})",
+        "設定ファイル: なし（新しく保存すると {0}）",
+    ),
+    (
+        "paths.config_dir",
+        "Config directory: {0}",
+        "設定の場所: {0}",
+    ),
+    ("paths.state", "State: {0}", "状態: {0}"),
+    ("paths.cache", "Cache: {0}", "キャッシュ: {0}"),
+    ("paths.logs", "Logs: {0}", "ログ: {0}"),
+    (
+        "paths.no_logs",
+        "Logs: not written to a file",
+        "ログ: ファイルには書きません",
+    ),
+    (
+        "cli.minimized",
+        "Start with the window minimized",
+        "ウィンドウを最小化した状態で起動する",
+    ),
+    (
+        "cli.autostart",
+        "Start Darwin minimized when you log in",
+        "ログイン時に Darwin を最小化して起動する",
+    ),
+    (
+        "cli.autostart.action",
+        "enable, disable, or show the current status",
+        "enable（有効）、disable（無効）、status（現在の状態）",
+    ),
+    (
+        "autostart.enabled",
+        "Launch at login is enabled ({0})",
+        "ログイン時の起動は有効です（{0}）",
+    ),
+    (
+        "autostart.disabled",
+        "Launch at login is disabled",
+        "ログイン時の起動は無効です",
+    ),
+    (
+        "autostart.no_home",
+        "could not find the home directory",
+        "ホームディレクトリが見つかりません",
+    ),
+    (
+        "autostart.write_failed",
+        "failed to register launch at login in {0}",
+        "{0} にログイン時の起動を登録できませんでした",
+    ),
+    // ローカル通信
+    (
+        "ipc.forwarded",
+        "Darwin is already running; passed the command line to it",
+        "Darwin は既に起動しているため、コマンドラインを渡しました",
+    ),
+    (
+        "ipc.unavailable",
+        "local control socket is unavailable: {0}",
+        "ローカル制御用のソケットを使えません: {0}",
+    ),
+    (
+        "ipc.listen_failed",
+        "failed to open the local control socket",
+        "ローカル制御用のソケットを開けませんでした",
+    ),
+    (
+        "ipc.unsafe_dir",
+        "{0} is not a directory only you can access",
+        "{0} は本人だけが使えるディレクトリではありません",
+    ),
+    (
+        "ipc.no_reply",
+        "the running instance did not reply",
+        "起動中のインスタンスから応答がありません",
+    ),
+    (
+        "ipc.unknown_command",
+        "unknown command: {0}",
+        "不明なコマンドです: {0}",
+    ),
+    ("ipc.received", "Received command", "コマンドを受信しました"),
+    (
+        "ipc.unknown_expression",
+        "unknown expression: {0}",
+        "不明な表情です: {0}",
+    ),
+    (
+        "ipc.unknown_sequence",
+        "unknown sequence: {0}",
+        "不明なシーケンスです: {0}",
+    ),
+    (
+        "ipc.filter_usage",
+        "usage: filter <highpass|lowpass> <hz|off>",
+        "使い方: filter <highpass|lowpass> <Hz|off>",
+    ),
+    (
+        "ipc.filter_changed",
+        "Filter cutoff changed",
+        "フィルタのカットオフを変更しました",
+    ),
+    (
+        "ipc.mute_usage",
+        "usage: mute [on|off]",
+        "使い方: mute [on|off]",
+    ),
+    ("ipc.muted", "Muted", "ミュートしました"),
+    ("ipc.unmuted", "Unmuted", "ミュートを解除しました"),
+    (
+        "ipc.not_running",
+        "Darwin is not running",
+        "Darwin は起動していません",
+    ),
+    (
+        "cli.ctl",
+        "Control the running instance (expression [name], sequence <name>, filter <highpass|lowpass> <hz|off>, mute [on|off], theme [name], gacha, fullscreen, status, quit)",
+        "起動中のインスタンスを操作する（expression [名前]、sequence <名前>、filter <highpass|lowpass> <Hz|off>、mute [on|off]、theme [名前]、gacha、fullscreen、status、quit）",
+    ),
+    (
+        "cli.ctl.command",
+        "Command and its arguments",
+        "コマンドと引数",
+    ),
+    ("ipc.opened", "Opened {0}", "{0} を開きました"),
+    (
+        "ipc.open_failed",
+        "could not open the forwarded avatar: {0}",
+        "渡されたアバターを開けませんでした: {0}",
+    ),
+    // 状態の読み上げ・通知
+    (
+        "announce.profile",
+        "Switched to the {0} profile",
+        "プロファイルを {0} に切り替えました",
+    ),
+    (
+        "announce.device_back",
+        "Audio input is back",
+        "音声の入力が戻りました",
+    ),
+    ("announce.said", "Announcing: {0}", "お知らせ: {0}"),
+    (
+        "announce.failed",
+        "Could not announce with {0}: {1}",
+        "{0} でお知らせできませんでした: {1}",
+    ),
+    // フォロー・サブスク通知
+    (
+        "alerts.connected",
+        "Listening for alerts from {0}",
+        "{0} の通知を待ち受けています",
+    ),
+    (
+        "alerts.connect_failed",
+        "Could not connect to {0}",
+        "{0} に接続できませんでした",
+    ),
+    (
+        "alerts.disconnected",
+        "Alert connection lost, retrying: {0}",
+        "通知の接続が切れました。再接続します: {0}",
+    ),
+    (
+        "alerts.closed",
+        "The server closed the connection",
+        "サーバーが接続を閉じました",
+    ),
+    (
+        "alerts.timed_out",
+        "No keepalive from the server",
+        "サーバーからキープアライブが届きません",
+    ),
+    (
+        "alerts.no_client_id",
+        "Twitch alerts need client_id in [alerts]",
+        "Twitch の通知には [alerts] の client_id が必要です",
+    ),
+    (
+        "alerts.no_user",
+        "The Twitch token does not belong to a user",
+        "Twitch のトークンがユーザーのものではありません",
+    ),
+    (
+        "alerts.subscribe_failed",
+        "Could not subscribe to {0} (check the token's scopes)",
+        "{0} を購読できませんでした（トークンのスコープを確認してください）",
+    ),
+    (
+        "alerts.revoked",
+        "Twitch revoked the {0} subscription",
+        "Twitch が {0} の購読を取り消しました",
+    ),
+    (
+        "alerts.follow",
+        "New follower: {0}",
+        "新しいフォロワー: {0}",
+    ),
+    (
+        "alerts.subscribe",
+        "New subscriber: {0}",
+        "新しいサブスク: {0}",
+    ),
+    ("alerts.raid", "Raid from {0}", "{0} さんからレイド"),
+    // 声の感情
+    (
+        "emotion.detected",
+        "Voice emotion changed",
+        "声の感情が変わりました",
+    ),
+    // 参照用の入力
+    (
+        "reference.started",
+        "Capturing reference input: {0}",
+        "参照用の入力をキャプチャしています: {0}",
+    ),
+    (
+        "reference.failed",
+        "Could not start the reference input: {0}",
+        "参照用の入力を開始できませんでした: {0}",
+    ),
+    // ビート検出
+    ("beat.locked", "Following the beat", "拍に合わせ始めました"),
+    ("beat.lost", "Lost the beat", "拍を見失いました"),
+    // 字幕
+    (
+        "captions.started",
+        "Transcribing captions with {0}",
+        "{0} で字幕を文字起こししています",
+    ),
+    (
+        "captions.failed",
+        "Captions are off: {0}",
+        "字幕を無効にしました: {0}",
+    ),
+    (
+        "captions.font_failed",
+        "Could not load caption font {0}",
+        "字幕のフォント {0} を読み込めませんでした",
+    ),
+    (
+        "captions.model_failed",
+        "Could not load speech recognition model {0}",
+        "音声認識のモデル {0} を読み込めませんでした",
+    ),
+    (
+        "captions.unavailable",
+        "this build has no speech recognition (build with --features stt)",
+        "このビルドには音声認識が入っていません（--features stt でビルドしてください）",
+    ),
+    // パーティクル
+    (
+        "particles.load_failed",
+        "Could not load particle image {0}: {1}",
+        "パーティクルの画像 {0} を読み込めませんでした: {1}",
+    ),
+    // MQTT
+    (
+        "mqtt.connected",
+        "Connected to MQTT broker {0}",
+        "MQTT ブローカー {0} に接続しました",
+    ),
+    (
+        "mqtt.connection_error",
+        "MQTT connection error, retrying: {0}",
+        "MQTT の接続エラーです。再接続します: {0}",
+    ),
+    (
+        "mqtt.triggered",
+        "MQTT trigger on {0}",
+        "{0} の MQTT トリガー",
+    ),
+    // Stream Deck
+    (
+        "cli.streamdeck",
+        "Run as a Stream Deck plugin (started by the Stream Deck application)",
+        "Stream Deck のプラグインとして動かす（Stream Deck アプリから起動される）",
+    ),
+    (
+        "cli.streamdeck.args",
+        "Arguments passed by Stream Deck (-port, -pluginUUID, -registerEvent, -info)",
+        "Stream Deck が渡す引数（-port, -pluginUUID, -registerEvent, -info）",
+    ),
+    (
+        "streamdeck.missing_arg",
+        "missing Stream Deck argument {0}",
+        "Stream Deck の引数 {0} がありません",
+    ),
+    (
+        "streamdeck.connect_failed",
+        "failed to connect to Stream Deck on port {0}",
+        "ポート {0} の Stream Deck に接続できませんでした",
+    ),
+    (
+        "streamdeck.connected",
+        "Connected to Stream Deck on port {0}",
+        "ポート {0} の Stream Deck に接続しました",
+    ),
+    (
+        "streamdeck.failed",
+        "Stream Deck is unavailable: {0}",
+        "Stream Deck を使えません: {0}",
+    ),
+    (
+        "streamdeck.disconnected",
+        "Stream Deck disconnected: {0}",
+        "Stream Deck との接続が切れました: {0}",
+    ),
+    (
+        "streamdeck.closed",
+        "closed by Stream Deck",
+        "Stream Deck が接続を閉じました",
+    ),
+    // リプレイ
+    (
+        "cli.record",
+        "Record state transitions to a replay file",
+        "状態の変化をリプレイファイルに記録する",
+    ),
+    (
+        "cli.replay",
+        "Play a replay file back instead of listening to audio",
+        "音声の代わりにリプレイファイルを再生する",
+    ),
+    (
+        "cli.test_signal",
+        "Analyse a built-in test tone or pink noise instead of the input, cycling through silence, whisper, talking and loud",
+        "入力の代わりに内蔵のテスト音かピンクノイズを解析する（無音・ささやき・発話・大声を繰り返す）",
+    ),
+    (
+        "cli.timings",
+        "Print how long each step of the startup took once the first frame is shown",
+        "最初のフレームを表示したときに、起動の各段階にかかった時間を表示する",
+    ),
+    // 起動時間
+    ("timings.header", "Startup times:", "起動にかかった時間:"),
+    ("timings.line", "  {0}: {1} ms", "  {0}: {1} ms"),
+    ("timings.devices", "Audio device", "音声デバイス"),
+    (
+        "timings.images",
+        "Loading and resizing images",
+        "画像の読み込みと拡大縮小",
+    ),
+    ("timings.surface", "Window surface", "ウィンドウの描画面"),
+    (
+        "timings.first_frame",
+        "First frame (total)",
+        "最初のフレーム（合計）",
+    ),
+    (
+        "replay.recording",
+        "Recording state transitions to {0}",
+        "状態の変化を {0} に記録しています",
+    ),
+    (
+        "replay.write_failed",
+        "failed to create replay file {0}",
+        "リプレイファイル {0} を作成できませんでした",
+    ),
+    (
+        "replay.record_failed",
+        "stopped recording the replay: {0}",
+        "リプレイの記録を中止しました: {0}",
+    ),
+    (
+        "replay.read_failed",
+        "failed to read replay file {0}",
+        "リプレイファイル {0} を読み込めませんでした",
+    ),
+    (
+        "replay.parse_failed",
+        "invalid event in {0} at line {1}",
+        "{0} の {1} 行目のイベントが不正です",
+    ),
+    (
+        "replay.playing",
+        "Replaying {0} ({1} events)",
+        "{0} を再生しています（{1} イベント）",
+    ),
+    (
+        "replay.recorded_with",
+        "Replay was recorded with {0}",
+        "リプレイは {0} で記録されました",
+    ),
+    (
+        "replay.finished",
+        "Replay finished",
+        "リプレイが終わりました",
+    ),
+    // レンダリング
+    ("render.surface_format", "Surface format", "サーフェス形式"),
+    (
+        "render.resize_failed",
+        "pixels.resize_surface failed: {0}",
+        "pixels.resize_surface に失敗しました: {0}",
+    ),
+    (
+        "render.render_failed",
+        "pixels.render() failed: {0}",
+        "pixels.render() に失敗しました: {0}",
+    ),
+    (
+        "render.recreating",
+        "Recreating render context (attempt {0})",
+        "描画コンテキストを再作成します（{0} 回目）",
+    ),
+    (
+        "render.recovered",
+        "Render context recovered",
+        "描画コンテキストを復旧しました",
+    ),
+    (
+        "render.recreate_failed",
+        "Failed to recreate render context: {0}",
+        "描画コンテキストを再作成できません: {0}",
+    ),
+    ("render.gpu_error", "GPU error: {0}", "GPU エラー: {0}"),
+    ("render.adapter", "Drawing with {0}", "{0} で描画します"),
+    (
+        "render.adapter_missing",
+        "No GPU named like \"{0}\"; letting wgpu pick one (see darwin gpus)",
+        "「{0}」という名前の GPU がありません。wgpu に選ばせます（darwin gpus で一覧）",
+    ),
+    ("render.adapter_line", "{0} ({1}, {2})", "{0}（{1}、{2}）"),
+    ("render.no_adapters", "No GPU found", "GPU が見つかりません"),
+    (
+        "render.backend_failed",
+        "Could not draw with {0}, trying the next one: {1}",
+        "{0} で描画できないので次を試します: {1}",
+    ),
+    (
+        "render.backend_default",
+        "None of the chosen graphics APIs worked; letting wgpu pick one",
+        "指定したグラフィックス API がどれも使えないので、wgpu に選ばせます",
+    ),
+    (
+        "render.adapter_not_on_backend",
+        "the GPU \"{0}\" is not available through this API",
+        "GPU「{0}」はこの API では使えません",
+    ),
+    (
+        "render.fatal",
+        "Rendering failed and could not continue",
+        "描画に失敗したため続行できません",
+    ),
+    (
+        "session.save_failed",
+        "Failed to save session state: {0}",
+        "セッション状態を保存できません: {0}",
+    ),
+    // 設定
+    (
+        "config.read_failed",
+        "Failed to read config {0}",
+        "設定ファイルを読み込めません: {0}",
+    ),
+    (
+        "config.parse_failed",
+        "Failed to parse config {0}",
+        "設定ファイルを解析できません: {0}",
+    ),
+    (
+        "config.bad_analysis_rate",
+        "audio.analysis_rate = {0} in {1} is outside 8000-192000 Hz",
+        "{1} の audio.analysis_rate = {0} が 8000〜192000 Hz の範囲外です",
+    ),
+    // validate
+    ("validate.error", "error", "エラー"),
+    ("validate.warning", "warning", "警告"),
+    ("validate.hint", "hint", "ヒント"),
+    (
+        "validate.summary",
+        "{0}: {1} error(s), {2} warning(s)",
+        "{0}: エラー {1} 件、警告 {2} 件",
+    ),
+    ("validate.failed", "validation failed", "検証に失敗しました"),
+    (
+        "validate.canvas_size",
+        "canvas size is {0}x{1}",
+        "キャンバスサイズが {0}x{1} です",
+    ),
+    (
+        "validate.canvas_size.hint",
+        "set [canvas] width and height to the output resolution, e.g. 1920 and 1080",
+        "[canvas] の width と height を出力解像度（例: 1920 と 1080）に設定してください",
+    ),
+    (
+        "validate.threshold",
+        "audio threshold {0} is out of range",
+        "音量閾値 {0} が範囲外です",
+    ),
+    (
+        "validate.threshold.hint",
+        "set [audio] threshold between 0.0 and 1.0 (exclusive); 0.001 is a good start",
+        "[audio] threshold を 0.0 より大きく 1.0 未満に設定してください（0.001 がおすすめ）",
+    ),
+    (
+        "validate.whisper_threshold",
+        "audio.whisper_threshold = {0} must be between 0 and audio.threshold ({1})",
+        "audio.whisper_threshold = {0} は 0 より大きく audio.threshold ({1}) 未満にしてください",
+    ),
+    (
+        "validate.whisper_threshold.hint",
+        "the whisper band is the level range just below the talking threshold",
+        "ささやき状態は発話の閾値のすぐ下の音量帯です",
+    ),
+    (
+        "validate.analysis_rate",
+        "audio.analysis_rate = {0} is outside 8000-192000 Hz",
+        "audio.analysis_rate = {0} が 8000〜192000 Hz の範囲外です",
+    ),
+    (
+        "validate.analysis_rate.hint",
+        "48000 works for every supported feature",
+        "48000 ならすべての機能で使えます",
+    ),
+    (
+        "validate.monitor_volume",
+        "audio.monitor.volume = {0} is outside 0.0-4.0",
+        "audio.monitor.volume = {0} が 0.0〜4.0 の範囲外です",
+    ),
+    (
+        "validate.monitor_volume.hint",
+        "1.0 plays the input unchanged; large values will distort",
+        "1.0 で入力そのままの音量です。大きすぎると音が割れます",
+    ),
+    (
+        "validate.preview_scale",
+        "preview scale {0} is out of range",
+        "プレビューの倍率 {0} が範囲外です",
+    ),
+    (
+        "validate.preview_scale.hint",
+        "set [preview] scale between 0.0 (exclusive) and 1.0, e.g. 0.25",
+        "[preview] scale を 0.0 より大きく 1.0 以下（例: 0.25）に設定してください",
+    ),
+    (
+        "validate.no_expressions",
+        "no expressions are defined",
+        "表情が定義されていません",
+    ),
+    (
+        "validate.no_expressions.hint",
+        "add at least one [expressions.<name>] table with idle and talking frames",
+        "idle と talking のフレームを持つ [expressions.<名前>] を1つ以上追加してください",
+    ),
+    (
+        "validate.default_missing",
+        "default_expression \"{0}\" does not exist",
+        "default_expression \"{0}\" が存在しません",
+    ),
+    (
+        "validate.default_missing.hint",
+        "set default_expression to one of: {0}",
+        "default_expression を次のいずれかに設定してください: {0}",
+    ),
+    (
+        "validate.no_frames",
+        "expression \"{0}\" has no {1} frames",
+        "表情 \"{0}\" に {1} フレームがありません",
+    ),
+    (
+        "validate.no_frames.hint",
+        "add `{1} = [\"...\"]` under [expressions.{0}]",
+        "[expressions.{0}] に `{1} = [\"...\"]` を追加してください",
+    ),
+    (
+        "validate.size_mismatch",
+        "expression \"{0}\": {1} is {2}x{3} but {4} is {5}x{6}",
+        "表情 \"{0}\": {1} は {2}x{3} ですが {4} は {5}x{6} です",
+    ),
+    (
+        "validate.size_mismatch.hint",
+        "export every frame of an expression at the same size so parts don't jump when switching",
+        "切り替え時にパーツがずれないよう、表情の全フレームを同じサイズで書き出してください",
+    ),
+    (
+        "validate.alpha_mismatch",
+        "expression \"{0}\": {1} and {2} disagree on transparency",
+        "表情 \"{0}\": {1} と {2} の透過の有無が一致しません",
+    ),
+    (
+        "validate.alpha_mismatch.hint",
+        "export all frames as PNG with alpha, or all without",
+        "全フレームをアルファ付き PNG で書き出すか、すべてアルファなしにしてください",
+    ),
+    (
+        "validate.missing_file",
+        "{0} does not exist",
+        "{0} が存在しません",
+    ),
+    (
+        "validate.missing_file.hint",
+        "check the file name and that the path is relative to the config file",
+        "ファイル名と、パスが設定ファイルからの相対パスになっているか確認してください",
+    ),
+    (
+        "validate.decode_failed",
+        "{0} could not be decoded: {1}",
+        "{0} をデコードできません: {1}",
+    ),
+    (
+        "validate.decode_failed.hint",
+        "re-export the image as PNG or JPEG",
+        "PNG または JPEG で書き出し直してください",
+    ),
+    (
+        "validate.zero_size",
+        "{0} has zero size",
+        "{0} のサイズが 0 です",
+    ),
+    (
+        "validate.zero_size.hint",
+        "re-export the image",
+        "画像を書き出し直してください",
+    ),
+    (
+        "validate.stretched",
+        "{0} is {1}x{2}, which will be stretched to the {3}x{4} canvas",
+        "{0} は {1}x{2} のため、{3}x{4} のキャンバスに引き伸ばされます",
+    ),
+    (
+        "validate.stretched.hint",
+        "match the image aspect ratio to the canvas or change [canvas] size",
+        "画像の縦横比をキャンバスに合わせるか、[canvas] のサイズを変更してください",
+    ),
+    (
+        "validate.too_small",
+        "{0} is {1}x{2}, less than half the canvas size",
+        "{0} は {1}x{2} で、キャンバスの半分未満のサイズです",
+    ),
+    (
+        "validate.too_small.hint",
+        "export at a higher resolution to avoid blurry upscaling",
+        "拡大でぼやけないよう、より高い解像度で書き出してください",
+    ),
+    (
+        "validate.transparent",
+        "{0} is fully transparent",
+        "{0} は完全に透明です",
+    ),
+    (
+        "validate.transparent.hint",
+        "check that the correct layer was exported",
+        "正しいレイヤーが書き出されているか確認してください",
+    ),
+    (
+        "validate.video_input",
+        "video file {0} does not exist",
+        "動画ファイル {0} が存在しません",
+    ),
+    (
+        "validate.video_input.hint",
+        "set video.format to read from a capture device instead of a file",
+        "キャプチャデバイスから読む場合は video.format を指定してください",
+    ),
+    (
+        "validate.video_state",
+        "video.states contains unknown state \"{0}\"",
+        "video.states に不明な状態 \"{0}\" があります",
+    ),
+    (
+        "validate.video_state.hint",
+        "use idle, whisper or talking",
+        "idle、whisper、talking のいずれかを指定してください",
+    ),
+    (
+        "validate.slot_rect",
+        "slot {0} rect {1} is empty or entirely outside the canvas",
+        "スロット {0} の範囲 {1} が空か、キャンバスの外にあります",
+    ),
+    (
+        "validate.slot_rect.hint",
+        "rect is [x, y, width, height] on the {0}x{1} canvas",
+        "rect は {0}x{1} のキャンバス上の [x, y, 幅, 高さ] です",
+    ),
+    (
+        "validate.slot_expression",
+        "slot {0} uses unknown expression \"{1}\"",
+        "スロット {0} が存在しない表情 \"{1}\" を指定しています",
+    ),
+    (
+        "validate.slot_feed_input",
+        "slot {0} has both input and feed",
+        "スロット {0} に input と feed の両方が指定されています",
+    ),
+    (
+        "validate.slot_feed_input.hint",
+        "a slot follows either an input device or a co-host's feed; remove one",
+        "スロットは入力デバイスか共演者のどちらか一方に合わせます。片方を消してください",
+    ),
+    (
+        "validate.slot_feed_listen",
+        "slot {0} waits for a feed, but feed.listen is not set",
+        "スロット {0} は共演者を待っていますが、feed.listen が指定されていません",
+    ),
+    (
+        "validate.slot_feed_listen.hint",
+        "set feed.listen to the address to receive on, for example \"0.0.0.0:9010\"",
+        "feed.listen に受け取るアドレスを指定してください（例: \"0.0.0.0:9010\"）",
+    ),
+    (
+        "validate.feed_rate",
+        "Invalid feed rate {0}",
+        "feed の送信回数 {0} が不正です",
+    ),
+    (
+        "validate.feed_rate.hint",
+        "set feed.rate to a positive number of packets per second, for example 30",
+        "feed.rate は1秒あたりの正の回数にしてください（例: 30）",
+    ),
+    (
+        "validate.sequence_empty",
+        "sequence \"{0}\" has no keyframes",
+        "シーケンス \"{0}\" にキーフレームがありません",
+    ),
+    (
+        "validate.sequence_empty.hint",
+        "add [[sequences.<name>.keyframes]] entries or remove the sequence",
+        "[[sequences.<name>.keyframes]] を追加するか、シーケンスを削除してください",
+    ),
+    (
+        "validate.hotkey_reserved",
+        "sequence \"{0}\" uses the reserved hotkey {1}",
+        "シーケンス \"{0}\" が予約済みのキー {1} を使っています",
+    ),
+    (
+        "validate.hotkey_reserved.hint",
+        "F toggles fullscreen, T opens threshold tuning and Escape quits; pick another key",
+        "F は全画面切り替え、T は閾値の調整、Escape は終了に使われます。別のキーを選んでください",
+    ),
+    (
+        "validate.hotkey_duplicate",
+        "hotkey {0} is bound to both \"{1}\" and \"{2}\"",
+        "キー {0} が \"{1}\" と \"{2}\" の両方に割り当てられています",
+    ),
+    (
+        "validate.hotkey_duplicate.hint",
+        "give each sequence its own hotkey",
+        "シーケンスごとに別のキーを割り当ててください",
+    ),
+    (
+        "validate.sequence_expression",
+        "sequence \"{0}\" switches to unknown expression \"{1}\"",
+        "シーケンス \"{0}\" が存在しない表情 \"{1}\" を指定しています",
+    ),
+    (
+        "validate.sequence_expression.hint",
+        "use a name defined under [expressions]",
+        "[expressions] に定義された名前を使ってください",
+    ),
+    (
+        "validate.mqtt_expression",
+        "MQTT trigger for {0} selects unknown expression \"{1}\"",
+        "{0} の MQTT トリガーが存在しない表情 \"{1}\" を指定しています",
+    ),
+    (
+        "validate.mqtt_sequence",
+        "MQTT trigger for {0} starts unknown sequence \"{1}\"",
+        "{0} の MQTT トリガーが存在しないシーケンス \"{1}\" を指定しています",
+    ),
+    (
+        "validate.mqtt_sequence.hint",
+        "use a name defined under [sequences]",
+        "[sequences] に定義された名前を使ってください",
+    ),
+    (
+        "validate.alerts_client_id.hint",
+        "register an application in the Twitch developer console and set its Client ID",
+        "Twitch の開発者コンソールでアプリを登録し、その Client ID を設定してください",
+    ),
+    (
+        "validate.alert_sequence",
+        "Alert starts unknown sequence \"{0}\"",
+        "通知が存在しないシーケンス \"{0}\" を指定しています",
+    ),
+    (
+        "validate.schedule_sequence",
+        "Schedule starts unknown sequence \"{0}\"",
+        "予定が存在しないシーケンス \"{0}\" を指定しています",
+    ),
+    (
+        "validate.outfit.hint",
+        "for example time = \"22:00..06:00\" or dates = \"12-01..12-31\"",
+        "例: time = \"22:00..06:00\" または dates = \"12-01..12-31\"",
+    ),
+    (
+        "validate.outfit_layer",
+        "Outfit rule refers to unknown layer \"{0}\"",
+        "着替えの規則が存在しないパーツ \"{0}\" を指定しています",
+    ),
+    (
+        "validate.focus_match",
+        "Focus rule has neither app nor title",
+        "前面のアプリの規則に app も title もありません",
+    ),
+    (
+        "validate.focus_match.hint",
+        "for example app = \"obs\" or title = \"Visual Studio Code\" (matched ignoring case)",
+        "例: app = \"obs\" または title = \"Visual Studio Code\"（大文字小文字は区別しません）",
+    ),
+    (
+        "validate.focus_expression",
+        "Focus rule uses unknown expression \"{0}\"",
+        "前面のアプリの規則が存在しない表情 \"{0}\" を指定しています",
+    ),
+    (
+        "validate.focus_layer",
+        "Focus rule refers to unknown layer \"{0}\"",
+        "前面のアプリの規則が存在しないパーツ \"{0}\" を指定しています",
+    ),
+    (
+        "validate.focus_priority",
+        "priority.order does not list \"focus\", so focus expressions never show",
+        "priority.order に \"focus\" が無いため、前面のアプリに合わせた表情が表示されません",
+    ),
+    (
+        "validate.focus_priority.hint",
+        "add \"focus\" to priority.order",
+        "priority.order に \"focus\" を加えてください",
+    ),
+    (
+        "validate.schedule.hint",
+        "for example cron = \"*/30 * * * *\" or every = 30",
+        "例: cron = \"*/30 * * * *\" または every = 30",
+    ),
+    (
+        "validate.pomodoro_minutes",
+        "Pomodoro periods must be positive (work {0}, break {1}, long break {2} minutes)",
+        "ポモドーロの時間は正の値にしてください（作業 {0} 分、休憩 {1} 分、長い休憩 {2} 分）",
+    ),
+    (
+        "validate.pomodoro_minutes.hint",
+        "set work_minutes, break_minutes and long_break_minutes in minutes",
+        "work_minutes・break_minutes・long_break_minutes を分で指定してください",
+    ),
+    (
+        "validate.pomodoro_expression",
+        "Pomodoro break uses unknown expression \"{0}\"",
+        "ポモドーロの休憩に存在しない表情 \"{0}\" を指定しています",
+    ),
+    (
+        "validate.gacha_duration",
+        "Invalid gacha duration {0}",
+        "ガチャの表示時間 {0} が不正です",
+    ),
+    (
+        "validate.gacha_duration.hint",
+        "set gacha.duration_secs to a positive number of seconds",
+        "gacha.duration_secs は正の秒数にしてください",
+    ),
+    (
+        "validate.gacha_expression",
+        "Gacha weights name unknown expression \"{0}\"",
+        "ガチャの重みに存在しない表情 \"{0}\" を指定しています",
+    ),
+    (
+        "validate.gacha_weight",
+        "Invalid gacha weight {1} for expression \"{0}\"",
+        "表情 \"{0}\" のガチャの重み {1} が不正です",
+    ),
+    (
+        "validate.gacha_weight.hint",
+        "use 0 or a positive number; 0 takes the expression out of the pool",
+        "0 以上の数にしてください。0 なら引かれません",
+    ),
+    (
+        "validate.gacha_priority",
+        "priority.order does not list \"gacha\", so drawn expressions never show",
+        "priority.order に \"gacha\" が無いため、引いた表情が表示されません",
+    ),
+    (
+        "validate.gacha_priority.hint",
+        "add \"gacha\" to priority.order",
+        "priority.order に \"gacha\" を加えてください",
+    ),
+    (
+        "validate.theme.hint",
+        "a theme sets any of background, text, accent, track (RGBA), font and radius",
+        "テーマには background・text・accent・track（RGBA）・font・radius を書けます",
+    ),
+    (
+        "validate.remote_token",
+        "The remote token is too short",
+        "リモコンのトークンが短すぎます",
+    ),
+    (
+        "validate.remote_token.hint",
+        "Use at least 16 characters, or leave token out to get a new one at each launch",
+        "16 文字以上にするか、token を省いて起動のたびに新しく作らせてください",
+    ),
+    (
+        "validate.viewer_reaction",
+        "Viewer reaction uses unknown sequence \"{0}\"",
+        "視聴者のリアクションに存在しないシーケンス \"{0}\" を指定しています",
+    ),
+    (
+        "validate.data_refresh",
+        "Data refresh intervals must be positive ({0} s, weather {1} min)",
+        "データを読み直す間隔は正の値にしてください（{0} 秒、天気 {1} 分）",
+    ),
+    (
+        "validate.data_refresh.hint",
+        "set data.refresh_secs in seconds and data.weather.refresh_minutes in minutes",
+        "data.refresh_secs を秒、data.weather.refresh_minutes を分で指定してください",
+    ),
+    (
+        "validate.emotion_expression",
+        "Emotion \"{0}\" selects unknown expression \"{1}\"",
+        "感情 \"{0}\" に存在しない表情 \"{1}\" を指定しています",
+    ),
+    (
+        "validate.eyes_layer",
+        "Eyes follow unknown layer \"{0}\"",
+        "目で追うパーツに存在しないパーツ \"{0}\" を指定しています",
+    ),
+    (
+        "validate.typing_layer",
+        "Typing shows unknown layer \"{0}\"",
+        "キーボードを打つ間に出すパーツに存在しないパーツ \"{0}\" を指定しています",
+    ),
+    (
+        "validate.smoothing_range",
+        "Smoothing of \"{0}\" has min {1} above max {2}",
+        "\"{0}\" の平滑化の min {1} が max {2} より大きくなっています",
+    ),
+    (
+        "validate.smoothing_range.hint",
+        "swap min and max, or remove one of them",
+        "min と max を入れ替えるか、片方を消してください",
+    ),
+    (
+        "validate.smoothing_deadzone",
+        "Invalid deadzone {1} for \"{0}\"",
+        "\"{0}\" の不感帯 {1} が不正です",
+    ),
+    (
+        "validate.smoothing_deadzone.hint",
+        "use 0 (no deadzone) or a positive width in the feature's own unit",
+        "0（不感帯なし）か、特徴量の単位での正の幅にしてください",
+    ),
+    (
+        "validate.mapping_parameter",
+        "Unknown mapping parameter \"{0}\"",
+        "対応表のパラメーター \"{0}\" は使えません",
+    ),
+    (
+        "validate.mapping_parameter.hint",
+        "use offset_x, offset_y, scale, rotation, tint, mouth or layer.<name>.<offset_x|offset_y|scale|rotation|opacity|visible>",
+        "offset_x, offset_y, scale, rotation, tint, mouth か layer.<名前>.<offset_x|offset_y|scale|rotation|opacity|visible> を指定してください",
+    ),
+    (
+        "validate.mapping_layer",
+        "Mapping \"{0}\" refers to unknown layer \"{1}\"",
+        "対応表の \"{0}\" が存在しないパーツ \"{1}\" を指定しています",
+    ),
+    (
+        "validate.mapping_layer.hint",
+        "use a name defined under [layers]",
+        "[layers] に定義された名前を使ってください",
+    ),
+    (
+        "validate.clip_layer",
+        "Layer \"{0}\" clips to \"{1}\", which is not another unclipped layer",
+        "パーツ \"{0}\" の切り抜き元 \"{1}\" が、切り抜かれていない別のパーツではありません",
+    ),
+    (
+        "validate.clip_layer.hint",
+        "set clip to the name of a layer under [layers] that has no clip of its own",
+        "clip には [layers] にある、clip を持たないパーツの名前を指定してください",
+    ),
+    (
+        "validate.clip_frame",
+        "Expression \"{0}\" frame {1} sets clip or slice, which only apply to layers",
+        "表情 \"{0}\" のフレーム {1} に clip か slice がありますが、これらはパーツでのみ使えます",
+    ),
+    (
+        "validate.clip_frame.hint",
+        "move the image under [layers] to clip or slice it",
+        "切り抜きや9スライスを使うには画像を [layers] に移してください",
+    ),
+    (
+        "validate.slice_too_wide",
+        "Layer \"{0}\": slice borders leave no middle in the {1}x{2} image",
+        "パーツ \"{0}\": slice の枠が {1}x{2} の画像いっぱいで、伸ばす部分が残りません",
+    ),
+    (
+        "validate.slice_too_wide.hint",
+        "make the borders thinner than the image, e.g. the size of its corners",
+        "枠は画像より細く、角の大きさ程度にしてください",
+    ),
+    (
+        "validate.quality_ratio",
+        "quality.recover_ratio = {0} should be between 0.0 and 1.0",
+        "quality.recover_ratio = {0} は 0.0 より大きく 1.0 より小さくしてください",
+    ),
+    (
+        "validate.quality_ratio.hint",
+        "at 1.0 or above the quality keeps switching back and forth under steady load",
+        "1.0 以上では、負荷が一定でも画質が上がったり下がったりを繰り返します",
+    ),
+    (
+        "validate.squash_intensity",
+        "squash.intensity = {0} is outside 0.0-1.0",
+        "squash.intensity = {0} が 0.0〜1.0 の範囲外です",
+    ),
+    (
+        "validate.squash_intensity.hint",
+        "0.1-0.2 gives a light bounce; 1.0 would flatten the avatar completely",
+        "0.1〜0.2 で軽く弾みます。1.0 ではアバターが完全につぶれます",
+    ),
+    (
+        "validate.voice_correlation",
+        "audio.voice_reference.correlation = {0} is outside 0.0-1.0",
+        "audio.voice_reference.correlation = {0} が 0.0〜1.0 の範囲外です",
+    ),
+    (
+        "validate.voice_correlation.hint",
+        "higher values ignore more game audio but may miss quiet speech",
+        "大きくするとゲーム音を拾いにくくなりますが、小さな声を取りこぼしやすくなります",
+    ),
+    (
+        "validate.adaptive",
+        "Invalid adaptive threshold: percentile {0}, window_secs {1}, range {2}..{3}",
+        "適応閾値の設定が正しくありません: percentile {0}, window_secs {1}, 範囲 {2}..{3}",
+    ),
+    (
+        "validate.adaptive.hint",
+        "percentile must be between 0 and 100, window_secs positive, and 0 < min <= max",
+        "percentile は 0〜100、window_secs は正の値、範囲は 0 < min <= max にしてください",
+    ),
+    (
+        "validate.echo_step",
+        "audio.echo_cancellation.step = {0} is outside 0.0-1.0",
+        "audio.echo_cancellation.step = {0} が 0.0〜1.0 の範囲外です",
+    ),
+    (
+        "validate.echo_step.hint",
+        "0.2 adapts within a few seconds without distorting speech",
+        "0.2 なら数秒で馴染み、声も崩れにくくなります",
+    ),
+    (
+        "validate.filter_cutoff",
+        "audio.highpass = {0} Hz is not below audio.lowpass = {1} Hz",
+        "audio.highpass = {0} Hz が audio.lowpass = {1} Hz 未満になっていません",
+    ),
+    (
+        "validate.filter_cutoff.hint",
+        "the band between the two cutoffs is what gets measured; 80 and 8000 suit most voices",
+        "2つのカットオフの間の帯域で音量を見ます。多くの声には 80 と 8000 が合います",
+    ),
+    (
+        "validate.music_bpm",
+        "Invalid BPM range {0}..{1}",
+        "BPM の範囲 {0}..{1} が正しくありません",
+    ),
+    (
+        "validate.music_bpm.hint",
+        "min_bpm must be positive and less than max_bpm",
+        "min_bpm は正の値で、max_bpm より小さくしてください",
+    ),
+    (
+        "validate.priority_duplicate",
+        "Expression source \"{0}\" appears more than once in priority.order",
+        "priority.order に表情の入力 \"{0}\" が複数回あります",
+    ),
+    (
+        "validate.priority_duplicate.hint",
+        "List each source (sequence, manual, gacha, timer, focus, remote, audio) at most once, highest priority first",
+        "各入力（sequence, manual, gacha, timer, focus, remote, audio）は1回だけ、優先度の高い順に並べてください",
+    ),
+    (
+        "validate.priority_timeout",
+        "Invalid timeout {1} for expression source \"{0}\"",
+        "表情の入力 \"{0}\" のタイムアウト {1} が正しくありません",
+    ),
+    (
+        "validate.priority_timeout.hint",
+        "Timeouts are in seconds and must not be negative; leave a source out to never time out",
+        "タイムアウトは秒数で、負の値にはできません。取り下げない入力は書かないでください",
+    ),
+    (
+        "validate.rate_limit_sequence",
+        "rate_limit.cooldowns names unknown sequence \"{0}\"",
+        "rate_limit.cooldowns に存在しないシーケンス \"{0}\" があります",
+    ),
+    (
+        "validate.rate_limit_cooldown",
+        "Invalid trigger cooldown {0}",
+        "トリガーのクールダウン {0} が正しくありません",
+    ),
+    (
+        "validate.rate_limit_cooldown.hint",
+        "Cooldowns are in seconds and must not be negative",
+        "クールダウンは秒数で、負の値にはできません",
+    ),
+    (
+        "validate.rate_limit_per_minute",
+        "rate_limit.per_minute is 0, so triggered sequences would never play",
+        "rate_limit.per_minute が 0 なので、トリガーされたシーケンスが再生されません",
+    ),
+    (
+        "validate.rate_limit_per_minute.hint",
+        "Set it to at least 1, or leave it out for no limit",
+        "1以上にするか、制限しないなら書かないでください",
+    ),
+    (
+        "validate.mascot",
+        "Invalid mascot speed {0} or rest range {1}..{2}",
+        "マスコットの速さ {0} または休憩の範囲 {1}..{2} が正しくありません",
+    ),
+    (
+        "validate.mascot.hint",
+        "speed and min_rest must not be negative, and min_rest must not exceed max_rest",
+        "speed と min_rest は負の値にせず、min_rest は max_rest 以下にしてください",
+    ),
+];
+
+/// Looks up a message in the current language, falling back to English and then the key itself.
+pub fn tr(key: &'static str) -> &'static str {
+    let lang = lang();
+    MESSAGES
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|(_, en, ja)| match lang {
+            Lang::En => *en,
+            Lang::Ja => *ja,
+        })
+        .unwrap_or(key)
+}
+
+// "{0}", "{1}" ... を引数で置き換える。前から一度だけ見ていくので、
+// 引数の中にある "{1}" などはそのまま残る
+pub fn format(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let arg = rest.find('}').and_then(|close| {
+            let arg = args.get(rest[1..close].parse::<usize>().ok()?)?;
+            Some((arg, close))
+        });
+        match arg {
+            Some((arg, close)) => {
+                let _ = write!(out, "{arg}");
+                rest = &rest[close + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[macro_export]
+macro_rules! t {
+    ($key:literal) => {
+        $crate::i18n::tr($key)
+    };
+    ($key:literal, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::format($crate::i18n::tr($key), &[$(&$arg as &dyn std::fmt::Display),+])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_substitutes_in_one_pass() {
+        assert_eq!(
+            format("{0} and {1}, {2} {x", &[&"{1}", &"b"]),
+            "{1} and b, {2} {x"
+        );
+    }
+}
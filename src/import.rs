@@ -0,0 +1,227 @@
+use crate::{
+    compose,
+    config::{Config, ExpressionConfig, FrameConfig},
+    psd::{Layer, Psd},
+    t,
+};
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+// 命名規則でレイヤーの役割を決める（"表情名/レイヤー名" で表情ごとに分けられる）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Base,
+    MouthIdle,
+    MouthWhisper,
+    MouthTalking,
+    EyesOpen,
+    EyesClosed,
+}
+
+fn classify(name: &str) -> (Option<&str>, Role) {
+    let (expression, layer) = match name.split_once('/') {
+        Some((expression, layer)) => (Some(expression.trim()), layer.trim()),
+        None => (None, name.trim()),
+    };
+    let layer = layer.to_lowercase();
+    let role = match layer.as_str() {
+        "mouth_closed" | "mouth_idle" => Role::MouthIdle,
+        "mouth_half" | "mouth_whisper" => Role::MouthWhisper,
+        "eyes_open" => Role::EyesOpen,
+        "eyes_closed" => Role::EyesClosed,
+        l if l.starts_with("mouth_open") || l.starts_with("mouth_talk") => Role::MouthTalking,
+        _ => Role::Base,
+    };
+    (expression, role)
+}
+
+/// Imports a layered PSD: composites one PNG per expression/state from the layer naming
+/// convention and writes the frame lists into the config.
+pub fn run(mut config: Config, psd_path: &Path, out: Option<PathBuf>) -> Result<()> {
+    let psd = Psd::open(psd_path)?;
+    let layers: Vec<(Option<&str>, Role, &Layer)> = psd
+        .layers
+        .iter()
+        .map(|layer| {
+            let (expression, role) = classify(&layer.name);
+            (expression, role, layer)
+        })
+        .collect();
+    if !layers
+        .iter()
+        .any(|(_, role, _)| *role == Role::MouthTalking)
+    {
+        bail!(t!("import.no_mouth", psd_path.display()));
+    }
+
+    let stem = psd_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "psd".to_string());
+    let out = out.unwrap_or_else(|| config.base_dir.join(&stem));
+    std::fs::create_dir_all(&out)?;
+
+    let mut expressions: BTreeSet<&str> = layers.iter().filter_map(|(e, _, _)| *e).collect();
+    let default = config.default_expression.clone();
+    expressions.insert(&default);
+
+    for expression in expressions {
+        // その表情専用のレイヤーがあればそれを、なければ共通のものを使う
+        let pick = |role: Role| -> Vec<&Layer> {
+            let own: Vec<&Layer> = layers
+                .iter()
+                .filter(|(e, r, _)| *r == role && *e == Some(expression))
+                .map(|(_, _, l)| *l)
+                .collect();
+            if !own.is_empty() {
+                return own;
+            }
+            layers
+                .iter()
+                .filter(|(e, r, _)| *r == role && e.is_none())
+                .map(|(_, _, l)| *l)
+                .collect()
+        };
+        let base: Vec<&Layer> = layers
+            .iter()
+            .filter(|(e, r, l)| *r == Role::Base && l.visible && e.is_none_or(|e| e == expression))
+            .map(|(_, _, l)| *l)
+            .collect();
+        let eyes_open = pick(Role::EyesOpen);
+        let eyes_closed = pick(Role::EyesClosed);
+        let idle = pick(Role::MouthIdle);
+        let whisper = pick(Role::MouthWhisper);
+        let talking = pick(Role::MouthTalking);
+
+        let write = |name: &str, state: &str, layers: Vec<&Layer>| -> Result<FrameConfig> {
+            let file = out.join(format!("{name}_{state}.png"));
+            let rgba = composite(&psd, &layers);
+            image::save_buffer(
+                &file,
+                &rgba,
+                psd.width as u32,
+                psd.height as u32,
+                image::ColorType::Rgba8,
+            )
+            .with_context(|| t!("import.write_failed", file.display()))?;
+            let path = file
+                .strip_prefix(&config.base_dir)
+                .map(Path::to_path_buf)
+                .unwrap_or(file);
+            Ok(FrameConfig::new(path))
+        };
+
+        let mut imported = ExpressionConfig {
+            idle: vec![write(
+                expression,
+                "idle",
+                parts(&base, &eyes_open, idle.first().copied()),
+            )?],
+            ..Default::default()
+        };
+        if let Some(mouth) = whisper.first() {
+            imported.whisper.push(write(
+                expression,
+                "whisper",
+                parts(&base, &eyes_open, Some(mouth)),
+            )?);
+        }
+        for (i, mouth) in talking.iter().enumerate() {
+            let state = if i == 0 {
+                "talking".to_string()
+            } else {
+                format!("talking{}", i + 1)
+            };
+            imported.talking.push(write(
+                expression,
+                &state,
+                parts(&base, &eyes_open, Some(mouth)),
+            )?);
+        }
+
+        // 目を閉じた差分は別の表情として書き出し、待機中のまばたきにも使う
+        if !eyes_closed.is_empty() {
+            let name = format!("{expression}_eyes_closed");
+            let closed = ExpressionConfig {
+                idle: vec![write(
+                    &name,
+                    "idle",
+                    parts(&base, &eyes_closed, idle.first().copied()),
+                )?],
+                talking: vec![write(
+                    &name,
+                    "talking",
+                    parts(&base, &eyes_closed, talking.first().copied()),
+                )?],
+                ..Default::default()
+            };
+            imported.blink = closed.idle.clone();
+            report(&name, &closed);
+            config.expressions.insert(name, closed);
+        }
+        report(expression, &imported);
+
+        config.expressions.insert(expression.to_string(), imported);
+    }
+
+    let file = config.save()?;
+    println!("{}", t!("import.saved", file.display()));
+    Ok(())
+}
+
+// 目と口以外のレイヤー + 目 + 状態ごとの口
+fn parts<'a>(base: &[&'a Layer], eyes: &[&'a Layer], mouth: Option<&'a Layer>) -> Vec<&'a Layer> {
+    let mut parts: Vec<&Layer> = base.iter().chain(eyes).copied().collect();
+    parts.extend(mouth);
+    parts
+}
+
+fn report(name: &str, expression: &ExpressionConfig) {
+    for (state, frames) in expression.states() {
+        if !frames.is_empty() {
+            println!("{}", t!("import.mapped", name, state, frames.len()));
+        }
+    }
+}
+
+fn composite(psd: &Psd, parts: &[&Layer]) -> Vec<u8> {
+    let mut canvas = vec![0u8; psd.width * psd.height * 4];
+    let premultiplied: Vec<Vec<u8>> = psd
+        .layers
+        .iter()
+        .map(|layer| {
+            let mut rgba = layer.rgba.clone();
+            compose::premultiply(&mut rgba);
+            rgba
+        })
+        .collect();
+    // PSD 上の順番（下から上）で重ねる
+    for (layer, rgba) in psd
+        .layers
+        .iter()
+        .zip(&premultiplied)
+        .filter(|(l, _)| parts.iter().any(|p| std::ptr::eq(*p, *l)))
+    {
+        for y in 0..layer.height {
+            let cy = layer.top + y as i32;
+            if cy < 0 || cy >= psd.height as i32 {
+                continue;
+            }
+            for x in 0..layer.width {
+                let cx = layer.left + x as i32;
+                if cx < 0 || cx >= psd.width as i32 {
+                    continue;
+                }
+                let s = (y * layer.width + x) * 4;
+                let d = (cy as usize * psd.width + cx as usize) * 4;
+                compose::blend(&mut canvas[d..d + 4], &rgba[s..s + 4]);
+            }
+        }
+    }
+    // PNG はストレートアルファで保存する
+    compose::unpremultiply(&mut canvas);
+    canvas
+}
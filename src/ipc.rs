@@ -0,0 +1,311 @@
+// 起動中のインスタンスとのローカル通信（Windows は名前付きパイプ、それ以外は Unix ソケット）。
+// Unix ソケットは本人しか入れないディレクトリに置き、ほかのユーザーからは操作できないようにする
+// 1行に1コマンドのテキストで、応答も1行で返す（"ok ..." または "error ..."）。
+//   expression [名前]   表情を選ぶ（名前なしでデフォルトに戻す）
+//   sequence <名前>     シーケンスを再生
+//   mute [on|off]       口を閉じたままにする（引数なしで切り替え）
+//   theme [名前]        重ねて表示するもののテーマ（名前なしで次のテーマ）
+//   fullscreen          フルスクリーンの切り替え
+//   status              "ok <表情> <口の状態>" を返す
+//   quit                終了
+use crate::{
+    bus::{self, Bus},
+    config::ExpressionSource,
+    filter::Band,
+    t,
+};
+use anyhow::{Context, Result, bail};
+use interprocess::local_socket::{
+    GenericFilePath, GenericNamespaced, ListenerOptions, Name, Stream, prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+// --instance で付けた名前（同時に動かす各インスタンスは別のソケットを持つ）
+static INSTANCE: OnceLock<String> = OnceLock::new();
+
+/// Names this process's instance, so that several can run side by side. Call before any
+/// other function here.
+pub fn set_instance(name: String) {
+    let _ = INSTANCE.set(name);
+}
+
+/// A command received from another process.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Command line of a second launch, forwarded instead of starting another instance.
+    Args(ForwardedArgs),
+    /// Select an expression, or go back to the default one with `None`.
+    Expression(Option<String>),
+    Sequence(String),
+    /// Change a filter cutoff on the analysis path, or turn it off with `None`.
+    Filter(Band, Option<f32>),
+    /// Keep the mouth closed, open it again, or flip it with `None`.
+    Mute(Option<bool>),
+    /// Switch the overlay theme, or to the next one with `None`.
+    Theme(Option<String>),
+    /// Draw a random expression with the gacha.
+    Gacha,
+    Fullscreen,
+    Quit,
+}
+
+/// What the render loop last reported, answered by `status` without waiting for it.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Status {
+    pub expression: String,
+    pub mouth: String,
+    pub muted: bool,
+    pub expressions: Vec<String>,
+    pub sequences: Vec<String>,
+}
+
+/// Arguments are relative to the working directory of the process that sent them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ForwardedArgs {
+    pub cwd: PathBuf,
+    pub args: Vec<String>,
+}
+
+// status は描画ループを待たずに答える
+enum Parsed {
+    Command(Command),
+    Status,
+}
+
+impl Command {
+    fn parse(line: &str, status: &Status) -> Result<Parsed, String> {
+        let (name, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        let command = match name {
+            "args" => serde_json::from_str(rest)
+                .map(Self::Args)
+                .map_err(|e| e.to_string())?,
+            "expression" if rest.is_empty() => Self::Expression(None),
+            "expression" if status.expressions.iter().any(|e| e == rest) => {
+                Self::Expression(Some(rest.to_string()))
+            }
+            "expression" => return Err(t!("ipc.unknown_expression", rest)),
+            "sequence" if status.sequences.iter().any(|s| s == rest) => {
+                Self::Sequence(rest.to_string())
+            }
+            "sequence" => return Err(t!("ipc.unknown_sequence", rest)),
+            "filter" => {
+                let (band, value) = rest.split_once(' ').unwrap_or((rest, ""));
+                let band = match band {
+                    "highpass" => Band::Highpass,
+                    "lowpass" => Band::Lowpass,
+                    _ => return Err(t!("ipc.filter_usage").to_string()),
+                };
+                let hz = match value.trim() {
+                    "off" => None,
+                    value => Some(
+                        value
+                            .parse::<f32>()
+                            .ok()
+                            .filter(|hz| *hz > 0.0)
+                            .ok_or_else(|| t!("ipc.filter_usage").to_string())?,
+                    ),
+                };
+                Self::Filter(band, hz)
+            }
+            "mute" => Self::Mute(match rest {
+                "" => None,
+                "on" => Some(true),
+                "off" => Some(false),
+                _ => return Err(t!("ipc.mute_usage").to_string()),
+            }),
+            "theme" => Self::Theme((!rest.is_empty()).then(|| rest.to_string())),
+            "gacha" => Self::Gacha,
+            "fullscreen" => Self::Fullscreen,
+            "quit" => Self::Quit,
+            "status" => return Ok(Parsed::Status),
+            _ => return Err(t!("ipc.unknown_command", name)),
+        };
+        Ok(Parsed::Command(command))
+    }
+
+    fn into_event(self) -> bus::Event {
+        match self {
+            Self::Args(args) => bus::Event::Open(args),
+            Self::Expression(name) => bus::Event::Expression(ExpressionSource::Manual, name),
+            Self::Sequence(name) => bus::Event::Sequence(name),
+            Self::Filter(band, hz) => bus::Event::Filter(band, hz),
+            Self::Mute(on) => bus::Event::Mute(on),
+            Self::Theme(name) => bus::Event::Theme(name),
+            Self::Gacha => bus::Event::Gacha,
+            Self::Fullscreen => bus::Event::Fullscreen,
+            Self::Quit => bus::Event::Quit,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        match self {
+            Self::Args(args) => format!("args {}", serde_json::json!(args)),
+            Self::Expression(name) => format!("expression {}", name.as_deref().unwrap_or("")),
+            Self::Sequence(name) => format!("sequence {name}"),
+            Self::Filter(band, hz) => format!(
+                "filter {} {}",
+                match band {
+                    Band::Highpass => "highpass",
+                    Band::Lowpass => "lowpass",
+                },
+                hz.map_or("off".to_string(), |hz| hz.to_string())
+            ),
+            Self::Mute(None) => "mute".to_string(),
+            Self::Mute(Some(on)) => format!("mute {}", if *on { "on" } else { "off" }),
+            Self::Theme(name) => format!("theme {}", name.as_deref().unwrap_or("")),
+            Self::Gacha => "gacha".to_string(),
+            Self::Fullscreen => "fullscreen".to_string(),
+            Self::Quit => "quit".to_string(),
+        }
+    }
+}
+
+// ユーザー（とインスタンス）ごとに別のソケットにする
+fn name() -> std::io::Result<Name<'static>> {
+    match INSTANCE.get() {
+        Some(instance) => socket(&format!("{instance}.sock")),
+        None => socket("sock"),
+    }
+}
+
+/// A per-user local socket name ending in `suffix`.
+pub fn socket(suffix: &str) -> std::io::Result<Name<'static>> {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default();
+    let file = format!("darwin-{user}.{suffix}");
+    // Linux の抽象名前空間のソケットには権限が無く、誰でもつなげるので使わない
+    if cfg!(unix) || !GenericNamespaced::is_supported() {
+        private_dir(&user)?
+            .join(file)
+            .to_fs_name::<GenericFilePath>()
+    } else {
+        file.to_ns_name::<GenericNamespaced>()
+    }
+}
+
+// 本人だけが読み書きできるソケットの置き場所（XDG_RUNTIME_DIR の下、無ければ一時ディレクトリの下）
+#[cfg(unix)]
+fn private_dir(user: &str) -> std::io::Result<PathBuf> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+    let runtime = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from);
+    let dir = match runtime.filter(|dir| dir.is_dir()) {
+        Some(runtime) => runtime.join("darwin"),
+        None => std::env::temp_dir().join(format!("darwin-{user}")),
+    };
+    match std::fs::DirBuilder::new().mode(0o700).create(&dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e),
+        _ => {}
+    }
+    // 先にほかのユーザーが作っていたり、広く開いていたりすれば使わない
+    let metadata = std::fs::symlink_metadata(&dir)?;
+    // SAFETY: 引数の無い問い合わせで、失敗しない
+    let uid = unsafe { libc::geteuid() };
+    if !metadata.is_dir() || metadata.uid() != uid || metadata.mode() & 0o077 != 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            t!("ipc.unsafe_dir", dir.display()),
+        ));
+    }
+    Ok(dir)
+}
+
+#[cfg(not(unix))]
+fn private_dir(_user: &str) -> std::io::Result<PathBuf> {
+    Ok(std::env::temp_dir())
+}
+
+/// Sends `command` to the running instance and returns its reply, or `None` if no instance
+/// is listening.
+pub fn send(command: &Command) -> Result<Option<String>> {
+    send_line(&command.to_line())
+}
+
+/// Like [`send`], with the command already written as a protocol line.
+pub fn send_line(line: &str) -> Result<Option<String>> {
+    let Ok(stream) = Stream::connect(name()?) else {
+        return Ok(None);
+    };
+    let mut stream = BufReader::new(stream);
+    stream.get_mut().write_all(format!("{line}\n").as_bytes())?;
+    let mut reply = String::new();
+    stream.read_line(&mut reply).context(t!("ipc.no_reply"))?;
+    Ok(Some(reply.trim_end().to_string()))
+}
+
+/// `darwin ctl`: sends one command line and prints the reply.
+pub fn ctl(words: &[String]) -> Result<()> {
+    let Some(reply) = send_line(&words.join(" "))? else {
+        bail!(t!("ipc.not_running"));
+    };
+    match reply.split_once(' ').unwrap_or((&reply, "")) {
+        ("ok", "") => Ok(()),
+        ("ok", rest) => {
+            println!("{rest}");
+            Ok(())
+        }
+        (_, rest) => bail!("{rest}"),
+    }
+}
+
+/// Listens for commands on a thread of its own and publishes them to the bus.
+pub struct Server {
+    status: Arc<Mutex<Status>>,
+}
+
+impl Server {
+    pub fn start(bus: Bus) -> Result<Self> {
+        let listener = ListenerOptions::new()
+            .name(name()?)
+            // 異常終了で残ったソケットファイルは置き換える
+            .try_overwrite(true)
+            .create_sync()
+            .context(t!("ipc.listen_failed"))?;
+        let status = Arc::new(Mutex::new(Status::default()));
+        let shared = status.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().filter_map(Result::ok) {
+                let bus = bus.clone();
+                let status = shared.clone();
+                // 接続ごとに読むので、止まったクライアントが他を待たせない
+                std::thread::spawn(move || serve(stream, &bus, &status));
+            }
+        });
+        Ok(Self { status })
+    }
+
+    pub fn set_status(&self, status: Status) {
+        *self.status.lock().unwrap() = status;
+    }
+}
+
+fn serve(stream: Stream, bus: &Bus, status: &Mutex<Status>) {
+    let mut stream = BufReader::new(stream);
+    let mut line = String::new();
+    while matches!(stream.read_line(&mut line), Ok(n) if n > 0) {
+        let status = status.lock().unwrap().clone();
+        let reply = match Command::parse(line.trim(), &status) {
+            Ok(Parsed::Command(command)) => {
+                tracing::debug!(command = line.trim(), "{}", t!("ipc.received"));
+                bus.publish(command.into_event());
+                "ok".to_string()
+            }
+            Ok(Parsed::Status) => format!("ok {} {}", status.expression, status.mouth),
+            Err(e) => format!("error {e}"),
+        };
+        if stream
+            .get_mut()
+            .write_all(format!("{reply}\n").as_bytes())
+            .is_err()
+        {
+            return;
+        }
+        line.clear();
+    }
+}
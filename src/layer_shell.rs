@@ -0,0 +1,311 @@
+// Wayland の wlr-layer-shell でデスクトップに直接重ねて表示する（デスクトップマスコット向け）
+use crate::{
+    config::LayerShellConfig,
+    dirty::Rect,
+    sink::{FrameSink, Timestamp},
+};
+use anyhow::Result;
+use std::sync::{Arc, Mutex};
+
+/// A layer-shell surface showing the composed canvas, driven by its own Wayland connection
+/// on a background thread.
+pub struct LayerShell {
+    latest: Arc<Mutex<Option<Vec<u8>>>>,
+}
+
+impl LayerShell {
+    /// Connects to the compositor and maps a `width` × `height` surface. Fails outside
+    /// Wayland or when the compositor has no layer-shell support.
+    pub fn start(config: &LayerShellConfig, width: u32, height: u32) -> Result<Self> {
+        let latest = Arc::new(Mutex::new(None));
+        wayland::start(config.clone(), width, height, latest.clone())?;
+        Ok(Self { latest })
+    }
+
+    /// Hands over the latest composed frame (premultiplied RGBA); it is shown on the
+    /// compositor's next frame.
+    pub fn present(&self, frame: &[u8]) {
+        let mut latest = self.latest.lock().unwrap();
+        match &mut *latest {
+            Some(pending) if pending.len() == frame.len() => pending.copy_from_slice(frame),
+            _ => *latest = Some(frame.to_vec()),
+        }
+    }
+}
+
+impl FrameSink for LayerShell {
+    fn present(&mut self, frame: &[u8], _region: Rect, _timestamp: Timestamp) {
+        LayerShell::present(self, frame);
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod wayland {
+    use crate::{
+        config::{LayerShellConfig, ShellAnchor, ShellLayer},
+        t,
+    };
+    use anyhow::{Context, Result};
+    use smithay_client_toolkit::{
+        compositor::{CompositorHandler, CompositorState, Region},
+        delegate_compositor, delegate_layer, delegate_output, delegate_registry, delegate_shm,
+        output::{OutputHandler, OutputState},
+        registry::{ProvidesRegistryState, RegistryState},
+        registry_handlers,
+        shell::{
+            WaylandSurface,
+            wlr_layer::{
+                Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface,
+                LayerSurfaceConfigure,
+            },
+        },
+        shm::{Shm, ShmHandler, slot::SlotPool},
+    };
+    use std::sync::{Arc, Mutex, mpsc};
+    use wayland_client::{
+        Connection, QueueHandle,
+        globals::registry_queue_init,
+        protocol::{wl_output, wl_shm, wl_surface},
+    };
+
+    pub fn start(
+        config: LayerShellConfig,
+        width: u32,
+        height: u32,
+        latest: Arc<Mutex<Option<Vec<u8>>>>,
+    ) -> Result<()> {
+        // 接続できたかどうかだけを呼び出し側に返し、以降はこのスレッドで描く
+        let (ready, result) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (mut surface, mut queue) = match Surface::connect(&config, width, height, latest) {
+                Ok(connected) => {
+                    let _ = ready.send(Ok(()));
+                    connected
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            while !surface.closed {
+                if let Err(e) = queue.blocking_dispatch(&mut surface) {
+                    tracing::warn!("{}", t!("layer_shell.failed", e));
+                    break;
+                }
+            }
+            tracing::info!("{}", t!("layer_shell.closed"));
+        });
+        result.recv().context(t!("layer_shell.unavailable"))?
+    }
+
+    struct Surface {
+        registry: RegistryState,
+        outputs: OutputState,
+        shm: Shm,
+        pool: SlotPool,
+        layer: LayerSurface,
+        width: u32,
+        height: u32,
+        latest: Arc<Mutex<Option<Vec<u8>>>>,
+        configured: bool,
+        closed: bool,
+    }
+
+    impl Surface {
+        fn connect(
+            config: &LayerShellConfig,
+            width: u32,
+            height: u32,
+            latest: Arc<Mutex<Option<Vec<u8>>>>,
+        ) -> Result<(Self, wayland_client::EventQueue<Self>)> {
+            let connection = Connection::connect_to_env().context(t!("layer_shell.no_wayland"))?;
+            let (globals, queue) = registry_queue_init(&connection)?;
+            let qh = queue.handle();
+            let compositor = CompositorState::bind(&globals, &qh)?;
+            let layer_shell =
+                LayerShell::bind(&globals, &qh).context(t!("layer_shell.unsupported"))?;
+            let shm = Shm::bind(&globals, &qh)?;
+
+            let layer = layer_shell.create_layer_surface(
+                &qh,
+                compositor.create_surface(&qh),
+                match config.layer {
+                    ShellLayer::Background => Layer::Background,
+                    ShellLayer::Bottom => Layer::Bottom,
+                    ShellLayer::Top => Layer::Top,
+                    ShellLayer::Overlay => Layer::Overlay,
+                },
+                Some("darwin"),
+                None,
+            );
+            layer.set_anchor(match config.anchor {
+                ShellAnchor::Center => Anchor::empty(),
+                ShellAnchor::Top => Anchor::TOP,
+                ShellAnchor::Bottom => Anchor::BOTTOM,
+                ShellAnchor::Left => Anchor::LEFT,
+                ShellAnchor::Right => Anchor::RIGHT,
+                ShellAnchor::TopLeft => Anchor::TOP | Anchor::LEFT,
+                ShellAnchor::TopRight => Anchor::TOP | Anchor::RIGHT,
+                ShellAnchor::BottomLeft => Anchor::BOTTOM | Anchor::LEFT,
+                ShellAnchor::BottomRight => Anchor::BOTTOM | Anchor::RIGHT,
+            });
+            let margin = config.margin;
+            layer.set_margin(margin, margin, margin, margin);
+            layer.set_size(width, height);
+            layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+            // 空の入力領域にすると、クリックは下のウィンドウに届く
+            if config.passthrough {
+                let region = Region::new(&compositor)?;
+                layer.set_input_region(Some(region.wl_region()));
+            }
+            layer.commit();
+
+            let pool = SlotPool::new((width * height * 4) as usize, &shm)?;
+            let surface = Self {
+                registry: RegistryState::new(&globals),
+                outputs: OutputState::new(&globals, &qh),
+                shm,
+                pool,
+                layer,
+                width,
+                height,
+                latest,
+                configured: false,
+                closed: false,
+            };
+            tracing::info!("{}", t!("layer_shell.started"));
+            Ok((surface, queue))
+        }
+
+        // 新しいフレームがあれば貼り替え、次のフレームの通知を頼む
+        fn draw(&mut self, qh: &QueueHandle<Self>) {
+            let surface = self.layer.wl_surface().clone();
+            surface.frame(qh, surface.clone());
+            if let Some(frame) = self.latest.lock().unwrap().take() {
+                let (width, height) = (self.width as i32, self.height as i32);
+                match self
+                    .pool
+                    .create_buffer(width, height, width * 4, wl_shm::Format::Argb8888)
+                {
+                    Ok((buffer, canvas)) => {
+                        // 乗算済みの RGBA から、リトルエンディアンの ARGB (B, G, R, A の順) へ
+                        for (dst, src) in canvas.chunks_exact_mut(4).zip(frame.chunks_exact(4)) {
+                            dst.copy_from_slice(&[src[2], src[1], src[0], src[3]]);
+                        }
+                        surface.damage_buffer(0, 0, width, height);
+                        if let Err(e) = buffer.attach_to(&surface) {
+                            tracing::warn!("{}", t!("layer_shell.failed", e));
+                        }
+                    }
+                    Err(e) => tracing::warn!("{}", t!("layer_shell.failed", e)),
+                }
+            }
+            self.layer.commit();
+        }
+    }
+
+    impl CompositorHandler for Surface {
+        fn scale_factor_changed(
+            &mut self,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+            _: &wl_surface::WlSurface,
+            _: i32,
+        ) {
+        }
+
+        fn transform_changed(
+            &mut self,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+            _: &wl_surface::WlSurface,
+            _: wl_output::Transform,
+        ) {
+        }
+
+        fn frame(
+            &mut self,
+            _: &Connection,
+            qh: &QueueHandle<Self>,
+            _: &wl_surface::WlSurface,
+            _: u32,
+        ) {
+            self.draw(qh);
+        }
+    }
+
+    impl LayerShellHandler for Surface {
+        fn closed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &LayerSurface) {
+            self.closed = true;
+        }
+
+        fn configure(
+            &mut self,
+            _: &Connection,
+            qh: &QueueHandle<Self>,
+            _: &LayerSurface,
+            _: LayerSurfaceConfigure,
+            _: u32,
+        ) {
+            // 大きさはこちらで決めているので、最初の configure で描き始めるだけ
+            if !self.configured {
+                self.configured = true;
+                self.draw(qh);
+            }
+        }
+    }
+
+    impl OutputHandler for Surface {
+        fn output_state(&mut self) -> &mut OutputState {
+            &mut self.outputs
+        }
+
+        fn new_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+
+        fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {
+        }
+
+        fn output_destroyed(
+            &mut self,
+            _: &Connection,
+            _: &QueueHandle<Self>,
+            _: wl_output::WlOutput,
+        ) {
+        }
+    }
+
+    impl ShmHandler for Surface {
+        fn shm_state(&mut self) -> &mut Shm {
+            &mut self.shm
+        }
+    }
+
+    impl ProvidesRegistryState for Surface {
+        fn registry(&mut self) -> &mut RegistryState {
+            &mut self.registry
+        }
+        registry_handlers![OutputState];
+    }
+
+    delegate_compositor!(Surface);
+    delegate_output!(Surface);
+    delegate_shm!(Surface);
+    delegate_layer!(Surface);
+    delegate_registry!(Surface);
+}
+
+#[cfg(not(target_os = "linux"))]
+mod wayland {
+    use crate::{config::LayerShellConfig, t};
+    use anyhow::Result;
+    use std::sync::{Arc, Mutex};
+
+    pub fn start(
+        _config: LayerShellConfig,
+        _width: u32,
+        _height: u32,
+        _latest: Arc<Mutex<Option<Vec<u8>>>>,
+    ) -> Result<()> {
+        anyhow::bail!(t!("layer_shell.unavailable"))
+    }
+}
@@ -0,0 +1,58 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use std::path::Path;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry, fmt, layer::SubscriberExt, util::SubscriberInitExt,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+fn format_layer<W>(
+    layer: fmt::Layer<Registry, fmt::format::DefaultFields, fmt::format::Format, W>,
+    format: LogFormat,
+) -> BoxedLayer
+where
+    W: for<'w> fmt::MakeWriter<'w> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().flatten_event(true).boxed(),
+    }
+}
+
+/// Installs the global subscriber. The returned guard flushes the log file and must be kept alive.
+pub fn init(format: LogFormat, log_dir: Option<&Path>) -> Result<Option<WorkerGuard>> {
+    // RUST_LOG が未指定なら debug
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("debug"));
+
+    let mut layers = vec![format_layer(fmt::layer(), format)];
+
+    // 長時間配信向けに日ごとにローテーションするログファイル
+    let guard = match log_dir {
+        Some(dir) => {
+            let appender = tracing_appender::rolling::daily(dir, "darwin.log");
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            layers.push(format_layer(
+                fmt::layer().with_writer(writer).with_ansi(false),
+                format,
+            ));
+            Some(guard)
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .try_init()?;
+
+    Ok(guard)
+}
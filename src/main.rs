@@ -1,207 +1,810 @@
-use anyhow::{Context, Result};
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use image::GenericImageView;
-use pixels::{Pixels, SurfaceTexture};
+mod adaptive;
+mod alerts;
+mod align;
+#[cfg(test)]
+mod allocations;
+mod announce;
+mod audio;
+mod autostart;
+mod avatar;
+mod beat;
+mod bus;
+mod cam_frame;
+mod captions;
+mod cli;
+mod clock;
+mod color;
+mod compose;
+mod config;
+mod data;
+mod desktop;
+mod dirty;
+mod easing;
+mod echo;
+mod editor;
+mod emotion;
+mod eyes;
+mod features;
+mod feed;
+mod filter;
+mod focus;
+mod gacha;
+mod gallery;
+#[cfg(test)]
+mod golden;
+mod health;
+mod host;
+mod http;
+mod i18n;
+mod import;
+mod ipc;
+mod layer_shell;
+mod logging;
+mod mapping;
+mod mascot;
+mod monitor;
+mod mqtt;
+mod notify;
+mod offline;
+mod panel;
+mod particles;
+mod paths;
+mod permission;
+mod pitch;
+mod pomodoro;
+mod preset;
+mod preview;
+mod priority;
+mod psd;
+mod qr;
+mod quality;
+mod random;
+mod rate_limit;
+mod reactivity;
+mod reference;
+mod remote;
+mod render;
+mod replay;
+mod resample;
+mod ring;
+mod schedule;
+mod sequence;
+mod session;
+mod shm;
+mod sink;
+mod slot;
+mod smoothing;
+mod sound;
+mod squash;
+mod state;
+mod stats;
+mod streamdeck;
+mod svg;
+mod sync;
+mod talk_time;
+mod test_signal;
+mod text;
+mod theme;
+mod timings;
+mod tuning;
+mod typing;
+mod validate;
+mod video;
+mod viewers;
+mod vmc;
+mod voice;
+mod watchdog;
+mod web;
+
+use anyhow::{Result, bail};
+use avatar::Mouth;
+use cli::Command;
+use config::Config;
+use sink::FrameSink;
 use std::{
-    path::Path,
+    cell::Cell,
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    rc::Rc,
     sync::Arc,
-    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
 };
 use winit::{
     dpi::LogicalSize,
-    event::{Event, KeyEvent, WindowEvent},
+    event::{ElementState, Event, KeyEvent, MouseButton, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
     keyboard::{KeyCode, PhysicalKey},
-    window::WindowBuilder,
+    window::{Icon, Window, WindowBuilder, WindowLevel},
 };
 
-fn load_image(path: &str, target_width: usize, target_height: usize) -> Option<Vec<u8>> {
-    let img = image::open(path).ok()?;
-    let img = img.resize_exact(
-        target_width as u32,
-        target_height as u32,
-        image::imageops::FilterType::Lanczos3,
-    );
-
-    let (img_w, img_h) = img.dimensions();
-    let rgba = img.to_rgba8();
-
-    // RGBAバッファを作成
-    let mut buffer = vec![0u8; target_width * target_height * 4];
+fn main() -> Result<()> {
+    // 時差はスレッドを作る前にしか読めない
+    clock::init();
+    // CLI ヘルプはシステムのロケールで表示
+    i18n::set_lang(i18n::detect(None));
 
-    for y in 0..img_h as usize {
-        for x in 0..img_w as usize {
-            let pixel = rgba.get_pixel(x as u32, y as u32);
-            let idx = (y * target_width + x) * 4;
-            buffer[idx] = pixel[0];
-            buffer[idx + 1] = pixel[1];
-            buffer[idx + 2] = pixel[2];
-            buffer[idx + 3] = pixel[3];
+    let mut cli = cli::parse();
+    if cli.portable {
+        paths::set_portable()?;
+    }
+    if !cli.gfx_backend.is_empty() {
+        render::select_backends(cli.gfx_backend.clone());
+    }
+    // GPU は環境変数で選ぶので、ログやほかのスレッドを始める前に決める（プロファイルを
+    // 切り替えても選び直さない）。設定が読めなければ後でちゃんと読むときに報告する
+    let missing_adapter = draws(cli.command.as_ref())
+        .then(|| cli.config.clone().or_else(paths::default_config))
+        .flatten()
+        .and_then(|path| Config::load(&path).ok())
+        .and_then(|config| config.gpu)
+        .and_then(|gpu| render::select_gpu(&gpu));
+    // ログは指定が無ければ OS ごとの場所に書く（作れなければファイルには書かない）
+    let log_dir = cli
+        .log_dir
+        .clone()
+        .or_else(|| paths::log_dir().filter(|dir| std::fs::create_dir_all(dir).is_ok()));
+    let _log_guard = logging::init(cli.log_format, log_dir.as_deref())?;
+    if let Some(name) = missing_adapter {
+        tracing::warn!("{}", t!("render.adapter_missing", name));
+    }
+    if let Some(instance) = cli.instance.clone() {
+        ipc::set_instance(instance);
+    }
+    match cli.command.take() {
+        Some(Command::Validate { path }) => validate::run(&path),
+        Some(Command::Align { first, second }) => {
+            align::run(load_config(cli.config)?, first, second)
+        }
+        Some(Command::Edit) => editor::run(load_config(cli.config)?),
+        Some(Command::Import { psd, out }) => import::run(load_config(cli.config)?, &psd, out),
+        Some(Command::Render { audio, out, fps }) => {
+            offline::run(load_config(cli.config)?, &audio, &out, fps)
         }
+        None if cli.watchdog => watchdog::supervise(),
+        None if cli.gallery => gallery::run(&load_config(cli.config)?),
+        Some(Command::Autostart { action }) => autostart::run(action, cli.config),
+        Some(Command::Ctl { command }) => ipc::ctl(&command),
+        Some(Command::Preset { action }) => preset::run(&load_config(cli.config)?, action),
+        Some(Command::Paths) => paths::print(cli.config.as_deref(), cli.log_dir.as_deref()),
+        Some(Command::Gpus) => {
+            render::print_adapters();
+            Ok(())
+        }
+        Some(Command::StreamDeck { args }) => {
+            let launch = streamdeck::Launch::parse(&args)?;
+            run(cli, Some(launch))
+        }
+        None => run(cli, None),
     }
+}
 
-    Some(buffer)
+// ウィンドウに描くコマンドか（GPU を選ぶ）
+fn draws(command: Option<&Command>) -> bool {
+    matches!(
+        command,
+        None | Some(Command::Align { .. } | Command::Edit | Command::StreamDeck { .. })
+    )
 }
 
-fn find_loopback_device() -> Option<cpal::Device> {
-    let host = cpal::default_host();
+// 重ねて表示するものすべてにテーマを当てる
+fn apply_theme(
+    theme: &theme::Theme,
+    talk_time: Option<&mut talk_time::TalkTime>,
+    pomodoro: Option<&mut pomodoro::Pomodoro>,
+    texts: Option<&mut text::TextOverlays>,
+) {
+    if let Some(talk_time) = talk_time {
+        talk_time.set_theme(theme);
+    }
+    if let Some(pomodoro) = pomodoro {
+        pomodoro.set_theme(theme);
+    }
+    if let Some(texts) = texts {
+        texts.set_theme(theme);
+    }
+}
 
-    // 利用可能な入力デバイスを表示
-    log::info!("Available input devices:");
-    if let Ok(devices) = host.input_devices() {
-        for (i, device) in devices.enumerate() {
-            if let Ok(name) = device.name() {
-                log::info!("  {}: {}", i, name);
-            }
-        }
+// フルスクリーンを切り替えて、ウォッチドッグ用の状態にも残す
+fn toggle_fullscreen(
+    window: &Window,
+    state: &mut session::SessionState,
+    state_file: Option<&Path>,
+) {
+    state.fullscreen = !state.fullscreen;
+    window.set_fullscreen(if state.fullscreen {
+        Some(winit::window::Fullscreen::Borderless(None))
+    } else {
+        None
+    });
+    if let Some(path) = state_file
+        && let Err(e) = session::save(path, state)
+    {
+        tracing::warn!("{}", t!("session.save_failed", e));
     }
+}
 
-    // BlackHole, Soundflower, Loopback, eqMacなどを探す
-    if let Ok(devices) = host.input_devices() {
-        for device in devices {
-            if let Ok(name) = device.name() {
-                let name_lower = name.to_lowercase();
-                if name_lower.contains("blackhole")
-                    || name_lower.contains("soundflower")
-                    || name_lower.contains("loopback")
-                    || name_lower.contains("eqmac")
-                    || name_lower.contains("multi-output")
-                {
-                    log::info!("Found loopback device: {}", name);
-                    return Some(device);
-                }
-            }
+// 入力の異常があればタイトルに添える
+fn window_title(window: &config::WindowConfig, warning: Option<health::AudioWarning>) -> String {
+    let title = window.title.as_deref().unwrap_or(t!("window.title"));
+    match warning {
+        Some(w) => format!("{title} - {}", w.message()),
+        None => title.to_string(),
+    }
+}
+
+// 設定したアイコン（大きな画像はアイコンの大きさに縮める）
+fn window_icon(config: &Config) -> Option<Icon> {
+    const SIZE: u32 = 256;
+    let path = config.resolve(config.window.icon.as_ref()?);
+    let image = match avatar::open_image(&path) {
+        Ok(image) => image,
+        Err(e) => {
+            tracing::warn!("{}", t!("window.icon_failed", path.display(), e));
+            return None;
         }
+    };
+    let image = if image.width() > SIZE || image.height() > SIZE {
+        image.thumbnail(SIZE, SIZE)
+    } else {
+        image
     }
+    .to_rgba8();
+    let (width, height) = image.dimensions();
+    Icon::from_rgba(image.into_raw(), width, height).ok()
+}
 
-    None
+fn load_config(path: Option<PathBuf>) -> Result<Config> {
+    let config = match path.or_else(paths::default_config) {
+        Some(path) => Config::load(&path)?,
+        None => Config {
+            base_dir: paths::config_dir(),
+            ..Config::default()
+        },
+    };
+    if config.language.is_some() {
+        i18n::set_lang(i18n::detect(config.language.as_deref()));
+    }
+    Ok(config)
 }
 
-fn setup_audio_capture(current_index: Arc<AtomicUsize>, _image_count: usize) -> Result<()> {
-    let host = cpal::default_host();
+fn run(cli: cli::Cli, stream_deck: Option<streamdeck::Launch>) -> Result<()> {
+    let cli::Cli {
+        config: config_path,
+        preview,
+        host,
+        record,
+        replay,
+        minimized,
+        test_signal,
+        timings: show_timings,
+        ..
+    } = cli;
+    if show_timings {
+        timings::enable();
+    }
+    // 既に起動していれば、コマンドラインを渡して終わる（音声デバイスを取り合わないように）
+    let forwarded = ipc::Command::Args(ipc::ForwardedArgs {
+        cwd: std::env::current_dir()?,
+        args: std::env::args().skip(1).collect(),
+    });
+    if ipc::send(&forwarded)?.is_some() {
+        tracing::info!("{}", t!("ipc.forwarded"));
+        return Ok(());
+    }
+    // 各サブシステムからのイベントは、ここでまとめて受け取る
+    let bus = bus::Bus::start();
+    let events = bus.subscribe();
+    let ipc_server = match ipc::Server::start(bus.clone()) {
+        Ok(server) => Some(server),
+        Err(e) => {
+            tracing::warn!("{}", t!("ipc.unavailable", e));
+            None
+        }
+    };
 
-    // ループバックデバイスを探すか、デフォルトの入力デバイスを使用
-    let device = find_loopback_device()
-        .or_else(|| host.default_input_device())
-        .context("No input device available")?;
+    let mut config = load_config(config_path)?;
+    config.preview.enabled |= preview;
+    config.audio.test_signal = test_signal.or(config.audio.test_signal);
+    if config.notifications.enabled {
+        notify::enable();
+    }
+    // 同じ設定ディレクトリで動く他のインスタンスと、選んだ表情を揃える
+    let sync = sync::Sync::start(&config.base_dir, bus.clone());
+    host::select(host.as_deref().or(config.audio.host.as_deref()))?;
 
-    let config = device.default_input_config()?;
-    log::debug!("Input config: {:?}", config);
+    // ウォッチドッグから再起動された場合は前回の状態を復元
+    let state_file = session::state_file();
+    if state_file.is_some() {
+        watchdog::install_panic_exit();
+    }
+    let mut state = state_file.as_deref().map(session::load).unwrap_or_default();
 
-    let threshold = 0.001f32; // 音量閾値
-    let last_switch = Arc::new(std::sync::Mutex::new(std::time::Instant::now()));
-    let _cooldown = std::time::Duration::from_millis(20);
+    // 画面サイズ（フルスクリーン用）
+    let width = config.canvas.width;
+    let height = config.canvas.height;
 
-    let stream = device.build_input_stream(
-        &config.into(),
-        move |data: &[f32], _: &cpal::InputCallbackInfo| {
-            // RMS音量を計算
-            let sum: f32 = data.iter().map(|&s| s * s).sum();
-            let rms = (sum / data.len() as f32).sqrt();
+    // アニメーションの乱数（seed があれば毎回同じ）
+    let random = random::Random::new(config.seed);
+    // 全表情の画像を読み込み (Pixelsはu8のRGBAバッファを使用)
+    let mut avatar = timings::measure(timings::Phase::Images, || avatar::Avatar::load(&config));
+    let mut talking_frames = avatar::TalkingFrames::new(&config.talking, random.stream("talking"));
+    let mut blinks = avatar::Blinks::new(random.stream("blink"));
+    // フェイストラッキング（届いている間はまばたきもそれに合わせる）
+    let tracking = config.vmc.as_ref().and_then(|vmc| {
+        vmc::listen(vmc)
+            .map_err(|e| tracing::warn!("{}", t!("vmc.failed", format!("{e:#}"))))
+            .ok()
+    });
+    let blink_threshold = config.vmc.as_ref().map_or(0.5, |vmc| vmc.blink_threshold);
+    let mut output = vec![0u8; (width * height * 4) as usize];
+    let mut dirty = dirty::DirtyTracker::new(width as usize, height as usize);
 
-            let mut last = last_switch.lock().unwrap();
+    // グリーンバック動画（対象の状態では画像の代わりに表示）
+    let video = config.video.as_ref().and_then(|video| {
+        match video::VideoSource::start(video, &config.base_dir, width, height) {
+            Ok(source) => Some(source),
+            Err(e) => {
+                tracing::warn!("{}", t!("video.failed", e));
+                None
+            }
+        }
+    });
+    let mut video_frame = Vec::new();
 
-            // シンプルなロジック：音があれば画像1、なければ画像0
-            if rms > threshold {
-                current_index.store(1, Ordering::Relaxed);
-                *last = std::time::Instant::now();
-                // println!("Audio triggered! RMS: {:.4}, switching to image {}", rms, 1);
-            } else {
-                current_index.store(0, Ordering::Relaxed);
-                // println!("Audio triggered! RMS: {:.4}, switching to image {}", rms, 0);
+    // デスクトップに直接重ねる表示（Wayland のレイヤーシェル）
+    let layer_shell = config.layer_shell.as_ref().and_then(|layer_shell| {
+        match layer_shell::LayerShell::start(layer_shell, width, height) {
+            Ok(surface) => Some(surface),
+            Err(e) => {
+                tracing::warn!("{}", t!("layer_shell.failed", e));
+                None
             }
-        },
-        |err| eprintln!("Audio stream error: {}", err),
-        None,
-    )?;
+        }
+    });
 
-    stream.play()?;
-    println!("Audio capture started. Listening...");
+    // ホットキーで再生するシーケンスと効果音
+    let mut sequencer = sequence::Sequencer::new(&config);
+    // 外部サービスから届いたシーケンスの順番待ち
+    let mut rate_limiter = rate_limit::RateLimiter::new(&config.rate_limit);
+    let mut effects = Vec::new();
+    let mut particles =
+        particles::Particles::new(width as usize, height as usize, random.stream("particles"));
+    let mut particles_shown = false;
+    // 描画が重いときに見た目を落とす
+    let mut load_monitor = config.quality.as_ref().map(quality::LoadMonitor::new);
+    // ウィンドウを見ていなくても状態の変化が分かるように知らせる
+    let announcer = config
+        .accessibility
+        .as_ref()
+        .map(|accessibility| announce::Announcer::new(accessibility.announce));
+    let announces_by_notification = config.accessibility.as_ref().is_some_and(|accessibility| {
+        accessibility.announce == config::AnnounceMethod::Notification
+    });
+    let announce = move |message: &str| {
+        if let Some(announcer) = &announcer {
+            announcer.say(message);
+        }
+    };
+    // 切り抜きのあるレイヤーを合成するための作業領域
+    let mut clip_scratch = compose::ClipScratch::default();
+    let mut sound_player = match sound::SoundPlayer::new() {
+        Ok(mut player) => {
+            for path in sequencer.sounds() {
+                player.preload(path);
+            }
+            Some(player)
+        }
+        Err(e) => {
+            tracing::warn!("{}", t!("sound.unavailable", e));
+            None
+        }
+    };
 
-    // ストリームを維持
-    loop {
-        std::thread::sleep(std::time::Duration::from_secs(1));
+    // Stream Deck のボタンで選んだ表情（シーケンス中はシーケンスが優先）
+    let stream_deck = stream_deck.and_then(|launch| {
+        let icons = avatar
+            .expressions
+            .keys()
+            .filter_map(|name| {
+                let frame = avatar.frame(name, Mouth::Idle, 0)?;
+                Some((name.clone(), streamdeck::icon(frame, width, height)?))
+            })
+            .collect();
+        match streamdeck::StreamDeck::connect(launch, icons, bus.clone()) {
+            Ok(deck) => Some(deck),
+            Err(e) => {
+                tracing::warn!("{}", t!("streamdeck.failed", e));
+                None
+            }
+        }
+    });
+    let mqtt = config
+        .mqtt
+        .as_ref()
+        .map(|mqtt| mqtt::Mqtt::start(mqtt, bus.clone()));
+    // スマートフォンのリモコンと、そのアドレスの QR コード
+    let remote = config.remote.as_ref().and_then(|c| {
+        let c = config::RemoteConfig {
+            tls: c.tls.as_ref().map(|tls| config::TlsConfig {
+                cert: config.resolve(&tls.cert),
+                key: config.resolve(&tls.key),
+            }),
+            ..c.clone()
+        };
+        remote::Remote::start(&c, bus.clone())
+            .map_err(|e| tracing::warn!("{}", t!("remote.failed", e)))
+            .ok()
+    });
+    let mut pairing = remote.as_ref().and_then(|remote| {
+        let hotkey = config.remote.as_ref().and_then(|c| c.qr_hotkey.as_deref());
+        remote::Pairing::new(remote.url(), hotkey, width as usize, height as usize)
+    });
+    if let Some(alerts) = &config.alerts {
+        alerts::start(alerts, bus.clone());
     }
-}
-
-fn main() -> Result<()> {
-    unsafe {
-        std::env::set_var("RUST_LOG", "debug");
+    let outfits: Vec<_> = config
+        .outfits
+        .iter()
+        .map(|outfit| config::OutfitConfig {
+            profile: outfit.profile.as_ref().map(|path| config.resolve(path)),
+            ..outfit.clone()
+        })
+        .collect();
+    let home = config.source.clone();
+    if let Err(e) = schedule::start(&config.schedules, &outfits, home, bus.clone()) {
+        tracing::warn!("{}", t!("schedule.failed", e));
+    }
+    if let Err(e) = focus::start(&config.focus, bus.clone()) {
+        tracing::warn!("{}", t!("focus.failed", format!("{e:#}")));
     }
-    env_logger::init();
+    // 表情を求める入力ごとの要求（優先順位は設定の priority）
+    let mut claims = priority::ExpressionClaims::new(&config.priority);
+    // Stream Deck と他のインスタンスに伝えた、手動で選んだ表情
+    let mut shown_manual: Option<String> = None;
+    let mut stats = config
+        .stats
+        .as_ref()
+        .map(|stats| stats::SessionStats::new(stats, config.resolve(&stats.dir), Instant::now()));
+    // 入力デバイスが無い間は、Space を押している間だけ口を動かす
+    let mut no_audio = false;
+    let mut manual_talking = false;
+    // ミュート中は声があっても口を閉じたままにする
+    let mut muted = false;
+    // マイクの許可が無い間だけウィンドウに出す案内
+    let mut permission_overlay: Option<permission::Overlay> = None;
+    // 閾値の調整モード（T キー）
+    let mut tuning: Option<tuning::Tuning> = None;
+    // タイトルに出している入力の異常
+    let mut audio_warning: Option<health::AudioWarning> = None;
+    // ローカル制御・リモコン・MQTT に最後に伝えた表情と口の状態、ミュート
+    let mut reported: Option<(String, Mouth, bool)> = None;
+    let mut sequence_names: Vec<String> = sequencer.names().map(str::to_string).collect();
 
-    // 画像ファイルのパス
-    let image_paths = vec!["image1.jpg", "image2.jpg"];
+    // メインのキャプチャが解析した状態（口・感情・特徴量・拍）
+    let (main_writer, mut main_state) = state::channel();
+    let emotion_config = config.emotion.clone();
+    // 声の高さ・音量で動かすパラメーター
+    let mut mapper = mapping::Mapper::new(&config.mappings);
+    let mut smoother = smoothing::Smoother::new(&config.smoothing);
+    // マウスを目で追う瞳のパーツ
+    let mut eyes = config.eyes.as_ref().and_then(|c| {
+        eyes::Eyes::new(c)
+            .map_err(|e| tracing::warn!("{}", t!("eyes.failed", format!("{e:#}"))))
+            .ok()
+    });
+    let mut typing = config.typing.as_ref().and_then(|c| {
+        typing::Typing::new(c)
+            .map_err(|e| tracing::warn!("{}", t!("typing.failed", format!("{e:#}"))))
+            .ok()
+    });
+    let mut modulation = (mapping::Params::default(), Vec::new());
+    // 音楽モードで検出した拍
+    let music_config = config.music.clone();
+    let mut seen_beats = 0;
+    let mut beat_at: Option<Instant> = None;
+    let mut pulsing = false;
+    // しゃべり始めのスクワッシュ
+    let mut squash = config.squash.as_ref().map(squash::Squash::new);
+    let mut squashing = false;
+    // 解析の前にかけるフィルタ（ctl で変えられる）
+    let controls = Arc::new(audio::Controls::new(&config.audio));
 
-    // 画面サイズ（フルスクリーン用）
-    let width = 1664;
-    let height = 1080;
-
-    // 画像を読み込み (Pixelsはu8のRGBAバッファを使用)
-    let mut images: Vec<Vec<u8>> = Vec::new();
-    for path in &image_paths {
-        log::debug!("Loading image from {}...", path);
-        if Path::new(path).exists() {
-            if let Some(buffer) = load_image(path, width as usize, height as usize) {
-                images.push(buffer);
-                log::debug!("Loaded image successfully");
-            }
-        } else {
-            log::debug!("Cannot found image at {}", path);
-        }
+    // リプレイの再生中は音声を使わず、記録された状態の変化で動かす
+    let mut player = replay.as_deref().map(replay::Player::load).transpose()?;
+    let recorder = record
+        .as_deref()
+        .map(replay::Recorder::create)
+        .transpose()?;
+    if let Some(recorder) = &recorder {
+        recorder.record(replay::ReplayEvent::Start {
+            config: config.source.clone(),
+            audio: config.audio.clone(),
+        });
     }
 
-    if images.is_empty() {
-        // デモ用のダミー画像を作成
-        println!("No images found. Creating demo images...");
-        let size = (width * height * 4) as usize;
-        let mut red_buffer = vec![0u8; size];
-        let mut blue_buffer = vec![0u8; size];
-        for i in (0..size).step_by(4) {
-            // Red
-            red_buffer[i] = 0x88;
-            red_buffer[i + 3] = 0xff;
-            // Blue
-            blue_buffer[i + 2] = 0x88;
-            blue_buffer[i + 3] = 0xff;
+    // マイクの字幕（音声認識にはメインの入力を渡す）
+    let (mut captions, captions_feed) = match &config.captions {
+        Some(c) if player.is_none() => {
+            let c = config::CaptionsConfig {
+                model: config.resolve(&c.model),
+                font: config.resolve(&c.font),
+                ..c.clone()
+            };
+            match captions::Captions::start(
+                &c,
+                width as usize,
+                height as usize,
+                config.audio.analysis_rate,
+                config.audio.threshold,
+            ) {
+                Ok((captions, feed)) => (Some(captions), Some(feed)),
+                Err(e) => {
+                    tracing::warn!("{}", t!("captions.failed", e));
+                    (None, None)
+                }
+            }
         }
-        images.push(red_buffer);
-        images.push(blue_buffer);
-    }
+        _ => (None, None),
+    };
 
-    // 現在の画像インデックス
-    let current_index = Arc::new(AtomicUsize::new(0));
-    let image_count = images.len();
+    // Webカメラ風の枠
+    let cam_frame = config
+        .cam_frame
+        .as_ref()
+        .map(|c| cam_frame::CamFrame::new(c, width as usize, height as usize));
+    // 発話時間の表示
+    let mut talk_time = config.talk_time.as_ref().and_then(|c| {
+        let c = config::TalkTimeConfig {
+            font: c.font.as_ref().map(|font| config.resolve(font)),
+            ..c.clone()
+        };
+        talk_time::TalkTime::new(&c, width as usize, height as usize)
+            .map_err(|e| tracing::warn!("{}", t!("talk_time.failed", e)))
+            .ok()
+    });
+    // 外部のデータを埋め込んだ文字
+    let mut texts = (!config.texts.is_empty())
+        .then(|| {
+            let texts: Vec<_> = config
+                .texts
+                .iter()
+                .map(|c| config::TextConfig {
+                    font: config.resolve(&c.font),
+                    ..c.clone()
+                })
+                .collect();
+            let variables = data::start(&config.data);
+            text::TextOverlays::new(&texts, variables, width as usize, height as usize)
+                .map_err(|e| tracing::warn!("{}", t!("text.failed", e)))
+                .ok()
+        })
+        .flatten();
+    // 作業と休憩のタイマー
+    let mut pomodoro = config.pomodoro.as_ref().and_then(|c| {
+        let c = config::PomodoroConfig {
+            font: c.font.as_ref().map(|font| config.resolve(font)),
+            ..c.clone()
+        };
+        pomodoro::Pomodoro::new(&c, width as usize, height as usize, Instant::now())
+            .map_err(|e| tracing::warn!("{}", t!("pomodoro.failed", e)))
+            .ok()
+    });
+    // 着替えの規則で隠しているパーツ
+    let mut hidden_layers: BTreeMap<bus::LayerSource, Vec<String>> = BTreeMap::new();
+    // 表情ガチャ
+    let mut gacha = config
+        .gacha
+        .as_ref()
+        .map(|c| gacha::Gacha::new(c, random.stream("gacha")));
+    // 重ねて表示するものの見た目（ホットキーや ctl theme で切り替える）
+    let mut themes = theme::Themes::new(&config::ThemesConfig {
+        files: config
+            .themes
+            .files
+            .iter()
+            .map(|f| config.resolve(f))
+            .collect(),
+        ..config.themes.clone()
+    });
+    let mut theme = themes.current().unwrap_or_else(|e| {
+        tracing::warn!("{}", t!("theme.failed", format!("{e:#}")));
+        theme::Theme::default()
+    });
+    apply_theme(
+        &theme,
+        talk_time.as_mut(),
+        pomodoro.as_mut(),
+        texts.as_mut(),
+    );
 
-    // オーディオキャプチャをセットアップ
-    let current_index_clone = current_index.clone();
+    // オーディオキャプチャをセットアップ（リプレイ中は記録された状態を描画ループから流す）
+    // リプレイで動かす状態（0 がメイン、以降は専用の入力を持つスロット）
+    let mut replay_writers = vec![None];
+    if player.is_some() {
+        replay_writers[0] = Some(main_writer);
+    } else {
+        let audio = config.audio.clone();
+        let bus = bus.clone();
+        let recorder = recorder.clone();
+        let controls = controls.clone();
+        let extra = audio::Analysis {
+            beat: config
+                .music
+                .as_ref()
+                .map(|music| beat::BeatDetector::new(music, config.audio.analysis_rate)),
+            captions: captions_feed,
+            emotion: config
+                .emotion
+                .as_ref()
+                .map(|emotion| emotion::EmotionDetector::new(emotion, &config.audio)),
+            features: (!mapper.is_empty()).then(|| {
+                features::Analyzer::new(config.audio.analysis_rate, config.audio.threshold)
+            }),
+        };
 
-    // Note: Audio thread needs to live as long as the app
-    let _audio_thread = std::thread::spawn(move || {
-        if let Err(e) = setup_audio_capture(current_index_clone, image_count) {
-            eprintln!("Audio capture error: {}", e);
-        }
+        // Note: Audio thread needs to live as long as the app
+        std::thread::spawn(move || {
+            audio::run(
+                main_writer,
+                None,
+                audio,
+                Some(bus),
+                recorder,
+                controls,
+                extra,
+            );
+        });
+    }
+
+    // 追加のスロット（専用の入力があればそれぞれキャプチャし、共演者のものは UDP で受け取る）
+    let mut feeds = Vec::new();
+    let mut slots: Vec<slot::Slot> = config
+        .slots
+        .iter()
+        .enumerate()
+        .map(|(i, slot)| {
+            if let Some(name) = &slot.feed {
+                let (writer, reader) = state::channel();
+                if player.is_some() {
+                    replay_writers.push(Some(writer));
+                } else {
+                    replay_writers.push(None);
+                    feeds.push((name.clone(), writer));
+                }
+                let rng = random.stream(&format!("slot {i}"));
+                return slot::Slot::new(slot, &config, Some(reader), rng);
+            }
+            let Some(input) = slot.input.clone() else {
+                replay_writers.push(None);
+                return slot::Slot::new(slot, &config, None, random.stream(&format!("slot {i}")));
+            };
+            let (writer, reader) = state::channel();
+            if player.is_some() {
+                replay_writers.push(Some(writer));
+            } else {
+                replay_writers.push(None);
+                let audio = config.audio.clone();
+                let recorder = recorder.as_ref().map(|r| r.for_source(i + 1));
+                let controls = controls.clone();
+                std::thread::spawn(move || {
+                    audio::run(
+                        writer,
+                        Some(input),
+                        audio,
+                        None,
+                        recorder,
+                        controls,
+                        audio::Analysis::default(),
+                    );
+                });
+            }
+            slot::Slot::new(
+                slot,
+                &config,
+                Some(reader),
+                random.stream(&format!("slot {i}")),
+            )
+        })
+        .collect();
+
+    let feed = config.feed.clone().unwrap_or_default();
+    if let Some(listen) = &feed.listen
+        && !feeds.is_empty()
+        && let Err(e) = feed::listen(listen, feeds)
+    {
+        tracing::warn!("{}", t!("feed.failed", format!("{e:#}")));
+    }
+    let mut feed_sender = feed.send.as_deref().and_then(|target| {
+        feed::Sender::new(&feed, target)
+            .map_err(|e| tracing::warn!("{}", t!("feed.failed", format!("{e:#}"))))
+            .ok()
     });
 
     // Winit セットアップ
     let event_loop = EventLoop::new()?;
-    let window = WindowBuilder::new()
-        .with_title("Image Viewer - ESC to exit, F to toggle fullscreen")
-        .with_inner_size(LogicalSize::new(width, height))
-        .build(&event_loop)?;
-
-    let mut pixels = {
-        let window_size = window.inner_size();
-        let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(width, height, surface_texture)?
+    let mut builder = WindowBuilder::new()
+        .with_title(window_title(&config.window, None))
+        .with_window_icon(window_icon(&config))
+        .with_inner_size(LogicalSize::new(width, height));
+    // マスコットは枠のない透明なウィンドウで、常に最前面に置く
+    if config.mascot.is_some() {
+        builder = builder
+            .with_decorations(false)
+            .with_transparent(true)
+            .with_resizable(false)
+            .with_window_level(WindowLevel::AlwaysOnTop);
+    }
+    let window = builder.build(&event_loop)?;
+    // 設定を開き直したら、タイトルとアイコンも変える
+    let mut window_config = config.window.clone();
+    // OBS より先に起動しておくとき、ウィンドウを前面に出さない
+    // （レイヤーシェルに出しているときは、ウィンドウは操作用として最小化しておく）
+    if minimized || layer_shell.is_some() {
+        window.set_minimized(true);
+    }
+
+    let mut renderer = timings::measure(timings::Phase::Surface, || {
+        render::Renderer::new(&window, width, height)
+    })?;
+    // ウィンドウと並べて、同じフレームを送る出力
+    let mut sinks: Vec<Box<dyn sink::FrameSink>> = Vec::new();
+    // 出力するフレームの時刻の基準
+    let started = Instant::now();
+    if let Some(layer_shell) = layer_shell {
+        sinks.push(Box::new(layer_shell));
+    }
+    for output in &config.outputs {
+        match sink::start(output, &config, width, height) {
+            Ok(sink) => sinks.push(sink),
+            Err(e) => tracing::warn!("{}", t!("sink.start_failed", e)),
+        }
+    }
+    let mut hopping = false;
+    let mut mascot = config.mascot.as_ref().map(|mascot| {
+        renderer.set_transparent();
+        mascot::Mascot::new(mascot, &window, Instant::now(), random.stream("mascot"))
+    });
+
+    // 配信者向けの小さなプレビューウィンドウ
+    let mut preview = if config.preview.enabled {
+        match preview::Preview::new(&event_loop, width, height, config.preview.scale) {
+            Ok(preview) => Some(preview),
+            Err(e) => {
+                tracing::warn!("{}", t!("preview.failed", e));
+                None
+            }
+        }
+    } else {
+        None
     };
 
-    let mut is_fullscreen = false;
+    if state.fullscreen {
+        window.set_fullscreen(Some(winit::window::Fullscreen::Borderless(None)));
+    }
+
+    // 描画が復旧不能なときは異常終了としてウォッチドッグに再起動させる
+    let render_failed = Rc::new(Cell::new(false));
+    let render_failed_clone = render_failed.clone();
 
     event_loop.run(move |event, elwt| {
         elwt.set_control_flow(ControlFlow::Poll);
 
         match event {
+            Event::WindowEvent { window_id, event }
+                if preview.as_ref().is_some_and(|p| p.id() == window_id) =>
+            {
+                if let Some(p) = &mut preview {
+                    match p.handle_event(&event, &output) {
+                        Ok(true) => {}
+                        Ok(false) => preview = None,
+                        Err(e) => {
+                            tracing::warn!("{}", t!("preview.failed", e));
+                            preview = None;
+                        }
+                    }
+                }
+            }
+
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
@@ -211,65 +814,709 @@ fn main() -> Result<()> {
                 event: WindowEvent::Resized(size),
                 ..
             } => {
-                if let Err(err) = pixels.resize_surface(size.width, size.height) {
-                    eprintln!("pixels.resize_surface failed: {}", err);
+                if let Err(err) = renderer.resize_surface(size.width, size.height) {
+                    tracing::error!("{}", t!("render.resize_failed", err));
+                    render_failed_clone.set(true);
                     elwt.exit();
                 }
                 window.request_redraw();
             }
 
+            Event::WindowEvent {
+                event:
+                    WindowEvent::KeyboardInput {
+                        event:
+                            KeyEvent {
+                                physical_key: PhysicalKey::Code(KeyCode::Space),
+                                state: key_state,
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            } if no_audio => manual_talking = key_state.is_pressed(),
+
+            Event::WindowEvent {
+                event:
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                        ..
+                    },
+                ..
+            } if permission_overlay.is_some() => permission::open_settings(),
+
             Event::WindowEvent {
                 event:
                     WindowEvent::KeyboardInput {
                         event:
                             KeyEvent {
                                 physical_key: PhysicalKey::Code(keycode),
-                                state: winit::event::ElementState::Pressed,
+                                state: ElementState::Pressed,
                                 ..
                             },
                         ..
                     },
                 ..
             } => match keycode {
+                // 調整中は矢印キーなどを調整に使う
+                keycode if tuning.is_some() && tuning::Tuning::handles(keycode) => {
+                    let thresholds = &controls.thresholds;
+                    match tuning.as_ref().and_then(|t| t.key(keycode, thresholds)) {
+                        None => window.set_title(&tuning::Tuning::title(thresholds)),
+                        Some(finish) => {
+                            if let tuning::Finish::Save = finish {
+                                let mut saved = config.clone();
+                                let audio = &mut saved.audio;
+                                (
+                                    audio.threshold,
+                                    audio.whisper_threshold,
+                                    audio.whisper_hold_ms,
+                                ) = thresholds.get();
+                                match saved.save_thresholds() {
+                                    Ok(path) => {
+                                        tracing::info!("{}", t!("tuning.saved", path.display()))
+                                    }
+                                    Err(e) => tracing::warn!("{}", t!("tuning.save_failed", e)),
+                                }
+                            } else {
+                                tracing::info!("{}", t!("tuning.cancelled"));
+                            }
+                            tuning = None;
+                            dirty.invalidate();
+                            window.set_title(&window_title(&window_config, audio_warning));
+                        }
+                    }
+                }
                 KeyCode::Escape => elwt.exit(),
-                KeyCode::KeyF => {
-                    is_fullscreen = !is_fullscreen;
-                    window.set_fullscreen(if is_fullscreen {
-                        Some(winit::window::Fullscreen::Borderless(None))
-                    } else {
-                        None
-                    });
+                KeyCode::KeyF => toggle_fullscreen(&window, &mut state, state_file.as_deref()),
+                KeyCode::KeyT => {
+                    tuning = Some(tuning::Tuning::new(
+                        &controls.thresholds,
+                        Instant::now(),
+                        &theme,
+                    ));
+                    tracing::info!("{}", t!("tuning.started"));
+                    window.set_title(&tuning::Tuning::title(&controls.thresholds));
+                }
+                keycode if talk_time.as_ref().is_some_and(|w| w.is_hotkey(keycode)) => {
+                    if let Some(talk_time) = &mut talk_time {
+                        talk_time.toggle();
+                    }
+                    dirty.invalidate();
+                }
+                keycode if pairing.as_ref().is_some_and(|p| p.is_hotkey(keycode)) => {
+                    if let Some(pairing) = &mut pairing {
+                        pairing.toggle();
+                    }
+                    dirty.invalidate();
+                }
+                keycode if themes.is_hotkey(keycode) => bus.publish(bus::Event::Theme(None)),
+                keycode if pomodoro.as_ref().is_some_and(|p| p.is_hotkey(keycode)) => {
+                    if let Some(pomodoro) = &mut pomodoro {
+                        pomodoro.dismiss(Instant::now());
+                    }
+                }
+                keycode if gacha.as_ref().is_some_and(|g| g.is_hotkey(keycode)) => {
+                    bus.publish(bus::Event::Gacha)
+                }
+                // 通知が重なったときに、まとめて飛ばす
+                keycode if rate_limiter.is_skip_hotkey(keycode) => {
+                    sequencer.stop();
+                    tracing::info!("{}", t!("rate_limit.skipped", rate_limiter.clear()));
+                }
+                _ => {
+                    if let Some(name) = sequencer.trigger_hotkey(keycode)
+                        && let Some(recorder) = &recorder
+                    {
+                        recorder.record(replay::ReplayEvent::Sequence {
+                            name: name.to_string(),
+                        });
+                    }
                 }
-                _ => {}
             },
 
             Event::WindowEvent {
                 event: WindowEvent::RedrawRequested,
                 ..
             } => {
-                let idx = current_index.load(Ordering::Relaxed);
-                let frame = pixels.frame_mut();
+                let now = Instant::now();
+                if let Some(p) = &mut player {
+                    for event in p.poll(now) {
+                        match event {
+                            replay::ReplayEvent::Start { config, .. } => {
+                                if let Some(config) = config {
+                                    tracing::info!(
+                                        "{}",
+                                        t!("replay.recorded_with", config.display())
+                                    );
+                                }
+                            }
+                            replay::ReplayEvent::Mouth {
+                                source,
+                                state,
+                                level,
+                            } => {
+                                if let Some(Some(writer)) = replay_writers.get_mut(source) {
+                                    writer.publish(state::RenderState {
+                                        mouth: state,
+                                        level,
+                                        ..Default::default()
+                                    });
+                                }
+                            }
+                            replay::ReplayEvent::Sequence { name } => {
+                                sequencer.trigger(&name);
+                            }
+                        }
+                    }
+                    if p.is_finished() {
+                        tracing::info!("{}", t!("replay.finished"));
+                        player = None;
+                    }
+                }
+                let mut live = main_state.latest();
+                let tracked = tracking.as_ref().and_then(|t| t.latest(now));
+                if let Some(tracked) = tracked {
+                    live.features
+                        .set(config::AudioFeature::MouthOpen, tracked.mouth_open);
+                    live.features
+                        .set(config::AudioFeature::Blink, tracked.blink);
+                }
+                // 声の特徴量で動かすパラメーター（口の状態の上書きを含む）
+                let layer_names = avatar.layers.iter().map(|layer| layer.name.as_str());
+                let (params, layer_params) = &mut modulation;
+                // 位置やパーツの変化は描き直す範囲の管理が見る。色味が変わったら全体
+                let tint = params.tint;
+                smoother.process(&mut live.features, now);
+                mapper.evaluate(&live.features, layer_names, now, params, layer_params);
+                // 着替えの規則で隠しているパーツ
+                for (layer, params) in avatar.layers.iter().zip(layer_params.iter_mut()) {
+                    if hidden_layers
+                        .values()
+                        .any(|layers| layers.contains(&layer.name))
+                    {
+                        params.visible = false;
+                    }
+                }
+                if let Some(eyes) = &mut eyes {
+                    let center = window.inner_position().ok().map(|position| {
+                        let size = window.inner_size();
+                        [
+                            position.x as f64 + size.width as f64 / 2.0,
+                            position.y as f64 + size.height as f64 / 2.0,
+                        ]
+                    });
+                    eyes.update(center, window.scale_factor(), now);
+                    let layer_names = avatar.layers.iter().map(|layer| layer.name.as_str());
+                    eyes.apply(layer_names, layer_params);
+                }
+                if let Some(typing) = &mut typing {
+                    typing.update(now);
+                    let layer_names = avatar.layers.iter().map(|layer| layer.name.as_str());
+                    typing.apply(layer_names, params, layer_params, now);
+                }
+                let (params, layer_params) = &modulation;
+                let tinted = params.tint != tint;
+                let mouth = if muted {
+                    Mouth::Idle
+                } else if no_audio {
+                    if manual_talking {
+                        Mouth::Talking
+                    } else {
+                        Mouth::Idle
+                    }
+                } else {
+                    params.mouth.map_or(live.mouth, Mouth::from_index)
+                };
 
-                // Copy current image to frame
-                if idx < images.len() {
-                    let image_data = &images[idx];
-                    if frame.len() == image_data.len() {
-                        frame.copy_from_slice(image_data);
+                let cue = sequencer.update(now, &mut effects);
+                for effect in effects.drain(..) {
+                    match effect {
+                        sequence::Effect::Sound(path) => {
+                            if let Some(player) = &mut sound_player {
+                                player.play(&path);
+                            }
+                        }
+                        sequence::Effect::Particles(config) => particles.burst(&config, now),
+                    }
+                }
+                // 優先順位の一番高い要求（既定ではシーケンス > 手動 > ガチャ > タイマー > 前面のアプリ > MQTT > 声）、なければデフォルト
+                let emotion_expression = live
+                    .emotion
+                    .zip(emotion_config.as_ref())
+                    .and_then(|(emotion, config)| emotion.expression(config))
+                    .filter(|name| avatar.expressions.contains_key(*name));
+                claims.set(config::ExpressionSource::Sequence, cue.expression, now);
+                claims.set(config::ExpressionSource::Audio, emotion_expression, now);
+                // 休憩に入ったり出たりしたら表情も変える
+                let pomodoro_changed = pomodoro.as_mut().is_some_and(|p| p.update(now));
+                let break_expression = pomodoro.as_ref().and_then(|p| p.expression());
+                claims.set(config::ExpressionSource::Timer, break_expression, now);
+                if let Some(gacha) = &mut gacha {
+                    gacha.update(now);
+                    claims.set(config::ExpressionSource::Gacha, gacha.expression(now), now);
+                }
+                let expression = claims.resolve(now).unwrap_or(&avatar.default);
+                if let Some(sender) = &mut feed_sender {
+                    sender.update(mouth, live.level, now);
+                }
+                if let Some(stats) = &mut stats {
+                    stats.update(mouth, live.level, expression, now);
+                }
+                // 変わったときだけ文字列を作る（毎フレーム確保しない）
+                if reported
+                    .as_ref()
+                    .is_none_or(|(e, m, x)| e != expression || *m != mouth || *x != muted)
+                {
+                    reported = Some((expression.to_string(), mouth, muted));
+                    let status = ipc::Status {
+                        expression: expression.to_string(),
+                        mouth: mouth.state().to_string(),
+                        muted,
+                        expressions: avatar.expressions.keys().cloned().collect(),
+                        sequences: sequence_names.clone(),
+                    };
+                    if let Some(remote) = &remote {
+                        remote.set_status(status.clone());
+                    }
+                    if let Some(server) = &ipc_server {
+                        server.set_status(status);
+                    }
+                    if let Some(mqtt) = &mqtt {
+                        mqtt.publish_state(expression, mouth.state());
+                    }
+                }
+                // 音楽モードでは拍ごとに跳ねるか、発話フレームを進める
+                if live.beats != seen_beats {
+                    seen_beats = live.beats;
+                    beat_at = Some(now);
+                }
+                let index = match &music_config {
+                    Some(music) if music.action == config::BeatAction::Advance => seen_beats,
+                    _ => talking_frames.update(
+                        mouth == Mouth::Talking,
+                        avatar.talking_count(expression),
+                        now,
+                    ),
+                };
+                let (image_data, info, mut animated) = match &video {
+                    Some(video)
+                        if video.shown_in(mouth.state()) && video.copy_latest(&mut video_frame) =>
+                    {
+                        let info = dirty::FrameInfo::standalone(&video_frame);
+                        (Some(video_frame.as_slice()), Some(info), true)
+                    }
+                    _ => {
+                        // まばたきは口を閉じている間だけ。トラッキングがあれば目の閉じ具合で
+                        let closed = match tracked {
+                            Some(tracked) => tracked.blink > blink_threshold,
+                            None => blinks.update(now),
+                        };
+                        let blink = (closed && mouth == Mouth::Idle)
+                            .then(|| avatar.blink(expression))
+                            .flatten();
+                        let frame = blink.or_else(|| avatar.frame(expression, mouth, index));
+                        let info = frame.and_then(|frame| avatar.frame_info(expression, frame));
+                        (frame, info, false)
+                    }
+                };
+                for slot in &mut slots {
+                    slot.update(&avatar, mouth, now);
+                }
+                let slot_frames = slots.iter().map(|slot| (slot.frame(&avatar), slot.rect()));
+                // パーティクルが飛んでいる間と、消えた直後は全体を描き直す
+                let shown = particles.update(now);
+                animated |= shown || particles_shown;
+                particles_shown = shown;
+                if let Some(captions) = &mut captions {
+                    animated |= captions.update(now);
+                }
+                if let Some(talk_time) = &mut talk_time {
+                    animated |= talk_time.update(live.mouth != Mouth::Idle, now);
+                }
+                animated |= pomodoro_changed;
+                if let Some(texts) = &mut texts {
+                    animated |= texts.update();
+                }
+                // 声の高さ・音量に合わせた位置と、重ねるパーツの状態
+                let mut transform = params.apply(cue.transform);
+                // マスコットが歩いている間は足取りに合わせて跳ねる
+                if let Some(mascot) = &mut mascot {
+                    let hop = mascot.update(&window, mouth, now);
+                    transform.offset[1] -= hop;
+                    animated |= hop > 0.0 || hopping;
+                    hopping = hop > 0.0;
+                }
+                animated |= tinted;
+                let bop = match (&music_config, beat_at) {
+                    (Some(music), Some(at)) if music.action == config::BeatAction::Pulse => {
+                        beat::pulse(music, now.duration_since(at))
+                    }
+                    _ => 0.0,
+                };
+                transform.scale *= 1.0 + bop;
+                // 跳ねている間と、戻った直後は全体を描き直す
+                animated |= bop > 0.0 || pulsing;
+                pulsing = bop > 0.0;
+                if let Some(squash) = &mut squash {
+                    let talking = mouth != Mouth::Idle;
+                    let moving = squash.update(talking, &mut transform, height as f32, now);
+                    animated |= moving || squashing;
+                    squashing = moving;
+                }
+                // 調整中のメーターは毎フレーム描き直す
+                if renderer.take_reset() || tuning.is_some() {
+                    dirty.invalidate();
+                }
+                // 前回から変わった領域だけを合成し直してアップロードする
+                let layer_states = avatar
+                    .layers
+                    .iter()
+                    .zip(layer_params)
+                    .map(|(layer, params)| {
+                        (layer.bounds, params.apply(transform), params.visibility())
+                    });
+                let region =
+                    dirty.update(info, transform, animated, layer_states, slot_frames.clone());
+                if let Some(region) = region {
+                    let (w, h) = (width as usize, height as usize);
+                    match image_data {
+                        Some(image_data) => {
+                            compose::draw_region(&mut output, image_data, w, h, transform, region)
+                        }
+                        None => {
+                            for y in region.y..region.y + region.height {
+                                output[region.row(y, w)].fill(0);
+                            }
+                        }
+                    }
+                    let layers = avatar.layers.iter().zip(layer_params);
+                    for (i, (layer, params)) in layers.clone().enumerate() {
+                        // 切り抜かれるレイヤーは、元のレイヤーと一緒に描く
+                        if layer.clip.is_some() {
+                            continue;
+                        }
+                        let base = (
+                            layer.pixels.as_slice(),
+                            params.apply(transform),
+                            params.visibility(),
+                        );
+                        let mut clipped = layers
+                            .clone()
+                            .filter(|(clipped, _)| clipped.clip == Some(i))
+                            .map(|(clipped, params)| {
+                                (
+                                    clipped.pixels.as_slice(),
+                                    params.apply(transform),
+                                    params.visibility(),
+                                )
+                            })
+                            .peekable();
+                        if clipped.peek().is_none() {
+                            let (pixels, transform, opacity) = base;
+                            compose::draw_over(
+                                &mut output,
+                                pixels,
+                                w,
+                                h,
+                                transform,
+                                opacity,
+                                region,
+                            );
+                        } else {
+                            compose::draw_clipped(
+                                &mut output,
+                                base,
+                                clipped,
+                                w,
+                                h,
+                                region,
+                                &mut clip_scratch,
+                            );
+                        }
+                    }
+                    if load_monitor
+                        .as_ref()
+                        .is_none_or(|monitor| monitor.quality().effects())
+                    {
+                        compose::tint(&mut output, w, region, params.tint);
+                    }
+                    for (frame, rect) in slot_frames {
+                        if let Some(frame) = frame {
+                            compose::draw_rect(&mut output, frame, w, h, rect, region);
+                        }
+                    }
+                    if let Some(cam_frame) = &cam_frame {
+                        cam_frame.draw(&mut output, region);
+                    }
+                    if let Some(captions) = &captions {
+                        captions.draw(&mut output, region);
+                    }
+                    if let Some(talk_time) = &talk_time {
+                        talk_time.draw(&mut output, region);
+                    }
+                    if let Some(pomodoro) = &pomodoro {
+                        pomodoro.draw(&mut output, region);
+                    }
+                    if let Some(texts) = &texts {
+                        texts.draw(&mut output, region);
+                    }
+                    particles.draw(&mut output, now);
+                    // 出力先で音声と合わせられるよう、合成した時刻と音声の位置を付ける
+                    let timestamp = sink::Timestamp {
+                        time: now.saturating_duration_since(started),
+                        audio: match &player {
+                            Some(player) => Some(player.elapsed(now)),
+                            None => live.position,
+                        },
+                    };
+                    for sink in &mut sinks {
+                        sink.present(&output, region, timestamp);
+                    }
+                    renderer.present(&output, region, timestamp);
+                    if let Some(frame) = renderer.frame_mut()
+                        && frame.len() == output.len()
+                    {
+                        // 案内はウィンドウにだけ重ね、出力には入れない
+                        if let Some(overlay) = &permission_overlay {
+                            overlay.draw(frame, region);
+                        }
+                        if let Some(pairing) = &pairing {
+                            pairing.draw(frame, region);
+                        }
+                        if let Some(tuning) = &mut tuning {
+                            tuning.draw(frame, w, h, &live, &controls.thresholds, now);
+                        }
                     }
                 }
 
-                if let Err(e) = pixels.render() {
-                    eprintln!("pixels.render() failed: {}", e);
-                    elwt.exit();
+                // 表示の待ち時間は含めず、合成にかかった時間で負荷を見る
+                if let Some(monitor) = &mut load_monitor
+                    && let Some(quality) = monitor.update(now.elapsed(), Instant::now())
+                {
+                    particles.set_share(quality.particles());
+                    dirty.invalidate();
+                }
+                match renderer.render(&window) {
+                    Ok(()) => timings::frame_shown(),
+                    Err(e) => {
+                        tracing::error!("{}", t!("render.render_failed", e));
+                        render_failed_clone.set(true);
+                        elwt.exit();
+                    }
                 }
             }
 
             Event::AboutToWait => {
+                let now = Instant::now();
+                for event in events.poll() {
+                    match event {
+                        bus::Event::Open(forwarded) => {
+                            // 二重起動されたら既存のウィンドウを前に出す
+                            window.set_minimized(false);
+                            window.focus_window();
+                            let args = std::iter::once("darwin".to_string()).chain(forwarded.args);
+                            let Some(path) = <cli::Cli as clap::Parser>::try_parse_from(args)
+                                .ok()
+                                .and_then(|cli| cli.config)
+                            else {
+                                continue;
+                            };
+                            bus.publish(bus::Event::Profile(forwarded.cwd.join(path)));
+                        }
+                        bus::Event::Profile(path) => {
+                            match load_config(Some(path.clone())) {
+                                Ok(mut new) => {
+                                    // 出力の大きさは起動時のまま
+                                    new.canvas.width = width;
+                                    new.canvas.height = height;
+                                    avatar = avatar::Avatar::load(&new);
+                                    sequencer = sequence::Sequencer::new(&new);
+                                    sequence_names =
+                                        sequencer.names().map(str::to_string).collect();
+                                    if let Some(player) = &mut sound_player {
+                                        for sound in sequencer.sounds() {
+                                            player.preload(sound);
+                                        }
+                                    }
+                                    claims = priority::ExpressionClaims::new(&new.priority);
+                                    rate_limiter = rate_limit::RateLimiter::new(&new.rate_limit);
+                                    gacha = new
+                                        .gacha
+                                        .as_ref()
+                                        .map(|c| gacha::Gacha::new(c, random.stream("gacha")));
+                                    reported = None;
+                                    dirty.invalidate();
+                                    window_config = new.window.clone();
+                                    if tuning.is_none() {
+                                        window.set_title(&window_title(
+                                            &window_config,
+                                            audio_warning,
+                                        ));
+                                    }
+                                    window.set_window_icon(window_icon(&new));
+                                    tracing::info!("{}", t!("ipc.opened", path.display()));
+                                    let name = path.file_stem().unwrap_or(path.as_os_str());
+                                    announce(&t!("announce.profile", name.to_string_lossy()));
+                                }
+                                Err(e) => {
+                                    let message = t!("ipc.open_failed", format!("{e:#}"));
+                                    tracing::warn!("{message}");
+                                    notify::critical(&message);
+                                }
+                            }
+                        }
+                        // 設定を開き直して無くなった表情は無視する
+                        bus::Event::Expression(_, Some(name))
+                            if !avatar.expressions.contains_key(&name) => {}
+                        bus::Event::Expression(source, name) => {
+                            claims.set(source, name.as_deref(), now)
+                        }
+                        bus::Event::ToggleExpression(name) => {
+                            let manual = config::ExpressionSource::Manual;
+                            let selected = claims.get(manual, now) == Some(name.as_str());
+                            claims.set(manual, (!selected).then_some(name.as_str()), now);
+                        }
+                        bus::Event::Sequence(name) => {
+                            if sequencer.trigger(&name)
+                                && let Some(recorder) = &recorder
+                            {
+                                recorder.record(replay::ReplayEvent::Sequence { name });
+                            }
+                        }
+                        bus::Event::Trigger(name) if sequence_names.contains(&name) => {
+                            rate_limiter.push(name, now)
+                        }
+                        bus::Event::Trigger(name)
+                            if gacha.as_ref().is_some_and(|g| g.is_trigger(&name)) =>
+                        {
+                            bus.publish(bus::Event::Gacha)
+                        }
+                        bus::Event::Trigger(_) => {}
+                        bus::Event::HideLayers(source, layers) => {
+                            hidden_layers.insert(source, layers);
+                            dirty.invalidate();
+                        }
+                        bus::Event::Gacha => {
+                            if let Some(gacha) = &mut gacha {
+                                gacha.draw(avatar.expressions.keys(), &avatar.default, now);
+                            }
+                        }
+                        bus::Event::Filter(band, hz) => {
+                            controls.cutoffs.set(band, hz);
+                            tracing::info!(?band, ?hz, "{}", t!("ipc.filter_changed"));
+                        }
+                        bus::Event::Mute(on) => {
+                            let was_muted = muted;
+                            muted = on.unwrap_or(!muted);
+                            let message = if muted {
+                                t!("ipc.muted")
+                            } else {
+                                t!("ipc.unmuted")
+                            };
+                            tracing::info!("{message}");
+                            if muted != was_muted {
+                                announce(message);
+                            }
+                        }
+                        bus::Event::Theme(name) => match themes.select(name.as_deref()) {
+                            Ok(selected) => {
+                                theme = selected;
+                                apply_theme(
+                                    &theme,
+                                    talk_time.as_mut(),
+                                    pomodoro.as_mut(),
+                                    texts.as_mut(),
+                                );
+                                dirty.invalidate();
+                                let message = t!("theme.selected", themes.name());
+                                tracing::info!("{message}");
+                                announce(&message);
+                            }
+                            Err(e) => {
+                                tracing::warn!("{}", t!("theme.failed", format!("{e:#}")))
+                            }
+                        },
+                        bus::Event::Fullscreen => {
+                            toggle_fullscreen(&window, &mut state, state_file.as_deref())
+                        }
+                        bus::Event::Quit => elwt.exit(),
+                        // 入力の異常はタイトルとプレビューに出す（配信画面には出さない）
+                        bus::Event::AudioWarning(warning) => {
+                            let missing = warning.is_some_and(health::AudioWarning::is_unavailable);
+                            let denied = warning == Some(health::AudioWarning::PermissionDenied);
+                            if denied != permission_overlay.is_some() {
+                                permission_overlay = denied
+                                    .then(|| {
+                                        permission::Overlay::new(width as usize, height as usize)
+                                    })
+                                    .flatten();
+                                dirty.invalidate();
+                            }
+                            if missing && !no_audio {
+                                tracing::info!("{}", t!("audio.manual_control"));
+                            }
+                            match warning {
+                                Some(warning) if missing && !no_audio => {
+                                    announce(warning.message());
+                                    // 通知で知らせているなら、同じものを二度出さない
+                                    if !announces_by_notification {
+                                        notify::critical(warning.message());
+                                    }
+                                }
+                                None if no_audio => announce(t!("announce.device_back")),
+                                _ => {}
+                            }
+                            no_audio = missing;
+                            manual_talking &= no_audio;
+                            audio_warning = warning;
+                            if tuning.is_none() {
+                                window.set_title(&window_title(&window_config, warning));
+                            }
+                            if let Some(p) = &mut preview {
+                                p.set_warning(warning);
+                            }
+                        }
+                    }
+                }
+                // 待っているシーケンスは、前のものが終わってから順に始める
+                if !sequencer.is_playing()
+                    && let Some(name) = rate_limiter.next(now)
+                    && sequencer.trigger(&name)
+                    && let Some(recorder) = &recorder
+                {
+                    recorder.record(replay::ReplayEvent::Sequence { name });
+                }
+                // 手動の選択が変わったとき（取り下げの時間切れを含む）だけ伝える
+                let manual = claims.get(config::ExpressionSource::Manual, now);
+                if manual != shown_manual.as_deref() {
+                    shown_manual = manual.map(str::to_string);
+                    if let Some(deck) = &stream_deck {
+                        deck.set_selected(manual);
+                    }
+                    sync.set_expression(manual);
+                }
+
                 window.request_redraw();
+                if let Some(p) = &preview {
+                    p.request_redraw();
+                }
+            }
+            Event::LoopExiting => {
+                if let Some(stats) = &stats {
+                    match stats.write(Instant::now()) {
+                        Ok(path) => tracing::info!("{}", t!("stats.written", path.display())),
+                        Err(e) => tracing::warn!("{}", e),
+                    }
+                }
             }
             _ => {}
         }
     })?;
 
+    if render_failed.get() {
+        bail!(t!("render.fatal"));
+    }
     Ok(())
 }
@@ -0,0 +1,229 @@
+// モジュレーションマトリクス: 音声の特徴量 → アバター・パーツのパラメーター
+use crate::{
+    color,
+    compose::Transform,
+    config::{Curve, MappingConfig},
+    features::Features,
+};
+use std::time::Instant;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Property {
+    OffsetX,
+    OffsetY,
+    Scale,
+    Rotation,
+    Opacity,
+    Visible,
+    Tint,
+    Mouth,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    Avatar(Property),
+    Layer(String, Property),
+}
+
+/// Checks a parameter name: `Some(None)` for an avatar parameter, `Some(Some(layer))` for a
+/// layer parameter, `None` if it is not a parameter.
+pub fn parse_parameter(parameter: &str) -> Option<Option<&str>> {
+    match parse(parameter)? {
+        Target::Avatar(_) => Some(None),
+        Target::Layer(..) => Some(parameter.split('.').nth(1)),
+    }
+}
+
+fn parse(parameter: &str) -> Option<Target> {
+    let property = |name: &str| match name {
+        "offset_x" => Some(Property::OffsetX),
+        "offset_y" => Some(Property::OffsetY),
+        "scale" => Some(Property::Scale),
+        "rotation" => Some(Property::Rotation),
+        "opacity" => Some(Property::Opacity),
+        "visible" => Some(Property::Visible),
+        "tint" => Some(Property::Tint),
+        "mouth" => Some(Property::Mouth),
+        _ => None,
+    };
+    match parameter.split('.').collect::<Vec<_>>()[..] {
+        // 透明度と表示はパーツだけ、色味と口はアバター全体だけ
+        ["opacity" | "visible"] => None,
+        [name] => property(name).map(Target::Avatar),
+        ["layer", _, "tint" | "mouth"] => None,
+        ["layer", layer, name] => property(name).map(|p| Target::Layer(layer.to_string(), p)),
+        _ => None,
+    }
+}
+
+/// Offset, scale, rotation and so on to apply on top of a layer's (or the avatar's) own
+/// placement.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Params {
+    pub offset: [f32; 2],
+    pub scale: f32,
+    pub rotation: f32,
+    pub opacity: f32,
+    pub visible: bool,
+    // リニアの RGB に掛ける係数
+    pub tint: [f32; 3],
+    // 口の状態の上書き（Mouth::from_index）
+    pub mouth: Option<usize>,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            offset: [0.0, 0.0],
+            scale: 1.0,
+            rotation: 0.0,
+            opacity: 1.0,
+            visible: true,
+            tint: [1.0; 3],
+            mouth: None,
+        }
+    }
+}
+
+impl Params {
+    pub fn apply(&self, transform: Transform) -> Transform {
+        Transform {
+            offset: [
+                transform.offset[0] + self.offset[0],
+                transform.offset[1] + self.offset[1],
+            ],
+            scale: transform.scale * self.scale,
+            rotation: transform.rotation + self.rotation,
+            stretch: transform.stretch,
+        }
+    }
+
+    /// Opacity to draw with, zero when hidden.
+    pub fn visibility(&self) -> f32 {
+        if self.visible { self.opacity } else { 0.0 }
+    }
+
+    // 同じパラメーターへの対応は、ずれと角度は足し、倍率は掛ける
+    fn add(&mut self, property: Property, value: f32, color: [f32; 3]) {
+        match property {
+            Property::OffsetX => self.offset[0] += value,
+            Property::OffsetY => self.offset[1] += value,
+            Property::Scale => self.scale *= value,
+            Property::Rotation => self.rotation += value,
+            Property::Opacity => self.opacity *= value.clamp(0.0, 1.0),
+            Property::Visible => self.visible &= value >= 0.5,
+            Property::Tint => {
+                let amount = value.clamp(0.0, 1.0);
+                for (tint, c) in self.tint.iter_mut().zip(color) {
+                    *tint *= 1.0 + (c - 1.0) * amount;
+                }
+            }
+            Property::Mouth => self.mouth = Some(value.round().max(0.0) as usize),
+        }
+    }
+}
+
+struct Mapping {
+    config: MappingConfig,
+    target: Target,
+    color: [f32; 3],
+    // 平滑化した出力
+    value: Option<f32>,
+}
+
+/// Evaluates the configured routes against the latest features.
+pub struct Mapper {
+    mappings: Vec<Mapping>,
+    last: Option<Instant>,
+    // パーツごとの結果を組み立てる場所（前回の結果と入れ替えて使い回す）
+    scratch: Vec<Params>,
+}
+
+impl Mapper {
+    pub fn new(config: &[MappingConfig]) -> Self {
+        let mappings = config
+            .iter()
+            .filter_map(|m| {
+                Some(Mapping {
+                    target: parse(&m.parameter)?,
+                    color: m.color.map_or([1.0; 3], |c| c.map(color::to_linear)),
+                    config: m.clone(),
+                    value: None,
+                })
+            })
+            .collect();
+        Self {
+            mappings,
+            last: None,
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mappings.is_empty()
+    }
+
+    /// Writes the parameters for the avatar to `params` and for each of `layers`, in order,
+    /// to `layer_params`.
+    pub fn evaluate<'a>(
+        &mut self,
+        features: &Features,
+        layers: impl Iterator<Item = &'a str> + Clone,
+        now: Instant,
+        params: &mut Params,
+        layer_params: &mut Vec<Params>,
+    ) {
+        let dt = self
+            .last
+            .replace(now)
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+        let mut avatar = Params::default();
+        self.scratch.clear();
+        self.scratch
+            .resize(layers.clone().count(), Params::default());
+        for mapping in &mut self.mappings {
+            let config = &mapping.config;
+            let value = features.get(config.feature);
+            // 入力の範囲で 0..1 にして、曲線をかけてから出力の範囲に写す
+            let [from, to] = config.input;
+            let t = if to != from {
+                ((value - from) / (to - from)).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let t = match config.curve {
+                Curve::Linear => t,
+                Curve::EaseIn => t * t,
+                Curve::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+                Curve::Smooth => t * t * (3.0 - 2.0 * t),
+                Curve::Step => {
+                    if t > 0.5 {
+                        1.0
+                    } else {
+                        0.0
+                    }
+                }
+            };
+            let target = config.output[0] + (config.output[1] - config.output[0]) * t;
+            let output = match (mapping.value, config.smoothing_ms) {
+                (Some(previous), ms) if ms > 0 => {
+                    let k = 1.0 - (-dt * 1000.0 / ms as f32).exp();
+                    previous + (target - previous) * k
+                }
+                _ => target,
+            };
+            mapping.value = Some(output);
+
+            match &mapping.target {
+                Target::Avatar(property) => avatar.add(*property, output, mapping.color),
+                Target::Layer(name, property) => {
+                    if let Some(i) = layers.clone().position(|l| l == name) {
+                        self.scratch[i].add(*property, output, mapping.color);
+                    }
+                }
+            }
+        }
+        *params = avatar;
+        std::mem::swap(layer_params, &mut self.scratch);
+    }
+}
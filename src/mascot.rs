@@ -0,0 +1,146 @@
+// デスクトップマスコット: 画面の端に座り、ときどき端に沿って歩く
+use crate::{
+    avatar::Mouth,
+    config::{MascotConfig, MascotEdge},
+    t,
+};
+use std::time::{Duration, Instant};
+use winit::{dpi::PhysicalPosition, window::Window};
+
+// 歩幅（ピクセル）。1歩ごとに1回跳ねる
+const STRIDE: f32 = 24.0;
+// 一度に座っている時間の上限（設定が無限大でも時刻の計算があふれないように）
+const LONGEST_REST: Duration = Duration::from_secs(24 * 60 * 60);
+
+// 設定の秒数を座っている時間に
+fn rest_duration(secs: f32) -> Duration {
+    Duration::try_from_secs_f32(secs.max(0.0))
+        .unwrap_or(LONGEST_REST)
+        .min(LONGEST_REST)
+}
+
+enum Behavior {
+    Sit { until: Instant },
+    Walk { target: f32 },
+}
+
+/// Moves the window along a screen edge: rests for a while, walks to a random spot, and
+/// stops to listen whenever the avatar talks.
+pub struct Mascot {
+    config: MascotConfig,
+    behavior: Behavior,
+    // ウィンドウの左端（画面座標）
+    x: f32,
+    position: Option<PhysicalPosition<i32>>,
+    walked: f32,
+    last: Instant,
+    rng: fastrand::Rng,
+}
+
+impl Mascot {
+    /// `rng` picks the rests and where to walk.
+    pub fn new(config: &MascotConfig, window: &Window, now: Instant, rng: fastrand::Rng) -> Self {
+        // Wayland などではウィンドウを動かせないので、その場に座るだけになる
+        if window.outer_position().is_err() {
+            tracing::warn!("{}", t!("mascot.no_positioning"));
+        }
+        let x = window
+            .outer_position()
+            .map_or(0.0, |position| position.x as f32);
+        let mut mascot = Self {
+            config: config.clone(),
+            behavior: Behavior::Sit { until: now },
+            x,
+            position: None,
+            walked: 0.0,
+            last: now,
+            rng,
+        };
+        mascot.behavior = Behavior::Sit {
+            until: now + mascot.rest(),
+        };
+        mascot
+    }
+
+    fn rest(&mut self) -> Duration {
+        let (min, max) = (
+            self.config.min_rest,
+            self.config.max_rest.max(self.config.min_rest),
+        );
+        rest_duration(min + self.rng.f32() * (max - min))
+    }
+
+    // 左端が動ける範囲と、端に沿う y 座標（モニターが分からなければ None）
+    fn track(&self, window: &Window) -> Option<(f32, f32, i32)> {
+        let monitor = window.current_monitor()?;
+        let (origin, size) = (monitor.position(), monitor.size());
+        let outer = window.outer_size();
+        let max_x = origin.x as f32 + size.width.saturating_sub(outer.width) as f32;
+        let margin = self.config.margin as i32;
+        let y = match self.config.edge {
+            MascotEdge::Top => origin.y + margin,
+            MascotEdge::Bottom => {
+                origin.y + size.height.saturating_sub(outer.height) as i32 - margin
+            }
+        };
+        Some((origin.x as f32, max_x, y))
+    }
+
+    /// Advances the behavior and moves the window. Returns how far the avatar is lifted by
+    /// its hop, in canvas pixels; zero while sitting.
+    pub fn update(&mut self, window: &Window, mouth: Mouth, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f32();
+        self.last = now;
+        let Some((min_x, max_x, y)) = self.track(window) else {
+            return 0.0;
+        };
+
+        // 話している間は立ち止まって、話し終わってから少し休む
+        if mouth != Mouth::Idle {
+            self.walked = 0.0;
+            self.behavior = Behavior::Sit {
+                until: now + rest_duration(self.config.min_rest),
+            };
+        }
+        match self.behavior {
+            Behavior::Sit { until } if now >= until && self.config.wander => {
+                self.behavior = Behavior::Walk {
+                    target: min_x + self.rng.f32() * (max_x - min_x),
+                };
+            }
+            Behavior::Sit { .. } => {}
+            Behavior::Walk { target } => {
+                let step = self.config.speed * elapsed;
+                let distance = target - self.x;
+                if distance.abs() <= step {
+                    self.x = target;
+                    self.walked = 0.0;
+                    self.behavior = Behavior::Sit {
+                        until: now + self.rest(),
+                    };
+                } else {
+                    self.x += step.copysign(distance);
+                    self.walked += step;
+                }
+            }
+        }
+        self.x = self.x.clamp(min_x, max_x.max(min_x));
+
+        // 変わったときだけ動かす
+        let position = PhysicalPosition::new(self.x.round() as i32, y);
+        if self.position != Some(position) {
+            self.position = Some(position);
+            window.set_outer_position(position);
+        }
+
+        match self.behavior {
+            Behavior::Walk { .. } => {
+                // 一歩ごとに上がって下りる
+                let phase = (self.walked / STRIDE).fract();
+                let height = 1.0 - (1.0 - 2.0 * phase).abs();
+                self.config.hop_easing.ease(height) * self.config.hop
+            }
+            Behavior::Sit { .. } => 0.0,
+        }
+    }
+}
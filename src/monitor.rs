@@ -0,0 +1,165 @@
+use crate::{
+    config::MonitorConfig,
+    resample::{self, Resampler},
+    t,
+};
+use anyhow::{Context, Result};
+use cpal::{
+    FromSample, SizedSample,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+use std::{
+    collections::VecDeque,
+    sync::mpsc::{self, Receiver, SyncSender},
+};
+
+// 溜まりすぎたら古いものから捨てて遅延を抑える（約 0.2 秒）
+const MAX_BUFFERED_SECONDS: f32 = 0.2;
+
+/// Input side of the monitor path, owned by the capture callback.
+pub struct MonitorTap {
+    sender: SyncSender<Vec<f32>>,
+    channels: usize,
+    volume: f32,
+    // 出力デバイスが入力のレートで開けなかったときに、出力のレートへ変換する
+    resampler: Resampler,
+}
+
+impl MonitorTap {
+    /// Forwards captured samples (interleaved, `channels` wide) to the monitor output.
+    pub fn push(&mut self, data: &[f32]) {
+        let mut mono = Vec::with_capacity(data.len() / self.channels);
+        resample::downmix(data, self.channels, &mut mono);
+        mono.iter_mut().for_each(|sample| *sample *= self.volume);
+        let output = if self.resampler.is_passthrough() {
+            mono
+        } else {
+            let mut output = Vec::new();
+            self.resampler.process(&mono, &mut output);
+            output
+        };
+        // 出力側が詰まっているときは捨てる
+        let _ = self.sender.try_send(output);
+    }
+}
+
+/// Starts playing captured audio on the configured output device. The stream must be kept
+/// alive on the capture thread.
+pub fn start(
+    config: &MonitorConfig,
+    sample_rate: u32,
+    input_channels: usize,
+) -> Result<(cpal::Stream, MonitorTap)> {
+    let host = crate::host::host();
+    let device = match &config.device {
+        Some(name) => find_output_device(&host, name).or_else(|| {
+            tracing::warn!("{}", t!("monitor.device_not_found", name));
+            host.default_output_device()
+        }),
+        None => host.default_output_device(),
+    }
+    .context(t!("sound.no_device"))?;
+
+    // 入力と同じサンプルレートで開けるならそれを使う（形式は f32 を優先）
+    let supported = device
+        .supported_output_configs()?
+        .filter(|c| {
+            c.min_sample_rate().0 <= sample_rate
+                && sample_rate <= c.max_sample_rate().0
+                && is_playable(c.sample_format())
+        })
+        .max_by_key(|c| c.sample_format() == cpal::SampleFormat::F32)
+        .map(|c| c.with_sample_rate(cpal::SampleRate(sample_rate)));
+    let supported = match supported {
+        Some(c) => c,
+        None => {
+            let c = device.default_output_config()?;
+            tracing::warn!(
+                "{}",
+                t!("monitor.rate_mismatch", sample_rate, c.sample_rate().0)
+            );
+            c
+        }
+    };
+
+    let output_rate = supported.sample_rate().0;
+    let channels = supported.channels() as usize;
+    let max_buffered = (supported.sample_rate().0 as f32 * MAX_BUFFERED_SECONDS) as usize;
+    let (sender, receiver) = mpsc::sync_channel(16);
+    let stream_config = supported.config();
+    let stream = match supported.sample_format() {
+        cpal::SampleFormat::F32 => {
+            build_stream::<f32>(&device, &stream_config, channels, max_buffered, receiver)?
+        }
+        cpal::SampleFormat::I16 => {
+            build_stream::<i16>(&device, &stream_config, channels, max_buffered, receiver)?
+        }
+        cpal::SampleFormat::U16 => {
+            build_stream::<u16>(&device, &stream_config, channels, max_buffered, receiver)?
+        }
+        format => anyhow::bail!(t!("sound.unsupported_format", format)),
+    };
+    stream.play()?;
+
+    if let Ok(name) = device.name() {
+        tracing::info!(device = %name, "{}", t!("monitor.started", name));
+    }
+
+    Ok((
+        stream,
+        MonitorTap {
+            sender,
+            channels: input_channels.max(1),
+            volume: config.volume,
+            resampler: Resampler::new(sample_rate, output_rate),
+        },
+    ))
+}
+
+// build_stream で書き込める形式か
+fn is_playable(format: cpal::SampleFormat) -> bool {
+    matches!(
+        format,
+        cpal::SampleFormat::F32 | cpal::SampleFormat::I16 | cpal::SampleFormat::U16
+    )
+}
+
+fn find_output_device(host: &cpal::Host, name: &str) -> Option<cpal::Device> {
+    let needle = name.to_lowercase();
+    host.output_devices().ok()?.find(|device| {
+        device
+            .name()
+            .is_ok_and(|n| n.to_lowercase().contains(&needle))
+    })
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    channels: usize,
+    max_buffered: usize,
+    receiver: Receiver<Vec<f32>>,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let mut buffer: VecDeque<f32> = VecDeque::with_capacity(max_buffered * 2);
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            for chunk in receiver.try_iter() {
+                buffer.extend(chunk);
+            }
+            if buffer.len() > max_buffered {
+                buffer.drain(..buffer.len() - max_buffered);
+            }
+            for frame in data.chunks_mut(channels) {
+                let sample = buffer.pop_front().unwrap_or(0.0);
+                frame.fill(T::from_sample(sample.clamp(-1.0, 1.0)));
+            }
+        },
+        |err| tracing::error!("{}", t!("monitor.stream_error", err)),
+        None,
+    )?;
+    Ok(stream)
+}
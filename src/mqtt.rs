@@ -0,0 +1,92 @@
+// MQTT ブローカーからのトリガー（ドアベルなど）と、状態の publish
+use crate::{
+    bus::{self, Bus},
+    config::{ExpressionSource, MqttConfig, MqttTrigger},
+    t,
+};
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+use std::time::Duration;
+
+// 接続が切れたときに再接続を試みる間隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Client connection; the network loop runs on a thread of its own, reconnects by itself and
+/// publishes matching triggers to the bus.
+pub struct Mqtt {
+    client: Client,
+    state_topic: Option<String>,
+}
+
+impl Mqtt {
+    pub fn start(config: &MqttConfig, bus: Bus) -> Self {
+        let mut options = MqttOptions::new(&config.client_id, &config.host, config.port);
+        options.set_keep_alive(Duration::from_secs(30));
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.as_deref().unwrap_or_default());
+        }
+        let (client, mut connection) = Client::new(options, 16);
+
+        let subscriber = client.clone();
+        let triggers = config.triggers.clone();
+        let broker = format!("{}:{}", config.host, config.port);
+        std::thread::spawn(move || {
+            for event in connection.iter() {
+                match event {
+                    // 再接続のたびに購読し直す
+                    Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                        tracing::info!("{}", t!("mqtt.connected", broker));
+                        for trigger in &triggers {
+                            let _ = subscriber.try_subscribe(&trigger.topic, QoS::AtMostOnce);
+                        }
+                    }
+                    Ok(Event::Incoming(Packet::Publish(publish))) => {
+                        let payload = String::from_utf8_lossy(&publish.payload);
+                        for trigger in triggers
+                            .iter()
+                            .filter(|t| matches(t, &publish.topic, payload.trim()))
+                        {
+                            tracing::info!("{}", t!("mqtt.triggered", publish.topic));
+                            if let Some(expression) = &trigger.expression {
+                                bus.publish(bus::Event::Expression(
+                                    ExpressionSource::Remote,
+                                    Some(expression.clone()),
+                                ));
+                            }
+                            if let Some(sequence) = &trigger.sequence {
+                                bus.publish(bus::Event::Trigger(sequence.clone()));
+                            }
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        tracing::warn!("{}", t!("mqtt.connection_error", e));
+                        std::thread::sleep(RETRY_INTERVAL);
+                    }
+                }
+            }
+        });
+
+        Self {
+            client,
+            state_topic: config.state_topic.clone(),
+        }
+    }
+
+    /// Publishes the shown expression and mouth state (retained, so new subscribers see it).
+    pub fn publish_state(&self, expression: &str, mouth: &str) {
+        let Some(topic) = &self.state_topic else {
+            return;
+        };
+        let payload = serde_json::json!({ "expression": expression, "mouth": mouth });
+        // 送信待ちが溢れているときは捨てる（描画を止めない）
+        let _ = self
+            .client
+            .try_publish(topic, QoS::AtMostOnce, true, payload.to_string());
+    }
+}
+
+// トピックはワイルドカード（+ と #）も使える
+fn matches(trigger: &MqttTrigger, topic: &str, payload: &str) -> bool {
+    rumqttc::matches(topic, &trigger.topic)
+        && trigger.payload.as_deref().is_none_or(|p| p == payload)
+}
@@ -0,0 +1,18 @@
+// 重大なこと（入力デバイスが外れた、設定を開き直せなかった、録画先の空きが少ない）をデスクトップ
+// 通知で知らせる。ショートカットから起動するとログは見えないので。どのスレッドからでも送れる
+use crate::{announce::Announcer, config::AnnounceMethod};
+use std::sync::OnceLock;
+
+static NOTIFIER: OnceLock<Announcer> = OnceLock::new();
+
+/// Shows critical events as desktop notifications from now on.
+pub fn enable() {
+    let _ = NOTIFIER.set(Announcer::new(AnnounceMethod::Notification));
+}
+
+/// Shows `message` as a desktop notification, if enabled.
+pub fn critical(message: &str) {
+    if let Some(notifier) = NOTIFIER.get() {
+        notifier.say(message);
+    }
+}
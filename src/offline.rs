@@ -0,0 +1,104 @@
+// 音声ファイルから、リアルタイムと同じ解析・合成でフレームを書き出す
+use crate::{
+    avatar::{Avatar, Blinks, Mouth, TalkingFrames},
+    compose,
+    config::Config,
+    dirty::Rect,
+    random::Random,
+    reactivity::ReactivityStateMachine,
+    resample::rms,
+    sink::FileOutput,
+    slot::Slot,
+    sound, t,
+};
+use anyhow::{Context, Result, bail};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+// 入力コールバック1回分に相当する解析の単位（秒）
+const BLOCK_SECONDS: f64 = 0.01;
+
+/// Renders the avatar reacting to `audio` at `fps`, without a window or audio device.
+pub fn run(config: Config, audio: &Path, out: &Path, fps: u32) -> Result<()> {
+    if fps == 0 {
+        bail!(t!("offline.bad_fps"));
+    }
+    let rate = config.audio.analysis_rate;
+    let samples =
+        sound::load_wav(audio, rate).with_context(|| t!("offline.read_failed", audio.display()))?;
+    let width = config.canvas.width;
+    let height = config.canvas.height;
+    let (w, h) = (width as usize, height as usize);
+
+    let avatar = Avatar::load(&config);
+    let random = Random::new(config.seed);
+    let mut talking_frames = TalkingFrames::new(&config.talking, random.stream("talking"));
+    let mut blinks = Blinks::new(random.stream("blink"));
+    let mut reactivity = ReactivityStateMachine::new(&config.audio);
+    // スロットはメインと同じ音声に反応させる
+    let mut slots: Vec<Slot> = config
+        .slots
+        .iter()
+        .enumerate()
+        .map(|(i, slot)| Slot::new(slot, &config, None, random.stream(&format!("slot {i}"))))
+        .collect();
+
+    let mut sink = FileOutput::open(out, Some(audio), width, height, fps)?;
+    let mut output = vec![0u8; w * h * 4];
+    let mut rgba = vec![0u8; w * h * 4];
+
+    // 時刻は音声の先頭を基準にした仮想的なもの
+    let start = Instant::now();
+    let block = ((rate as f64 * BLOCK_SECONDS) as usize).max(1);
+    let duration = samples.len() as f64 / rate as f64;
+    let frame_count = (duration * fps as f64).ceil() as usize;
+    let mut analyzed = 0;
+    let mut state = Mouth::Idle;
+    tracing::info!("{}", t!("offline.started", audio.display(), frame_count));
+
+    for index in 0..frame_count {
+        let time = index as f64 / fps as f64;
+        let now = start + Duration::from_secs_f64(time);
+
+        // このフレームの時刻までに届いているはずのブロックを解析する
+        while analyzed + block <= samples.len() && (analyzed + block) as f64 / rate as f64 <= time {
+            let level = rms(&samples[analyzed..analyzed + block]);
+            analyzed += block;
+            let at = start + Duration::from_secs_f64(analyzed as f64 / rate as f64);
+            if let Some(transition) = reactivity.update(level, at) {
+                state = transition.to;
+            }
+        }
+
+        let expression = avatar.default.as_str();
+        let frame_index = talking_frames.update(
+            state == Mouth::Talking,
+            avatar.talking_count(expression),
+            now,
+        );
+        let blink = (blinks.update(now) && state == Mouth::Idle)
+            .then(|| avatar.blink(expression))
+            .flatten();
+        match blink.or_else(|| avatar.frame(expression, state, frame_index)) {
+            Some(frame) => output.copy_from_slice(frame),
+            None => output.fill(0),
+        }
+        for slot in &mut slots {
+            slot.update(&avatar, state, now);
+            if let Some(frame) = slot.frame(&avatar) {
+                compose::draw_rect(&mut output, frame, w, h, slot.rect(), Rect::full(w, h));
+            }
+        }
+
+        // 書き出すのは通常の（乗算済みでない）アルファ
+        rgba.copy_from_slice(&output);
+        compose::unpremultiply(&mut rgba);
+        sink.write(index, &rgba, width, height)?;
+    }
+
+    sink.finish()?;
+    println!("{}", t!("offline.finished", frame_count, out.display()));
+    Ok(())
+}
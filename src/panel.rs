@@ -0,0 +1,241 @@
+// キャンバスに重ねる小さな枠: 1行の文字（フォントがあれば）と棒グラフ。発話時間やポモドーロ、文字のオーバーレイで使う
+use crate::{color, compose, dirty::Rect, t, theme::Theme};
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont, point};
+use anyhow::{Context, Result};
+use std::{path::Path, sync::Arc, time::Duration};
+
+// 枠の内側の余白と、文字と棒の間（ピクセル）
+const PADDING: usize = 6;
+const GAP: usize = 4;
+
+/// Where a panel goes and how it looks; each widget fills this in from its own config.
+#[derive(Debug, Clone)]
+pub struct Style {
+    // 枠の左上の位置と、棒の大きさ（ピクセル）
+    pub position: [u32; 2],
+    pub width: u32,
+    pub height: u32,
+    // 棒の埋まった側と残りの側
+    pub fill: [u8; 4],
+    pub empty: [u8; 4],
+    pub background: [u8; 4],
+    pub size: f32,
+    pub text_color: [u8; 4],
+    // 枠の角の丸み（ピクセル）
+    pub radius: f32,
+}
+
+/// A line of text over a bar, rendered once per change and blended onto each frame.
+pub struct Panel {
+    // 各表示の設定のままの見た目と、テーマを重ねた見た目
+    base: Style,
+    style: Style,
+    base_font: Option<Arc<FontVec>>,
+    font: Option<Arc<FontVec>>,
+    canvas_width: usize,
+    canvas_height: usize,
+    // 描いた文字と棒の長さ（テーマを変えたら描き直す）
+    content: Option<(String, usize)>,
+    // 描いた画像
+    layer: Option<(Rect, Vec<u8>)>,
+}
+
+impl Panel {
+    /// Loads the font if one is given; without one only the bar is drawn.
+    pub fn new(style: Style, font: Option<&Path>, width: usize, height: usize) -> Result<Self> {
+        let font = font.map(load_font).transpose()?;
+        Ok(Self {
+            base: style.clone(),
+            style,
+            base_font: font.clone(),
+            font,
+            canvas_width: width,
+            canvas_height: height,
+            content: None,
+            layer: None,
+        })
+    }
+
+    /// Lays `theme` over the widget's own style and redraws what is shown.
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.style = theme.apply(&self.base);
+        self.font = theme.font.clone().or_else(|| self.base_font.clone());
+        if let Some((text, filled)) = self.content.take() {
+            self.set(&text, filled);
+        }
+    }
+
+    /// Width of the whole bar, so callers can round the filled part to pixels.
+    pub fn bar_width(&self) -> usize {
+        self.style.width as usize
+    }
+
+    /// Renders `text` over a bar filled `filled` pixels from the left. With a bar of zero
+    /// size only the text is drawn.
+    pub fn set(&mut self, text: &str, filled: usize) {
+        let filled = filled.min(self.bar_width());
+        self.layer = self.render(text, filled);
+        self.content = Some((text.to_string(), filled));
+    }
+
+    /// Stops drawing until the next `set`.
+    pub fn clear(&mut self) {
+        self.layer = None;
+        self.content = None;
+    }
+
+    fn render(&self, text: &str, filled: usize) -> Option<(Rect, Vec<u8>)> {
+        let style = &self.style;
+        let bar_width = style.width as usize;
+        let bar_height = style.height as usize;
+        let scaled = self
+            .font
+            .as_ref()
+            .map(|font| font.as_scaled(PxScale::from(style.size)));
+        // 棒の大きさが 0 なら文字だけ
+        let has_bar = bar_width > 0 && bar_height > 0;
+        let gap = if has_bar { GAP } else { 0 };
+        let text_height = scaled.map_or(0, |font| font.height().ceil() as usize + gap);
+        // 文字が棒より長ければ枠を広げる
+        let text_width = scaled.map_or(0.0, |font| {
+            text.chars()
+                .map(|c| font.h_advance(font.glyph_id(c)))
+                .sum::<f32>()
+        });
+        let width = bar_width.max(text_width.ceil() as usize) + PADDING * 2;
+        let height = text_height + if has_bar { bar_height } else { 0 } + PADDING * 2;
+        if !has_bar && scaled.is_none() {
+            return None;
+        }
+
+        let mut pixels = vec![0u8; width * height * 4];
+        let fill =
+            |pixels: &mut [u8], x0: usize, y0: usize, x1: usize, y1: usize, rgba: [u8; 4]| {
+                let color = premultiplied(rgba);
+                for y in y0..y1 {
+                    for x in x0..x1 {
+                        let i = (y * width + x) * 4;
+                        compose::blend(&mut pixels[i..i + 4], &color);
+                    }
+                }
+            };
+        let background = premultiplied(style.background);
+        for y in 0..height {
+            for x in 0..width {
+                let coverage = rounded(x, y, width, height, style.radius);
+                let i = (y * width + x) * 4;
+                compose::blend_opacity(&mut pixels[i..i + 4], &background, coverage);
+            }
+        }
+        if has_bar {
+            let bar_top = PADDING + text_height;
+            let (bar_left, bar_bottom) = (PADDING, bar_top + bar_height);
+            fill(
+                &mut pixels,
+                bar_left,
+                bar_top,
+                bar_left + filled,
+                bar_bottom,
+                style.fill,
+            );
+            fill(
+                &mut pixels,
+                bar_left + filled,
+                bar_top,
+                bar_left + bar_width,
+                bar_bottom,
+                style.empty,
+            );
+        }
+
+        if let (Some(font), Some(scaled)) = (self.font.as_deref(), scaled) {
+            let mut x = PADDING as f32;
+            let baseline = PADDING as f32 + scaled.ascent();
+            let [r, g, b, a] = style.text_color;
+            for c in text.chars() {
+                let id = scaled.glyph_id(c);
+                let glyph = id.with_scale_and_position(scaled.scale(), point(x, baseline));
+                x += scaled.h_advance(id);
+                let Some(outlined) = font.outline_glyph(glyph) else {
+                    continue;
+                };
+                let bounds = outlined.px_bounds();
+                outlined.draw(|gx, gy, coverage| {
+                    let px = bounds.min.x as i32 + gx as i32;
+                    let py = bounds.min.y as i32 + gy as i32;
+                    if px < 0 || py < 0 || px as usize >= width || py as usize >= height {
+                        return;
+                    }
+                    let i = (py as usize * width + px as usize) * 4;
+                    let alpha = (a as f32 * coverage.clamp(0.0, 1.0)).round() as u8;
+                    compose::blend(&mut pixels[i..i + 4], &premultiplied([r, g, b, alpha]));
+                });
+            }
+        }
+
+        let [x, y] = style.position;
+        Some((
+            Rect {
+                x: x as usize,
+                y: y as usize,
+                width,
+                height,
+            },
+            pixels,
+        ))
+    }
+
+    /// Draws the panel where it overlaps `clip`.
+    pub fn draw(&self, dst: &mut [u8], clip: Rect) {
+        if let Some((rect, pixels)) = &self.layer {
+            compose::draw_layer(
+                dst,
+                self.canvas_width,
+                self.canvas_height,
+                *rect,
+                pixels,
+                clip,
+            );
+        }
+    }
+}
+
+/// Reads a TrueType or OpenType font file.
+pub fn load_font(path: &Path) -> Result<Arc<FontVec>> {
+    std::fs::read(path)
+        .ok()
+        .and_then(|data| FontVec::try_from_vec(data).ok())
+        .map(Arc::new)
+        .with_context(|| t!("panel.font_failed", path.display()))
+}
+
+/// How much of the pixel at (`x`, `y`) lies inside a `width` × `height` box whose corners
+/// are rounded by `radius`.
+pub fn rounded(x: usize, y: usize, width: usize, height: usize, radius: f32) -> f32 {
+    let radius = radius.min(width as f32 / 2.0).min(height as f32 / 2.0);
+    if radius.is_nan() || radius <= 0.0 {
+        return 1.0;
+    }
+    // 角の円の中心に一番近い点からの距離で、縁をなめらかにする
+    let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+    let cx = px.clamp(radius, width as f32 - radius);
+    let cy = py.clamp(radius, height as f32 - radius);
+    let distance = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+    (radius - distance + 0.5).clamp(0.0, 1.0)
+}
+
+/// `m:ss`, or `h:mm:ss` from an hour on.
+pub fn clock_time(time: Duration) -> String {
+    let secs = time.as_secs();
+    match secs / 3600 {
+        0 => format!("{}:{:02}", secs / 60, secs % 60),
+        hours => format!("{hours}:{:02}:{:02}", secs / 60 % 60, secs % 60),
+    }
+}
+
+/// A config color (straight alpha) made premultiplied for blending.
+pub fn premultiplied(rgba: [u8; 4]) -> [u8; 4] {
+    let a = rgba[3] as f32 / 255.0;
+    let c = |i: usize| color::to_srgb(color::to_linear(rgba[i]) * a);
+    [c(0), c(1), c(2), rgba[3]]
+}
@@ -0,0 +1,146 @@
+// シーケンスから出す紙吹雪などのパーティクル（キャンバスの一番上に重ねる）
+use crate::{avatar::open_image, color, compose, config::ParticlesConfig, t};
+use std::{collections::HashMap, path::PathBuf, time::Instant};
+
+// 重力加速度（キャンバスの高さ / 秒²）
+const GRAVITY: f32 = 1.6;
+
+// 寿命の最後のこの割合でフェードアウトする
+const FADE: f32 = 0.3;
+
+struct Sprite {
+    pixels: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+// 位置は発生時の値と経過時間から毎回計算する
+struct Particle {
+    sprite: usize,
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    born: Instant,
+    lifetime: f32,
+}
+
+/// Particles thrown by sequence keyframes, drawn over everything else.
+pub struct Particles {
+    width: usize,
+    height: usize,
+    sprites: Vec<Sprite>,
+    // 読み込み済みの画像（パスと大きさごと）
+    loaded: HashMap<(PathBuf, u32), usize>,
+    particles: Vec<Particle>,
+    rng: fastrand::Rng,
+    // 出す数の割合（負荷が高いときに減らす）
+    share: f32,
+}
+
+impl Particles {
+    pub fn new(width: usize, height: usize, rng: fastrand::Rng) -> Self {
+        Self {
+            width,
+            height,
+            sprites: Vec::new(),
+            loaded: HashMap::new(),
+            particles: Vec::new(),
+            rng,
+            share: 1.0,
+        }
+    }
+
+    /// Throws only `share` (0.0..=1.0) of each later burst.
+    pub fn set_share(&mut self, share: f32) {
+        self.share = share.clamp(0.0, 1.0);
+    }
+
+    /// Throws `config.count` sprites from the bottom of the canvas.
+    pub fn burst(&mut self, config: &ParticlesConfig, now: Instant) {
+        let Some(sprite) = self.sprite(config) else {
+            return;
+        };
+        let (w, h) = (self.width as f32, self.height as f32);
+        let rng = &mut self.rng;
+        let count = (config.count as f32 * self.share).round() as u32;
+        for _ in 0..count {
+            self.particles.push(Particle {
+                sprite,
+                x: w * (0.2 + 0.6 * rng.f32()),
+                y: h,
+                vx: h * 0.6 * (rng.f32() - 0.5),
+                // 高さの3割から8割くらいまで上がる
+                vy: -h * (1.0 + 0.6 * rng.f32()),
+                born: now,
+                lifetime: config.lifetime.max(0.1) * (0.8 + 0.4 * rng.f32()),
+            });
+        }
+    }
+
+    fn sprite(&mut self, config: &ParticlesConfig) -> Option<usize> {
+        let key = (config.image.clone(), config.size.max(1));
+        if let Some(&index) = self.loaded.get(&key) {
+            return Some(index);
+        }
+        let img = match open_image(&config.image) {
+            Ok(img) => img.to_rgba8(),
+            Err(e) => {
+                tracing::warn!("{}", t!("particles.load_failed", config.image.display(), e));
+                return None;
+            }
+        };
+        let scale = key.1 as f32 / img.width().max(img.height()).max(1) as f32;
+        let width = ((img.width() as f32 * scale).round() as u32).max(1);
+        let height = ((img.height() as f32 * scale).round() as u32).max(1);
+        let pixels = color::resize_premultiplied(&img, width, height).into_raw();
+        self.sprites.push(Sprite {
+            pixels,
+            width: width as usize,
+            height: height as usize,
+        });
+        let index = self.sprites.len() - 1;
+        self.loaded.insert(key, index);
+        Some(index)
+    }
+
+    /// Drops particles past their lifetime. Returns whether any are still on screen.
+    pub fn update(&mut self, now: Instant) -> bool {
+        self.particles
+            .retain(|p| now.duration_since(p.born).as_secs_f32() < p.lifetime);
+        !self.particles.is_empty()
+    }
+
+    pub fn draw(&self, dst: &mut [u8], now: Instant) {
+        let gravity = GRAVITY * self.height as f32;
+        for particle in &self.particles {
+            let sprite = &self.sprites[particle.sprite];
+            let t = now.duration_since(particle.born).as_secs_f32();
+            let x = particle.x + particle.vx * t - sprite.width as f32 / 2.0;
+            let y =
+                particle.y + particle.vy * t + 0.5 * gravity * t * t - sprite.height as f32 / 2.0;
+            let opacity = ((particle.lifetime - t) / (particle.lifetime * FADE)).clamp(0.0, 1.0);
+            self.blit(dst, sprite, x.round() as i32, y.round() as i32, opacity);
+        }
+    }
+
+    fn blit(&self, dst: &mut [u8], sprite: &Sprite, left: i32, top: i32, opacity: f32) {
+        // キャンバスからはみ出た部分は描かない
+        let x0 = left.max(0);
+        let x1 = (left + sprite.width as i32).min(self.width as i32);
+        if x1 <= x0 {
+            return;
+        }
+        for sy in 0..sprite.height {
+            let y = top + sy as i32;
+            if y < 0 || y >= self.height as i32 {
+                continue;
+            }
+            let s = (sy * sprite.width + (x0 - left) as usize) * 4;
+            let d = (y as usize * self.width + x0 as usize) * 4;
+            let len = (x1 - x0) as usize * 4;
+            let (dst, src) = (&mut dst[d..d + len], &sprite.pixels[s..s + len]);
+            compose::blend_row_opacity(dst, src, opacity);
+        }
+    }
+}
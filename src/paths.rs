@@ -0,0 +1,182 @@
+// 設定・キャッシュ・ログ・状態を置く場所。ふだんは OS ごとの決まった場所
+// （XDG / Application Support / AppData）を使う。--portable では実行ファイルの隣にすべてを
+// まとめ、USB メモリなどに入れたままどこからでも（OBS のスクリプトやショートカットからでも）
+// 同じように起動できる
+use crate::{config::CONFIG_FILE_NAME, t};
+use anyhow::{Context, Result};
+use std::{
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+static PORTABLE: OnceLock<PathBuf> = OnceLock::new();
+
+// OS ごとの場所の種類
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Config,
+    Cache,
+    State,
+    Logs,
+}
+
+/// Keeps the config, state and logs next to the executable from now on.
+pub fn set_portable() -> Result<()> {
+    let dir = exe_dir().context(t!("paths.no_exe_dir"))?;
+    let _ = PORTABLE.set(dir);
+    Ok(())
+}
+
+/// The executable's directory in portable mode.
+pub fn portable() -> Option<&'static Path> {
+    PORTABLE.get().map(PathBuf::as_path)
+}
+
+// 実行ファイルのあるディレクトリ
+fn exe_dir() -> Option<PathBuf> {
+    std::env::current_exe()
+        .ok()?
+        .parent()
+        .map(Path::to_path_buf)
+}
+
+// ホームディレクトリ
+#[cfg(unix)]
+fn home() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform(kind: Kind) -> Option<PathBuf> {
+    // XDG の環境変数、無ければホームの下の既定の場所
+    let xdg = |var: &str, fallback: &str| match std::env::var_os(var) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => home().map(|home| home.join(fallback)),
+    };
+    let base = match kind {
+        Kind::Config => xdg("XDG_CONFIG_HOME", ".config"),
+        Kind::Cache => xdg("XDG_CACHE_HOME", ".cache"),
+        Kind::State | Kind::Logs => xdg("XDG_STATE_HOME", ".local/state"),
+    }?;
+    let dir = base.join("darwin");
+    Some(match kind {
+        Kind::Logs => dir.join("logs"),
+        _ => dir,
+    })
+}
+
+#[cfg(target_os = "macos")]
+fn platform(kind: Kind) -> Option<PathBuf> {
+    const ID: &str = "com.potistudio.darwin";
+    let library = home()?.join("Library");
+    Some(match kind {
+        Kind::Config | Kind::State => library.join("Application Support").join(ID),
+        Kind::Cache => library.join("Caches").join(ID),
+        Kind::Logs => library.join("Logs").join(ID),
+    })
+}
+
+#[cfg(windows)]
+fn platform(kind: Kind) -> Option<PathBuf> {
+    let var = |name: &str| {
+        std::env::var_os(name)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    };
+    // 設定は移動プロファイルに付いてくる Roaming、それ以外はこの PC だけの Local
+    Some(match kind {
+        Kind::Config => var("APPDATA")?.join("Darwin"),
+        Kind::Cache => var("LOCALAPPDATA")?.join("Darwin").join("cache"),
+        Kind::State => var("LOCALAPPDATA")?.join("Darwin"),
+        Kind::Logs => var("LOCALAPPDATA")?.join("Darwin").join("logs"),
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn platform(_kind: Kind) -> Option<PathBuf> {
+    None
+}
+
+/// Where relative paths point and a new config is written when no config file is given:
+/// next to the executable in portable mode, otherwise the OS's config directory (or the
+/// working directory if there is none).
+pub fn config_dir() -> PathBuf {
+    match portable() {
+        Some(dir) => dir.to_path_buf(),
+        None => platform(Kind::Config)
+            .or_else(|| std::path::absolute(".").ok())
+            .unwrap_or_else(|| PathBuf::from(".")),
+    }
+}
+
+/// The config used when none is given: `darwin.toml` in the working directory, then in
+/// [`config_dir`], then next to the executable, so a shortcut started from another directory
+/// still finds its avatar. Portable mode only looks next to the executable.
+pub fn default_config() -> Option<PathBuf> {
+    let dirs = match portable() {
+        Some(dir) => vec![dir.to_path_buf()],
+        None => [std::path::absolute(".").ok(), Some(config_dir()), exe_dir()]
+            .into_iter()
+            .flatten()
+            .collect(),
+    };
+    dirs.into_iter()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|file| file.is_file())
+}
+
+/// Where files that can be rebuilt at any time are kept.
+pub fn cache_dir() -> PathBuf {
+    match portable() {
+        Some(dir) => dir.join("cache"),
+        None => platform(Kind::Cache).unwrap_or_else(|| std::env::temp_dir().join("darwin")),
+    }
+}
+
+/// Where state kept between runs (the watchdog's session state) lives.
+pub fn state_dir() -> PathBuf {
+    match portable() {
+        Some(dir) => dir.join("state"),
+        None => platform(Kind::State).unwrap_or_else(std::env::temp_dir),
+    }
+}
+
+/// Where logs are written when `--log-dir` is not given, if anywhere.
+pub fn log_dir() -> Option<PathBuf> {
+    match portable() {
+        Some(dir) => Some(dir.join("logs")),
+        None => platform(Kind::Logs),
+    }
+}
+
+/// Prints where everything is (`darwin paths`). `config` and `log_dir` are the ones given
+/// on the command line, if any.
+pub fn print(config: Option<&Path>, log_dir: Option<&Path>) -> Result<()> {
+    if let Some(dir) = portable() {
+        println!("{}", t!("paths.portable", dir.display()));
+    }
+    let config = match config {
+        Some(path) => Some(std::path::absolute(crate::config::config_file_path(path))?),
+        None => default_config(),
+    };
+    match config {
+        Some(file) => println!("{}", t!("paths.config", file.display())),
+        None => println!(
+            "{}",
+            t!(
+                "paths.config_missing",
+                config_dir().join(CONFIG_FILE_NAME).display()
+            )
+        ),
+    }
+    println!("{}", t!("paths.config_dir", config_dir().display()));
+    println!("{}", t!("paths.state", state_dir().display()));
+    println!("{}", t!("paths.cache", cache_dir().display()));
+    match log_dir.map(Path::to_path_buf).or_else(self::log_dir) {
+        Some(dir) => println!("{}", t!("paths.logs", dir.display())),
+        None => println!("{}", t!("paths.no_logs")),
+    }
+    Ok(())
+}
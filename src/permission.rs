@@ -0,0 +1,188 @@
+// マイクへのアクセス許可（macOS では許可が無いと無音が届き続けるだけなので、先に確かめる）
+use crate::{compose, dirty::Rect, t};
+use ab_glyph::{Font, FontVec, PxScale, ScaleFont, point};
+
+/// Asks for microphone access if the user has not decided yet, and returns whether capture
+/// is allowed. Blocks until the system prompt is answered; call it off the event loop.
+#[cfg(target_os = "macos")]
+pub fn microphone() -> bool {
+    match macos::status() {
+        macos::AUTHORIZED => true,
+        macos::NOT_DETERMINED => {
+            tracing::info!("{}", t!("permission.prompting"));
+            macos::request()
+        }
+        // 拒否されているか、ペアレンタルコントロールなどで制限されている
+        _ => false,
+    }
+}
+
+/// Other platforms do not gate capture behind a prompt.
+#[cfg(not(target_os = "macos"))]
+pub fn microphone() -> bool {
+    true
+}
+
+/// Opens the microphone page of System Settings.
+pub fn open_settings() {
+    #[cfg(target_os = "macos")]
+    if let Err(e) = std::process::Command::new("open")
+        .arg("x-apple.systempreferences:com.apple.preference.security?Privacy_Microphone")
+        .spawn()
+    {
+        tracing::warn!("{}", t!("permission.open_failed", e));
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use block::ConcreteBlock;
+    use objc::{
+        class, msg_send,
+        runtime::{BOOL, Object, YES},
+        sel, sel_impl,
+    };
+    use std::sync::mpsc;
+
+    // AVAuthorizationStatus
+    pub const NOT_DETERMINED: isize = 0;
+    pub const AUTHORIZED: isize = 3;
+
+    #[link(name = "AVFoundation", kind = "framework")]
+    unsafe extern "C" {
+        static AVMediaTypeAudio: *mut Object;
+    }
+
+    pub fn status() -> isize {
+        // SAFETY: クラスメソッドに定数の NSString を渡すだけ
+        unsafe {
+            msg_send![class!(AVCaptureDevice), authorizationStatusForMediaType: AVMediaTypeAudio]
+        }
+    }
+
+    // システムのダイアログを出し、答えが返るまで待つ
+    pub fn request() -> bool {
+        let (sender, receiver) = mpsc::channel();
+        let handler = ConcreteBlock::new(move |granted: BOOL| {
+            let _ = sender.send(granted == YES);
+        })
+        .copy();
+        // SAFETY: ブロックはコピー済みで、呼ばれるまで AVFoundation が保持する
+        unsafe {
+            let _: () = msg_send![
+                class!(AVCaptureDevice),
+                requestAccessForMediaType: AVMediaTypeAudio
+                completionHandler: &*handler
+            ];
+        }
+        receiver.recv().unwrap_or(false)
+    }
+}
+
+// 案内に使うシステムフォント（最初に読めたもの）
+const FONTS: &[&str] = &[
+    "/System/Library/Fonts/ヒラギノ角ゴシック W4.ttc",
+    "/System/Library/Fonts/Helvetica.ttc",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+];
+const TEXT_SIZE: f32 = 22.0;
+const PADDING: usize = 24;
+
+/// Instructions shown over the main window (not the stream output) while microphone
+/// access is denied. Clicking the window opens System Settings.
+pub struct Overlay {
+    width: usize,
+    rect: Rect,
+    // 乗算済みの RGBA
+    pixels: Vec<u8>,
+}
+
+impl Overlay {
+    /// Renders the instructions for a `width` × `height` window. `None` when no system
+    /// font could be loaded; the window title still says what is wrong.
+    pub fn new(width: usize, height: usize) -> Option<Self> {
+        let font = FONTS.iter().find_map(|path| {
+            let data = std::fs::read(path).ok()?;
+            FontVec::try_from_vec_and_index(data, 0).ok()
+        })?;
+        let scaled = font.as_scaled(PxScale::from(TEXT_SIZE));
+        let lines = [
+            t!("permission.overlay_title"),
+            t!("permission.overlay_body"),
+            t!("permission.overlay_action"),
+        ];
+        let advance = |line: &str| -> f32 {
+            line.chars()
+                .map(|c| scaled.h_advance(scaled.glyph_id(c)))
+                .sum()
+        };
+        let line_height = (scaled.height() + scaled.line_gap()).ceil() as usize;
+        let text_width = lines
+            .iter()
+            .map(|line| advance(line))
+            .fold(0.0, f32::max)
+            .ceil() as usize;
+        let panel_width = (text_width + PADDING * 2).min(width);
+        let panel_height = (line_height * lines.len() + PADDING * 2).min(height);
+        if panel_width == 0 || panel_height == 0 {
+            return None;
+        }
+
+        // 半透明の黒い板に白い文字
+        let mut pixels = [0, 0, 0, 0xc0].repeat(panel_width * panel_height);
+        for (row, line) in lines.iter().enumerate() {
+            let mut x = (panel_width as f32 - advance(line)) / 2.0;
+            let baseline = (PADDING + row * line_height) as f32 + scaled.ascent();
+            for c in line.chars() {
+                let id = scaled.glyph_id(c);
+                let glyph = id.with_scale_and_position(scaled.scale(), point(x, baseline));
+                x += scaled.h_advance(id);
+                let Some(outlined) = font.outline_glyph(glyph) else {
+                    continue;
+                };
+                let bounds = outlined.px_bounds();
+                outlined.draw(|gx, gy, coverage| {
+                    let px = bounds.min.x as i32 + gx as i32;
+                    let py = bounds.min.y as i32 + gy as i32;
+                    if px < 0 || py < 0 || px as usize >= panel_width || py as usize >= panel_height
+                    {
+                        return;
+                    }
+                    let i = (py as usize * panel_width + px as usize) * 4;
+                    let alpha = pixels[i + 3] as f32 / 255.0;
+                    let value = coverage.clamp(0.0, 1.0);
+                    let a = value + alpha * (1.0 - value);
+                    let c = (value * 255.0).round() as u8;
+                    pixels[i..i + 4].copy_from_slice(&[c, c, c, (a * 255.0).round() as u8]);
+                });
+            }
+        }
+
+        Some(Self {
+            width,
+            rect: Rect {
+                x: (width - panel_width) / 2,
+                y: (height - panel_height) / 2,
+                width: panel_width,
+                height: panel_height,
+            },
+            pixels,
+        })
+    }
+
+    /// Draws the panel where it overlaps `clip`.
+    pub fn draw(&self, dst: &mut [u8], clip: Rect) {
+        let rect = self.rect;
+        let x0 = rect.x.max(clip.x);
+        let x1 = (rect.x + rect.width).min(clip.x + clip.width);
+        if x1 <= x0 {
+            return;
+        }
+        for y in rect.y.max(clip.y)..(rect.y + rect.height).min(clip.y + clip.height) {
+            let s = ((y - rect.y) * rect.width + (x0 - rect.x)) * 4;
+            let d = (y * self.width + x0) * 4;
+            let len = (x1 - x0) * 4;
+            compose::blend_row(&mut dst[d..d + len], &self.pixels[s..s + len]);
+        }
+    }
+}
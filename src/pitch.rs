@@ -0,0 +1,88 @@
+// 声の高さの推定（YIN）
+use crate::resample::{Resampler, rms};
+
+// 解析は 16kHz に落としてから行う
+const RATE: u32 = 16_000;
+// 32ms の窓を 16ms ずつずらす
+const WINDOW: usize = 512;
+const HOP: usize = 256;
+// 検出する声の高さの範囲 (Hz)
+const MIN_PITCH: f32 = 70.0;
+const MAX_PITCH: f32 = 1000.0;
+// 累積平均正規化差分がこれを下回る最初の谷を周期とする
+const YIN_THRESHOLD: f32 = 0.15;
+
+/// YIN pitch tracker fed from the audio callback.
+pub struct PitchTracker {
+    resampler: Resampler,
+    threshold: f32,
+    samples: Vec<f32>,
+    resampled: Vec<f32>,
+    difference: Vec<f32>,
+    pitch: f32,
+}
+
+impl PitchTracker {
+    /// `rate` is the sample rate of the mono input; quieter input than `threshold` is unvoiced.
+    pub fn new(rate: u32, threshold: f32) -> Self {
+        Self {
+            resampler: Resampler::new(rate, RATE),
+            threshold,
+            samples: Vec::new(),
+            resampled: Vec::new(),
+            difference: vec![0.0; max_lag() + 1],
+            pitch: 0.0,
+        }
+    }
+
+    /// Takes mono input and returns the latest estimate in Hz, or 0 while unvoiced.
+    pub fn update(&mut self, input: &[f32]) -> f32 {
+        self.resampled.clear();
+        self.resampler.process(input, &mut self.resampled);
+        self.samples.extend_from_slice(&self.resampled);
+        let needed = WINDOW + max_lag();
+        while self.samples.len() >= needed {
+            self.pitch = if rms(&self.samples[..WINDOW]) >= self.threshold {
+                self.estimate().unwrap_or(0.0)
+            } else {
+                0.0
+            };
+            self.samples.drain(..HOP);
+        }
+        self.pitch
+    }
+
+    fn estimate(&mut self) -> Option<f32> {
+        let x = &self.samples;
+        let max_lag = max_lag();
+        let min_lag = (RATE as f32 / MAX_PITCH) as usize;
+
+        // 差分関数を累積平均で正規化する
+        self.difference[0] = 1.0;
+        let mut sum = 0.0;
+        for tau in 1..=max_lag {
+            let d: f32 = (0..WINDOW).map(|j| (x[j] - x[j + tau]).powi(2)).sum();
+            sum += d;
+            self.difference[tau] = if sum > 0.0 { d * tau as f32 / sum } else { 1.0 };
+        }
+
+        let d = &self.difference;
+        let mut tau = (min_lag..max_lag).find(|&t| d[t] < YIN_THRESHOLD)?;
+        while tau + 1 < max_lag && d[tau + 1] < d[tau] {
+            tau += 1;
+        }
+        // 前後との放物線補間で周期を細かく求める
+        let (a, b, c) = (d[tau - 1], d[tau], d[tau + 1]);
+        let denominator = a + c - 2.0 * b;
+        let shift = if denominator.abs() > f32::EPSILON {
+            ((a - c) / (2.0 * denominator)).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        Some(RATE as f32 / (tau as f32 + shift))
+    }
+}
+
+fn max_lag() -> usize {
+    (RATE as f32 / MIN_PITCH) as usize
+}
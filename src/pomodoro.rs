@@ -0,0 +1,159 @@
+// ポモドーロ: 作業と休憩を決まった間隔で繰り返し、休憩中は休憩の表情と残り時間を出す
+use crate::{
+    config::PomodoroConfig,
+    dirty::Rect,
+    panel::{self, Panel, Style},
+    t,
+    theme::Theme,
+};
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use winit::keyboard::KeyCode;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Work,
+    Break,
+}
+
+/// Alternates work and break periods, counted from launch.
+pub struct Pomodoro {
+    config: PomodoroConfig,
+    panel: Panel,
+    phase: Phase,
+    // 今の区間の始まりと長さ
+    started: Instant,
+    length: Duration,
+    // 終えた作業の数（何回目で長い休憩にするか）
+    completed: u32,
+    // 描いた元の値（区間、残り秒、棒の長さ）。値が変わったときだけ描き直す
+    shown: Option<(Phase, u64, usize)>,
+}
+
+impl Pomodoro {
+    /// Starts the first work period at `now`.
+    pub fn new(config: &PomodoroConfig, width: usize, height: usize, now: Instant) -> Result<Self> {
+        let style = Style {
+            position: config.position,
+            width: config.width,
+            height: config.height,
+            fill: config.fill_color,
+            empty: config.empty_color,
+            background: config.background,
+            size: config.size,
+            text_color: config.text_color,
+            radius: 0.0,
+        };
+        Ok(Self {
+            config: config.clone(),
+            panel: Panel::new(style, config.font.as_deref(), width, height)?,
+            phase: Phase::Work,
+            started: now,
+            length: minutes(config.work_minutes),
+            completed: 0,
+            shown: None,
+        })
+    }
+
+    pub fn is_hotkey(&self, keycode: KeyCode) -> bool {
+        self.config.hotkey.as_deref() == Some(format!("{keycode:?}").as_str())
+    }
+
+    /// Ends a break early and starts the next work period. Does nothing while working.
+    pub fn dismiss(&mut self, now: Instant) {
+        if self.phase == Phase::Break {
+            self.work(now);
+            tracing::info!("{}", t!("pomodoro.dismissed"));
+        }
+    }
+
+    fn work(&mut self, now: Instant) {
+        self.phase = Phase::Work;
+        self.started = now;
+        self.length = minutes(self.config.work_minutes);
+    }
+
+    /// The expression to show during a break.
+    pub fn expression(&self) -> Option<&str> {
+        match self.phase {
+            Phase::Break => self.config.expression.as_deref(),
+            Phase::Work => None,
+        }
+    }
+
+    /// Moves on to the next period when this one is over. Returns whether the timer looks
+    /// different.
+    pub fn update(&mut self, now: Instant) -> bool {
+        let mut changed = false;
+        while now.saturating_duration_since(self.started) >= self.length {
+            let end = self.started + self.length;
+            match self.phase {
+                Phase::Work => {
+                    self.completed += 1;
+                    let long = self.config.long_break_every > 0
+                        && self.completed.is_multiple_of(self.config.long_break_every);
+                    let length = if long {
+                        self.config.long_break_minutes
+                    } else {
+                        self.config.break_minutes
+                    };
+                    self.phase = Phase::Break;
+                    self.started = end;
+                    self.length = minutes(length);
+                    tracing::info!("{}", t!("pomodoro.break", panel::clock_time(self.length)));
+                }
+                Phase::Break => {
+                    self.work(end);
+                    tracing::info!("{}", t!("pomodoro.work"));
+                }
+            }
+            changed = true;
+        }
+
+        let visible = self.phase == Phase::Break || self.config.show_during_work;
+        if !visible {
+            // 消えた直後は一度描き直させる
+            return std::mem::take(&mut self.shown).is_some() || changed;
+        }
+        let remaining = self
+            .length
+            .saturating_sub(now.saturating_duration_since(self.started));
+        let ratio = remaining.as_secs_f32() / self.length.as_secs_f32().max(f32::EPSILON);
+        let filled = (self.panel.bar_width() as f32 * ratio).round() as usize;
+        // 残り時間は切り上げて、0:00 を表示したまま待たないようにする
+        let secs = remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0);
+        let shown = (self.phase, secs, filled);
+        if self.shown == Some(shown) {
+            return changed;
+        }
+        self.shown = Some(shown);
+        let time = panel::clock_time(Duration::from_secs(secs));
+        let label = match self.phase {
+            Phase::Work => t!("pomodoro.work_label", time),
+            Phase::Break => t!("pomodoro.break_label", time),
+        };
+        self.panel.set(&label, filled);
+        true
+    }
+
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.panel.set_theme(theme);
+    }
+
+    /// Draws the timer where it overlaps `clip`.
+    pub fn draw(&self, dst: &mut [u8], clip: Rect) {
+        if self.shown.is_some() {
+            self.panel.draw(dst, clip);
+        }
+    }
+}
+
+// 設定を誤っても止まらないよう、1秒から100日あまりの間に収める
+fn minutes(minutes: f32) -> Duration {
+    let secs = if minutes.is_nan() {
+        0.0
+    } else {
+        minutes * 60.0
+    };
+    Duration::from_secs_f32(secs.clamp(1.0, 1e7))
+}
@@ -0,0 +1,131 @@
+// 調整値だけを書き出して共有できるプリセット（darwin preset export/import）。
+// 閾値・フィルタ・アニメーションの設定だけを扱い、画像などの素材には触れないので、
+// 「このマイクとこのループバックならこの値」を他の人のアバターにもそのまま当てられる
+use crate::{cli::PresetAction, config::Config, t};
+use anyhow::{Context, Result};
+use std::path::Path;
+
+// プリセットに入れる表と、その中のキー（None なら表全体）
+const SECTIONS: &[(&str, Option<&[&str]>)] = &[
+    (
+        "audio",
+        Some(&[
+            "threshold",
+            "whisper_threshold",
+            "whisper_hold_ms",
+            "highpass",
+            "lowpass",
+            "adaptive",
+            "voice_reference",
+            "echo_cancellation",
+        ]),
+    ),
+    ("talking", None),
+    ("squash", None),
+    ("music", None),
+];
+
+const HEADER: &str = "# Darwin preset: audio thresholds, filters and animation timing.\n\
+                      # Apply it with `darwin preset import <file>`.\n\n";
+
+pub fn run(config: &Config, action: PresetAction) -> Result<()> {
+    match action {
+        PresetAction::Export { file } => {
+            export(config, &file)?;
+            println!("{}", t!("preset.exported", file.display()));
+        }
+        PresetAction::Import { file } => {
+            let written = import(config, &file)?;
+            println!(
+                "{}",
+                t!("preset.imported", file.display(), written.display())
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Writes the tuning part of `config` to `file`, defaults included, so the preset gives
+/// the same result whatever the other config has.
+pub fn export(config: &Config, file: &Path) -> Result<()> {
+    let all = toml::Table::try_from(config)?;
+    let mut preset = toml::Table::new();
+    for &(section, keys) in SECTIONS {
+        let Some(value) = all.get(section) else {
+            continue;
+        };
+        let value = match (keys, value) {
+            (Some(keys), toml::Value::Table(table)) => toml::Value::Table(
+                table
+                    .iter()
+                    .filter(|(key, _)| keys.contains(&key.as_str()))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+            ),
+            (_, value) => value.clone(),
+        };
+        preset.insert(section.to_string(), round(value));
+    }
+    std::fs::write(
+        file,
+        format!("{HEADER}{}", toml::to_string_pretty(&preset)?),
+    )?;
+    Ok(())
+}
+
+/// Replaces the tuning settings of the config file with those in `file` and leaves the rest
+/// (and comments) alone. Settings the preset leaves out go back to their defaults. Returns
+/// the config file written.
+pub fn import(config: &Config, file: &Path) -> Result<std::path::PathBuf> {
+    let text =
+        std::fs::read_to_string(file).with_context(|| t!("preset.read_failed", file.display()))?;
+    // 値の型が合っているかは、設定として読んで確かめる
+    toml::from_str::<Config>(&text).with_context(|| t!("preset.invalid", file.display()))?;
+    let preset: toml_edit::DocumentMut = text
+        .parse()
+        .with_context(|| t!("preset.invalid", file.display()))?;
+
+    config.edit(|doc| {
+        for &(section, keys) in SECTIONS {
+            let value = preset.get(section);
+            let Some(keys) = keys else {
+                match value {
+                    Some(value) => doc[section] = value.clone(),
+                    None => {
+                        doc.remove(section);
+                    }
+                }
+                continue;
+            };
+            if value.is_none() && doc.get(section).is_none() {
+                continue;
+            }
+            let table = doc.entry(section).or_insert(toml_edit::table());
+            for &key in keys {
+                match value.and_then(|value| value.get(key)) {
+                    Some(value) => table[key] = value.clone(),
+                    None => {
+                        if let Some(table) = table.as_table_like_mut() {
+                            table.remove(key);
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+// f32 のままだと 0.0010000000474974513 のような値になるので丸める
+fn round(value: toml::Value) -> toml::Value {
+    match value {
+        toml::Value::Float(value) => toml::Value::Float((value * 1e6).round() / 1e6),
+        toml::Value::Array(values) => toml::Value::Array(values.into_iter().map(round).collect()),
+        toml::Value::Table(table) => toml::Value::Table(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, round(value)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
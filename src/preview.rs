@@ -0,0 +1,118 @@
+use crate::{health::AudioWarning, render::Renderer, t};
+use anyhow::Result;
+use winit::{
+    dpi::LogicalSize,
+    event::WindowEvent,
+    event_loop::EventLoopWindowTarget,
+    window::{Window, WindowBuilder, WindowId, WindowLevel},
+};
+
+/// Small always-on-top window mirroring the main output at reduced size.
+pub struct Preview {
+    window: Window,
+    renderer: Renderer,
+    size: (usize, usize),
+    // 元の出力サイズ
+    source_size: (usize, usize),
+    warning: Option<AudioWarning>,
+}
+
+impl Preview {
+    pub fn new<T>(
+        elwt: &EventLoopWindowTarget<T>,
+        width: u32,
+        height: u32,
+        scale: f32,
+    ) -> Result<Self> {
+        let preview_width = ((width as f32 * scale).round() as u32).max(1);
+        let preview_height = ((height as f32 * scale).round() as u32).max(1);
+
+        let window = WindowBuilder::new()
+            .with_title(t!("preview.title"))
+            .with_inner_size(LogicalSize::new(preview_width, preview_height))
+            .with_window_level(WindowLevel::AlwaysOnTop)
+            .build(elwt)?;
+        let renderer = Renderer::new(&window, preview_width, preview_height)?;
+
+        Ok(Self {
+            window,
+            renderer,
+            size: (preview_width as usize, preview_height as usize),
+            source_size: (width as usize, height as usize),
+            warning: None,
+        })
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    /// Shows a colored banner while the input has a problem.
+    pub fn set_warning(&mut self, warning: Option<AudioWarning>) {
+        self.warning = warning;
+        self.window.set_title(&match warning {
+            Some(w) => format!("{} - {}", t!("preview.title"), w.message()),
+            None => t!("preview.title").to_string(),
+        });
+    }
+
+    pub fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+
+    /// Handles an event for the preview window. Returns `false` once it should be closed.
+    /// `output` is the main window's composed frame.
+    pub fn handle_event(&mut self, event: &WindowEvent, output: &[u8]) -> Result<bool> {
+        match event {
+            WindowEvent::CloseRequested => return Ok(false),
+            WindowEvent::Resized(size) => {
+                self.renderer.resize_surface(size.width, size.height)?;
+                self.window.request_redraw();
+            }
+            WindowEvent::RedrawRequested => {
+                let (size, source_size, warning) = (self.size, self.source_size, self.warning);
+                if let Some(frame) = self.renderer.frame_mut() {
+                    downscale(frame, size, output, source_size);
+                    if let Some(warning) = warning {
+                        draw_banner(frame, size, warning);
+                    }
+                }
+                self.renderer.render(&self.window)?;
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+}
+
+// 合成済みの出力を最近傍で縮小（シーケンスの変形もそのまま映す）
+fn downscale(dst: &mut [u8], dst_size: (usize, usize), src: &[u8], src_size: (usize, usize)) {
+    let (dst_width, dst_height) = dst_size;
+    let (src_width, src_height) = src_size;
+    if src.len() != src_width * src_height * 4 || dst.len() != dst_width * dst_height * 4 {
+        return;
+    }
+    for y in 0..dst_height {
+        let sy = y * src_height / dst_height;
+        for x in 0..dst_width {
+            let sx = x * src_width / dst_width;
+            let d = (y * dst_width + x) * 4;
+            let s = (sy * src_width + sx) * 4;
+            dst[d..d + 4].copy_from_slice(&src[s..s + 4]);
+        }
+    }
+}
+
+// 上端の帯（クリップ: 赤、無音: 黄、デバイスなし・許可なし: 灰）
+fn draw_banner(frame: &mut [u8], size: (usize, usize), warning: AudioWarning) {
+    let (width, height) = size;
+    let color = match warning {
+        AudioWarning::Clipping => [0xe0, 0x30, 0x30, 0xff],
+        AudioWarning::Silence => [0xe0, 0xc0, 0x20, 0xff],
+        AudioWarning::NoDevice | AudioWarning::PermissionDenied => [0x80, 0x80, 0x80, 0xff],
+    };
+    let rows = (height / 12).max(2).min(height);
+    for pixel in frame[..rows * width * 4].chunks_exact_mut(4) {
+        pixel.copy_from_slice(&color);
+    }
+}
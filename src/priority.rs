@@ -0,0 +1,63 @@
+// 表情を求める入力（シーケンス・手動・タイマー・MQTT・声）の優先順位と、入力ごとの取り下げ
+use crate::config::{ExpressionSource, PriorityConfig};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+
+/// What each input currently asks for. The highest-priority request that has not timed out
+/// wins, instead of whichever input wrote last.
+pub struct ExpressionClaims {
+    order: Vec<ExpressionSource>,
+    timeouts: BTreeMap<ExpressionSource, Duration>,
+    // 要求している表情と、要求し始めた時刻
+    claims: BTreeMap<ExpressionSource, (String, Instant)>,
+}
+
+impl ExpressionClaims {
+    pub fn new(config: &PriorityConfig) -> Self {
+        Self {
+            order: config.order.clone(),
+            timeouts: config
+                .timeout
+                .iter()
+                .map(|(source, secs)| {
+                    // 長すぎて Duration に入らなければ、期限が無いのと同じ
+                    let timeout =
+                        Duration::try_from_secs_f32(secs.max(0.0)).unwrap_or(Duration::MAX);
+                    (*source, timeout)
+                })
+                .collect(),
+            claims: BTreeMap::new(),
+        }
+    }
+
+    /// Sets what `source` asks for, or withdraws its request with `None`. Asking again for
+    /// the same expression keeps the original start time, so timeouts still apply.
+    pub fn set(&mut self, source: ExpressionSource, name: Option<&str>, now: Instant) {
+        match name {
+            Some(name) if self.claims.get(&source).is_some_and(|(n, _)| n == name) => {}
+            Some(name) => {
+                self.claims.insert(source, (name.to_string(), now));
+            }
+            None => {
+                self.claims.remove(&source);
+            }
+        }
+    }
+
+    /// What `source` asks for, unless it has timed out.
+    pub fn get(&self, source: ExpressionSource, now: Instant) -> Option<&str> {
+        let (name, since) = self.claims.get(&source)?;
+        let expired = self
+            .timeouts
+            .get(&source)
+            .is_some_and(|timeout| now.saturating_duration_since(*since) >= *timeout);
+        (!expired).then_some(name.as_str())
+    }
+
+    /// The expression of the highest-priority live request.
+    pub fn resolve(&self, now: Instant) -> Option<&str> {
+        self.order.iter().find_map(|source| self.get(*source, now))
+    }
+}
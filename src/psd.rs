@@ -0,0 +1,283 @@
+// Photoshop (PSD) ファイルの最小限のリーダー（8bit RGB のレイヤーのみ）
+use crate::t;
+use anyhow::{Context, Result, bail, ensure};
+use std::path::Path;
+
+/// A raster layer, already converted to straight-alpha RGBA covering its own bounds.
+pub struct Layer {
+    pub name: String,
+    pub visible: bool,
+    pub left: i32,
+    pub top: i32,
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+pub struct Psd {
+    pub width: usize,
+    pub height: usize,
+    /// Bottom-most layer first, in the order they are composited.
+    pub layers: Vec<Layer>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).context("PSD is truncated")?;
+        let slice = self.data.get(self.pos..end).context("PSD is truncated")?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn i16(&mut self) -> Result<i16> {
+        Ok(i16::from_be_bytes(self.bytes(2)?.try_into()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    // 長さ付きのブロックを飛ばす
+    fn skip_block(&mut self) -> Result<()> {
+        let len = self.u32()? as usize;
+        self.bytes(len)?;
+        Ok(())
+    }
+}
+
+struct Record {
+    name: String,
+    visible: bool,
+    top: i32,
+    left: i32,
+    bottom: i32,
+    right: i32,
+    opacity: u8,
+    // (チャンネル ID, データ長)
+    channels: Vec<(i16, usize)>,
+    // グループの開始・終了を表すだけのレイヤー
+    divider: bool,
+}
+
+impl Psd {
+    pub fn open(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)?;
+        Self::parse(&data).with_context(|| t!("psd.parse_failed", path.display()))
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut r = Reader { data, pos: 0 };
+        ensure!(r.bytes(4)? == b"8BPS", "not a PSD file");
+        ensure!(
+            r.u16()? == 1,
+            "PSB (large document) files are not supported"
+        );
+        r.bytes(6)?;
+        let _channels = r.u16()?;
+        let height = r.u32()? as usize;
+        let width = r.u32()? as usize;
+        let depth = r.u16()?;
+        let mode = r.u16()?;
+        if depth != 8 || mode != 3 {
+            bail!(t!("psd.unsupported", depth, mode));
+        }
+
+        r.skip_block()?; // カラーモードデータ
+        r.skip_block()?; // 画像リソース
+
+        let _layer_and_mask_len = r.u32()?;
+        let layer_info_len = r.u32()? as usize;
+        if layer_info_len == 0 {
+            return Ok(Self {
+                width,
+                height,
+                layers: Vec::new(),
+            });
+        }
+        let count = r.i16()?.unsigned_abs() as usize;
+
+        let records = (0..count)
+            .map(|_| read_record(&mut r))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut layers = Vec::new();
+        for record in records {
+            let w = (record.right as i64 - record.left as i64).max(0) as usize;
+            let h = (record.bottom as i64 - record.top as i64).max(0) as usize;
+            // 壊れたファイルで巨大な領域を確保しないよう、残りのデータで表せる大きさか確かめる
+            // （PackBits は 2 バイトで最大 128 バイトに展開される）
+            let pixels = w.checked_mul(h).context("PSD layer is too large")?;
+            let remaining = r.data.len().saturating_sub(r.pos);
+            ensure!(
+                pixels <= remaining.saturating_mul(64),
+                "PSD layer is larger than its data"
+            );
+            let mut rgba = vec![255u8; pixels * 4];
+            for &(id, len) in &record.channels {
+                let channel = r.bytes(len)?;
+                let target = match id {
+                    0..=2 => id as usize,
+                    -1 => 3,
+                    // マスクなどは使わない
+                    _ => continue,
+                };
+                if w == 0 || h == 0 {
+                    continue;
+                }
+                let plane = decode_channel(channel, w, h)?;
+                for (i, value) in plane.into_iter().enumerate() {
+                    rgba[i * 4 + target] = value;
+                }
+            }
+            if record.divider || w == 0 || h == 0 {
+                continue;
+            }
+            if record.opacity != 255 {
+                for pixel in rgba.chunks_exact_mut(4) {
+                    pixel[3] = (pixel[3] as u32 * record.opacity as u32 / 255) as u8;
+                }
+            }
+            layers.push(Layer {
+                name: record.name,
+                visible: record.visible,
+                left: record.left,
+                top: record.top,
+                width: w,
+                height: h,
+                rgba,
+            });
+        }
+
+        Ok(Self {
+            width,
+            height,
+            layers,
+        })
+    }
+}
+
+fn read_record(r: &mut Reader) -> Result<Record> {
+    let top = r.i32()?;
+    let left = r.i32()?;
+    let bottom = r.i32()?;
+    let right = r.i32()?;
+    let channel_count = r.u16()? as usize;
+    let channels = (0..channel_count)
+        .map(|_| Ok((r.i16()?, r.u32()? as usize)))
+        .collect::<Result<Vec<_>>>()?;
+    ensure!(r.bytes(4)? == b"8BIM", "bad layer record signature");
+    let _blend_mode = r.bytes(4)?;
+    let opacity = r.u8()?;
+    let _clipping = r.u8()?;
+    let flags = r.u8()?;
+    let _filler = r.u8()?;
+
+    let extra_len = r.u32()? as usize;
+    let extra_end = r.pos + extra_len;
+    r.skip_block()?; // レイヤーマスク
+    r.skip_block()?; // ブレンド範囲
+
+    // Pascal 文字列（長さバイトを含めて 4 の倍数に揃えられている）
+    let name_len = r.u8()? as usize;
+    let mut name = String::from_utf8_lossy(r.bytes(name_len)?).into_owned();
+    let padded = (name_len + 1).div_ceil(4) * 4;
+    r.bytes(padded - name_len - 1)?;
+
+    let mut divider = false;
+    while r.pos + 12 <= extra_end {
+        let signature = r.bytes(4)?;
+        ensure!(
+            signature == b"8BIM" || signature == b"8B64",
+            "bad additional layer info signature"
+        );
+        let key = r.bytes(4)?;
+        let len = r.u32()? as usize;
+        let block = r.bytes(len)?;
+        match key {
+            // Unicode のレイヤー名（Pascal 文字列より優先）
+            b"luni" if block.len() >= 4 => {
+                let chars = u32::from_be_bytes(block[..4].try_into()?) as usize;
+                let units: Vec<u16> = block[4..]
+                    .chunks_exact(2)
+                    .take(chars)
+                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                    .collect();
+                name = String::from_utf16_lossy(&units);
+            }
+            b"lsct" if block.len() >= 4 => {
+                divider = u32::from_be_bytes(block[..4].try_into()?) != 0;
+            }
+            _ => {}
+        }
+    }
+    r.pos = extra_end;
+
+    Ok(Record {
+        name,
+        visible: flags & 0x02 == 0,
+        top,
+        left,
+        bottom,
+        right,
+        opacity,
+        channels,
+        divider,
+    })
+}
+
+// 非圧縮または PackBits (RLE) のチャンネルデータを展開
+fn decode_channel(data: &[u8], width: usize, height: usize) -> Result<Vec<u8>> {
+    let mut r = Reader { data, pos: 0 };
+    match r.u16()? {
+        0 => Ok(r.bytes(width * height)?.to_vec()),
+        1 => {
+            let row_lengths = (0..height)
+                .map(|_| Ok(r.u16()? as usize))
+                .collect::<Result<Vec<_>>>()?;
+            let mut plane = Vec::with_capacity(width * height);
+            for len in row_lengths {
+                let row = r.bytes(len)?;
+                let start = plane.len();
+                unpack_bits(row, &mut plane)?;
+                plane.resize(start + width, 0);
+            }
+            Ok(plane)
+        }
+        compression => bail!(t!("psd.compression", compression)),
+    }
+}
+
+fn unpack_bits(mut data: &[u8], out: &mut Vec<u8>) -> Result<()> {
+    while let Some((&header, rest)) = data.split_first() {
+        let n = header as i8;
+        data = rest;
+        if n >= 0 {
+            let len = n as usize + 1;
+            ensure!(data.len() >= len, "PackBits data is truncated");
+            out.extend_from_slice(&data[..len]);
+            data = &data[len..];
+        } else if n != -128 {
+            let (&value, rest) = data.split_first().context("PackBits data is truncated")?;
+            out.extend(std::iter::repeat_n(value, (1 - n as isize) as usize));
+            data = rest;
+        }
+    }
+    Ok(())
+}
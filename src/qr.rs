@@ -0,0 +1,573 @@
+// QR コードの生成（バイトモード、誤り訂正レベル M、バージョン 1〜10）。
+// リモコンの URL を読ませるだけなので、それより長いデータは扱わない。
+// 手順は JIS X 0510 / ISO/IEC 18004 のとおり: データ → RS 符号 → 配置 → マスク選択。
+
+// バージョンごとの、ブロックあたりの誤り訂正コード語数と、(ブロック数, データコード語数) の2組
+const BLOCKS: [(usize, [(usize, usize); 2]); 10] = [
+    (10, [(1, 16), (0, 0)]),
+    (16, [(1, 28), (0, 0)]),
+    (26, [(1, 44), (0, 0)]),
+    (18, [(2, 32), (0, 0)]),
+    (24, [(2, 43), (0, 0)]),
+    (16, [(4, 27), (0, 0)]),
+    (18, [(4, 31), (0, 0)]),
+    (22, [(2, 38), (2, 39)]),
+    (22, [(3, 36), (2, 37)]),
+    (26, [(4, 43), (1, 44)]),
+];
+
+// 位置合わせパターンの中心
+const ALIGNMENT: [&[usize]; 10] = [
+    &[],
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+];
+
+/// A QR code symbol; `true` modules are dark.
+pub struct QrCode {
+    size: usize,
+    modules: Vec<bool>,
+}
+
+impl QrCode {
+    /// Encodes `data` in the smallest version that fits, or `None` if it is too long.
+    pub fn encode(data: &[u8]) -> Option<Self> {
+        let version = (1..=BLOCKS.len()).find(|&v| data.len() <= capacity(v))?;
+        let codewords = add_ecc(version, &data_codewords(version, data));
+
+        let mut qr = Builder::new(version);
+        qr.function_patterns();
+        qr.place(&codewords);
+        // 見分けやすさの減点が一番少ないマスクを選ぶ
+        let mask = (0..8)
+            .min_by_key(|&mask| {
+                let mut candidate = qr.clone();
+                candidate.apply_mask(mask);
+                candidate.format(mask);
+                candidate.penalty()
+            })
+            .unwrap_or(0);
+        qr.apply_mask(mask);
+        qr.format(mask);
+        Some(Self {
+            size: qr.size,
+            modules: qr.modules,
+        })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether the module at column `x`, row `y` is dark. Outside the symbol is light.
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.size && y < self.size && self.modules[y * self.size + x]
+    }
+
+    /// Text for a terminal with two rows per line. Dark terminals are assumed, so the light
+    /// modules and the quiet zone are the filled blocks.
+    pub fn to_terminal(&self) -> String {
+        const QUIET: usize = 2;
+        let light = |x: usize, y: usize| {
+            let (x, y) = (x.wrapping_sub(QUIET), y.wrapping_sub(QUIET));
+            !self.get(x, y)
+        };
+        let size = self.size + QUIET * 2;
+        let mut out = String::new();
+        for y in (0..size).step_by(2) {
+            for x in 0..size {
+                out.push(match (light(x, y), y + 1 < size && light(x, y + 1)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+// バイトモードで入るバイト数
+fn capacity(version: usize) -> usize {
+    let count_bits = if version < 10 { 8 } else { 16 };
+    (data_length(version) * 8 - 4 - count_bits) / 8
+}
+
+fn data_length(version: usize) -> usize {
+    let (_, groups) = BLOCKS[version - 1];
+    groups.iter().map(|(blocks, length)| blocks * length).sum()
+}
+
+// モード・文字数・データ・終端を並べ、埋め草で満たす
+fn data_codewords(version: usize, data: &[u8]) -> Vec<u8> {
+    let mut bits = Bits::default();
+    bits.push(0b0100, 4);
+    bits.push(data.len() as u32, if version < 10 { 8 } else { 16 });
+    for &byte in data {
+        bits.push(byte as u32, 8);
+    }
+    let capacity = data_length(version) * 8;
+    bits.push(0, (capacity - bits.len).min(4));
+    bits.push(0, (8 - bits.len % 8) % 8);
+    let mut codewords = bits.bytes;
+    for pad in [0xEC, 0x11].into_iter().cycle() {
+        if codewords.len() >= data_length(version) {
+            break;
+        }
+        codewords.push(pad);
+    }
+    codewords
+}
+
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Bits {
+    fn push(&mut self, value: u32, count: usize) {
+        for i in (0..count).rev() {
+            if self.len.is_multiple_of(8) {
+                self.bytes.push(0);
+            }
+            if value >> i & 1 != 0 {
+                *self.bytes.last_mut().unwrap() |= 0x80 >> (self.len % 8);
+            }
+            self.len += 1;
+        }
+    }
+}
+
+// ブロックに分けて誤り訂正コード語を付け、ブロックをまたいで交互に並べる
+fn add_ecc(version: usize, data: &[u8]) -> Vec<u8> {
+    let (ecc_length, groups) = BLOCKS[version - 1];
+    let divisor = rs_divisor(ecc_length);
+    let mut blocks = Vec::new();
+    let mut rest = data;
+    for (count, length) in groups {
+        for _ in 0..count {
+            let (block, next) = rest.split_at(length);
+            blocks.push((block, rs_remainder(block, &divisor)));
+            rest = next;
+        }
+    }
+    let longest = blocks.iter().map(|(data, _)| data.len()).max().unwrap_or(0);
+    let mut out = Vec::new();
+    for i in 0..longest {
+        out.extend(blocks.iter().filter_map(|(data, _)| data.get(i)));
+    }
+    for i in 0..ecc_length {
+        out.extend(blocks.iter().map(|(_, ecc)| ecc[i]));
+    }
+    out
+}
+
+// GF(2^8)（原始多項式 x^8 + x^4 + x^3 + x^2 + 1）での掛け算
+fn gf_mul(x: u8, y: u8) -> u8 {
+    let mut z: u16 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11D);
+        z ^= ((y as u16 >> i) & 1) * x as u16;
+    }
+    z as u8
+}
+
+// 生成多項式（最高次の係数 1 は省く）
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0u8; degree];
+    result[degree - 1] = 1;
+    let mut root = 1u8;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_mul(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_mul(root, 0x02);
+    }
+    result
+}
+
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0u8; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (r, &d) in result.iter_mut().zip(divisor) {
+            *r ^= gf_mul(d, factor);
+        }
+    }
+    result
+}
+
+// 形式情報の15ビット（BCH(15,5) で符号化してマスクパターン 0x5412 をかける）
+fn format_bits(mask: u32) -> u32 {
+    // レベル M の指示子は 00
+    let data = mask;
+    let mut rem = data;
+    for _ in 0..10 {
+        rem = (rem << 1) ^ ((rem >> 9) * 0x537);
+    }
+    (data << 10 | rem) ^ 0x5412
+}
+
+// 型番情報の18ビット（BCH(18,6)）
+fn version_bits(version: usize) -> u32 {
+    let mut rem = version as u32;
+    for _ in 0..12 {
+        rem = (rem << 1) ^ ((rem >> 11) * 0x1F25);
+    }
+    (version as u32) << 12 | rem
+}
+
+#[derive(Clone)]
+struct Builder {
+    version: usize,
+    size: usize,
+    modules: Vec<bool>,
+    // 機能パターン（データを置かず、マスクもかけない）
+    function: Vec<bool>,
+}
+
+impl Builder {
+    fn new(version: usize) -> Self {
+        let size = version * 4 + 17;
+        Self {
+            version,
+            size,
+            modules: vec![false; size * size],
+            function: vec![false; size * size],
+        }
+    }
+
+    fn set_function(&mut self, x: usize, y: usize, dark: bool) {
+        let i = y * self.size + x;
+        self.modules[i] = dark;
+        self.function[i] = true;
+    }
+
+    fn function_patterns(&mut self) {
+        let size = self.size;
+        // タイミングパターン
+        for i in 0..size {
+            self.set_function(6, i, i % 2 == 0);
+            self.set_function(i, 6, i % 2 == 0);
+        }
+        // 位置検出パターン（分離パターンを含む）
+        for (cx, cy) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            for dy in -4i32..=4 {
+                for dx in -4i32..=4 {
+                    let (x, y) = (cx as i32 + dx, cy as i32 + dy);
+                    if x < 0 || y < 0 || x >= size as i32 || y >= size as i32 {
+                        continue;
+                    }
+                    let distance = dx.abs().max(dy.abs());
+                    self.set_function(x as usize, y as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+        // 位置合わせパターン（位置検出パターンと重なる角は除く）
+        let centers = ALIGNMENT[self.version - 1];
+        let last = centers.len().saturating_sub(1);
+        for (i, &cy) in centers.iter().enumerate() {
+            for (j, &cx) in centers.iter().enumerate() {
+                if (i, j) == (0, 0) || (i, j) == (0, last) || (i, j) == (last, 0) {
+                    continue;
+                }
+                for dy in -2i32..=2 {
+                    for dx in -2i32..=2 {
+                        let dark = dx.abs().max(dy.abs()) != 1;
+                        self.set_function(
+                            (cx as i32 + dx) as usize,
+                            (cy as i32 + dy) as usize,
+                            dark,
+                        );
+                    }
+                }
+            }
+        }
+        // 形式情報の場所を先に取っておく
+        self.format(0);
+        // 型番情報（バージョン 7 以上）
+        if self.version >= 7 {
+            let bits = version_bits(self.version);
+            for i in 0..18 {
+                let dark = bits >> i & 1 != 0;
+                let (a, b) = (size - 11 + i % 3, i / 3);
+                self.set_function(a, b, dark);
+                self.set_function(b, a, dark);
+            }
+        }
+    }
+
+    // 形式情報（誤り訂正レベル M とマスク番号）を2か所に書く
+    fn format(&mut self, mask: u32) {
+        let size = self.size;
+        let bits = format_bits(mask);
+        let bit = |i: usize| bits >> i & 1 != 0;
+        for i in 0..=5 {
+            self.set_function(8, i, bit(i));
+        }
+        self.set_function(8, 7, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(7, 8, bit(8));
+        for i in 9..15 {
+            self.set_function(14 - i, 8, bit(i));
+        }
+        for i in 0..8 {
+            self.set_function(size - 1 - i, 8, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(8, size - 15 + i, bit(i));
+        }
+        // 常に暗いモジュール
+        self.set_function(8, size - 8, true);
+    }
+
+    // 右下から2列ずつ、上下に折り返しながら置く（縦のタイミングパターンの列は飛ばす）
+    fn place(&mut self, codewords: &[u8]) {
+        let size = self.size;
+        let total = codewords.len() * 8;
+        let mut i = 0;
+        let mut right = size - 1;
+        while right >= 1 {
+            if right == 6 {
+                right = 5;
+            }
+            let upward = (right + 1) & 2 == 0;
+            for vertical in 0..size {
+                let y = if upward {
+                    size - 1 - vertical
+                } else {
+                    vertical
+                };
+                for x in [right, right - 1] {
+                    let index = y * size + x;
+                    if !self.function[index] && i < total {
+                        self.modules[index] = codewords[i / 8] >> (7 - i % 8) & 1 != 0;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    fn apply_mask(&mut self, mask: u32) {
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let invert = match mask {
+                    0 => (x + y) % 2 == 0,
+                    1 => y % 2 == 0,
+                    2 => x % 3 == 0,
+                    3 => (x + y) % 3 == 0,
+                    4 => (x / 3 + y / 2) % 2 == 0,
+                    5 => x * y % 2 + x * y % 3 == 0,
+                    6 => (x * y % 2 + x * y % 3) % 2 == 0,
+                    _ => ((x + y) % 2 + x * y % 3) % 2 == 0,
+                };
+                let i = y * self.size + x;
+                if invert && !self.function[i] {
+                    self.modules[i] = !self.modules[i];
+                }
+            }
+        }
+    }
+
+    // 読み取りにくさの減点（同色の連続、2x2 の塊、位置検出パターンに似た並び、明暗の偏り）
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let at = |x: usize, y: usize| self.modules[y * size + x];
+        let mut penalty = 0;
+        for transposed in [false, true] {
+            for a in 0..size {
+                let line: Vec<bool> = (0..size)
+                    .map(|b| if transposed { at(a, b) } else { at(b, a) })
+                    .collect();
+                let mut run = 1;
+                for b in 1..=size {
+                    if b < size && line[b] == line[b - 1] {
+                        run += 1;
+                        continue;
+                    }
+                    if run >= 5 {
+                        penalty += run - 2;
+                    }
+                    run = 1;
+                }
+                // 1:1:3:1:1 の並びの片側に4つ以上の明
+                const FINDER: [bool; 7] = [true, false, true, true, true, false, true];
+                for b in 0..size.saturating_sub(6) {
+                    if line[b..b + 7] != FINDER {
+                        continue;
+                    }
+                    let light = |range: std::ops::Range<usize>| range.into_iter().all(|i| !line[i]);
+                    let before = b >= 4 && light(b - 4..b);
+                    let after = b + 11 <= size && light(b + 7..b + 11);
+                    if before || after {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+        for y in 0..size - 1 {
+            for x in 0..size - 1 {
+                let color = at(x, y);
+                if at(x + 1, y) == color && at(x, y + 1) == color && at(x + 1, y + 1) == color {
+                    penalty += 3;
+                }
+            }
+        }
+        let dark = self.modules.iter().filter(|&&m| m).count();
+        let total = size * size;
+        // 暗の割合が 50% から 5% 離れるごとに 10
+        let deviation = (dark * 20).abs_diff(total * 10) / total;
+        penalty + deviation * 10
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_bits_for_level_m() {
+        // ISO/IEC 18004 の表（レベル M、マスク 0〜7）
+        let expected = [
+            0x5412, 0x5125, 0x5E7C, 0x5B4B, 0x45F9, 0x40CE, 0x4F97, 0x4AA0,
+        ];
+        for (mask, expected) in expected.into_iter().enumerate() {
+            assert_eq!(format_bits(mask as u32), expected, "mask {mask}");
+        }
+    }
+
+    #[test]
+    fn version_bits_from_standard() {
+        let expected = [(7, 0x07C94), (8, 0x085BC), (9, 0x09A99), (10, 0x0A4D3)];
+        for (version, expected) in expected {
+            assert_eq!(version_bits(version), expected, "version {version}");
+        }
+    }
+
+    #[test]
+    fn rs_remainder_of_known_block() {
+        // 1-M の "HELLO WORLD"（英数字モード）のデータコード語と誤り訂正コード語
+        let data = [
+            32, 91, 11, 120, 209, 114, 220, 77, 67, 64, 236, 17, 236, 17, 236, 17,
+        ];
+        assert_eq!(
+            rs_remainder(&data, &rs_divisor(10)),
+            [196, 35, 39, 119, 235, 215, 231, 226, 93, 23]
+        );
+    }
+
+    #[test]
+    fn version_1_matches_reference_encoder() {
+        // qrcodegen（バイトモード、レベル M、マスク自動）の出力
+        const EXPECTED: [&str; 21] = [
+            "#######..####.#######",
+            "#.....#.##.##.#.....#",
+            "#.###.#.#.###.#.###.#",
+            "#.###.#.#..#..#.###.#",
+            "#.###.#...###.#.###.#",
+            "#.....#...#.#.#.....#",
+            "#######.#.#.#.#######",
+            "........#..##........",
+            "#.....#.#.#.###..###.",
+            ".#.#.#.###..#.#.#.#..",
+            "#.######.#.#.#..####.",
+            "..#..#..........###..",
+            "#.#...#.###...#.....#",
+            "........#######...#.#",
+            "#######...#.#.##...#.",
+            "#.....#..#####...##..",
+            "#.###.#..##.#...##.#.",
+            "#.###.#...#.#...###..",
+            "#.###.#..#....#.#..##",
+            "#.....#........#.##..",
+            "#######.##.#.#.##..#.",
+        ];
+        let qr = QrCode::encode(b"darwin").unwrap();
+        assert_eq!(qr.size(), 21);
+        let actual: Vec<String> = (0..21)
+            .map(|y| {
+                (0..21)
+                    .map(|x| if qr.get(x, y) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(actual, EXPECTED);
+    }
+
+    #[test]
+    fn version_7_matches_reference_encoder() {
+        // qrcodegen の出力を1行ずつ、左端を最下位ビットにしたもの
+        const EXPECTED: [u64; 45] = [
+            0x1fd1769ad67f,
+            0x104bdc2ffb41,
+            0x17488d65505d,
+            0x175821d0ca5d,
+            0x175f75fa5f5d,
+            0x1041d91c3241,
+            0x1fd55555557f,
+            0x001f771e3e00,
+            0x090dddf37a55,
+            0x1680887bd8a3,
+            0x1c322359dfde,
+            0x097f6e0a38b4,
+            0x03cdcd3fff7b,
+            0x1680994cd2ab,
+            0x1c322b8995eb,
+            0x097f76c2b59f,
+            0x03cddc569d6b,
+            0x1680897dd4b9,
+            0x1c3223c8d5cb,
+            0x097f7483f40b,
+            0x03fdd9f665f1,
+            0x17108d1de51f,
+            0x1d5a2555e556,
+            0x091f77115f19,
+            0x03f5ddfc53f3,
+            0x162889d18a2f,
+            0x1d6a2291f846,
+            0x19d76e2f5bb9,
+            0x0a95d578c477,
+            0x162889df07ab,
+            0x1d6a231f23c8,
+            0x09d777334bbd,
+            0x0295dc6d4068,
+            0x162889554799,
+            0x1d6a22d643d0,
+            0x09d7720beb9e,
+            0x03f5dffdc0d9,
+            0x17188b14d700,
+            0x1d52275d147f,
+            0x091f77132841,
+            0x03fdddf5935d,
+            0x1f7088acb65d,
+            0x1dda22630b5d,
+            0x088f77772841,
+            0x1a25ddda917f,
+        ];
+        let qr = QrCode::encode(&[b'x'; 110]).unwrap();
+        assert_eq!(qr.size(), 45);
+        for (y, expected) in EXPECTED.into_iter().enumerate() {
+            let row = (0..45).fold(0u64, |row, x| row | (qr.get(x, y) as u64) << x);
+            assert_eq!(row, expected, "row {y}");
+        }
+    }
+}
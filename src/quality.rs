@@ -0,0 +1,136 @@
+// 描画が重いときに見た目を少しずつ落とす。合成にかかった時間を見て、予算を超え続けたら
+// 一段下げ、十分に余裕が続いたら一段戻す（行ったり来たりしないよう、戻す条件は厳しめ）
+use crate::{config::QualityConfig, t};
+use std::time::{Duration, Instant};
+
+// 合成時間の平均をならす割合（1フレームごと）
+const SMOOTHING: f32 = 0.1;
+
+/// How much of the optional drawing is done.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Quality {
+    /// Everything.
+    Full,
+    /// Half the particles, no tint.
+    Reduced,
+    /// No particles, no tint.
+    Low,
+}
+
+impl Quality {
+    /// Share of each particle burst that is thrown.
+    pub fn particles(self) -> f32 {
+        match self {
+            Self::Full => 1.0,
+            Self::Reduced => 0.5,
+            Self::Low => 0.0,
+        }
+    }
+
+    /// Whether color effects such as the tint are drawn.
+    pub fn effects(self) -> bool {
+        self == Self::Full
+    }
+
+    fn lower(self) -> Self {
+        match self {
+            Self::Full => Self::Reduced,
+            Self::Reduced | Self::Low => Self::Low,
+        }
+    }
+
+    fn higher(self) -> Self {
+        match self {
+            Self::Full | Self::Reduced => Self::Full,
+            Self::Low => Self::Reduced,
+        }
+    }
+}
+
+/// Watches how long each frame takes to compose and steps the quality down or up.
+pub struct LoadMonitor {
+    budget: f32,
+    recover_below: f32,
+    degrade_after: Duration,
+    recover_after: Duration,
+    // 平均の合成時間（ミリ秒）
+    average: Option<f32>,
+    // 予算を超え始めた時刻と、余裕ができ始めた時刻
+    over_since: Option<Instant>,
+    under_since: Option<Instant>,
+    quality: Quality,
+}
+
+impl LoadMonitor {
+    pub fn new(config: &QualityConfig) -> Self {
+        // 長すぎて Duration に入らなければ、いつまでも切り替えないのと同じ
+        let seconds =
+            |secs: f32| Duration::try_from_secs_f32(secs.max(0.0)).unwrap_or(Duration::MAX);
+        Self {
+            budget: config.budget_ms,
+            recover_below: config.budget_ms * config.recover_ratio,
+            degrade_after: seconds(config.degrade_after),
+            recover_after: seconds(config.recover_after),
+            average: None,
+            over_since: None,
+            under_since: None,
+            quality: Quality::Full,
+        }
+    }
+
+    pub fn quality(&self) -> Quality {
+        self.quality
+    }
+
+    /// Feeds the time one frame took to compose. Returns the new quality when it changes.
+    pub fn update(&mut self, elapsed: Duration, now: Instant) -> Option<Quality> {
+        let ms = elapsed.as_secs_f32() * 1000.0;
+        let average = match self.average {
+            Some(average) => average + (ms - average) * SMOOTHING,
+            None => ms,
+        };
+        self.average = Some(average);
+
+        let over = average > self.budget;
+        let under = average < self.recover_below;
+        if !over {
+            self.over_since = None;
+        }
+        if !under {
+            self.under_since = None;
+        }
+        let next = if over {
+            let since = *self.over_since.get_or_insert(now);
+            (now.duration_since(since) >= self.degrade_after).then(|| self.quality.lower())
+        } else if under {
+            let since = *self.under_since.get_or_insert(now);
+            (now.duration_since(since) >= self.recover_after).then(|| self.quality.higher())
+        } else {
+            None
+        };
+        let next = next.filter(|next| *next != self.quality)?;
+
+        // 変えたら、また一定の時間を見てから判断する
+        self.over_since = None;
+        self.under_since = None;
+        if next > self.quality {
+            tracing::warn!(
+                frame_ms = average,
+                "{}",
+                t!(
+                    "quality.reduced",
+                    format!("{next:?}"),
+                    format!("{average:.1}")
+                )
+            );
+        } else {
+            tracing::info!(
+                frame_ms = average,
+                "{}",
+                t!("quality.restored", format!("{next:?}"))
+            );
+        }
+        self.quality = next;
+        Some(next)
+    }
+}
@@ -0,0 +1,86 @@
+// 外部サービス（配信の通知、MQTT）から始まるシーケンスのクールダウンと流量制限
+use crate::{config::RateLimitConfig, t};
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+use winit::keyboard::KeyCode;
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Queue of triggered sequences. Repeats within a sequence's cooldown are dropped, and the
+/// queued ones are handed out one at a time, no faster than the per-minute limit.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    // シーケンスごとの、最後に受け付けた時刻
+    accepted: HashMap<String, Instant>,
+    // 直近1分に始めた時刻
+    started: VecDeque<Instant>,
+    queue: VecDeque<String>,
+}
+
+impl RateLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            config: config.clone(),
+            accepted: HashMap::new(),
+            started: VecDeque::new(),
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Queues `name` unless it is cooling down or the queue is full.
+    pub fn push(&mut self, name: String, now: Instant) {
+        let cooldown = self
+            .config
+            .cooldowns
+            .get(&name)
+            .copied()
+            .unwrap_or(self.config.cooldown);
+        // 長すぎて Duration に入らない待ち時間は、ずっと待つのと同じ
+        let cooldown = Duration::try_from_secs_f32(cooldown.max(0.0)).unwrap_or(Duration::MAX);
+        if let Some(last) = self.accepted.get(&name)
+            && now.saturating_duration_since(*last) < cooldown
+        {
+            tracing::debug!("{}", t!("rate_limit.cooling_down", name));
+            return;
+        }
+        if self.queue.len() >= self.config.queue {
+            tracing::info!("{}", t!("rate_limit.queue_full", name));
+            return;
+        }
+        self.accepted.insert(name.clone(), now);
+        self.queue.push_back(name);
+    }
+
+    pub fn is_skip_hotkey(&self, keycode: KeyCode) -> bool {
+        self.config.skip_hotkey.as_deref() == Some(format!("{keycode:?}").as_str())
+    }
+
+    /// Empties the queue and returns how many were waiting.
+    pub fn clear(&mut self) -> usize {
+        let waiting = self.queue.len();
+        self.queue.clear();
+        waiting
+    }
+
+    /// The next queued sequence, if the per-minute limit allows starting one now. Call it
+    /// only when nothing is playing, so queued sequences play back to back.
+    pub fn next(&mut self, now: Instant) -> Option<String> {
+        while self
+            .started
+            .front()
+            .is_some_and(|started| now.saturating_duration_since(*started) >= WINDOW)
+        {
+            self.started.pop_front();
+        }
+        if let Some(limit) = self.config.per_minute
+            && self.started.len() >= limit as usize
+        {
+            return None;
+        }
+        let name = self.queue.pop_front()?;
+        self.started.push_back(now);
+        Some(name)
+    }
+}
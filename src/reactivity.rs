@@ -0,0 +1,264 @@
+// 音量から口の状態を決める状態機械（時刻は呼び出し側が渡すので、そのまま試験できる）
+use crate::{avatar::Mouth, config::AudioConfig};
+use std::{
+    sync::atomic::{AtomicU32, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// A change of mouth state, stamped with the time of the sample that caused it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transition {
+    pub from: Mouth,
+    pub to: Mouth,
+    pub at: Instant,
+}
+
+/// Thresholds shared between the capture threads and the tuning mode, which changes them
+/// while the app runs. A whisper threshold of zero means no whisper state.
+#[derive(Debug, Default)]
+pub struct Thresholds {
+    threshold: AtomicU32,
+    whisper: AtomicU32,
+    whisper_hold_ms: AtomicU64,
+}
+
+impl Thresholds {
+    pub fn new(audio: &AudioConfig) -> Self {
+        let thresholds = Self::default();
+        thresholds.set(
+            audio.threshold,
+            audio.whisper_threshold,
+            audio.whisper_hold_ms,
+        );
+        thresholds
+    }
+
+    pub fn set(&self, threshold: f32, whisper: Option<f32>, whisper_hold_ms: u64) {
+        self.threshold
+            .store(threshold.max(0.0).to_bits(), Ordering::Relaxed);
+        self.whisper
+            .store(whisper.unwrap_or(0.0).max(0.0).to_bits(), Ordering::Relaxed);
+        self.whisper_hold_ms
+            .store(whisper_hold_ms, Ordering::Relaxed);
+    }
+
+    /// The talking threshold, the whisper threshold and the whisper hold time.
+    pub fn get(&self) -> (f32, Option<f32>, u64) {
+        let whisper = f32::from_bits(self.whisper.load(Ordering::Relaxed));
+        (
+            f32::from_bits(self.threshold.load(Ordering::Relaxed)),
+            (whisper > 0.0).then_some(whisper),
+            self.whisper_hold_ms.load(Ordering::Relaxed),
+        )
+    }
+}
+
+/// Turns timestamped input levels into mouth states: thresholds classify each level and a
+/// whisper is held for a while after the level drops so the mouth doesn't flicker shut.
+pub struct ReactivityStateMachine {
+    audio: AudioConfig,
+    whisper_hold: Duration,
+    state: Mouth,
+    last_whisper: Option<Instant>,
+}
+
+impl ReactivityStateMachine {
+    pub fn new(audio: &AudioConfig) -> Self {
+        Self {
+            audio: audio.clone(),
+            whisper_hold: Duration::from_millis(audio.whisper_hold_ms),
+            state: Mouth::Idle,
+            last_whisper: None,
+        }
+    }
+
+    /// Picks up thresholds changed while running.
+    pub fn retune(&mut self, thresholds: &Thresholds) {
+        let (threshold, whisper, hold_ms) = thresholds.get();
+        self.audio.threshold = threshold;
+        self.audio.whisper_threshold = whisper;
+        self.whisper_hold = Duration::from_millis(hold_ms);
+    }
+
+    /// Feeds one level sample taken at `at`; returns the transition if the state changed.
+    pub fn update(&mut self, level: f32, at: Instant) -> Option<Transition> {
+        let next = match Mouth::from_level(level, &self.audio) {
+            Mouth::Whisper => {
+                self.last_whisper = Some(at);
+                Mouth::Whisper
+            }
+            // 小声が途切れてもしばらくはささやき状態のまま
+            Mouth::Idle
+                if self.state == Mouth::Whisper
+                    && self
+                        .last_whisper
+                        .is_some_and(|t| at.saturating_duration_since(t) < self.whisper_hold) =>
+            {
+                Mouth::Whisper
+            }
+            mouth => mouth,
+        };
+
+        let from = std::mem::replace(&mut self.state, next);
+        (from != next).then_some(Transition { from, to: next, at })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Mouth::{Idle, Talking, Whisper};
+    use proptest::prelude::*;
+
+    struct Case {
+        name: &'static str,
+        whisper_threshold: Option<f32>,
+        whisper_hold_ms: u64,
+        // (ミリ秒, 音量)
+        samples: &'static [(u64, f32)],
+        // (ミリ秒, 変化前, 変化後)
+        expected: &'static [(u64, Mouth, Mouth)],
+    }
+
+    const THRESHOLD: f32 = 0.1;
+
+    const CASES: &[Case] = &[
+        Case {
+            name: "silence stays idle",
+            whisper_threshold: None,
+            whisper_hold_ms: 150,
+            samples: &[(0, 0.0), (10, 0.05), (20, 0.1)],
+            expected: &[],
+        },
+        Case {
+            name: "talking follows the threshold without hold",
+            whisper_threshold: None,
+            whisper_hold_ms: 150,
+            samples: &[(0, 0.0), (10, 0.2), (20, 0.3), (30, 0.05), (40, 0.2)],
+            expected: &[
+                (10, Idle, Talking),
+                (30, Talking, Idle),
+                (40, Idle, Talking),
+            ],
+        },
+        Case {
+            name: "whisper band between the thresholds",
+            whisper_threshold: Some(0.02),
+            whisper_hold_ms: 0,
+            samples: &[(0, 0.05), (10, 0.2), (20, 0.05), (30, 0.01)],
+            expected: &[
+                (0, Idle, Whisper),
+                (10, Whisper, Talking),
+                (20, Talking, Whisper),
+                (30, Whisper, Idle),
+            ],
+        },
+        Case {
+            name: "whisper is held after the level drops",
+            whisper_threshold: Some(0.02),
+            whisper_hold_ms: 150,
+            samples: &[(0, 0.05), (100, 0.0), (149, 0.0), (150, 0.0)],
+            expected: &[(0, Idle, Whisper), (150, Whisper, Idle)],
+        },
+        Case {
+            name: "hold restarts with every whisper sample",
+            whisper_threshold: Some(0.02),
+            whisper_hold_ms: 150,
+            samples: &[(0, 0.05), (100, 0.05), (200, 0.0), (249, 0.0), (250, 0.0)],
+            expected: &[(0, Idle, Whisper), (250, Whisper, Idle)],
+        },
+        Case {
+            name: "no whisper hold out of talking",
+            whisper_threshold: Some(0.02),
+            whisper_hold_ms: 150,
+            samples: &[(0, 0.05), (10, 0.2), (20, 0.0)],
+            expected: &[
+                (0, Idle, Whisper),
+                (10, Whisper, Talking),
+                (20, Talking, Idle),
+            ],
+        },
+        Case {
+            name: "levels equal to a threshold do not cross it",
+            whisper_threshold: Some(0.02),
+            whisper_hold_ms: 0,
+            samples: &[(0, 0.02), (10, THRESHOLD)],
+            expected: &[(10, Idle, Whisper)],
+        },
+    ];
+
+    #[test]
+    fn table() {
+        let start = Instant::now();
+        for case in CASES {
+            let audio = AudioConfig {
+                threshold: THRESHOLD,
+                whisper_threshold: case.whisper_threshold,
+                whisper_hold_ms: case.whisper_hold_ms,
+                ..Default::default()
+            };
+            let mut machine = ReactivityStateMachine::new(&audio);
+            let at = |ms: u64| start + Duration::from_millis(ms);
+
+            let transitions: Vec<Transition> = case
+                .samples
+                .iter()
+                .filter_map(|&(ms, level)| machine.update(level, at(ms)))
+                .collect();
+            let expected: Vec<Transition> = case
+                .expected
+                .iter()
+                .map(|&(ms, from, to)| Transition {
+                    from,
+                    to,
+                    at: at(ms),
+                })
+                .collect();
+            assert_eq!(transitions, expected, "{}", case.name);
+        }
+    }
+
+    proptest! {
+        // ささやきが終わるのは、最後にささやきの音量が来てから保持時間が過ぎた後だけ。
+        // 変化は必ず直前の状態から始まる
+        #[test]
+        fn whisper_is_never_released_before_the_hold(
+            threshold in 0.01f32..0.5,
+            whisper_ratio in prop::option::of(0.05f32..0.95),
+            hold_ms in 0u64..500,
+            // (前のサンプルからのミリ秒, 音量)
+            samples in prop::collection::vec((0u64..100, prop_oneof![Just(0.0f32), 0.0f32..1.0]), 0..200),
+        ) {
+            let audio = AudioConfig {
+                threshold,
+                whisper_threshold: whisper_ratio.map(|r| threshold * r),
+                whisper_hold_ms: hold_ms,
+                ..Default::default()
+            };
+            let hold = Duration::from_millis(hold_ms);
+            let mut machine = ReactivityStateMachine::new(&audio);
+            let start = Instant::now();
+            let (mut ms, mut state, mut last_whisper) = (0, Idle, None);
+            for (gap, level) in samples {
+                ms += gap;
+                let at = start + Duration::from_millis(ms);
+                if Mouth::from_level(level, &audio) == Whisper {
+                    last_whisper = Some(at);
+                }
+                let Some(transition) = machine.update(level, at) else {
+                    continue;
+                };
+                prop_assert_eq!(transition.from, state);
+                prop_assert_ne!(transition.to, state);
+                prop_assert_eq!(transition.at, at);
+                // 無音で起きる変化は口を閉じることだけ
+                prop_assert!(level > 0.0 || transition.to == Idle);
+                if transition.from == Whisper && transition.to == Idle {
+                    let since = at.saturating_duration_since(last_whisper.unwrap());
+                    prop_assert!(since >= hold, "released after {since:?}");
+                }
+                state = transition.to;
+            }
+        }
+    }
+}
@@ -0,0 +1,67 @@
+// 解析の参照にする2つ目の入力（メインのキャプチャと同じ解析レートのモノラルにして渡す）
+use crate::{
+    resample::{Resampler, downmix},
+    ring, t,
+};
+use anyhow::{Context, Result};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+/// Receiving side of a reference input, owned by the main capture's analysis thread.
+pub struct Reference {
+    consumer: ring::Consumer,
+    channels: usize,
+    resampler: Resampler,
+    data: Vec<f32>,
+    mono: Vec<f32>,
+}
+
+impl Reference {
+    /// Appends everything captured since the last call to `output`.
+    pub fn read(&mut self, output: &mut Vec<f32>) {
+        self.data.clear();
+        self.mono.clear();
+        self.consumer.read(&mut self.data);
+        downmix(&self.data, self.channels, &mut self.mono);
+        self.resampler.process(&self.mono, output);
+    }
+}
+
+/// Starts capturing `input` resampled to `rate`. Without `input`, captures the loopback device
+/// if `loopback` is set and the default input device otherwise. The stream must be kept alive
+/// on the capture thread.
+pub fn start(input: Option<&str>, loopback: bool, rate: u32) -> Result<(cpal::Stream, Reference)> {
+    let host = crate::host::host();
+    let device = match input {
+        Some(name) => crate::audio::find_input_device(&host, name),
+        None if loopback => crate::audio::find_loopback_device(),
+        None => host.default_input_device(),
+    }
+    .context(t!("audio.no_device"))?;
+    let config = device.default_input_config()?;
+    let channels = config.channels() as usize;
+    let resampler = Resampler::new(config.sample_rate().0, rate);
+
+    // 解析側が止まっても溜め込まないように、1秒分を超えたら捨てる
+    let (producer, consumer) = ring::channel(config.sample_rate().0 as usize * channels);
+    let stream = device.build_input_stream(
+        &config.into(),
+        move |data: &[f32], _: &cpal::InputCallbackInfo| producer.push(data),
+        |err| tracing::error!("{}", t!("audio.stream_error", err)),
+        None,
+    )?;
+    stream.play()?;
+
+    if let Ok(name) = device.name() {
+        tracing::info!(device = %name, "{}", t!("reference.started", name));
+    }
+    Ok((
+        stream,
+        Reference {
+            consumer,
+            channels,
+            resampler,
+            data: Vec::new(),
+            mono: Vec::new(),
+        },
+    ))
+}
@@ -0,0 +1,534 @@
+// スマートフォンをリモコンにする: 大きなボタンのページと、それが呼ぶ API（HTTP）
+//   GET  /                        ボタンのページ（何もインストールせずにブラウザで開く）
+//   GET  /api/status              今の表情・口の状態・ミュートと、選べる表情・シーケンス（JSON）
+//   POST /api/expression?name=    表情を選ぶ（選んでいる表情ならデフォルトに戻す。name なしでも戻す）
+//   POST /api/sequence?name=      シーケンスを再生
+//   POST /api/mute                ミュートの切り替え
+//   POST /api/viewers[?enabled=on|off]  視聴者のリアクションを受け付けるかの切り替え
+// 視聴者のリアクション（/viewer/）は別の入口で、リモコンのトークンでは通さない（viewers を参照）
+//   GET  /viewer/reactions        受け付けているか、と選べるリアクション（JSON）
+//   POST /viewer/react?name=&user=  リアクションを送る（user は視聴者の名前）
+// アドレスは QR コードにしてウィンドウ（出力には入れない）と端末に出し、カメラで読ませる。
+// どのリクエストにもトークン（Authorization: Bearer か ?token=）が要る。QR コードの URL に入れておき、
+// ページはそれを API に付けて送る。
+use crate::{
+    bus::{self, Bus},
+    compose,
+    config::{ExpressionSource, RemoteConfig},
+    dirty::Rect,
+    http::{self, Request},
+    ipc::Status,
+    qr::QrCode,
+    t,
+    viewers::Viewers,
+};
+use anyhow::{Context, Result};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use serde::Serialize;
+use std::{
+    io::{ErrorKind, IsTerminal, Read, Write},
+    net::{IpAddr, SocketAddr, TcpListener, TcpStream, UdpSocket},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+use winit::keyboard::KeyCode;
+
+// 止めるときに待たせないよう、この間隔で止める合図を見る
+const POLL: Duration = Duration::from_millis(200);
+// リクエストを送ってこない接続を切るまで
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+// 同時に相手をする接続の数
+const MAX_CONNECTIONS: usize = 32;
+// QR コードの周りに空ける白い余白（モジュール数）
+const QUIET_ZONE: usize = 4;
+
+const PAGE: &str = r#"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Darwin</title>
+<style>
+body { margin: 0; padding: 12px; font-family: system-ui, sans-serif; background: #18181b; color: #eee; }
+h2 { margin: 20px 4px 8px; font-size: 14px; font-weight: normal; opacity: 0.7; }
+.grid { display: grid; grid-template-columns: repeat(auto-fill, minmax(140px, 1fr)); gap: 10px; }
+button { padding: 24px 8px; border: 0; border-radius: 14px; font-size: 20px; background: #34343a; color: #eee; }
+button.on { background: #4f7cff; color: #fff; }
+#mute { width: 100%; }
+#mute.on { background: #e04848; }
+#viewers { width: 100%; margin-top: 10px; }
+#offline { margin: 20px 4px; opacity: 0.7; }
+</style>
+</head>
+<body>
+<button id="mute"></button>
+<button id="viewers" hidden></button>
+<h2 id="expressions-title"></h2>
+<div class="grid" id="expressions"></div>
+<h2 id="sequences-title"></h2>
+<div class="grid" id="sequences"></div>
+<div id="offline" hidden></div>
+<script>
+const LABELS = __LABELS__;
+const AUTH = { Authorization: "Bearer " + (new URLSearchParams(location.search).get("token") || "") };
+const $ = (id) => document.getElementById(id);
+$("expressions-title").textContent = LABELS.expressions;
+$("sequences-title").textContent = LABELS.sequences;
+let shown = "";
+async function send(path) {
+  await fetch(path, { method: "POST", headers: AUTH }).catch(() => {});
+  refresh();
+}
+function button(label, on, path) {
+  const b = document.createElement("button");
+  b.textContent = label;
+  b.className = on ? "on" : "";
+  b.onclick = () => send(path);
+  return b;
+}
+async function refresh() {
+  let status;
+  try {
+    const response = await fetch("/api/status", { headers: AUTH });
+    if (response.status === 401) {
+      $("offline").textContent = LABELS.unpaired;
+      $("offline").hidden = false;
+      return;
+    }
+    status = await response.json();
+  } catch {
+    $("offline").textContent = LABELS.offline;
+    $("offline").hidden = false;
+    return;
+  }
+  $("offline").hidden = true;
+  const key = JSON.stringify(status);
+  if (key === shown) return;
+  shown = key;
+  $("mute").textContent = status.muted ? LABELS.unmute : LABELS.mute;
+  $("mute").className = status.muted ? "on" : "";
+  $("viewers").hidden = status.viewers === null;
+  $("viewers").textContent = status.viewers ? LABELS.viewers_on : LABELS.viewers_off;
+  $("viewers").className = status.viewers ? "on" : "";
+  $("expressions").replaceChildren(...status.expressions.map((name) =>
+    button(name, name === status.expression, "/api/expression?name=" + encodeURIComponent(name))));
+  $("sequences").replaceChildren(...status.sequences.map((name) =>
+    button(name, false, "/api/sequence?name=" + encodeURIComponent(name))));
+}
+$("mute").onclick = () => send("/api/mute");
+$("viewers").onclick = () => send("/api/viewers");
+refresh();
+setInterval(refresh, 1000);
+</script>
+</body>
+</html>
+"#;
+
+// トークンのバイト数（URL には base64 で入れる）
+const TOKEN_BYTES: usize = 16;
+
+struct Shared {
+    status: Mutex<Status>,
+    page: String,
+    token: String,
+    tls: Option<Arc<ServerConfig>>,
+    viewers: Option<Viewers>,
+    stop: AtomicBool,
+}
+
+// /api/status の応答。視聴者の入口が無ければ viewers は null
+#[derive(Serialize)]
+struct RemoteStatus<'a> {
+    #[serde(flatten)]
+    status: &'a Status,
+    viewers: Option<bool>,
+}
+
+/// Serves the phone remote on a thread of its own and publishes its buttons to the bus.
+pub struct Remote {
+    shared: Arc<Shared>,
+    url: String,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Remote {
+    /// Paths in `config.tls` must already be resolved.
+    pub fn start(config: &RemoteConfig, bus: Bus) -> Result<Self> {
+        let tls = config
+            .tls
+            .as_ref()
+            .map(|tls| http::tls_config(&tls.cert, &tls.key))
+            .transpose()?;
+        let token = match &config.token {
+            Some(token) => token.clone(),
+            None => new_token()?,
+        };
+        let listener =
+            TcpListener::bind(&config.bind).with_context(|| t!("web.bind_failed", &config.bind))?;
+        listener.set_nonblocking(true)?;
+        let scheme = if tls.is_some() { "https" } else { "http" };
+        let address = format!("{scheme}://{}/", reachable(listener.local_addr()?));
+        let labels = serde_json::json!({
+            "mute": t!("remote.mute"),
+            "unmute": t!("remote.unmute"),
+            "expressions": t!("remote.expressions"),
+            "sequences": t!("remote.sequences"),
+            "offline": t!("remote.offline"),
+            "unpaired": t!("remote.unpaired"),
+            "viewers_on": t!("remote.viewers_on"),
+            "viewers_off": t!("remote.viewers_off"),
+        });
+        // トークンは記録に残さず、QR コードにだけ入れる
+        tracing::info!("{}", t!("remote.started", address));
+        let url = format!("{address}?token={token}");
+        let shared = Arc::new(Shared {
+            status: Mutex::new(Status::default()),
+            page: PAGE.replace("__LABELS__", &labels.to_string()),
+            token,
+            tls,
+            viewers: config.viewers.as_ref().map(Viewers::new),
+            stop: AtomicBool::new(false),
+        });
+        // 端末から起動したときは、その場で読めるように QR コードも出す
+        if std::io::stderr().is_terminal()
+            && let Some(code) = QrCode::encode(url.as_bytes())
+        {
+            eprint!("{}", code.to_terminal());
+        }
+
+        let thread = {
+            let shared = shared.clone();
+            let connections = http::Connections::new(MAX_CONNECTIONS);
+            std::thread::spawn(move || {
+                while !shared.stop.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            // 埋まっていれば何も返さずに閉じる
+                            let Some(connection) = connections.enter() else {
+                                tracing::debug!("http: too many connections");
+                                continue;
+                            };
+                            let shared = shared.clone();
+                            let bus = bus.clone();
+                            std::thread::spawn(move || {
+                                let _connection = connection;
+                                if let Err(e) = accept(stream, &shared, &bus) {
+                                    tracing::debug!("http: {e}");
+                                }
+                            });
+                        }
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(POLL),
+                        Err(e) => {
+                            tracing::debug!("http: {e}");
+                            std::thread::sleep(POLL);
+                        }
+                    }
+                }
+            })
+        };
+        Ok(Self {
+            shared,
+            url,
+            thread: Some(thread),
+        })
+    }
+
+    /// The address to open on the phone, token included.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn set_status(&self, status: Status) {
+        *self.shared.status.lock().unwrap() = status;
+    }
+}
+
+impl Drop for Remote {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// すべてのアドレスで待ち受けているときは、LAN 側のアドレスを URL にする。
+// UDP は connect しても何も送らないので、経路だけを調べられる
+fn reachable(local: SocketAddr) -> SocketAddr {
+    if !local.ip().is_unspecified() {
+        return local;
+    }
+    let lan = UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("192.0.2.1:80")?;
+            socket.local_addr()
+        })
+        .map(|addr| addr.ip())
+        .unwrap_or(IpAddr::from([127, 0, 0, 1]));
+    SocketAddr::new(lan, local.port())
+}
+
+// 推測されないトークンを新しく作る
+fn new_token() -> Result<String> {
+    let mut bytes = [0u8; TOKEN_BYTES];
+    rustls::crypto::ring::default_provider()
+        .secure_random
+        .fill(&mut bytes)
+        .ok()
+        .context(t!("remote.no_random"))?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+fn accept(mut stream: TcpStream, shared: &Shared, bus: &Bus) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    match &shared.tls {
+        Some(tls) => {
+            let connection = ServerConnection::new(tls.clone()).map_err(std::io::Error::other)?;
+            let mut stream = StreamOwned::new(connection, stream);
+            serve(&mut stream, shared, bus)?;
+            // 閉じたことを相手に伝えてから切る
+            stream.conn.send_close_notify();
+            stream.flush()
+        }
+        None => serve(&mut stream, shared, bus),
+    }
+}
+
+// Authorization: Bearer のトークン。ヘッダーを付けられないときは ?token= でもよい
+fn bearer(request: &Request) -> Option<String> {
+    request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string)
+        .or_else(|| request.param("token"))
+}
+
+fn serve(stream: &mut (impl Read + Write), shared: &Shared, bus: &Bus) -> std::io::Result<()> {
+    let request = Request::read(stream)?;
+    if let Some(action) = request.path.strip_prefix("/viewer/") {
+        return serve_viewer(stream, &request, action, shared, bus);
+    }
+    if !bearer(&request).is_some_and(|token| http::same_secret(&token, &shared.token)) {
+        tracing::debug!(path = request.path, "{}", t!("remote.unauthorized"));
+        return http::respond(
+            stream,
+            "401 Unauthorized",
+            "text/plain; charset=utf-8",
+            b"unauthorized",
+        );
+    }
+    let status = shared.status.lock().unwrap().clone();
+    let known = |names: &[String], name: &str| names.iter().any(|n| n == name);
+
+    let event = match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => {
+            return http::respond(
+                stream,
+                "200 OK",
+                "text/html; charset=utf-8",
+                shared.page.as_bytes(),
+            );
+        }
+        ("GET", "/api/status") => {
+            let body = RemoteStatus {
+                status: &status,
+                viewers: shared.viewers.as_ref().map(Viewers::is_enabled),
+            };
+            let body = serde_json::to_vec(&body).unwrap_or_default();
+            return http::respond(stream, "200 OK", "application/json", &body);
+        }
+        ("POST", "/api/expression") => match request.param("name").filter(|n| !n.is_empty()) {
+            None => Some(bus::Event::Expression(ExpressionSource::Manual, None)),
+            Some(name) if known(&status.expressions, &name) => {
+                Some(bus::Event::ToggleExpression(name))
+            }
+            Some(_) => None,
+        },
+        ("POST", "/api/sequence") => request
+            .param("name")
+            .filter(|name| known(&status.sequences, name))
+            .map(bus::Event::Sequence),
+        ("POST", "/api/mute") => Some(bus::Event::Mute(None)),
+        ("POST", "/api/viewers") if shared.viewers.is_some() => {
+            let viewers = shared.viewers.as_ref().unwrap();
+            let enabled = match request.param("enabled").as_deref() {
+                Some("on") => true,
+                Some("off") => false,
+                _ => !viewers.is_enabled(),
+            };
+            viewers.set_enabled(enabled);
+            if enabled {
+                tracing::info!("{}", t!("viewers.enabled"));
+            } else {
+                tracing::info!("{}", t!("viewers.paused"));
+            }
+            return http::respond(stream, "200 OK", "text/plain; charset=utf-8", b"ok");
+        }
+        _ => {
+            return http::respond(
+                stream,
+                "404 Not Found",
+                "text/plain; charset=utf-8",
+                b"not found",
+            );
+        }
+    };
+    match event {
+        Some(event) => {
+            tracing::debug!(path = request.path, "{}", t!("ipc.received"));
+            bus.publish(event);
+            http::respond(stream, "200 OK", "text/plain; charset=utf-8", b"ok")
+        }
+        None => http::respond(
+            stream,
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            b"unknown name",
+        ),
+    }
+}
+
+// 視聴者の入口。独自のトークン（あれば）で通し、許可と回数制限は Viewers に任せる
+fn serve_viewer(
+    stream: &mut impl Write,
+    request: &Request,
+    action: &str,
+    shared: &Shared,
+    bus: &Bus,
+) -> std::io::Result<()> {
+    let Some(viewers) = &shared.viewers else {
+        return http::respond(
+            stream,
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            b"not found",
+        );
+    };
+    if let Some(expected) = viewers.token()
+        && !bearer(request).is_some_and(|token| http::same_secret(&token, expected))
+    {
+        return http::respond(
+            stream,
+            "401 Unauthorized",
+            "text/plain; charset=utf-8",
+            b"unauthorized",
+        );
+    }
+    match (request.method.as_str(), action) {
+        ("GET", "reactions") => {
+            let body = serde_json::json!({
+                "enabled": viewers.is_enabled(),
+                "reactions": viewers.reactions(),
+            });
+            http::respond(
+                stream,
+                "200 OK",
+                "application/json",
+                body.to_string().as_bytes(),
+            )
+        }
+        ("POST", "react") => {
+            let name = request.param("name").unwrap_or_default();
+            let user = request.param("user").unwrap_or_default();
+            match viewers.admit(&name, &user, Instant::now()) {
+                Ok(()) => {
+                    tracing::info!("{}", t!("viewers.reacted", user, name));
+                    bus.publish(bus::Event::Trigger(name));
+                    http::respond(stream, "200 OK", "text/plain; charset=utf-8", b"ok")
+                }
+                Err(refusal) => {
+                    tracing::debug!(?refusal, user, name, "{}", t!("viewers.refused"));
+                    http::respond(
+                        stream,
+                        refusal.status(),
+                        "text/plain; charset=utf-8",
+                        format!("{refusal:?}").as_bytes(),
+                    )
+                }
+            }
+        }
+        _ => http::respond(
+            stream,
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            b"not found",
+        ),
+    }
+}
+
+/// The remote's address as a QR code, shown over the main window (not the stream output)
+/// while its hotkey is toggled on.
+pub struct Pairing {
+    hotkey: Option<String>,
+    visible: bool,
+    canvas_width: usize,
+    canvas_height: usize,
+    rect: Rect,
+    // 乗算済みの RGBA（白黒なので不透明）
+    pixels: Vec<u8>,
+}
+
+impl Pairing {
+    /// Renders the QR code for `url` as large as fits a `width` × `height` window. `None`
+    /// when the address is too long for a QR code or the window too small.
+    pub fn new(url: &str, hotkey: Option<&str>, width: usize, height: usize) -> Option<Self> {
+        let code = QrCode::encode(url.as_bytes())?;
+        let modules = code.size() + QUIET_ZONE * 2;
+        // 整数倍にして、モジュールの境目をぼかさない
+        let scale = width.min(height) * 2 / 3 / modules;
+        if scale == 0 {
+            return None;
+        }
+        let side = modules * scale;
+        let mut pixels = Vec::with_capacity(side * side * 4);
+        for y in 0..side {
+            for x in 0..side {
+                let (mx, my) = (x / scale, y / scale);
+                let dark = code.get(mx.wrapping_sub(QUIET_ZONE), my.wrapping_sub(QUIET_ZONE));
+                let value = if dark { 0 } else { 255 };
+                pixels.extend_from_slice(&[value, value, value, 255]);
+            }
+        }
+        Some(Self {
+            hotkey: hotkey.map(str::to_string),
+            visible: false,
+            canvas_width: width,
+            canvas_height: height,
+            rect: Rect {
+                x: (width - side) / 2,
+                y: (height - side) / 2,
+                width: side,
+                height: side,
+            },
+            pixels,
+        })
+    }
+
+    pub fn is_hotkey(&self, keycode: KeyCode) -> bool {
+        self.hotkey.as_deref() == Some(format!("{keycode:?}").as_str())
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Draws the code where it overlaps `clip`, if it is shown.
+    pub fn draw(&self, dst: &mut [u8], clip: Rect) {
+        if self.visible {
+            compose::draw_layer(
+                dst,
+                self.canvas_width,
+                self.canvas_height,
+                self.rect,
+                &self.pixels,
+                clip,
+            );
+        }
+    }
+}
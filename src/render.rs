@@ -0,0 +1,316 @@
+use crate::{
+    config::{GfxBackend, GpuConfig, PowerPreference},
+    dirty::{self, Rect},
+    sink::{FrameSink, Timestamp},
+    t,
+};
+use anyhow::{Result, bail};
+use pixels::{Pixels, PixelsBuilder, SurfaceTexture, wgpu};
+use std::sync::{
+    Arc, OnceLock,
+    atomic::{AtomicBool, Ordering},
+};
+use winit::window::Window;
+
+// 連続してこの回数だけ再作成に失敗したら諦める
+const MAX_RECOVERY_ATTEMPTS: u32 = 5;
+
+// すべてのウィンドウで使う GPU の選び方
+static GPU: OnceLock<GpuConfig> = OnceLock::new();
+// 順に試すグラフィックス API（どれも使えなければ wgpu に任せる）
+static BACKENDS: OnceLock<Vec<GfxBackend>> = OnceLock::new();
+// WGPU_ADAPTER_NAME に設定したアダプター名
+static ADAPTER: OnceLock<String> = OnceLock::new();
+
+/// Picks the GPU for every window from now on. It sets an environment variable, so it must
+/// be called before any thread starts; only the first call counts, and backends already
+/// chosen with [`select_backends`] win over the config's. Returns the configured adapter
+/// name when no GPU matches it.
+pub fn select_gpu(config: &GpuConfig) -> Option<String> {
+    if GPU.set(config.clone()).is_err() {
+        return None;
+    }
+    if !config.backends.is_empty() {
+        select_backends(config.backends.clone());
+    }
+    let name = config.adapter.as_ref()?;
+    // pixels は環境変数でしかアダプターを名前で選べず、合うものが無いと wgpu がパニックするので
+    // 先に確かめる
+    if !has_adapter(wgpu::Backends::all(), name) {
+        return Some(name.clone());
+    }
+    // SAFETY: main の最初、ログやほかのスレッドを始める前の一度だけ呼ばれる
+    unsafe { std::env::set_var("WGPU_ADAPTER_NAME", name) };
+    let _ = ADAPTER.set(name.clone());
+    None
+}
+
+/// Tries these graphics APIs in order for every window from now on, then whatever wgpu
+/// picks. Only the first call counts.
+pub fn select_backends(backends: Vec<GfxBackend>) {
+    let _ = BACKENDS.set(backends);
+}
+
+fn wgpu_backends(backend: GfxBackend) -> wgpu::Backends {
+    match backend {
+        GfxBackend::Vulkan => wgpu::Backends::VULKAN,
+        GfxBackend::Metal => wgpu::Backends::METAL,
+        GfxBackend::Dx12 => wgpu::Backends::DX12,
+        GfxBackend::Gl => wgpu::Backends::GL,
+    }
+}
+
+/// Every GPU adapter wgpu can draw with through `backends`.
+pub fn adapters(backends: wgpu::Backends) -> Vec<wgpu::AdapterInfo> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        ..Default::default()
+    });
+    instance
+        .enumerate_adapters(backends)
+        .map(|adapter| adapter.get_info())
+        .collect()
+}
+
+// wgpu と同じく、名前の一部が大文字小文字を区別せずに合えばよい
+fn has_adapter(backends: wgpu::Backends, name: &str) -> bool {
+    let name = name.to_lowercase();
+    adapters(backends)
+        .iter()
+        .any(|info| info.name.to_lowercase().contains(&name))
+}
+
+/// Lists the adapters (`darwin gpus`).
+pub fn print_adapters() {
+    let adapters = adapters(wgpu::Backends::all());
+    if adapters.is_empty() {
+        println!("{}", t!("render.no_adapters"));
+    }
+    for info in adapters {
+        println!(
+            "{}",
+            t!(
+                "render.adapter_line",
+                info.name,
+                format!("{:?}", info.device_type),
+                format!("{:?}", info.backend)
+            )
+        );
+    }
+}
+
+/// Owns the pixels/wgpu context and rebuilds it when the surface or device is lost.
+pub struct Renderer {
+    pixels: Option<Pixels>,
+    width: u32,
+    height: u32,
+    gpu_error: Arc<AtomicBool>,
+    failures: u32,
+    // 作り直したばかりのフレームは空なので、全体を描き直してもらう
+    reset: bool,
+    // 前回画面に出してからフレームが変わったか。変わっていなければアップロードも描画もしない
+    stale: bool,
+    transparent: bool,
+}
+
+impl Renderer {
+    pub fn new(window: &Window, width: u32, height: u32) -> Result<Self> {
+        let gpu_error = Arc::new(AtomicBool::new(false));
+        let pixels = create_pixels(window, width, height, &gpu_error)?;
+        Ok(Self {
+            pixels: Some(pixels),
+            width,
+            height,
+            gpu_error,
+            failures: 0,
+            reset: true,
+            stale: true,
+            transparent: false,
+        })
+    }
+
+    /// Clears to transparent instead of black around the frame, for borderless windows that
+    /// show the desktop behind them. Whether it shows through depends on the platform.
+    pub fn set_transparent(&mut self) {
+        self.transparent = true;
+        if let Some(pixels) = &mut self.pixels {
+            pixels.clear_color(wgpu::Color::TRANSPARENT);
+        }
+    }
+
+    /// True once after the frame buffer was (re)created and holds nothing yet.
+    pub fn take_reset(&mut self) -> bool {
+        std::mem::take(&mut self.reset)
+    }
+
+    /// The frame buffer, to draw into; the next [`Renderer::render`] shows it.
+    pub fn frame_mut(&mut self) -> Option<&mut [u8]> {
+        self.stale = true;
+        self.pixels.as_mut().map(|p| p.frame_mut())
+    }
+
+    pub fn resize_surface(&mut self, width: u32, height: u32) -> Result<()> {
+        if let Some(pixels) = &mut self.pixels {
+            pixels.resize_surface(width, height)?;
+        }
+        self.stale = true;
+        Ok(())
+    }
+
+    /// Presents the frame, recreating the context on surface or device loss. Does nothing
+    /// when the frame hasn't been touched since it was last shown.
+    /// Only returns an error once recovery has failed repeatedly.
+    pub fn render(&mut self, window: &Window) -> Result<()> {
+        if self.gpu_error.swap(false, Ordering::Relaxed) {
+            self.pixels = None;
+        }
+
+        if let Some(pixels) = &self.pixels {
+            if !self.stale {
+                return Ok(());
+            }
+            match pixels.render() {
+                Ok(()) => {
+                    self.failures = 0;
+                    self.stale = false;
+                    return Ok(());
+                }
+                // 一時的なタイムアウトはこのフレームを飛ばすだけ
+                Err(pixels::Error::Surface(wgpu::SurfaceError::Timeout)) => return Ok(()),
+                Err(e) => {
+                    tracing::warn!("{}", t!("render.render_failed", e));
+                    self.pixels = None;
+                }
+            }
+        }
+
+        self.recover(window)
+    }
+
+    fn recover(&mut self, window: &Window) -> Result<()> {
+        self.failures += 1;
+        tracing::info!("{}", t!("render.recreating", self.failures));
+
+        match create_pixels(window, self.width, self.height, &self.gpu_error) {
+            Ok(mut pixels) => {
+                if self.transparent {
+                    pixels.clear_color(wgpu::Color::TRANSPARENT);
+                }
+                self.pixels = Some(pixels);
+                self.reset = true;
+                tracing::info!("{}", t!("render.recovered"));
+                Ok(())
+            }
+            Err(e) if self.failures < MAX_RECOVERY_ATTEMPTS => {
+                tracing::warn!("{}", t!("render.recreate_failed", e));
+                Ok(())
+            }
+            Err(e) => bail!(e),
+        }
+    }
+}
+
+/// Takes the changed region into the frame buffer; [`Renderer::render`] shows it.
+impl FrameSink for Renderer {
+    fn present(&mut self, frame: &[u8], region: Rect, _timestamp: Timestamp) {
+        let width = self.width as usize;
+        if let Some(dst) = self.frame_mut()
+            && dst.len() == frame.len()
+        {
+            dirty::copy_rect(dst, frame, width, region);
+        }
+    }
+}
+
+fn create_pixels(
+    window: &Window,
+    width: u32,
+    height: u32,
+    gpu_error: &Arc<AtomicBool>,
+) -> Result<Pixels> {
+    // 指定されたグラフィックス API を順に試し、どれも駄目なら wgpu に任せる
+    let backends = BACKENDS.get().map(Vec::as_slice).unwrap_or_default();
+    let mut built = None;
+    for &backend in backends {
+        match build_pixels(window, width, height, Some(backend)) {
+            Ok(pixels) => {
+                built = Some(pixels);
+                break;
+            }
+            Err(e) => tracing::warn!(
+                "{}",
+                t!(
+                    "render.backend_failed",
+                    format!("{backend:?}"),
+                    format!("{e:#}")
+                )
+            ),
+        }
+    }
+    let pixels = match built {
+        Some(pixels) => pixels,
+        None => {
+            if !backends.is_empty() {
+                tracing::warn!("{}", t!("render.backend_default"));
+            }
+            build_pixels(window, width, height, None)?
+        }
+    };
+    tracing::debug!(
+        surface_format = ?pixels.surface_texture_format(),
+        "{}",
+        t!("render.surface_format")
+    );
+    let adapter = pixels.adapter().get_info();
+    tracing::info!(
+        backend = ?adapter.backend,
+        device_type = ?adapter.device_type,
+        "{}",
+        t!("render.adapter", adapter.name)
+    );
+
+    // wgpu の既定ハンドラはパニックするので、フラグを立てて次のフレームで作り直す
+    let flag = gpu_error.clone();
+    pixels.device().on_uncaptured_error(Box::new(move |e| {
+        tracing::error!("{}", t!("render.gpu_error", e));
+        flag.store(true, Ordering::Relaxed);
+    }));
+
+    Ok(pixels)
+}
+
+// `backend` が None なら pixels の既定（WGPU_BACKEND か、すべて）
+fn build_pixels(
+    window: &Window,
+    width: u32,
+    height: u32,
+    backend: Option<GfxBackend>,
+) -> Result<Pixels> {
+    let window_size = window.inner_size();
+    let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, window);
+    // フレームは sRGB で持ち、GPU 側でリニアに戻してから sRGB のサーフェスに書く
+    let mut builder = PixelsBuilder::new(width, height, surface_texture)
+        .texture_format(wgpu::TextureFormat::Rgba8UnormSrgb);
+    if let Some(backend) = backend {
+        let backends = wgpu_backends(backend);
+        // 名前で選んだアダプターがこの API に無いと wgpu がパニックする
+        if let Some(name) = ADAPTER.get()
+            && !has_adapter(backends, name)
+        {
+            bail!(t!("render.adapter_not_on_backend", name));
+        }
+        builder = builder.wgpu_backend(backends);
+    }
+    if let Some(preference) = GPU.get().and_then(|gpu| gpu.power_preference) {
+        builder = builder.request_adapter_options(wgpu::RequestAdapterOptions {
+            power_preference: match preference {
+                PowerPreference::LowPower => wgpu::PowerPreference::LowPower,
+                PowerPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+            },
+            force_fallback_adapter: false,
+            // pixels がウィンドウのサーフェスを入れる
+            compatible_surface: None,
+        });
+    }
+    Ok(builder.build()?)
+}
@@ -0,0 +1,147 @@
+// 状態の変化を記録・再生するリプレイファイル（1行1イベントの JSON）
+use crate::{avatar::Mouth, config::AudioConfig, t};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+/// One line of a replay file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    // 記録開始からの秒数
+    pub t: f64,
+    #[serde(flatten)]
+    pub event: ReplayEvent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ReplayEvent {
+    /// Written first: the config and the analysis parameters in effect.
+    Start {
+        config: Option<PathBuf>,
+        audio: AudioConfig,
+    },
+    /// Mouth state change of the main avatar (`source` 0) or of slot `source - 1`.
+    Mouth {
+        source: usize,
+        state: Mouth,
+        level: f32,
+    },
+    /// A sequence was started.
+    Sequence { name: String },
+}
+
+/// Appends events to a replay file from any thread. Writing happens on a thread of its own
+/// so the audio callback never touches the disk.
+#[derive(Clone)]
+pub struct Recorder {
+    started: Instant,
+    source: usize,
+    sender: mpsc::Sender<Entry>,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = std::fs::File::create(path)
+            .with_context(|| t!("replay.write_failed", path.display()))?;
+        let (sender, receiver) = mpsc::channel::<Entry>();
+        std::thread::spawn(move || {
+            let mut writer = BufWriter::new(file);
+            for entry in receiver {
+                let result = serde_json::to_writer(&mut writer, &entry)
+                    .map_err(std::io::Error::from)
+                    .and_then(|()| writer.write_all(b"\n"))
+                    // 異常終了しても途中までは残るように毎回書き出す
+                    .and_then(|()| writer.flush());
+                if let Err(e) = result {
+                    tracing::error!("{}", t!("replay.record_failed", e));
+                    return;
+                }
+            }
+        });
+        tracing::info!("{}", t!("replay.recording", path.display()));
+        Ok(Self {
+            started: Instant::now(),
+            source: 0,
+            sender,
+        })
+    }
+
+    /// A recorder whose mouth events are attributed to `source`.
+    pub fn for_source(&self, source: usize) -> Self {
+        Self {
+            source,
+            ..self.clone()
+        }
+    }
+
+    pub fn record(&self, event: ReplayEvent) {
+        let t = self.started.elapsed().as_secs_f64();
+        // 書き込みスレッドが止まっていたら記録は諦める
+        let _ = self.sender.send(Entry { t, event });
+    }
+
+    pub fn record_mouth(&self, state: Mouth, level: f32) {
+        self.record(ReplayEvent::Mouth {
+            source: self.source,
+            state,
+            level,
+        });
+    }
+}
+
+/// Hands out the events of a replay file as their time comes.
+pub struct Player {
+    entries: VecDeque<Entry>,
+    started: Instant,
+}
+
+impl Player {
+    pub fn load(path: &Path) -> Result<Self> {
+        let file =
+            std::fs::File::open(path).with_context(|| t!("replay.read_failed", path.display()))?;
+        let mut entries = VecDeque::new();
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.with_context(|| t!("replay.read_failed", path.display()))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: Entry = serde_json::from_str(&line)
+                .with_context(|| t!("replay.parse_failed", path.display(), number + 1))?;
+            entries.push_back(entry);
+        }
+        tracing::info!("{}", t!("replay.playing", path.display(), entries.len()));
+        Ok(Self {
+            entries,
+            started: Instant::now(),
+        })
+    }
+
+    /// Events due by `now`, in recorded order.
+    pub fn poll(&mut self, now: Instant) -> Vec<ReplayEvent> {
+        // ファイルの t は Duration に入らないほど大きいこともあるので、秒のまま比べる
+        let elapsed = now.saturating_duration_since(self.started).as_secs_f64();
+        let mut due = Vec::new();
+        while let Some(entry) = self.entries.front()
+            && entry.t.max(0.0) <= elapsed
+        {
+            due.extend(self.entries.pop_front().map(|e| e.event));
+        }
+        due
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// How far into the recording playback is at `now`.
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.started)
+    }
+}
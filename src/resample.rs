@@ -0,0 +1,124 @@
+// ストリーミング用の線形補間リサンプラー
+
+/// Converts a mono stream from one sample rate to another across successive blocks.
+pub struct Resampler {
+    // 入力 1 サンプルあたりの出力位置の進み
+    step: f64,
+    // 次の出力サンプルの、直前ブロック末尾から見た位置
+    position: f64,
+    last: f32,
+}
+
+impl Resampler {
+    pub fn new(from: u32, to: u32) -> Self {
+        Self {
+            step: from as f64 / to.max(1) as f64,
+            position: 0.0,
+            last: 0.0,
+        }
+    }
+
+    pub fn is_passthrough(&self) -> bool {
+        self.step == 1.0
+    }
+
+    /// Appends the resampled `input` to `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        if self.is_passthrough() {
+            output.extend_from_slice(input);
+            return;
+        }
+
+        // position 0 は前ブロックの最後のサンプル、1 が input[0]
+        let len = input.len() as f64;
+        while self.position < len {
+            let index = self.position as usize;
+            let frac = (self.position - index as f64) as f32;
+            let a = if index == 0 {
+                self.last
+            } else {
+                input[index - 1]
+            };
+            let b = input[index];
+            output.push(a + (b - a) * frac);
+            self.position += self.step;
+        }
+        self.position -= len;
+        if let Some(&last) = input.last() {
+            self.last = last;
+        }
+    }
+}
+
+/// Mixes interleaved frames down to mono, appending to `output`.
+pub fn downmix(input: &[f32], channels: usize, output: &mut Vec<f32>) {
+    let channels = channels.max(1);
+    output.extend(
+        input
+            .chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32),
+    );
+}
+
+/// Root-mean-square level of a block, the value the thresholds are compared against.
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f32 = samples.iter().map(|&s| s * s).sum();
+    (sum / samples.len() as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn peak(samples: &[f32]) -> f32 {
+        samples.iter().fold(0.0, |peak, s| peak.max(s.abs()))
+    }
+
+    proptest! {
+        #[test]
+        fn rms_stays_within_the_peak(samples in prop::collection::vec(-1.0f32..=1.0, 0..4096)) {
+            let level = rms(&samples);
+            prop_assert!(level.is_finite() && level >= 0.0);
+            prop_assert!(level <= peak(&samples) * (1.0 + 1e-5), "{level}");
+        }
+
+        #[test]
+        fn silence_is_zero(len in 0usize..4096) {
+            prop_assert_eq!(rms(&vec![0.0; len]), 0.0);
+        }
+
+        // 線形補間なので、入力の振れ幅を超えない（ブロックの分け方にもよらない）
+        #[test]
+        fn resampling_stays_within_the_peak(
+            from in 8_000u32..=96_000,
+            to in 8_000u32..=96_000,
+            blocks in prop::collection::vec(prop::collection::vec(-1.0f32..=1.0, 0..512), 0..16),
+        ) {
+            let mut resampler = Resampler::new(from, to);
+            let mut output = Vec::new();
+            let mut input_peak = 0.0f32;
+            for block in &blocks {
+                input_peak = input_peak.max(peak(block));
+                resampler.process(block, &mut output);
+            }
+            prop_assert!(output.iter().all(|s| s.is_finite()));
+            prop_assert!(peak(&output) <= input_peak * (1.0 + 1e-5));
+        }
+
+        #[test]
+        fn downmix_stays_within_the_peak(
+            channels in 1usize..=8,
+            samples in prop::collection::vec(-1.0f32..=1.0, 0..2048),
+        ) {
+            let mut output = Vec::new();
+            downmix(&samples, channels, &mut output);
+            prop_assert_eq!(output.len(), samples.len().div_ceil(channels));
+            prop_assert!(output.iter().all(|s| s.is_finite()));
+            prop_assert!(peak(&output) <= peak(&samples) * (1.0 + 1e-5));
+        }
+    }
+}
@@ -0,0 +1,74 @@
+// 音声コールバックから解析スレッドへサンプルを渡す、固定長ブロックのリングバッファ
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+    mpsc::{self, Receiver, SyncSender, TryRecvError},
+};
+
+// 1ブロックのサンプル数（インターリーブのまま）
+const BLOCK: usize = 512;
+
+struct Block {
+    len: usize,
+    samples: [f32; BLOCK],
+}
+
+/// Callback side. [`Producer::push`] neither allocates nor locks: the queue is a bounded
+/// array of blocks, and the consumer never blocks on it, so there is no waker to notify.
+pub struct Producer {
+    sender: SyncSender<Block>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl Producer {
+    /// Queues `data`, dropping what does not fit when the consumer falls behind.
+    pub fn push(&self, data: &[f32]) {
+        for chunk in data.chunks(BLOCK) {
+            let mut block = Block {
+                len: chunk.len(),
+                samples: [0.0; BLOCK],
+            };
+            block.samples[..chunk.len()].copy_from_slice(chunk);
+            if self.sender.try_send(block).is_err() {
+                self.dropped.fetch_add(chunk.len(), Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Analysis thread side.
+pub struct Consumer {
+    receiver: Receiver<Block>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl Consumer {
+    /// Appends every queued sample to `output`. Returns `false` once the producer is gone.
+    pub fn read(&self, output: &mut Vec<f32>) -> bool {
+        loop {
+            match self.receiver.try_recv() {
+                Ok(block) => output.extend_from_slice(&block.samples[..block.len]),
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => return false,
+            }
+        }
+    }
+
+    /// Samples dropped since the last call because the queue was full.
+    pub fn take_dropped(&self) -> usize {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+}
+
+/// A queue holding at least `capacity` samples.
+pub fn channel(capacity: usize) -> (Producer, Consumer) {
+    let (sender, receiver) = mpsc::sync_channel(capacity.div_ceil(BLOCK).max(1));
+    let dropped = Arc::new(AtomicUsize::new(0));
+    (
+        Producer {
+            sender,
+            dropped: dropped.clone(),
+        },
+        Consumer { receiver, dropped },
+    )
+}
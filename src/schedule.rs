@@ -0,0 +1,335 @@
+// 決まった時刻や間隔でシーケンスを始める（「30分ごとに水分補給の表示を10秒」など）
+// 時刻は cron と同じ5項目（分 時 日 月 曜日）で、*・リスト・範囲・/間隔が使える。
+// 時刻や日付の範囲で着替える規則（プロファイルとパーツの表示の切り替え）もここで見る
+use crate::{
+    bus::{self, Bus},
+    clock,
+    config::{OutfitConfig, ScheduleConfig},
+    t,
+};
+use anyhow::{Context, Result, bail};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+
+// 時刻を見る間隔。分の変わり目を逃さない程度に
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// 読み込んだ cron 式。各項目で使える値をビットで持つ
+struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    // 日と曜日の両方が * でなければ、どちらかに合えばよい（cron と同じ）
+    either_day: bool,
+}
+
+impl Cron {
+    fn parse(expression: &str) -> Result<Self> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            bail!(t!("schedule.bad_cron", expression));
+        };
+        let field = |text: &str, min: u32, max: u32| {
+            parse_field(text, min, max).with_context(|| t!("schedule.bad_cron", expression))
+        };
+        let mut weekdays = field(weekday, 0, 7)?;
+        // 日曜は 0 でも 7 でもよい
+        if weekdays & 1 << 7 != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: field(minute, 0, 59)?,
+            hours: field(hour, 0, 23)?,
+            days: field(day, 1, 31)?,
+            months: field(month, 1, 12)?,
+            weekdays,
+            either_day: !day.starts_with('*') && !weekday.starts_with('*'),
+        })
+    }
+
+    fn matches(&self, time: OffsetDateTime) -> bool {
+        let bit = |set: u64, value: u8| set & 1 << value != 0;
+        let day = bit(self.days, time.day());
+        let weekday = bit(self.weekdays, time.weekday().number_days_from_sunday());
+        let day = if self.either_day {
+            day || weekday
+        } else {
+            day && weekday
+        };
+        day && bit(self.minutes, time.minute())
+            && bit(self.hours, time.hour())
+            && bit(self.months, time.month() as u8)
+    }
+}
+
+// "1,5-10,*/15" のような1項目を、使える値のビットに
+fn parse_field(text: &str, min: u32, max: u32) -> Option<u64> {
+    let mut set = 0u64;
+    for part in text.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|&s| s > 0)?),
+            None => (part, 1),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            range => match range.split_once('-') {
+                Some((first, last)) => (first.parse().ok()?, last.parse().ok()?),
+                // "5/10" は 5 から最後まで 10 おき
+                None if step > 1 => (range.parse().ok()?, max),
+                None => {
+                    let value = range.parse().ok()?;
+                    (value, value)
+                }
+            },
+        };
+        if first < min || last > max || first > last {
+            return None;
+        }
+        for value in (first..=last).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Some(set)
+}
+
+enum When {
+    Cron(Cron),
+    Every(Duration),
+}
+
+impl When {
+    fn parse(config: &ScheduleConfig) -> Result<Self> {
+        match (&config.cron, config.every) {
+            (Some(cron), None) => Ok(Self::Cron(Cron::parse(cron)?)),
+            // 見る間隔より短いと数えきれない
+            (None, Some(minutes)) => match Duration::try_from_secs_f32(minutes * 60.0) {
+                Ok(interval) if interval >= POLL_INTERVAL => Ok(Self::Every(interval)),
+                _ => bail!(t!("schedule.bad_every", minutes)),
+            },
+            _ => bail!(t!("schedule.cron_or_every", config.sequence)),
+        }
+    }
+}
+
+// 時・分または月・日の範囲。終わりが始まりより前なら日（年）をまたぐ
+#[derive(Debug, Clone, Copy)]
+struct Span {
+    start: (u8, u8),
+    end: (u8, u8),
+}
+
+impl Span {
+    fn parse(text: &str, point: fn(&str) -> Option<(u8, u8)>) -> Option<Self> {
+        let (start, end) = text.split_once("..")?;
+        Some(Self {
+            start: point(start.trim())?,
+            end: point(end.trim())?,
+        })
+    }
+
+    fn contains(self, value: (u8, u8), end_included: bool) -> bool {
+        let before_end = value < self.end || end_included && value == self.end;
+        if self.start <= self.end {
+            self.start <= value && before_end
+        } else {
+            self.start <= value || before_end
+        }
+    }
+}
+
+// "22:30"
+fn time_of_day(text: &str) -> Option<(u8, u8)> {
+    let (hour, minute) = text.split_once(':')?;
+    let (hour, minute) = (hour.parse().ok()?, minute.parse().ok()?);
+    (hour < 24 && minute < 60).then_some((hour, minute))
+}
+
+// "12-24"
+fn month_day(text: &str) -> Option<(u8, u8)> {
+    let (month, day) = text.split_once('-')?;
+    let (month, day) = (month.parse().ok()?, day.parse().ok()?);
+    ((1..=12).contains(&month) && (1..=31).contains(&day)).then_some((month, day))
+}
+
+struct Outfit {
+    time: Option<Span>,
+    dates: Option<Span>,
+    profile: Option<PathBuf>,
+    show: Vec<String>,
+    hide: Vec<String>,
+}
+
+impl Outfit {
+    fn parse(config: &OutfitConfig) -> Result<Self> {
+        let time = match &config.time {
+            Some(text) => Some(
+                Span::parse(text, time_of_day).with_context(|| t!("schedule.bad_time", text))?,
+            ),
+            None => None,
+        };
+        let dates = match &config.dates {
+            Some(text) => {
+                Some(Span::parse(text, month_day).with_context(|| t!("schedule.bad_dates", text))?)
+            }
+            None => None,
+        };
+        Ok(Self {
+            time,
+            dates,
+            profile: config.profile.clone(),
+            show: config.show.clone(),
+            hide: config.hide.clone(),
+        })
+    }
+
+    fn matches(&self, time: OffsetDateTime) -> bool {
+        let clock = (time.hour(), time.minute());
+        let date = (time.month() as u8, time.day());
+        self.time.is_none_or(|span| span.contains(clock, false))
+            && self.dates.is_none_or(|span| span.contains(date, true))
+    }
+}
+
+/// Checks that a schedule can be used, for `darwin validate`.
+pub fn check(config: &ScheduleConfig) -> Result<()> {
+    When::parse(config).map(|_| ())
+}
+
+/// Checks that an outfit rule can be used, for `darwin validate`.
+pub fn check_outfit(config: &OutfitConfig) -> Result<()> {
+    Outfit::parse(config).map(|_| ())
+}
+
+/// Publishes the scheduled sequences to the bus from a thread of its own, so they are
+/// rate limited like the other triggers, and switches outfits as their rules start and
+/// stop matching. `home` is the profile to go back to when no rule asks for another.
+pub fn start(
+    configs: &[ScheduleConfig],
+    outfits: &[OutfitConfig],
+    home: Option<PathBuf>,
+    bus: Bus,
+) -> Result<()> {
+    let entries = configs
+        .iter()
+        .map(|config| Ok((config.sequence.clone(), When::parse(config)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let outfits = outfits
+        .iter()
+        .map(Outfit::parse)
+        .collect::<Result<Vec<_>>>()?;
+    if entries.is_empty() && outfits.is_empty() {
+        return Ok(());
+    }
+    let started = Instant::now();
+    std::thread::spawn(move || {
+        // 間隔のものは起動から数え、時刻のものは同じ分に2度始めない。
+        // 長すぎて Instant に入らない次の時刻は None で、もう始めない
+        let mut next: Vec<Option<Instant>> = entries
+            .iter()
+            .map(|(_, when)| match when {
+                When::Every(interval) => started.checked_add(*interval),
+                When::Cron(_) => None,
+            })
+            .collect();
+        let mut last_minute = None;
+        let mut wearing = None;
+        loop {
+            let now = Instant::now();
+            let local = clock::now();
+            let minute = (local.date(), local.hour(), local.minute());
+            let new_minute = last_minute != Some(minute);
+            last_minute = Some(minute);
+            for ((sequence, when), next) in entries.iter().zip(&mut next) {
+                let due = match when {
+                    When::Cron(cron) => new_minute && cron.matches(local),
+                    When::Every(interval) => {
+                        let due = next.is_some_and(|next| now >= next);
+                        // スリープから戻ったときなどに、溜まった分をまとめて始めない
+                        while let Some(at) = next.filter(|at| *at <= now) {
+                            *next = at.checked_add(*interval);
+                        }
+                        due
+                    }
+                };
+                if due {
+                    tracing::info!("{}", t!("schedule.triggered", sequence));
+                    bus.publish(bus::Event::Trigger(sequence.clone()));
+                }
+            }
+            if new_minute && !outfits.is_empty() {
+                wear(&outfits, local, &mut wearing, home.as_deref(), &bus);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    });
+    Ok(())
+}
+
+// 当てはまる規則が変わったら、プロファイルとパーツの表示を切り替える
+fn wear(
+    outfits: &[Outfit],
+    local: OffsetDateTime,
+    wearing: &mut Option<Vec<usize>>,
+    home: Option<&Path>,
+    bus: &Bus,
+) {
+    let active: Vec<usize> = (0..outfits.len())
+        .filter(|&i| outfits[i].matches(local))
+        .collect();
+    if wearing.as_ref() == Some(&active) {
+        return;
+    }
+    // プロファイルは当てはまる規則のうち最初のもの
+    let profile = |rules: &[usize]| rules.iter().find_map(|&i| outfits[i].profile.as_deref());
+    let was = wearing.as_deref().map(profile);
+    match (was, profile(&active)) {
+        (Some(was), now) if was == now => {}
+        (_, Some(path)) => bus.publish(bus::Event::Profile(path.to_path_buf())),
+        // 起動したときに当てはまらなければ、そのままのプロファイルで始める
+        (None, None) => {}
+        (Some(_), None) => {
+            if let Some(home) = home {
+                bus.publish(bus::Event::Profile(home.to_path_buf()));
+            }
+        }
+    }
+    let rules = outfits.iter().enumerate().map(|(i, outfit)| {
+        (
+            active.contains(&i),
+            outfit.show.as_slice(),
+            outfit.hide.as_slice(),
+        )
+    });
+    tracing::info!("{}", t!("schedule.outfit", active.len()));
+    bus.publish(bus::Event::HideLayers(
+        bus::LayerSource::Outfit,
+        hidden_layers(rules),
+    ));
+    *wearing = Some(active);
+}
+
+/// The layers to hide for rules given as (applies, show, hide): the layers a rule shows
+/// are only shown while it applies, and those it hides are hidden while it does. A layer
+/// shown by an applying rule stays visible.
+pub fn hidden_layers<'a>(
+    rules: impl Iterator<Item = (bool, &'a [String], &'a [String])> + Clone,
+) -> Vec<String> {
+    let shown: BTreeSet<&String> = rules
+        .clone()
+        .filter(|(applies, ..)| *applies)
+        .flat_map(|(_, show, _)| show)
+        .collect();
+    rules
+        .flat_map(|(applies, show, hide)| if applies { hide } else { show })
+        .filter(|layer| !shown.contains(layer))
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
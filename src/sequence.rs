@@ -0,0 +1,204 @@
+use crate::{
+    compose::Transform,
+    config::{Config, Easing, KeyframeConfig, ParticlesConfig},
+    t,
+};
+use std::{path::PathBuf, time::Instant};
+use winit::keyboard::KeyCode;
+
+// 前のキーフレームから値を引き継いだ状態
+struct Keyframe {
+    time: f32,
+    expression: Option<String>,
+    transform: Transform,
+    // 次のキーフレームへの進み方
+    easing: Easing,
+    sound: Option<PathBuf>,
+    particles: Option<ParticlesConfig>,
+}
+
+struct Sequence {
+    name: String,
+    hotkey: Option<String>,
+    keyframes: Vec<Keyframe>,
+}
+
+struct Active {
+    index: usize,
+    started: Instant,
+    // 発火済みのキーフレーム数
+    fired: usize,
+}
+
+/// Something a keyframe fires once when it is reached.
+#[derive(Debug, Clone)]
+pub enum Effect {
+    Sound(PathBuf),
+    Particles(ParticlesConfig),
+}
+
+/// What the active sequence wants on screen right now.
+#[derive(Debug, Default)]
+pub struct Cue<'a> {
+    pub expression: Option<&'a str>,
+    pub transform: Transform,
+}
+
+/// Plays the configured timeline sequences, one at a time.
+pub struct Sequencer {
+    sequences: Vec<Sequence>,
+    active: Option<Active>,
+}
+
+impl Sequencer {
+    pub fn new(config: &Config) -> Self {
+        let sequences = config
+            .sequences
+            .iter()
+            .filter(|(_, sequence)| !sequence.keyframes.is_empty())
+            .map(|(name, sequence)| {
+                let mut keyframes: Vec<&KeyframeConfig> = sequence.keyframes.iter().collect();
+                keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+                let mut expression = None;
+                let mut transform = Transform::default();
+                let mut easing = Easing::default();
+                let keyframes = keyframes
+                    .into_iter()
+                    .map(|k| {
+                        if k.expression.is_some() {
+                            expression = k.expression.clone();
+                        }
+                        if let Some(offset) = k.offset {
+                            transform.offset = offset;
+                        }
+                        if let Some(scale) = k.scale {
+                            transform.scale = scale;
+                        }
+                        if let Some(rotation) = k.rotation {
+                            transform.rotation = rotation;
+                        }
+                        if let Some(e) = k.easing {
+                            easing = e;
+                        }
+                        Keyframe {
+                            time: k.time.max(0.0),
+                            expression: expression.clone(),
+                            transform,
+                            easing,
+                            sound: k.sound.as_ref().map(|p| config.resolve(p)),
+                            particles: k.particles.as_ref().map(|p| ParticlesConfig {
+                                image: config.resolve(&p.image),
+                                ..p.clone()
+                            }),
+                        }
+                    })
+                    .collect();
+
+                Sequence {
+                    name: name.clone(),
+                    hotkey: sequence.hotkey.clone(),
+                    keyframes,
+                }
+            })
+            .collect();
+
+        Self {
+            sequences,
+            active: None,
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.sequences.iter().map(|s| s.name.as_str())
+    }
+
+    /// Every sound any sequence may play, for preloading.
+    pub fn sounds(&self) -> impl Iterator<Item = &PathBuf> {
+        self.sequences
+            .iter()
+            .flat_map(|s| s.keyframes.iter())
+            .filter_map(|k| k.sound.as_ref())
+    }
+
+    /// Starts the named sequence, restarting it (or replacing another one) if already playing.
+    pub fn trigger(&mut self, name: &str) -> bool {
+        let Some(index) = self.sequences.iter().position(|s| s.name == name) else {
+            return false;
+        };
+        self.start(index);
+        true
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Stops the active sequence without firing its remaining keyframes.
+    pub fn stop(&mut self) {
+        self.active = None;
+    }
+
+    /// Starts the sequence bound to `keycode`, if any, and returns its name.
+    pub fn trigger_hotkey(&mut self, keycode: KeyCode) -> Option<&str> {
+        let key = format!("{:?}", keycode);
+        let index = self
+            .sequences
+            .iter()
+            .position(|s| s.hotkey.as_deref() == Some(key.as_str()))?;
+        self.start(index);
+        Some(&self.sequences[index].name)
+    }
+
+    fn start(&mut self, index: usize) {
+        let name = &self.sequences[index].name;
+        tracing::info!(sequence = name, "{}", t!("sequence.started", name));
+        self.active = Some(Active {
+            index,
+            started: Instant::now(),
+            fired: 0,
+        });
+    }
+
+    /// Advances the active sequence. Effects of keyframes reached since the last call are
+    /// appended to `effects`.
+    pub fn update(&mut self, now: Instant, effects: &mut Vec<Effect>) -> Cue<'_> {
+        let Some(active) = &mut self.active else {
+            return Cue::default();
+        };
+        let sequence = &self.sequences[active.index];
+        let elapsed = now.duration_since(active.started).as_secs_f32();
+
+        while let Some(keyframe) = sequence.keyframes.get(active.fired)
+            && keyframe.time <= elapsed
+        {
+            effects.extend(keyframe.sound.clone().map(Effect::Sound));
+            effects.extend(keyframe.particles.clone().map(Effect::Particles));
+            active.fired += 1;
+        }
+
+        // 最後のキーフレームを過ぎたら通常表示に戻る
+        if active.fired == sequence.keyframes.len() {
+            tracing::info!(sequence = %sequence.name, "{}", t!("sequence.finished", sequence.name));
+            self.active = None;
+            return Cue::default();
+        }
+
+        let next = &sequence.keyframes[active.fired];
+        let Some(prev) = active.fired.checked_sub(1).map(|i| &sequence.keyframes[i]) else {
+            // 最初のキーフレーム前は何もしない
+            return Cue::default();
+        };
+        let span = next.time - prev.time;
+        let t = if span > 0.0 {
+            (elapsed - prev.time) / span
+        } else {
+            1.0
+        };
+
+        Cue {
+            expression: prev.expression.as_deref(),
+            transform: prev.transform.lerp(&next.transform, prev.easing.ease(t)),
+        }
+    }
+}
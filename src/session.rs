@@ -0,0 +1,29 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+// ウォッチドッグが子プロセスに状態ファイルの場所を渡す環境変数
+pub const STATE_FILE_ENV: &str = "DARWIN_STATE_FILE";
+
+/// Runtime state that survives a watchdog restart.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionState {
+    pub fullscreen: bool,
+}
+
+pub fn state_file() -> Option<PathBuf> {
+    std::env::var_os(STATE_FILE_ENV).map(PathBuf::from)
+}
+
+pub fn load(path: &Path) -> SessionState {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|text| toml::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(path: &Path, state: &SessionState) -> Result<()> {
+    std::fs::write(path, toml::to_string(state)?)?;
+    Ok(())
+}
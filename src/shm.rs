@@ -0,0 +1,191 @@
+// 共有メモリでのフレーム出力（OBS のソースプラグインなどが読む）
+//
+// ファイルの並び（リトルエンディアン）:
+//   0  magic           b"DARWINFB"
+//   8  version         u32 = 2
+//   12 header_size     u32 = 80（フレームはこの位置から）
+//   16 width           u32
+//   20 height          u32
+//   24 stride          u32（1行のバイト数）
+//   28 flags           u32（bit 0: 乗算済みアルファ）
+//   32 buffers         u32 = 2
+//   36 front           u32  最新のフレームが入っているバッファの番号
+//   40 sequence        u64  フレームを書き終えるたびに増える
+//   48 heartbeat       u64  書き手が生きている間、0.5 秒ごとに更新する UNIX 時刻（ミリ秒）。終了時は 0
+//   56 time            u64  front のフレームを合成した時刻。出力を始めてからのマイクロ秒（単調時計）
+//   64 audio           u64  そのフレームに反映した音声の位置（マイクロ秒）。音声が無ければ u64::MAX
+//   72 (予約)
+//   80 バッファ 0、続いてバッファ 1（それぞれ stride × height バイトの RGBA）
+//
+// 読み手は sequence を読み、front のバッファと時刻を写してから sequence をもう一度読む。
+// 変わっていたら書き換えの途中だったかもしれないので写し直す。
+// heartbeat が数秒止まっていたら書き手はいない。
+use crate::{
+    dirty::Rect,
+    sink::{FrameSink, Timestamp},
+    t,
+};
+use anyhow::{Context, Result};
+use memmap2::MmapRaw;
+use std::{
+    fs::OpenOptions,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering},
+    },
+    time::{Duration, SystemTime},
+};
+
+const MAGIC: &[u8; 8] = b"DARWINFB";
+const VERSION: u32 = 2;
+const HEADER_SIZE: usize = 80;
+const BUFFERS: usize = 2;
+const FLAG_PREMULTIPLIED: u32 = 1;
+const HEARTBEAT_INTERVAL: Duration = Duration::from_millis(500);
+
+const FRONT: usize = 36;
+const SEQUENCE: usize = 40;
+const HEARTBEAT: usize = 48;
+const TIME: usize = 56;
+const AUDIO: usize = 64;
+
+/// Where the frames of the output named `name` are shared: `/dev/shm` where it exists
+/// (memory only), the temporary directory elsewhere.
+pub fn path(name: &str) -> PathBuf {
+    let dir = PathBuf::from("/dev/shm");
+    let dir = if dir.is_dir() {
+        dir
+    } else {
+        std::env::temp_dir()
+    };
+    dir.join(format!("{name}.frames"))
+}
+
+struct Shared {
+    map: MmapRaw,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    fn u32_at(&self, offset: usize) -> &AtomicU32 {
+        // SAFETY: ヘッダーの位置は境界がそろっていて、マップはこの構造体と同じだけ生きる
+        unsafe { &*(self.map.as_mut_ptr().add(offset) as *const AtomicU32) }
+    }
+
+    fn u64_at(&self, offset: usize) -> &AtomicU64 {
+        // SAFETY: u32_at と同じ
+        unsafe { &*(self.map.as_mut_ptr().add(offset) as *const AtomicU64) }
+    }
+
+    fn beat(&self) {
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        self.u64_at(HEARTBEAT)
+            .store(now.as_millis() as u64, Ordering::Release);
+    }
+}
+
+/// Shares every frame through a memory-mapped file laid out as described at the top of
+/// this module, double-buffered so readers copy without waiting for the writer.
+pub struct SharedMemory {
+    shared: Arc<Shared>,
+    path: PathBuf,
+    frame_size: usize,
+}
+
+impl SharedMemory {
+    pub fn start(name: &str, width: u32, height: u32) -> Result<Self> {
+        let path = path(name);
+        let frame_size = width as usize * height as usize * 4;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .with_context(|| t!("shm.open_failed", path.display()))?;
+        file.set_len((HEADER_SIZE + frame_size * BUFFERS) as u64)
+            .with_context(|| t!("shm.open_failed", path.display()))?;
+        let map = MmapRaw::map_raw(&file).with_context(|| t!("shm.open_failed", path.display()))?;
+
+        let mut header = [0u8; 36];
+        header[..8].copy_from_slice(MAGIC);
+        for (i, value) in [
+            VERSION,
+            HEADER_SIZE as u32,
+            width,
+            height,
+            width * 4,
+            FLAG_PREMULTIPLIED,
+            BUFFERS as u32,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            header[8 + i * 4..12 + i * 4].copy_from_slice(&value.to_le_bytes());
+        }
+        // SAFETY: マップはヘッダーより大きく、まだ誰にも渡していない
+        unsafe {
+            std::ptr::copy_nonoverlapping(header.as_ptr(), map.as_mut_ptr(), header.len());
+        }
+
+        let shared = Arc::new(Shared {
+            map,
+            stop: AtomicBool::new(false),
+        });
+        shared.beat();
+        // フレームが変わらない間も、生きていることを読み手に知らせる
+        let beating = shared.clone();
+        std::thread::spawn(move || {
+            while !beating.stop.load(Ordering::Relaxed) {
+                beating.beat();
+                std::thread::sleep(HEARTBEAT_INTERVAL);
+            }
+        });
+        tracing::info!("{}", t!("shm.started", path.display()));
+        Ok(Self {
+            shared,
+            path,
+            frame_size,
+        })
+    }
+}
+
+impl FrameSink for SharedMemory {
+    fn present(&mut self, frame: &[u8], _region: Rect, timestamp: Timestamp) {
+        if frame.len() != self.frame_size {
+            return;
+        }
+        let shared = &self.shared;
+        // 読み手が見ていない方のバッファに書いてから入れ替える
+        let back = 1 - shared.u32_at(FRONT).load(Ordering::Acquire) as usize % BUFFERS;
+        // SAFETY: バッファはマップの範囲内で、書くのはこのスレッドだけ
+        unsafe {
+            let dst = shared
+                .map
+                .as_mut_ptr()
+                .add(HEADER_SIZE + back * self.frame_size);
+            std::ptr::copy_nonoverlapping(frame.as_ptr(), dst, frame.len());
+        }
+        let audio = timestamp
+            .audio
+            .map_or(u64::MAX, |audio| audio.as_micros() as u64);
+        shared
+            .u64_at(TIME)
+            .store(timestamp.time.as_micros() as u64, Ordering::Release);
+        shared.u64_at(AUDIO).store(audio, Ordering::Release);
+        shared.u32_at(FRONT).store(back as u32, Ordering::Release);
+        shared.u64_at(SEQUENCE).fetch_add(1, Ordering::AcqRel);
+    }
+}
+
+impl Drop for SharedMemory {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        self.shared.u64_at(HEARTBEAT).store(0, Ordering::Release);
+        // 読み手が開いたままでも、その読み手のマップは残る
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
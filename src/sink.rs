@@ -0,0 +1,309 @@
+// 合成したフレームの出力先（ウィンドウ、レイヤーシェル、録画など）。いくつでも同時に使える
+use crate::{
+    compose,
+    config::{Config, OutputConfig},
+    dirty::Rect,
+    notify, shm, t, web,
+};
+use anyhow::{Context, Result, bail};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+// この拡張子なら ffmpeg で動画にする
+const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "mov", "webm", "mkv"];
+// 録画先の空き容量を確かめる間隔
+const FREE_SPACE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// When a frame was composed, for lining it up with the audio afterwards.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Timestamp {
+    // 出力を始めてからの時間（単調時計）
+    pub time: Duration,
+    // そのフレームに反映した音声の位置。音声が無ければ None
+    pub audio: Option<Duration>,
+}
+
+/// Somewhere composed frames go. Frames are premultiplied RGBA at the canvas size,
+/// `region` is the part that changed since the previous call and `timestamp` says when
+/// the frame was composed.
+pub trait FrameSink {
+    fn present(&mut self, frame: &[u8], region: Rect, timestamp: Timestamp);
+}
+
+/// Starts the sink for one `[[outputs]]` entry.
+pub fn start(
+    output: &OutputConfig,
+    config: &Config,
+    width: u32,
+    height: u32,
+) -> Result<Box<dyn FrameSink>> {
+    match output {
+        OutputConfig::Record { path, fps } => Ok(Box::new(Recording::start(
+            &config.resolve(path),
+            *fps,
+            width,
+            height,
+            config.notifications.min_free_mb * 1024 * 1024,
+        )?)),
+        OutputConfig::Shm { name } => Ok(Box::new(shm::SharedMemory::start(name, width, height)?)),
+        OutputConfig::Http {
+            bind,
+            fps,
+            format,
+            quality,
+        } => Ok(Box::new(web::WebStream::start(
+            bind, *fps, *format, *quality, width, height,
+        )?)),
+    }
+}
+
+/// Numbered PNGs in a directory or an `ffmpeg` encoder. WebM and MOV files keep the alpha
+/// channel (VP9 and ProRes 4444).
+pub enum FileOutput {
+    Frames(PathBuf),
+    Video { child: Child, stdin: ChildStdin },
+}
+
+impl FileOutput {
+    /// Opens `out` for `width` × `height` frames at `fps`, muxing in `audio` if given.
+    pub fn open(
+        out: &Path,
+        audio: Option<&Path>,
+        width: u32,
+        height: u32,
+        fps: u32,
+    ) -> Result<Self> {
+        let extension = out
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        if !VIDEO_EXTENSIONS.contains(&extension.as_str()) {
+            std::fs::create_dir_all(out)
+                .with_context(|| t!("offline.write_failed", out.display()))?;
+            return Ok(Self::Frames(out.to_path_buf()));
+        }
+
+        let mut command = Command::new("ffmpeg");
+        command
+            .args(["-hide_banner", "-loglevel", "error", "-y"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .args(["-s", &format!("{width}x{height}")])
+            .args(["-r", &fps.to_string()])
+            .args(["-i", "-"]);
+        // 音声も一緒に入れておくと編集ソフトでそのまま合わせられる
+        if let Some(audio) = audio {
+            command.arg("-i").arg(audio).arg("-shortest");
+        }
+        // 透過を残せる形式ではアルファ付きのコーデックにする
+        match extension.as_str() {
+            "webm" => {
+                command.args(["-c:v", "libvpx-vp9", "-pix_fmt", "yuva420p"]);
+            }
+            "mov" => {
+                command.args(["-c:v", "prores_ks", "-profile:v", "4444"]);
+                command.args(["-pix_fmt", "yuva444p10le"]);
+            }
+            _ => {}
+        }
+        let mut child = command
+            .arg(out)
+            .stdin(Stdio::piped())
+            .spawn()
+            .context(t!("offline.ffmpeg_failed"))?;
+        let stdin = child.stdin.take().context(t!("offline.ffmpeg_failed"))?;
+        Ok(Self::Video { child, stdin })
+    }
+
+    /// Writes frame number `index` (straight, not premultiplied, RGBA).
+    pub fn write(&mut self, index: usize, rgba: &[u8], width: u32, height: u32) -> Result<()> {
+        match self {
+            Self::Frames(dir) => {
+                let file = dir.join(format!("frame_{index:06}.png"));
+                image::save_buffer(&file, rgba, width, height, image::ColorType::Rgba8)
+                    .with_context(|| t!("offline.write_failed", file.display()))
+            }
+            Self::Video { stdin, .. } => stdin.write_all(rgba).context(t!("offline.ffmpeg_failed")),
+        }
+    }
+
+    pub fn finish(self) -> Result<()> {
+        if let Self::Video { mut child, stdin } = self {
+            drop(stdin);
+            if !child.wait()?.success() {
+                bail!(t!("offline.ffmpeg_failed"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Records the live output at a fixed frame rate on a thread of its own, repeating the
+/// latest frame when nothing changed. The timestamp of each written frame goes to
+/// [`timestamps_path`]. The file is finished when the sink is dropped. When less than
+/// `min_free` bytes are left on the disk, a notification says so (once until space is freed).
+pub struct Recording {
+    latest: Arc<Mutex<(Vec<u8>, Timestamp)>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Recording {
+    pub fn start(path: &Path, fps: u32, width: u32, height: u32, min_free: u64) -> Result<Self> {
+        if fps == 0 {
+            bail!(t!("offline.bad_fps"));
+        }
+        let mut file = FileOutput::open(path, None, width, height, fps)?;
+        let timestamps = timestamps_path(path);
+        let mut timestamps = std::fs::File::create(&timestamps)
+            .map(std::io::BufWriter::new)
+            .with_context(|| t!("offline.write_failed", timestamps.display()))?;
+        writeln!(timestamps, "frame,time_ms,audio_ms")?;
+        let latest = Arc::new(Mutex::new((
+            vec![0u8; width as usize * height as usize * 4],
+            Timestamp::default(),
+        )));
+        let stop = Arc::new(AtomicBool::new(false));
+        let path = path.to_path_buf();
+        tracing::info!("{}", t!("sink.recording", path.display()));
+
+        let thread = {
+            let (latest, stop) = (latest.clone(), stop.clone());
+            std::thread::spawn(move || {
+                let interval = Duration::from_secs_f64(1.0 / fps as f64);
+                let started = Instant::now();
+                let mut rgba = Vec::new();
+                let mut index = 0;
+                let mut checked: Option<Instant> = None;
+                let mut low = false;
+                while !stop.load(Ordering::Relaxed) {
+                    if checked.is_none_or(|at| at.elapsed() >= FREE_SPACE_INTERVAL) {
+                        checked = Some(Instant::now());
+                        let free = free_space(&path).filter(|free| *free < min_free);
+                        if let Some(free) = free
+                            && !low
+                        {
+                            let mb = free / (1024 * 1024);
+                            let message = t!("sink.disk_low", path.display(), mb);
+                            tracing::warn!("{message}");
+                            notify::critical(&message);
+                        }
+                        low = free.is_some();
+                    }
+                    // 書き出すのは通常の（乗算済みでない）アルファ
+                    let timestamp = {
+                        let latest = latest.lock().unwrap();
+                        rgba.clone_from(&latest.0);
+                        latest.1
+                    };
+                    compose::unpremultiply(&mut rgba);
+                    if let Err(e) = file.write(index, &rgba, width, height) {
+                        tracing::warn!("{}", t!("sink.failed", path.display(), e));
+                        return;
+                    }
+                    // 同じフレームを繰り返すときは、同じ時刻が並ぶ
+                    let audio = timestamp
+                        .audio
+                        .map(|audio| format!("{:.3}", audio.as_secs_f64() * 1000.0));
+                    let _ = writeln!(
+                        timestamps,
+                        "{index},{:.3},{}",
+                        timestamp.time.as_secs_f64() * 1000.0,
+                        audio.unwrap_or_default()
+                    );
+                    index += 1;
+                    let next = started + interval * index as u32;
+                    std::thread::sleep(next.saturating_duration_since(Instant::now()));
+                }
+                let _ = timestamps.flush();
+                match file.finish() {
+                    Ok(()) => tracing::info!("{}", t!("sink.recorded", index, path.display())),
+                    Err(e) => tracing::warn!("{}", t!("sink.failed", path.display(), e)),
+                }
+            })
+        };
+        Ok(Self {
+            latest,
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl FrameSink for Recording {
+    fn present(&mut self, frame: &[u8], _region: Rect, timestamp: Timestamp) {
+        let mut latest = self.latest.lock().unwrap();
+        if latest.0.len() == frame.len() {
+            latest.0.copy_from_slice(frame);
+            latest.1 = timestamp;
+        }
+    }
+}
+
+/// The CSV (`frame,time_ms,audio_ms`) written next to a recording: `clip.mp4` gets
+/// `clip.mp4.timestamps.csv`, a directory of frames gets `timestamps.csv` inside it.
+fn timestamps_path(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        return path.join("timestamps.csv");
+    }
+    let mut name = path.as_os_str().to_owned();
+    name.push(".timestamps.csv");
+    PathBuf::from(name)
+}
+
+// 録画先（ファイルならそのディレクトリ）のあるディスクの空き容量（バイト）
+fn free_space(path: &Path) -> Option<u64> {
+    let dir = if path.is_dir() {
+        path
+    } else {
+        path.parent().filter(|dir| !dir.as_os_str().is_empty())?
+    };
+    disk_free(dir)
+}
+
+#[cfg(unix)]
+fn disk_free(dir: &Path) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let dir = std::ffi::CString::new(dir.as_os_str().as_bytes()).ok()?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: dir は NUL 終端の文字列で、stat は成功したときだけ読む
+    if unsafe { libc::statvfs(dir.as_ptr(), stat.as_mut_ptr()) } != 0 {
+        return None;
+    }
+    let stat = unsafe { stat.assume_init() };
+    // 型は OS によって違う（macOS では u32）
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+fn disk_free(dir: &Path) -> Option<u64> {
+    use windows::{Win32::Storage::FileSystem::GetDiskFreeSpaceExW, core::HSTRING};
+    let mut free = 0u64;
+    // SAFETY: 書き込み先は free だけ
+    unsafe { GetDiskFreeSpaceExW(&HSTRING::from(dir), Some(&mut free), None, None) }.ok()?;
+    Some(free)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn disk_free(_dir: &Path) -> Option<u64> {
+    None
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
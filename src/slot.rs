@@ -0,0 +1,59 @@
+use crate::{
+    avatar::{Avatar, Mouth, TalkingFrames},
+    config::{Config, SlotConfig},
+    state,
+};
+use std::time::Instant;
+
+/// Extra avatar drawn into a rectangle of the canvas, reacting to its own input.
+pub struct Slot {
+    rect: [i32; 4],
+    expression: String,
+    // 専用の入力があればその状態、無ければメインの口の状態に合わせる
+    input: Option<state::Reader>,
+    talking_frames: TalkingFrames,
+    // 今のフレーム（口の状態と発話フレームの番号）
+    mouth: Mouth,
+    index: usize,
+}
+
+impl Slot {
+    /// `input` is the state of the slot's own capture, if it has one.
+    pub fn new(
+        slot: &SlotConfig,
+        config: &Config,
+        input: Option<state::Reader>,
+        rng: fastrand::Rng,
+    ) -> Self {
+        Self {
+            rect: slot.rect,
+            expression: slot.expression.clone(),
+            input,
+            talking_frames: TalkingFrames::new(&config.talking, rng),
+            mouth: Mouth::Idle,
+            index: 0,
+        }
+    }
+
+    pub fn rect(&self) -> [i32; 4] {
+        self.rect
+    }
+
+    /// Picks this slot's frame for the redraw at `now`; `main` is the main capture's mouth.
+    pub fn update(&mut self, avatar: &Avatar, main: Mouth, now: Instant) {
+        self.mouth = self
+            .input
+            .as_mut()
+            .map_or(main, |input| input.latest().mouth);
+        self.index = self.talking_frames.update(
+            self.mouth == Mouth::Talking,
+            avatar.talking_count(&self.expression),
+            now,
+        );
+    }
+
+    /// The frame picked by the last [`Slot::update`].
+    pub fn frame<'a>(&self, avatar: &'a Avatar) -> Option<&'a [u8]> {
+        avatar.frame(&self.expression, self.mouth, self.index)
+    }
+}
@@ -0,0 +1,72 @@
+// 特徴量を対応表に渡す前に整える（範囲の制限・不感帯・平滑化）。音声の解析からでも
+// トラッキングからでも、どこから来た値にも同じ設定がかかる
+use crate::{
+    config::{AudioFeature, SmoothingConfig},
+    features::Features,
+};
+use std::{collections::BTreeMap, time::Instant};
+
+struct Channel {
+    feature: AudioFeature,
+    config: SmoothingConfig,
+    // 不感帯を越えたときに採った値
+    held: Option<f32>,
+    // 平滑化した出力
+    value: Option<f32>,
+}
+
+/// Conditions the features shared by every mapping, once per frame.
+pub struct Smoother {
+    channels: Vec<Channel>,
+    last: Option<Instant>,
+}
+
+impl Smoother {
+    pub fn new(config: &BTreeMap<AudioFeature, SmoothingConfig>) -> Self {
+        Self {
+            channels: config
+                .iter()
+                .map(|(feature, config)| Channel {
+                    feature: *feature,
+                    config: config.clone(),
+                    held: None,
+                    value: None,
+                })
+                .collect(),
+            last: None,
+        }
+    }
+
+    /// Replaces each configured feature in `features` with its conditioned value.
+    pub fn process(&mut self, features: &mut Features, now: Instant) {
+        let dt = self
+            .last
+            .replace(now)
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+        for channel in &mut self.channels {
+            let config = &channel.config;
+            let mut value = features.get(channel.feature);
+            if let Some(min) = config.min {
+                value = value.max(min);
+            }
+            if let Some(max) = config.max {
+                value = value.min(max);
+            }
+            // 前に採った値から不感帯の幅以上動いたときだけ採り直す
+            let held = match channel.held {
+                Some(held) if (value - held).abs() < config.deadzone => held,
+                _ => value,
+            };
+            channel.held = Some(held);
+            let output = match (channel.value, config.smoothing_ms) {
+                (Some(previous), ms) if ms > 0 => {
+                    let k = 1.0 - (-dt * 1000.0 / ms as f32).exp();
+                    previous + (held - previous) * k
+                }
+                _ => held,
+            };
+            channel.value = Some(output);
+            features.set(channel.feature, output);
+        }
+    }
+}
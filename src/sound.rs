@@ -0,0 +1,145 @@
+use crate::{
+    resample::{Resampler, downmix},
+    t,
+};
+use anyhow::{Context, Result};
+use cpal::{
+    FromSample, SizedSample,
+    traits::{DeviceTrait, HostTrait, StreamTrait},
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, mpsc},
+};
+
+// 再生中の効果音（モノラル、出力デバイスのサンプルレート）
+struct Voice {
+    samples: Arc<[f32]>,
+    position: usize,
+}
+
+/// Plays short WAV sound effects on the default output device.
+pub struct SoundPlayer {
+    sender: mpsc::Sender<Voice>,
+    sample_rate: u32,
+    cache: HashMap<PathBuf, Arc<[f32]>>,
+    _stream: cpal::Stream,
+}
+
+impl SoundPlayer {
+    pub fn new() -> Result<Self> {
+        let host = crate::host::host();
+        let device = host
+            .default_output_device()
+            .context(t!("sound.no_device"))?;
+        let config = device.default_output_config()?;
+        let sample_rate = config.sample_rate().0;
+        let channels = config.channels() as usize;
+        let (sender, receiver) = mpsc::channel();
+
+        let stream = match config.sample_format() {
+            cpal::SampleFormat::F32 => {
+                build_stream::<f32>(&device, &config.into(), channels, receiver)?
+            }
+            cpal::SampleFormat::I16 => {
+                build_stream::<i16>(&device, &config.into(), channels, receiver)?
+            }
+            cpal::SampleFormat::U16 => {
+                build_stream::<u16>(&device, &config.into(), channels, receiver)?
+            }
+            format => anyhow::bail!(t!("sound.unsupported_format", format)),
+        };
+        stream.play()?;
+
+        Ok(Self {
+            sender,
+            sample_rate,
+            cache: HashMap::new(),
+            _stream: stream,
+        })
+    }
+
+    /// Decodes the file ahead of time so the first trigger doesn't stall.
+    pub fn preload(&mut self, path: &Path) {
+        if self.cache.contains_key(path) {
+            return;
+        }
+        match load_wav(path, self.sample_rate) {
+            Ok(samples) => {
+                self.cache.insert(path.to_path_buf(), samples);
+            }
+            Err(e) => tracing::warn!("{}", t!("sound.load_failed", path.display(), e)),
+        }
+    }
+
+    pub fn play(&mut self, path: &Path) {
+        self.preload(path);
+        if let Some(samples) = self.cache.get(path) {
+            let _ = self.sender.send(Voice {
+                samples: samples.clone(),
+                position: 0,
+            });
+        }
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    channels: usize,
+    receiver: mpsc::Receiver<Voice>,
+) -> Result<cpal::Stream>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let mut voices: Vec<Voice> = Vec::new();
+    let stream = device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            voices.extend(receiver.try_iter());
+            for frame in data.chunks_mut(channels) {
+                let mut mixed = 0.0;
+                for voice in &mut voices {
+                    if let Some(&s) = voice.samples.get(voice.position) {
+                        mixed += s;
+                        voice.position += 1;
+                    }
+                }
+                let value = T::from_sample(mixed.clamp(-1.0, 1.0));
+                frame.fill(value);
+            }
+            voices.retain(|v| v.position < v.samples.len());
+        },
+        |err| tracing::error!("{}", t!("sound.stream_error", err)),
+        None,
+    )?;
+    Ok(stream)
+}
+
+/// Reads a WAV file as mono samples at `sample_rate`.
+pub fn load_wav(path: &Path, sample_rate: u32) -> Result<Arc<[f32]>> {
+    let reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let channels = spec.channels.max(1) as usize;
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let max = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / max))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    let mut mono = Vec::with_capacity(samples.len() / channels);
+    downmix(&samples, channels, &mut mono);
+
+    let mut resampler = Resampler::new(spec.sample_rate, sample_rate);
+    if resampler.is_passthrough() {
+        return Ok(mono.into());
+    }
+    let mut resampled = Vec::new();
+    resampler.process(&mono, &mut resampled);
+    Ok(resampled.into())
+}
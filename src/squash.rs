@@ -0,0 +1,65 @@
+// しゃべり始めにアバターをつぶして、弾むように戻す（スクワッシュ＆ストレッチ）。
+// 1枚絵のままでも動きが出る
+use crate::{
+    compose::Transform,
+    config::{Easing, SquashConfig},
+};
+use std::{
+    f32::consts::TAU,
+    time::{Duration, Instant},
+};
+
+/// Squash and stretch started on each speech onset.
+pub struct Squash {
+    intensity: f32,
+    duration: Duration,
+    easing: Easing,
+    anchor_bottom: bool,
+    talking: bool,
+    started: Option<Instant>,
+}
+
+impl Squash {
+    pub fn new(config: &SquashConfig) -> Self {
+        Self {
+            intensity: config.intensity,
+            duration: Duration::from_millis(config.duration_ms.max(1)),
+            easing: config.easing,
+            anchor_bottom: config.anchor_bottom,
+            talking: false,
+            started: None,
+        }
+    }
+
+    /// Applies the squash to `transform` for a canvas `height` pixels tall. Returns whether
+    /// it is still moving.
+    pub fn update(
+        &mut self,
+        talking: bool,
+        transform: &mut Transform,
+        height: f32,
+        now: Instant,
+    ) -> bool {
+        if talking && !self.talking {
+            self.started = Some(now);
+        }
+        self.talking = talking;
+        let Some(started) = self.started else {
+            return false;
+        };
+        let t = now.duration_since(started).as_secs_f32() / self.duration.as_secs_f32();
+        if t >= 1.0 {
+            self.started = None;
+            return false;
+        }
+        // つぶれた状態から始まり、伸びて、小さくつぶれて止まる
+        let amount = self.intensity * (1.0 - self.easing.ease(t)) * (t * TAU).cos();
+        let [x, y] = [1.0 + amount, 1.0 - amount];
+        transform.stretch = [transform.stretch[0] * x, transform.stretch[1] * y];
+        // 足元が浮いたり沈んだりしないよう、下端をそろえる
+        if self.anchor_bottom {
+            transform.offset[1] += height * transform.scale * amount / 2.0;
+        }
+        true
+    }
+}
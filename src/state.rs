@@ -0,0 +1,100 @@
+// キャプチャから描画ループへ渡す状態（トリプルバッファで、ロックも待ちもなく最新のものを渡す）
+use crate::{avatar::Mouth, emotion::Emotion, features::Features};
+use std::{
+    cell::UnsafeCell,
+    sync::{
+        Arc,
+        atomic::{AtomicU8, Ordering},
+    },
+    time::Duration,
+};
+
+/// Everything one capture publishes to the render loop after each analysed block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderState {
+    pub mouth: Mouth,
+    // 口の判定に使った音量 (RMS)
+    pub level: f32,
+    pub emotion: Option<Emotion>,
+    pub features: Features,
+    // これまでに検出した拍の数
+    pub beats: usize,
+    // キャプチャを始めてから解析した音声の長さ（音声が無ければ None）
+    pub position: Option<Duration>,
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        Self {
+            mouth: Mouth::Idle,
+            level: 0.0,
+            emotion: None,
+            features: Features::default(),
+            beats: 0,
+            position: None,
+        }
+    }
+}
+
+// 書き込み中・受け渡し待ち・読み込み中の3枚。back の下位2ビットが受け渡し待ちの番号で、
+// FRESH は書き込み側が新しく渡してから読み込み側がまだ受け取っていないことを表す
+const INDEX: u8 = 0b11;
+const FRESH: u8 = 0b100;
+
+struct Shared {
+    buffers: [UnsafeCell<RenderState>; 3],
+    back: AtomicU8,
+}
+
+// 各バッファに触れるのは、その番号を持っている側だけ
+unsafe impl Sync for Shared {}
+
+/// Publishing side, owned by one capture thread.
+pub struct Writer {
+    shared: Arc<Shared>,
+    index: usize,
+}
+
+impl Writer {
+    pub fn publish(&mut self, state: RenderState) {
+        // SAFETY: 書き込み中のバッファは、交換するまで他の誰も持っていない
+        unsafe { *self.shared.buffers[self.index].get() = state };
+        let previous = self
+            .shared
+            .back
+            .swap(self.index as u8 | FRESH, Ordering::AcqRel);
+        self.index = (previous & INDEX) as usize;
+    }
+}
+
+/// Reading side, owned by the render loop.
+pub struct Reader {
+    shared: Arc<Shared>,
+    index: usize,
+}
+
+impl Reader {
+    /// The most recently published state.
+    pub fn latest(&mut self) -> RenderState {
+        if self.shared.back.load(Ordering::Relaxed) & FRESH != 0 {
+            let previous = self.shared.back.swap(self.index as u8, Ordering::AcqRel);
+            self.index = (previous & INDEX) as usize;
+        }
+        // SAFETY: 読み込み中のバッファは、交換するまで他の誰も持っていない
+        unsafe { *self.shared.buffers[self.index].get() }
+    }
+}
+
+pub fn channel() -> (Writer, Reader) {
+    let shared = Arc::new(Shared {
+        buffers: Default::default(),
+        back: AtomicU8::new(1),
+    });
+    (
+        Writer {
+            shared: shared.clone(),
+            index: 0,
+        },
+        Reader { shared, index: 2 },
+    )
+}
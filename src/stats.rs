@@ -0,0 +1,207 @@
+// 配信後に振り返るためのセッションの統計。手元のファイルに書くだけで、どこにも送らない
+use crate::{
+    avatar::Mouth,
+    config::{StatsConfig, StatsFormat},
+    t,
+};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+// 1秒ごとのピーク音量を数える区切り (dB)。最初より小さければ無音とみなす
+const EDGES_DB: [i32; 6] = [-60, -50, -40, -30, -20, -10];
+const PEAK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Serialize)]
+struct Bucket {
+    range: String,
+    seconds: u64,
+}
+
+#[derive(Serialize)]
+struct Summary {
+    started: String,
+    duration_secs: f64,
+    talking_percent: f64,
+    whisper_percent: f64,
+    idle_percent: f64,
+    expression_switches: u32,
+    // 表情ごとの表示時間（秒）
+    expressions: BTreeMap<String, f64>,
+    peak_level_histogram: Vec<Bucket>,
+}
+
+/// Collects what the avatar did during a session and writes a summary when it ends.
+pub struct SessionStats {
+    dir: PathBuf,
+    format: StatsFormat,
+    started: Instant,
+    started_at: SystemTime,
+    // 前のフレームの時刻と口の状態
+    last: Option<(Instant, Mouth)>,
+    // 待機・ささやき・発話の時間
+    mouths: [Duration; 3],
+    expressions: BTreeMap<String, Duration>,
+    expression: Option<String>,
+    switches: u32,
+    // 区切りごとの秒数（先頭は無音）
+    histogram: [u64; EDGES_DB.len() + 1],
+    peak: f32,
+    peak_since: Instant,
+}
+
+impl SessionStats {
+    /// `dir` is where the summary goes, already resolved against the config.
+    pub fn new(config: &StatsConfig, dir: PathBuf, now: Instant) -> Self {
+        Self {
+            dir,
+            format: config.format,
+            started: now,
+            started_at: SystemTime::now(),
+            last: None,
+            mouths: [Duration::ZERO; 3],
+            expressions: BTreeMap::new(),
+            expression: None,
+            switches: 0,
+            histogram: [0; EDGES_DB.len() + 1],
+            peak: 0.0,
+            peak_since: now,
+        }
+    }
+
+    /// Counts the time since the previous frame towards what was shown then.
+    pub fn update(&mut self, mouth: Mouth, level: f32, expression: &str, now: Instant) {
+        if let Some((last, shown)) = self.last {
+            let elapsed = now.saturating_duration_since(last);
+            self.mouths[shown as usize] += elapsed;
+            if let Some(shown) = &self.expression {
+                *self.expressions.entry(shown.clone()).or_default() += elapsed;
+            }
+        }
+        self.last = Some((now, mouth));
+        if self.expression.as_deref() != Some(expression) {
+            if self.expression.is_some() {
+                self.switches += 1;
+            }
+            self.expression = Some(expression.to_string());
+        }
+
+        self.peak = self.peak.max(level);
+        if now.saturating_duration_since(self.peak_since) >= PEAK_INTERVAL {
+            let db = 20.0 * self.peak.max(1e-9).log10();
+            let bucket = EDGES_DB.iter().filter(|&&edge| db >= edge as f32).count();
+            self.histogram[bucket] += 1;
+            self.peak = 0.0;
+            self.peak_since = now;
+        }
+    }
+
+    fn summary(&self, now: Instant) -> Summary {
+        let total = self.mouths.iter().sum::<Duration>().as_secs_f64();
+        let percent = |mouth: Mouth| match total {
+            0.0 => 0.0,
+            total => round(self.mouths[mouth as usize].as_secs_f64() / total * 100.0),
+        };
+        let peak_level_histogram = self
+            .histogram
+            .iter()
+            .enumerate()
+            .map(|(i, &seconds)| Bucket {
+                range: match i {
+                    0 => format!("< {} dB", EDGES_DB[0]),
+                    i if i == EDGES_DB.len() => format!(">= {} dB", EDGES_DB[i - 1]),
+                    i => format!("{} to {} dB", EDGES_DB[i - 1], EDGES_DB[i]),
+                },
+                seconds,
+            })
+            .collect();
+        Summary {
+            started: utc(self.started_at, "-", "T", ":") + "Z",
+            duration_secs: round(now.saturating_duration_since(self.started).as_secs_f64()),
+            talking_percent: percent(Mouth::Talking),
+            whisper_percent: percent(Mouth::Whisper),
+            idle_percent: percent(Mouth::Idle),
+            expression_switches: self.switches,
+            expressions: self
+                .expressions
+                .iter()
+                .map(|(name, time)| (name.clone(), round(time.as_secs_f64())))
+                .collect(),
+            peak_level_histogram,
+        }
+    }
+
+    /// Writes the summary to `session-<UTC start time>.json` (or `.csv`) in the stats
+    /// directory and returns its path.
+    pub fn write(&self, now: Instant) -> Result<PathBuf> {
+        let summary = self.summary(now);
+        let (extension, text) = match self.format {
+            StatsFormat::Json => ("json", serde_json::to_string_pretty(&summary)? + "\n"),
+            StatsFormat::Csv => ("csv", csv(&summary)),
+        };
+        let path = self.dir.join(format!(
+            "session-{}.{extension}",
+            utc(self.started_at, "", "-", "")
+        ));
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|()| std::fs::write(&path, text))
+            .with_context(|| t!("stats.write_failed", path.display()))?;
+        Ok(path)
+    }
+}
+
+// 表計算ソフトで開けるよう「項目,値」の2列にする
+fn csv(summary: &Summary) -> String {
+    let mut out = String::from("metric,value\n");
+    let _ = writeln!(out, "started,{}", summary.started);
+    let _ = writeln!(out, "duration_secs,{}", summary.duration_secs);
+    let _ = writeln!(out, "talking_percent,{}", summary.talking_percent);
+    let _ = writeln!(out, "whisper_percent,{}", summary.whisper_percent);
+    let _ = writeln!(out, "idle_percent,{}", summary.idle_percent);
+    let _ = writeln!(out, "expression_switches,{}", summary.expression_switches);
+    for (name, seconds) in &summary.expressions {
+        let _ = writeln!(
+            out,
+            "\"expression {}\",{seconds}",
+            name.replace('"', "\"\"")
+        );
+    }
+    for bucket in &summary.peak_level_histogram {
+        let _ = writeln!(out, "\"peak {}\",{}", bucket.range, bucket.seconds);
+    }
+    out
+}
+
+fn round(value: f64) -> f64 {
+    (value * 10.0).round() / 10.0
+}
+
+// UTC の日時を文字列に（日付の区切り、日付と時刻の間、時刻の区切りを指定する）
+fn utc(time: SystemTime, date: &str, between: &str, clock: &str) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let (days, secs) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    // 1970-01-01 からの日数をグレゴリオ暦に（H. Hinnant の civil_from_days）
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}{date}{month:02}{date}{day:02}{between}{:02}{clock}{:02}{clock}{:02}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
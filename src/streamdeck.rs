@@ -0,0 +1,242 @@
+// Stream Deck のプラグインとして動くときの WebSocket 接続
+//
+// プラグインの manifest.json では CodePath に `darwin`、引数の先頭に `streamdeck` を指定する
+// （Stream Deck が -port, -pluginUUID, -registerEvent, -info を後ろに付けて起動する）。
+// アクションは次の2つで、どちらも設定 (settings) で対象を選ぶ:
+//   com.potistudio.darwin.expression  { "expression": "<表情名>" }  押すたびに表情を切り替え・解除
+//   com.potistudio.darwin.sequence    { "sequence": "<シーケンス名>" } シーケンスを再生
+// 表情アクションのボタンには表情の画像が表示され、選択中は状態 1 になる。
+use crate::{
+    bus::{self, Bus},
+    color, compose, t,
+};
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use serde_json::{Value, json};
+use std::{collections::BTreeMap, net::TcpStream, sync::mpsc, time::Duration};
+use tungstenite::{Message, WebSocket};
+
+const EXPRESSION_ACTION: &str = "com.potistudio.darwin.expression";
+const SEQUENCE_ACTION: &str = "com.potistudio.darwin.sequence";
+
+// 受信待ちの合間に、表示の更新を送る
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// ボタン画像の大きさ（Stream Deck XL の高解像度キー）
+const ICON_SIZE: u32 = 144;
+
+/// The arguments the Stream Deck application launches a plugin with.
+#[derive(Debug, Clone)]
+pub struct Launch {
+    port: u16,
+    uuid: String,
+    register_event: String,
+}
+
+impl Launch {
+    /// Parses `-port N -pluginUUID ID -registerEvent NAME -info JSON`.
+    pub fn parse(args: &[String]) -> Result<Self> {
+        let value = |name: &str| {
+            args.iter()
+                .position(|a| a == name)
+                .and_then(|i| args.get(i + 1))
+                .cloned()
+                .with_context(|| t!("streamdeck.missing_arg", name))
+        };
+        Ok(Self {
+            port: value("-port")?
+                .parse()
+                .with_context(|| t!("streamdeck.missing_arg", "-port"))?,
+            uuid: value("-pluginUUID")?,
+            register_event: value("-registerEvent")?,
+        })
+    }
+}
+
+/// Connection to the Stream Deck application, running on its own thread. Button presses are
+/// published to the bus.
+pub struct StreamDeck {
+    selected: mpsc::Sender<Option<String>>,
+}
+
+impl StreamDeck {
+    /// Connects and registers the plugin. `icons` maps expression names to PNG images shown
+    /// on their buttons.
+    pub fn connect(launch: Launch, icons: BTreeMap<String, Vec<u8>>, bus: Bus) -> Result<Self> {
+        let stream = TcpStream::connect(("127.0.0.1", launch.port))
+            .with_context(|| t!("streamdeck.connect_failed", launch.port))?;
+        let url = format!("ws://127.0.0.1:{}", launch.port);
+        let (mut socket, _) = tungstenite::client(url, stream)
+            .map_err(|e| anyhow::anyhow!("{e}"))
+            .with_context(|| t!("streamdeck.connect_failed", launch.port))?;
+        send(
+            &mut socket,
+            json!({ "event": launch.register_event, "uuid": launch.uuid }),
+        )?;
+        socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+        tracing::info!("{}", t!("streamdeck.connected", launch.port));
+
+        let (selected, selected_rx) = mpsc::channel();
+        let icons = icons
+            .into_iter()
+            .map(|(name, png)| {
+                let data = base64::engine::general_purpose::STANDARD.encode(png);
+                (name, format!("data:image/png;base64,{data}"))
+            })
+            .collect();
+        std::thread::spawn(move || {
+            let mut plugin = Plugin {
+                socket,
+                buttons: BTreeMap::new(),
+                icons,
+                selected: None,
+            };
+            if let Err(e) = plugin.run(&bus, &selected_rx) {
+                tracing::warn!("{}", t!("streamdeck.disconnected", e));
+            }
+        });
+
+        Ok(Self { selected })
+    }
+
+    /// Tells the buttons which expression is selected (`None` for the default).
+    pub fn set_selected(&self, expression: Option<&str>) {
+        let _ = self.selected.send(expression.map(str::to_string));
+    }
+}
+
+/// PNG for a button, scaled down from a canvas-sized premultiplied frame.
+pub fn icon(frame: &[u8], width: u32, height: u32) -> Option<Vec<u8>> {
+    let mut straight = frame.to_vec();
+    compose::unpremultiply(&mut straight);
+    let image = image::RgbaImage::from_raw(width, height, straight)?;
+    let scale = ICON_SIZE as f32 / width.max(height) as f32;
+    let mut icon = color::resize_premultiplied(
+        &image,
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+    );
+    compose::unpremultiply(&mut icon);
+
+    let mut png = Vec::new();
+    icon.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .ok()?;
+    Some(png)
+}
+
+// ボタン（context）ごとのアクションと設定
+struct Button {
+    action: String,
+    settings: Value,
+}
+
+struct Plugin {
+    socket: WebSocket<TcpStream>,
+    buttons: BTreeMap<String, Button>,
+    icons: BTreeMap<String, String>,
+    selected: Option<String>,
+}
+
+impl Plugin {
+    fn run(&mut self, bus: &Bus, selected: &mpsc::Receiver<Option<String>>) -> Result<()> {
+        loop {
+            if let Some(latest) = selected.try_iter().last() {
+                self.selected = latest;
+                let contexts: Vec<String> = self.buttons.keys().cloned().collect();
+                for context in contexts {
+                    self.refresh(&context)?;
+                }
+            }
+
+            let message = match self.socket.read() {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Close(_)) => bail!(t!("streamdeck.closed")),
+                Ok(_) => continue,
+                Err(tungstenite::Error::Io(e))
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    continue;
+                }
+                Err(e) => bail!(e),
+            };
+            let Ok(event) = serde_json::from_str::<Value>(&message) else {
+                continue;
+            };
+            self.handle(&event, bus)?;
+        }
+    }
+
+    fn handle(&mut self, event: &Value, bus: &Bus) -> Result<()> {
+        let name = event["event"].as_str().unwrap_or_default();
+        let Some(context) = event["context"].as_str().map(str::to_string) else {
+            return Ok(());
+        };
+        let action = event["action"].as_str().unwrap_or_default().to_string();
+        let settings = event["payload"]["settings"].clone();
+
+        match name {
+            "willAppear" | "didReceiveSettings" => {
+                self.buttons
+                    .insert(context.clone(), Button { action, settings });
+                self.refresh(&context)?;
+            }
+            "willDisappear" => {
+                self.buttons.remove(&context);
+            }
+            "keyDown" => {
+                let request = match action.as_str() {
+                    EXPRESSION_ACTION => settings["expression"]
+                        .as_str()
+                        .map(|e| bus::Event::ToggleExpression(e.to_string())),
+                    SEQUENCE_ACTION => settings["sequence"]
+                        .as_str()
+                        .map(|s| bus::Event::Sequence(s.to_string())),
+                    _ => None,
+                };
+                match request {
+                    Some(request) => bus.publish(request),
+                    // 設定がないボタンは警告マークを出す
+                    None => send(
+                        &mut self.socket,
+                        json!({ "event": "showAlert", "context": context }),
+                    )?,
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // 表情ボタンの画像と、選択中かどうかの状態を送る
+    fn refresh(&mut self, context: &str) -> Result<()> {
+        let Some(button) = self.buttons.get(context) else {
+            return Ok(());
+        };
+        if button.action != EXPRESSION_ACTION {
+            return Ok(());
+        }
+        let expression = button.settings["expression"].as_str().unwrap_or_default();
+        let state = u8::from(self.selected.as_deref() == Some(expression));
+        let image = self.icons.get(expression).cloned();
+
+        send(
+            &mut self.socket,
+            json!({ "event": "setState", "context": context, "payload": { "state": state } }),
+        )?;
+        if let Some(image) = image {
+            send(
+                &mut self.socket,
+                json!({ "event": "setImage", "context": context, "payload": { "image": image } }),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn send(socket: &mut WebSocket<TcpStream>, value: Value) -> Result<()> {
+    socket.send(Message::Text(value.to_string()))?;
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use resvg::{tiny_skia, usvg};
+use std::path::Path;
+
+pub fn is_svg(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+}
+
+fn parse(path: &Path) -> Result<usvg::Tree> {
+    let data = std::fs::read(path)?;
+    Ok(usvg::Tree::from_data(&data, &usvg::Options::default())?)
+}
+
+/// Rasterizes at the document's own size.
+pub fn open(path: &Path) -> Result<RgbaImage> {
+    let tree = parse(path)?;
+    let size = tree.size().to_int_size();
+    render(&tree, size.width(), size.height())
+}
+
+/// Rasterizes stretched to exactly `width` x `height`, so vector art stays sharp at any
+/// canvas resolution.
+pub fn rasterize(path: &Path, width: u32, height: u32) -> Result<RgbaImage> {
+    render(&parse(path)?, width, height)
+}
+
+fn render(tree: &usvg::Tree, width: u32, height: u32) -> Result<RgbaImage> {
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).context("empty SVG size")?;
+    let size = tree.size();
+    let transform = tiny_skia::Transform::from_scale(
+        width as f32 / size.width(),
+        height as f32 / size.height(),
+    );
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia は乗算済みアルファなので、他の画像と同じストレートアルファに戻す
+    let data = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(width, height, data).context("invalid SVG size")
+}
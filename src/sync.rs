@@ -0,0 +1,141 @@
+// 同じプロファイル（設定ファイルのディレクトリ）で動いているインスタンス同士で、選んだ表情を揃える。
+// 最初に起動したものがソケットを持って中継役になり、全員（中継役自身も）がそこにつなぐ。
+// 中継役が終了したら、残ったインスタンスのどれかが引き継ぐ。
+//   expression [名前]   選んだ表情（名前なしでデフォルト）
+use crate::{
+    bus::{self, Bus},
+    config::ExpressionSource,
+    ipc, t,
+};
+use interprocess::local_socket::{Listener, ListenerOptions, Name, SendHalf, Stream, prelude::*};
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{BufRead, BufReader, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+// 中継役が見つからないときに選び直す間隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Default)]
+struct Shared {
+    // 中継役への接続
+    link: Option<SendHalf>,
+    // 最後に送った、または受け取った表情
+    known: Option<String>,
+}
+
+/// Keeps the selected expression in step with the other instances of the same profile.
+pub struct Sync {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Sync {
+    /// Joins the group of instances running with the profile in `profile` and publishes
+    /// their selections to the bus.
+    pub fn start(profile: &Path, bus: Bus) -> Self {
+        let profile = profile.canonicalize().unwrap_or(profile.to_path_buf());
+        let mut hasher = DefaultHasher::new();
+        profile.hash(&mut hasher);
+        let suffix = format!("sync-{:016x}.sock", hasher.finish());
+
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let link = shared.clone();
+        std::thread::spawn(move || {
+            loop {
+                if let Err(e) = join(&suffix, &link, &bus) {
+                    tracing::debug!("{}", t!("sync.retrying", e));
+                }
+                link.lock().unwrap().link = None;
+                std::thread::sleep(RETRY_INTERVAL);
+            }
+        });
+        Self { shared }
+    }
+
+    /// Tells the other instances about the locally selected expression, if it changed.
+    pub fn set_expression(&self, name: Option<&str>) {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.known.as_deref() == name {
+            return;
+        }
+        shared.known = name.map(str::to_string);
+        let line = format!("expression {}\n", name.unwrap_or(""));
+        if let Some(link) = &mut shared.link
+            && link.write_all(line.as_bytes()).is_err()
+        {
+            shared.link = None;
+        }
+    }
+}
+
+// 中継役になれればなり、そこにつないで切れるまで受け取る
+fn join(suffix: &str, shared: &Mutex<Shared>, bus: &Bus) -> std::io::Result<()> {
+    let name = ipc::socket(suffix)?;
+    let stream = match ListenerOptions::new().name(name.clone()).create_sync() {
+        Ok(listener) => host(listener, name)?,
+        Err(_) => match Stream::connect(name.clone()) {
+            Ok(stream) => stream,
+            // 異常終了で残ったソケットファイルなら置き換える
+            Err(_) => {
+                let listener = ListenerOptions::new()
+                    .name(name.clone())
+                    .try_overwrite(true)
+                    .create_sync()?;
+                host(listener, name)?
+            }
+        },
+    };
+    let (receive, send) = stream.split();
+    shared.lock().unwrap().link = Some(send);
+    tracing::info!("{}", t!("sync.joined"));
+
+    for line in BufReader::new(receive).lines() {
+        let line = line?;
+        let Some(name) = line.strip_prefix("expression") else {
+            continue;
+        };
+        let name = Some(name.trim()).filter(|name| !name.is_empty());
+        shared.lock().unwrap().known = name.map(str::to_string);
+        bus.publish(bus::Event::Expression(
+            ExpressionSource::Manual,
+            name.map(str::to_string),
+        ));
+    }
+    Ok(())
+}
+
+fn host(listener: Listener, name: Name<'static>) -> std::io::Result<Stream> {
+    tracing::info!("{}", t!("sync.hosting"));
+    std::thread::spawn(move || relay(listener));
+    Stream::connect(name)
+}
+
+// 受け取った行を他の全員に流す。後から来たインスタンスには最後の状態を先に送る
+fn relay(listener: Listener) {
+    let peers: Arc<Mutex<Vec<(usize, SendHalf)>>> = Arc::default();
+    let last: Arc<Mutex<Option<String>>> = Arc::default();
+    for (id, stream) in listener.incoming().filter_map(Result::ok).enumerate() {
+        let (receive, mut send) = stream.split();
+        if let Some(line) = &*last.lock().unwrap()
+            && send.write_all(line.as_bytes()).is_err()
+        {
+            continue;
+        }
+        peers.lock().unwrap().push((id, send));
+
+        let (peers, last) = (peers.clone(), last.clone());
+        std::thread::spawn(move || {
+            for line in BufReader::new(receive).lines().map_while(Result::ok) {
+                let line = format!("{line}\n");
+                *last.lock().unwrap() = Some(line.clone());
+                peers.lock().unwrap().retain_mut(|(peer, send)| {
+                    *peer == id || send.write_all(line.as_bytes()).is_ok()
+                });
+            }
+            peers.lock().unwrap().retain(|(peer, _)| *peer != id);
+        });
+    }
+}
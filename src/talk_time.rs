@@ -0,0 +1,109 @@
+// このセッションで話した時間と黙っていた時間を、キャンバスに棒グラフ（フォントがあれば文字も）で出す
+use crate::{
+    config::TalkTimeConfig,
+    dirty::Rect,
+    panel::{self, Panel, Style},
+    t,
+    theme::Theme,
+};
+use anyhow::Result;
+use std::time::{Duration, Instant};
+use winit::keyboard::KeyCode;
+
+/// Cumulative talk time against silence, drawn as a small panel on the canvas.
+pub struct TalkTime {
+    panel: Panel,
+    hotkey: Option<String>,
+    visible: bool,
+    talking: Duration,
+    silent: Duration,
+    // 前のフレームの時刻と、そのとき話していたか
+    last: Option<(Instant, bool)>,
+    // 描いた元の値（秒、棒の長さ）。値が変わったときだけ描き直す
+    shown: Option<(u64, usize)>,
+}
+
+impl TalkTime {
+    /// Loads the font if one is configured; without one only the bar is drawn.
+    pub fn new(config: &TalkTimeConfig, width: usize, height: usize) -> Result<Self> {
+        let style = Style {
+            position: config.position,
+            width: config.width,
+            height: config.height,
+            fill: config.talk_color,
+            empty: config.silence_color,
+            background: config.background,
+            size: config.size,
+            text_color: config.text_color,
+            radius: 0.0,
+        };
+        Ok(Self {
+            panel: Panel::new(style, config.font.as_deref(), width, height)?,
+            hotkey: config.hotkey.clone(),
+            visible: config.visible,
+            talking: Duration::ZERO,
+            silent: Duration::ZERO,
+            last: None,
+            shown: None,
+        })
+    }
+
+    pub fn is_hotkey(&self, keycode: KeyCode) -> bool {
+        self.hotkey.as_deref() == Some(format!("{keycode:?}").as_str())
+    }
+
+    /// Shows or hides the widget; the caller redraws the whole canvas.
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Counts the time since the previous frame. Returns whether the widget looks different.
+    pub fn update(&mut self, talking: bool, now: Instant) -> bool {
+        if let Some((last, was_talking)) = self.last {
+            let elapsed = now.saturating_duration_since(last);
+            if was_talking {
+                self.talking += elapsed;
+            } else {
+                self.silent += elapsed;
+            }
+        }
+        self.last = Some((now, talking));
+        if !self.visible {
+            return false;
+        }
+
+        let total = (self.talking + self.silent).as_secs_f32();
+        let ratio = if total > 0.0 {
+            self.talking.as_secs_f32() / total
+        } else {
+            0.0
+        };
+        let filled = (self.panel.bar_width() as f32 * ratio).round() as usize;
+        let shown = (self.talking.as_secs(), filled);
+        if self.shown == Some(shown) {
+            return false;
+        }
+        self.shown = Some(shown);
+        self.panel.set(&self.label(ratio), filled);
+        true
+    }
+
+    fn label(&self, ratio: f32) -> String {
+        t!(
+            "talk_time.label",
+            panel::clock_time(self.talking),
+            (ratio * 100.0).round()
+        )
+    }
+
+    pub fn set_theme(&mut self, theme: &Theme) {
+        self.panel.set_theme(theme);
+    }
+
+    /// Draws the widget where it overlaps `clip`.
+    pub fn draw(&self, dst: &mut [u8], clip: Rect) {
+        if self.visible {
+            self.panel.draw(dst, clip);
+        }
+    }
+}
@@ -0,0 +1,129 @@
+// 設定確認用のテスト信号。キャプチャの代わりに解析へ流し、無音・ささやき・発話・大声を順に繰り返す
+use crate::{
+    config::{AudioConfig, TestSignal},
+    t,
+};
+use std::time::Instant;
+
+// 音の高さ (Hz)
+const TONE: f32 = 220.0;
+// 区切って話すフェーズで、1秒あたりに鳴らす回数
+const SYLLABLES: u64 = 4;
+
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Silence,
+    Whisper,
+    Talking,
+    Syllables,
+    Loud,
+}
+
+impl Phase {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Silence => "silence",
+            Self::Whisper => "whisper",
+            Self::Talking => "talking",
+            Self::Syllables => "syllables",
+            Self::Loud => "loud",
+        }
+    }
+}
+
+/// Generates a test tone or pink noise at the analysis rate, stepping through levels that
+/// should reach every mouth state and transition. Samples are produced in real time.
+pub struct Generator {
+    signal: TestSignal,
+    rate: u32,
+    // フェーズ、音量 (RMS)、長さ（サンプル数）
+    phases: Vec<(Phase, f32, u64)>,
+    index: usize,
+    // 今のフェーズで出したサンプル数
+    position: u64,
+    angle: f32,
+    pink: [f32; 3],
+    // ピンクノイズの RMS を 1 にそろえる係数
+    pink_gain: f32,
+    started: Instant,
+    produced: u64,
+}
+
+impl Generator {
+    pub fn new(signal: TestSignal, audio: &AudioConfig, now: Instant) -> Self {
+        let rate = audio.analysis_rate.max(1);
+        let seconds = |s: f32| (s * rate as f32) as u64;
+        let threshold = audio.threshold;
+        let mut phases = vec![(Phase::Silence, 0.0, seconds(2.0))];
+        // ささやきは閾値の間に入る音量で
+        if let Some(whisper) = audio.whisper_threshold {
+            phases.push((Phase::Whisper, (whisper + threshold) / 2.0, seconds(3.0)));
+        }
+        phases.extend([
+            (Phase::Talking, threshold * 2.0, seconds(3.0)),
+            (Phase::Syllables, threshold * 2.0, seconds(4.0)),
+            (Phase::Loud, threshold * 6.0, seconds(3.0)),
+        ]);
+
+        let mut generator = Self {
+            signal,
+            rate,
+            phases,
+            index: 0,
+            position: 0,
+            angle: 0.0,
+            pink: [0.0; 3],
+            pink_gain: 1.0,
+            started: now,
+            produced: 0,
+        };
+        // 1秒ぶん鳴らして大きさを測る
+        let sum: f32 = (0..rate).map(|_| generator.pink().powi(2)).sum();
+        generator.pink_gain = 1.0 / (sum / rate as f32).sqrt().max(f32::EPSILON);
+        tracing::info!("{}", t!("test_signal.phase", Phase::Silence.name()));
+        generator
+    }
+
+    // Paul Kellet の簡易ピンクノイズフィルタ
+    fn pink(&mut self) -> f32 {
+        let white = fastrand::f32() * 2.0 - 1.0;
+        let b = &mut self.pink;
+        b[0] = 0.99765 * b[0] + white * 0.099046;
+        b[1] = 0.963 * b[1] + white * 0.2965164;
+        b[2] = 0.57 * b[2] + white * 1.0526913;
+        (b[0] + b[1] + b[2] + white * 0.1848) * self.pink_gain
+    }
+
+    /// Appends the mono samples due by `now`.
+    pub fn read(&mut self, now: Instant, output: &mut Vec<f32>) {
+        let due =
+            (now.saturating_duration_since(self.started).as_secs_f64() * self.rate as f64) as u64;
+        while self.produced < due {
+            let (phase, level, length) = self.phases[self.index];
+            let on = match phase {
+                Phase::Syllables => {
+                    (self.position * SYLLABLES * 2 / self.rate as u64).is_multiple_of(2)
+                }
+                _ => true,
+            };
+            // RMS が level になるように
+            let sample = match self.signal {
+                TestSignal::Tone => {
+                    self.angle = (self.angle + TONE / self.rate as f32).fract();
+                    (self.angle * std::f32::consts::TAU).sin() * std::f32::consts::SQRT_2
+                }
+                TestSignal::Pink => self.pink(),
+            };
+            output.push(if on { sample * level } else { 0.0 });
+
+            self.produced += 1;
+            self.position += 1;
+            if self.position >= length {
+                self.position = 0;
+                self.index = (self.index + 1) % self.phases.len();
+                let phase = self.phases[self.index].0;
+                tracing::info!("{}", t!("test_signal.phase", phase.name()));
+            }
+        }
+    }
+}
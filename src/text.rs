@@ -0,0 +1,87 @@
+// 文字のオーバーレイ: テンプレートの {名前} をデータの値で埋めてキャンバスに重ねる
+use crate::{
+    config::TextConfig,
+    data::Variables,
+    dirty::Rect,
+    panel::{Panel, Style},
+    theme::Theme,
+};
+use anyhow::Result;
+
+struct Text {
+    template: String,
+    panel: Panel,
+    // 描いた文字（値が足りず隠しているときは None）
+    shown: Option<String>,
+}
+
+/// The text overlays of the config, redrawn when their values change.
+pub struct TextOverlays {
+    texts: Vec<Text>,
+    variables: Variables,
+}
+
+impl TextOverlays {
+    /// `configs` have their fonts resolved against the config already.
+    pub fn new(
+        configs: &[TextConfig],
+        variables: Variables,
+        width: usize,
+        height: usize,
+    ) -> Result<Self> {
+        let texts = configs
+            .iter()
+            .map(|config| {
+                // 棒は描かない
+                let style = Style {
+                    position: config.position,
+                    width: 0,
+                    height: 0,
+                    fill: [0; 4],
+                    empty: [0; 4],
+                    background: config.background,
+                    size: config.size,
+                    text_color: config.color,
+                    radius: 0.0,
+                };
+                Ok(Text {
+                    template: config.template.clone(),
+                    panel: Panel::new(style, Some(&config.font), width, height)?,
+                    shown: None,
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { texts, variables })
+    }
+
+    /// Fills in the latest values. Returns whether any overlay looks different.
+    pub fn update(&mut self) -> bool {
+        let mut changed = false;
+        for text in &mut self.texts {
+            let rendered = self.variables.render(&text.template);
+            if rendered == text.shown {
+                continue;
+            }
+            match &rendered {
+                Some(rendered) => text.panel.set(rendered, 0),
+                None => text.panel.clear(),
+            }
+            text.shown = rendered;
+            changed = true;
+        }
+        changed
+    }
+
+    pub fn set_theme(&mut self, theme: &Theme) {
+        for text in &mut self.texts {
+            text.panel.set_theme(theme);
+        }
+    }
+
+    /// Draws the overlays where they overlap `clip`.
+    pub fn draw(&self, dst: &mut [u8], clip: Rect) {
+        for text in &self.texts {
+            text.panel.draw(dst, clip);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+// 重ねて表示するもの（発話時間・ポモドーロ・文字のオーバーレイ・調整モードのメーター）の見た目。
+// テーマファイル（TOML）から読み、ホットキーか `ctl theme [名前]` で切り替える。
+// 選ぶたびにファイルを読み直すので、編集したテーマはもう一度選べば反映される。
+use crate::{
+    config::ThemesConfig,
+    panel::{self, Style},
+    t,
+};
+use ab_glyph::FontVec;
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use winit::keyboard::KeyCode;
+
+// テーマファイルの中身。無い項目は各表示の設定のまま
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    background: Option<[u8; 4]>,
+    text: Option<[u8; 4]>,
+    accent: Option<[u8; 4]>,
+    track: Option<[u8; 4]>,
+    font: Option<PathBuf>,
+    radius: Option<f32>,
+}
+
+/// Colors, font and corner radius laid over each overlay's own settings.
+#[derive(Clone, Default)]
+pub struct Theme {
+    pub background: Option<[u8; 4]>,
+    pub text: Option<[u8; 4]>,
+    // 棒の埋まった側と残りの側
+    pub accent: Option<[u8; 4]>,
+    pub track: Option<[u8; 4]>,
+    pub font: Option<Arc<FontVec>>,
+    pub radius: Option<f32>,
+}
+
+impl Theme {
+    /// Reads a theme file; its font path is relative to the file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| t!("theme.read_failed", path.display()))?;
+        let file: ThemeFile =
+            toml::from_str(&text).with_context(|| t!("theme.read_failed", path.display()))?;
+        let font = file
+            .font
+            .map(|font| panel::load_font(&path.parent().unwrap_or(Path::new(".")).join(font)))
+            .transpose()?;
+        Ok(Self {
+            background: file.background,
+            text: file.text,
+            accent: file.accent,
+            track: file.track,
+            font,
+            radius: file.radius,
+        })
+    }
+
+    /// `style` with the theme's settings in place of its own.
+    pub fn apply(&self, style: &Style) -> Style {
+        Style {
+            fill: self.accent.unwrap_or(style.fill),
+            empty: self.track.unwrap_or(style.empty),
+            background: self.background.unwrap_or(style.background),
+            text_color: self.text.unwrap_or(style.text_color),
+            radius: self.radius.unwrap_or(style.radius),
+            ..style.clone()
+        }
+    }
+}
+
+/// The configured theme files, one of them in use.
+pub struct Themes {
+    files: Vec<PathBuf>,
+    hotkey: Option<String>,
+    current: usize,
+}
+
+impl Themes {
+    /// `config.files` must already be resolved.
+    pub fn new(config: &ThemesConfig) -> Self {
+        Self {
+            files: config.files.clone(),
+            hotkey: config.hotkey.clone(),
+            current: 0,
+        }
+    }
+
+    pub fn is_hotkey(&self, keycode: KeyCode) -> bool {
+        self.hotkey.as_deref() == Some(format!("{keycode:?}").as_str())
+    }
+
+    /// The theme in use; the overlays' own look when no themes are configured.
+    pub fn current(&self) -> Result<Theme> {
+        match self.files.get(self.current) {
+            Some(path) => Theme::load(path),
+            None => Ok(Theme::default()),
+        }
+    }
+
+    /// Switches to the theme named `name` (its file name without the extension), or to
+    /// the next one in the list with `None`. The current theme stays if this one fails.
+    pub fn select(&mut self, name: Option<&str>) -> Result<Theme> {
+        if self.files.is_empty() {
+            bail!(t!("theme.none"));
+        }
+        let index = match name {
+            Some(name) => self
+                .files
+                .iter()
+                .position(|path| path.file_stem().is_some_and(|stem| stem == name))
+                .with_context(|| t!("theme.unknown", name))?,
+            None => (self.current + 1) % self.files.len(),
+        };
+        let theme = Theme::load(&self.files[index])?;
+        self.current = index;
+        Ok(theme)
+    }
+
+    /// The name of the theme in use.
+    pub fn name(&self) -> String {
+        self.files
+            .get(self.current)
+            .and_then(|path| path.file_stem())
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
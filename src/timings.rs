@@ -0,0 +1,90 @@
+// 起動にかかった時間の内訳（--timings）。素材が多くて起動が遅いときに、どこで時間が
+// かかっているかを見る。音声デバイスは別のスレッドで開くので、どのスレッドからでも記録できる
+use crate::t;
+use std::{
+    sync::{Mutex, OnceLock},
+    time::{Duration, Instant},
+};
+
+/// A timed step of the startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Finding and opening the audio input device.
+    Devices,
+    /// Decoding and resizing every image of the avatar.
+    Images,
+    /// Creating the GPU surface for the window.
+    Surface,
+    /// From the start until the first frame was on screen.
+    FirstFrame,
+}
+
+impl Phase {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Devices => t!("timings.devices"),
+            Self::Images => t!("timings.images"),
+            Self::Surface => t!("timings.surface"),
+            Self::FirstFrame => t!("timings.first_frame"),
+        }
+    }
+}
+
+struct Timings {
+    started: Instant,
+    phases: Vec<(Phase, Duration)>,
+    // 最初のフレームまでの分を出したか（それより後に終わったものは、終わったときに出す）
+    reported: bool,
+}
+
+static TIMINGS: OnceLock<Mutex<Timings>> = OnceLock::new();
+
+/// Starts timing the startup. Until this is called nothing is measured.
+pub fn enable() {
+    let _ = TIMINGS.set(Mutex::new(Timings {
+        started: Instant::now(),
+        phases: Vec::new(),
+        reported: false,
+    }));
+}
+
+/// Runs `f`, timing it as `phase`. Only the first run of each phase counts.
+pub fn measure<T>(phase: Phase, f: impl FnOnce() -> T) -> T {
+    let Some(timings) = TIMINGS.get() else {
+        return f();
+    };
+    let start = Instant::now();
+    let value = f();
+    let elapsed = start.elapsed();
+    let mut timings = timings.lock().unwrap_or_else(|e| e.into_inner());
+    if !timings.phases.iter().any(|(p, _)| *p == phase) {
+        timings.phases.push((phase, elapsed));
+        if timings.reported {
+            print(phase, elapsed);
+        }
+    }
+    value
+}
+
+/// Called after each presented frame; the first time, prints what was measured so far.
+pub fn frame_shown() {
+    let Some(timings) = TIMINGS.get() else {
+        return;
+    };
+    let mut timings = timings.lock().unwrap_or_else(|e| e.into_inner());
+    if timings.reported {
+        return;
+    }
+    timings.reported = true;
+    let elapsed = timings.started.elapsed();
+    timings.phases.push((Phase::FirstFrame, elapsed));
+    println!("{}", t!("timings.header"));
+    for &(phase, elapsed) in &timings.phases {
+        print(phase, elapsed);
+    }
+}
+
+fn print(phase: Phase, elapsed: Duration) {
+    let ms = format!("{:.1}", elapsed.as_secs_f64() * 1000.0);
+    println!("{}", t!("timings.line", phase.label(), ms));
+}
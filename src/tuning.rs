@@ -0,0 +1,220 @@
+// 閾値の調整モード: 音量メーターと閾値を大きく表示し、矢印キーでその場で動かして設定に保存する
+use crate::{
+    avatar::Mouth, compose, panel, reactivity::Thresholds, state::RenderState, t, theme::Theme,
+};
+use std::time::{Duration, Instant};
+use winit::keyboard::KeyCode;
+
+// メーターの目盛り（dB）
+const FLOOR_DB: f32 = -80.0;
+// 1回のキー操作で変える量（約 1 dB）
+const STEP: f32 = 1.122;
+const HOLD_STEP_MS: u64 = 25;
+// ピークの目印を残しておく時間
+const PEAK_HOLD: Duration = Duration::from_millis(1500);
+
+// 乗算済みの RGBA
+const PANEL: [u8; 4] = [0, 0, 0, 0xc0];
+const WHISPER_BAND: [u8; 4] = [20, 30, 55, 0x80];
+const TALKING_BAND: [u8; 4] = [15, 40, 20, 0x80];
+const TICK: [u8; 4] = [90, 90, 90, 255];
+const THRESHOLD_LINE: [u8; 4] = [255, 255, 255, 255];
+const WHISPER_LINE: [u8; 4] = [120, 180, 255, 255];
+const PEAK_LINE: [u8; 4] = [255, 220, 60, 255];
+
+/// How the tuning mode was left.
+pub enum Finish {
+    Save,
+    Cancel,
+}
+
+/// The tuning mode: adjusts the shared thresholds from the keyboard and draws the level
+/// meter over the main window (not the stream output).
+pub struct Tuning {
+    original: (f32, Option<f32>, u64),
+    peak: f32,
+    peak_at: Instant,
+    // 板の色（乗算済み）と角の丸み。テーマがあればそれに合わせる
+    panel: [u8; 4],
+    radius: f32,
+}
+
+impl Tuning {
+    pub fn new(thresholds: &Thresholds, now: Instant, theme: &Theme) -> Self {
+        Self {
+            original: thresholds.get(),
+            peak: 0.0,
+            peak_at: now,
+            panel: theme.background.map_or(PANEL, panel::premultiplied),
+            radius: theme.radius.unwrap_or(0.0),
+        }
+    }
+
+    /// Whether the tuning mode takes `keycode` while it is open.
+    pub fn handles(keycode: KeyCode) -> bool {
+        matches!(
+            keycode,
+            KeyCode::ArrowUp
+                | KeyCode::ArrowDown
+                | KeyCode::ArrowLeft
+                | KeyCode::ArrowRight
+                | KeyCode::BracketLeft
+                | KeyCode::BracketRight
+                | KeyCode::Enter
+                | KeyCode::NumpadEnter
+                | KeyCode::Escape
+                | KeyCode::KeyT
+        )
+    }
+
+    /// Applies a key to `thresholds`. Returns how the mode ends, if it does; cancelling puts
+    /// the thresholds back as they were when it opened.
+    pub fn key(&self, keycode: KeyCode, thresholds: &Thresholds) -> Option<Finish> {
+        let (mut threshold, mut whisper, mut hold_ms) = thresholds.get();
+        match keycode {
+            // ↑↓: 発話の閾値
+            KeyCode::ArrowUp => threshold *= STEP,
+            KeyCode::ArrowDown => threshold /= STEP,
+            // →←: ささやきの閾値（小さくしすぎると無効になり、→で閾値の半分から戻る）
+            KeyCode::ArrowRight => {
+                whisper = Some(whisper.map_or(threshold / 2.0, |w| w * STEP));
+            }
+            KeyCode::ArrowLeft => {
+                whisper = whisper.map(|w| w / STEP).filter(|w| *w > threshold / 100.0);
+            }
+            // [ ]: ささやきを保つ時間
+            KeyCode::BracketLeft => hold_ms = hold_ms.saturating_sub(HOLD_STEP_MS),
+            KeyCode::BracketRight => hold_ms += HOLD_STEP_MS,
+            KeyCode::Enter | KeyCode::NumpadEnter => return Some(Finish::Save),
+            KeyCode::Escape | KeyCode::KeyT => {
+                let (threshold, whisper, hold_ms) = self.original;
+                thresholds.set(threshold, whisper, hold_ms);
+                return Some(Finish::Cancel);
+            }
+            _ => return None,
+        }
+        threshold = threshold.clamp(1e-6, 1.0);
+        // ささやきの閾値は発話の閾値より下に保つ
+        let whisper = whisper.map(|w| w.min(threshold / STEP));
+        thresholds.set(threshold, whisper, hold_ms);
+        None
+    }
+
+    /// Window title showing the current values and the keys.
+    pub fn title(thresholds: &Thresholds) -> String {
+        let (threshold, whisper, hold_ms) = thresholds.get();
+        let whisper = whisper.map_or(t!("tuning.off").to_string(), |w| format!("{w:.5}"));
+        t!("tuning.title", format!("{threshold:.5}"), whisper, hold_ms)
+    }
+
+    /// Draws the meter across the middle of a `width` × `height` frame.
+    pub fn draw(
+        &mut self,
+        frame: &mut [u8],
+        width: usize,
+        height: usize,
+        live: &RenderState,
+        thresholds: &Thresholds,
+        now: Instant,
+    ) {
+        let level = live.level;
+        if level >= self.peak || now.saturating_duration_since(self.peak_at) > PEAK_HOLD {
+            self.peak = level;
+            self.peak_at = now;
+        }
+
+        let margin = width / 20;
+        let (x0, x1) = (margin, width.saturating_sub(margin));
+        let panel_height = (height / 5).max(24).min(height);
+        let y0 = (height - panel_height) / 2;
+        let y1 = y0 + panel_height;
+        if x1 <= x0 + 2 {
+            return;
+        }
+        // 音量をメーター上の x 座標へ（対数目盛り）
+        let x_of = |level: f32| {
+            let db = 20.0 * level.max(1e-9).log10();
+            let t = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+            x0 + ((x1 - x0 - 1) as f32 * t) as usize
+        };
+
+        let (threshold, whisper, _) = thresholds.get();
+        let threshold_x = x_of(threshold);
+        // 板の形（角の丸み）に沿って、板の [from, to) の列に色を重ねる
+        let radius = self.radius;
+        let on_panel = |frame: &mut [u8], from: usize, to: usize, color: [u8; 4]| {
+            for y in y0..y1 {
+                for x in from..to.min(x1) {
+                    let coverage = panel::rounded(x - x0, y - y0, x1 - x0, y1 - y0, radius);
+                    if coverage > 0.0 {
+                        let i = (y * width + x) * 4;
+                        compose::blend_opacity(&mut frame[i..i + 4], &color, coverage);
+                    }
+                }
+            }
+        };
+        on_panel(frame, x0, x1, self.panel);
+        if let Some(whisper) = whisper {
+            on_panel(frame, x_of(whisper), threshold_x, WHISPER_BAND);
+        }
+        on_panel(frame, threshold_x, x1, TALKING_BAND);
+
+        // 10 dB ごとの目盛り
+        for db in (FLOOR_DB as i32..=0).step_by(10) {
+            let x = x_of(10f32.powf(db as f32 / 20.0));
+            fill(frame, width, (x, y1 - panel_height / 6), (x + 1, y1), TICK);
+        }
+
+        let bar = match live.mouth {
+            Mouth::Idle => [150, 150, 150, 255],
+            Mouth::Whisper => [80, 160, 255, 255],
+            Mouth::Talking => [80, 220, 120, 255],
+        };
+        let inset = panel_height / 4;
+        fill(
+            frame,
+            width,
+            (x0, y0 + inset),
+            (x_of(level), y1 - inset),
+            bar,
+        );
+
+        let peak_x = x_of(self.peak);
+        fill(
+            frame,
+            width,
+            (peak_x, y0 + inset),
+            (peak_x + 2, y1 - inset),
+            PEAK_LINE,
+        );
+        if let Some(whisper) = whisper {
+            let x = x_of(whisper);
+            fill(frame, width, (x, y0), (x + 3, y1), WHISPER_LINE);
+        }
+        fill(
+            frame,
+            width,
+            (threshold_x, y0),
+            (threshold_x + 3, y1),
+            THRESHOLD_LINE,
+        );
+    }
+}
+
+// [start, end) の矩形に色を重ねる（はみ出した分は切る）
+fn fill(
+    frame: &mut [u8],
+    width: usize,
+    start: (usize, usize),
+    end: (usize, usize),
+    color: [u8; 4],
+) {
+    let height = frame.len() / 4 / width.max(1);
+    let (x1, y1) = (end.0.min(width), end.1.min(height));
+    for y in start.1..y1 {
+        for x in start.0..x1 {
+            let i = (y * width + x) * 4;
+            compose::blend(&mut frame[i..i + 4], &color);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// キーボードを打っている間だけ手のパーツを出し、キーを押すたびに体を少し揺らす。
+// OS から読むのはキーを押した回数だけで、どのキーかは見ない
+use crate::{config::TypingConfig, desktop::Desktop, mapping::Params};
+use anyhow::Result;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+// キーを押した回数を見る間隔
+const POLL_INTERVAL: Duration = Duration::from_millis(30);
+// 押したときの揺れが戻るまでの時定数
+const SHAKE_DECAY: Duration = Duration::from_millis(60);
+
+/// Shows the typing layers and shakes the avatar while keys are being pressed.
+pub struct Typing {
+    config: TypingConfig,
+    // 別のスレッドが読んだキーを押した回数
+    presses: Arc<AtomicU64>,
+    seen: Option<u64>,
+    // 最後にキーが押された時刻
+    pressed_at: Option<Instant>,
+    // 揺れのずれ（キャンバスのピクセル）
+    shake: f32,
+    last: Option<Instant>,
+}
+
+impl Typing {
+    pub fn new(config: &TypingConfig) -> Result<Self> {
+        let desktop = Desktop::connect()?;
+        let presses = Arc::new(AtomicU64::new(0));
+        let shared = presses.clone();
+        std::thread::spawn(move || {
+            loop {
+                if let Some(count) = desktop.key_presses() {
+                    shared.store(count, Ordering::Relaxed);
+                }
+                std::thread::sleep(POLL_INTERVAL);
+            }
+        });
+        Ok(Self {
+            config: config.clone(),
+            presses,
+            seen: None,
+            pressed_at: None,
+            shake: 0.0,
+            last: None,
+        })
+    }
+
+    /// Picks up the key presses since the last frame.
+    pub fn update(&mut self, now: Instant) {
+        let dt = self
+            .last
+            .replace(now)
+            .map_or(0.0, |last| now.duration_since(last).as_secs_f32());
+        self.shake *= (-dt / SHAKE_DECAY.as_secs_f32()).exp();
+        let count = self.presses.load(Ordering::Relaxed);
+        // 最初に読んだ回数は、起動前に押した分なので数えない
+        if self.seen.replace(count).is_some_and(|seen| seen != count) {
+            self.pressed_at = Some(now);
+            self.shake = self.config.shake;
+        }
+    }
+
+    /// Whether a key was pressed within the hold time.
+    pub fn is_typing(&self, now: Instant) -> bool {
+        self.pressed_at.is_some_and(|at| {
+            now.saturating_duration_since(at) < Duration::from_millis(self.config.hold_ms)
+        })
+    }
+
+    /// Hides the typing layers unless typing and adds the shake to the whole avatar.
+    pub fn apply<'a>(
+        &self,
+        layers: impl Iterator<Item = &'a str>,
+        params: &mut Params,
+        layer_params: &mut [Params],
+        now: Instant,
+    ) {
+        let typing = self.is_typing(now);
+        for (name, params) in layers.zip(layer_params) {
+            if !typing && self.config.layers.iter().any(|layer| layer == name) {
+                params.visible = false;
+            }
+        }
+        params.offset[1] += self.shake;
+    }
+}
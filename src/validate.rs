@@ -0,0 +1,914 @@
+use crate::avatar::open_image;
+use crate::config::{self, AlertSource, Config};
+use crate::mapping;
+use crate::schedule;
+use crate::t;
+use crate::theme;
+use anyhow::{Result, bail};
+use image::GenericImageView;
+use std::{collections::HashMap, fmt, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug)]
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+    pub hint: String,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Error => t!("validate.error"),
+            Severity::Warning => t!("validate.warning"),
+        };
+        write!(
+            f,
+            "{}: {}\n  {}: {}",
+            label,
+            self.message,
+            t!("validate.hint"),
+            self.hint
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    fn error(&mut self, message: impl Into<String>, hint: impl Into<String>) {
+        self.issues.push(Issue {
+            severity: Severity::Error,
+            message: message.into(),
+            hint: hint.into(),
+        });
+    }
+
+    fn warning(&mut self, message: impl Into<String>, hint: impl Into<String>) {
+        self.issues.push(Issue {
+            severity: Severity::Warning,
+            message: message.into(),
+            hint: hint.into(),
+        });
+    }
+
+    pub fn error_count(&self) -> usize {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Error)
+            .count()
+    }
+
+    pub fn warning_count(&self) -> usize {
+        self.issues.len() - self.error_count()
+    }
+}
+
+// 読み込んだ画像の情報（フレーム間の比較用）
+struct ImageInfo {
+    width: u32,
+    height: u32,
+    has_alpha: bool,
+}
+
+pub fn validate(config: &Config) -> Report {
+    let mut report = Report::default();
+
+    if config.canvas.width == 0 || config.canvas.height == 0 {
+        report.error(
+            t!(
+                "validate.canvas_size",
+                config.canvas.width,
+                config.canvas.height
+            ),
+            t!("validate.canvas_size.hint"),
+        );
+    }
+
+    if !(config.audio.threshold > 0.0 && config.audio.threshold < 1.0) {
+        report.error(
+            t!("validate.threshold", config.audio.threshold),
+            t!("validate.threshold.hint"),
+        );
+    }
+
+    if let Some(whisper) = config.audio.whisper_threshold
+        && !(whisper > 0.0 && whisper < config.audio.threshold)
+    {
+        report.error(
+            t!(
+                "validate.whisper_threshold",
+                whisper,
+                config.audio.threshold
+            ),
+            t!("validate.whisper_threshold.hint"),
+        );
+    }
+
+    if !config::ANALYSIS_RATES.contains(&config.audio.analysis_rate) {
+        report.error(
+            t!("validate.analysis_rate", config.audio.analysis_rate),
+            t!("validate.analysis_rate.hint"),
+        );
+    }
+
+    if !(0.0..=4.0).contains(&config.audio.monitor.volume) {
+        report.warning(
+            t!("validate.monitor_volume", config.audio.monitor.volume),
+            t!("validate.monitor_volume.hint"),
+        );
+    }
+
+    if let (Some(highpass), Some(lowpass)) = (config.audio.highpass, config.audio.lowpass)
+        && highpass >= lowpass
+    {
+        report.error(
+            t!("validate.filter_cutoff", highpass, lowpass),
+            t!("validate.filter_cutoff.hint"),
+        );
+    }
+
+    if let Some(voice) = &config.audio.voice_reference
+        && !(0.0..=1.0).contains(&voice.correlation)
+    {
+        report.error(
+            t!("validate.voice_correlation", voice.correlation),
+            t!("validate.voice_correlation.hint"),
+        );
+    }
+
+    if let Some(quality) = &config.quality
+        && !(quality.recover_ratio > 0.0 && quality.recover_ratio < 1.0)
+    {
+        report.warning(
+            t!("validate.quality_ratio", quality.recover_ratio),
+            t!("validate.quality_ratio.hint"),
+        );
+    }
+
+    if let Some(squash) = &config.squash
+        && !(0.0..1.0).contains(&squash.intensity)
+    {
+        report.error(
+            t!("validate.squash_intensity", squash.intensity),
+            t!("validate.squash_intensity.hint"),
+        );
+    }
+
+    if let Some(echo) = &config.audio.echo_cancellation
+        && !(echo.step > 0.0 && echo.step <= 1.0)
+    {
+        report.error(
+            t!("validate.echo_step", echo.step),
+            t!("validate.echo_step.hint"),
+        );
+    }
+
+    if let Some(adaptive) = &config.audio.adaptive
+        && !(adaptive.percentile > 0.0
+            && adaptive.percentile < 100.0
+            && adaptive.window_secs > 0.0
+            && adaptive.min > 0.0
+            && adaptive.min <= adaptive.max)
+    {
+        report.error(
+            t!(
+                "validate.adaptive",
+                adaptive.percentile,
+                adaptive.window_secs,
+                adaptive.min,
+                adaptive.max
+            ),
+            t!("validate.adaptive.hint"),
+        );
+    }
+
+    if !(config.preview.scale > 0.0 && config.preview.scale <= 1.0) {
+        report.error(
+            t!("validate.preview_scale", config.preview.scale),
+            t!("validate.preview_scale.hint"),
+        );
+    }
+
+    if config.expressions.is_empty() {
+        report.error(
+            t!("validate.no_expressions"),
+            t!("validate.no_expressions.hint"),
+        );
+    } else if !config.expressions.contains_key(&config.default_expression) {
+        let names: Vec<&str> = config.expressions.keys().map(String::as_str).collect();
+        report.error(
+            t!("validate.default_missing", config.default_expression),
+            t!("validate.default_missing.hint", names.join(", ")),
+        );
+    }
+
+    // 同じ画像を何度もデコードしないようにキャッシュ
+    let mut cache: HashMap<std::path::PathBuf, Option<ImageInfo>> = HashMap::new();
+
+    for (name, expression) in &config.expressions {
+        // ささやきフレームは任意
+        for (state, frames) in [("idle", &expression.idle), ("talking", &expression.talking)] {
+            if frames.is_empty() {
+                report.error(
+                    t!("validate.no_frames", name, state),
+                    t!("validate.no_frames.hint", name, state),
+                );
+            }
+        }
+
+        let mut first: Option<(&Path, u32, u32, bool)> = None;
+        for frame in expression
+            .states()
+            .into_iter()
+            .flat_map(|(_, frames)| frames)
+        {
+            if frame.clip.is_some() || frame.slice.is_some() {
+                report.warning(
+                    t!("validate.clip_frame", name, frame.path.display()),
+                    t!("validate.clip_frame.hint"),
+                );
+            }
+            let path = config.resolve(&frame.path);
+            let info = cache
+                .entry(path.clone())
+                .or_insert_with(|| check_image(&path, config, &mut report));
+            let Some(info) = info else { continue };
+
+            match first {
+                None => first = Some((&frame.path, info.width, info.height, info.has_alpha)),
+                Some((first_frame, w, h, alpha)) => {
+                    if (w, h) != (info.width, info.height) {
+                        report.warning(
+                            t!(
+                                "validate.size_mismatch",
+                                name,
+                                frame.path.display(),
+                                info.width,
+                                info.height,
+                                first_frame.display(),
+                                w,
+                                h
+                            ),
+                            t!("validate.size_mismatch.hint"),
+                        );
+                    }
+                    if alpha != info.has_alpha {
+                        report.warning(
+                            t!(
+                                "validate.alpha_mismatch",
+                                name,
+                                frame.path.display(),
+                                first_frame.display()
+                            ),
+                            t!("validate.alpha_mismatch.hint"),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(video) = &config.video {
+        if video.format.is_none() && !config.resolve(Path::new(&video.input)).exists() {
+            report.error(
+                t!("validate.video_input", video.input),
+                t!("validate.video_input.hint"),
+            );
+        }
+        for state in &video.states {
+            if !matches!(state.as_str(), "idle" | "whisper" | "talking") {
+                report.error(
+                    t!("validate.video_state", state),
+                    t!("validate.video_state.hint"),
+                );
+            }
+        }
+    }
+
+    for (i, slot) in config.slots.iter().enumerate() {
+        let [x, y, w, h] = slot.rect;
+        let canvas = (config.canvas.width as i32, config.canvas.height as i32);
+        if w <= 0 || h <= 0 || x >= canvas.0 || y >= canvas.1 || x + w <= 0 || y + h <= 0 {
+            report.error(
+                t!("validate.slot_rect", i, format!("{:?}", slot.rect)),
+                t!("validate.slot_rect.hint", canvas.0, canvas.1),
+            );
+        }
+        if !config.expressions.contains_key(&slot.expression) {
+            report.error(
+                t!("validate.slot_expression", i, slot.expression),
+                t!("validate.sequence_expression.hint"),
+            );
+        }
+        if slot.feed.is_some() && slot.input.is_some() {
+            report.error(
+                t!("validate.slot_feed_input", i),
+                t!("validate.slot_feed_input.hint"),
+            );
+        }
+        if slot.feed.is_some() && config.feed.as_ref().is_none_or(|f| f.listen.is_none()) {
+            report.error(
+                t!("validate.slot_feed_listen", i),
+                t!("validate.slot_feed_listen.hint"),
+            );
+        }
+    }
+    if let Some(feed) = &config.feed
+        && !(feed.rate > 0.0 && feed.rate.is_finite())
+    {
+        report.error(
+            t!("validate.feed_rate", feed.rate),
+            t!("validate.feed_rate.hint"),
+        );
+    }
+
+    for trigger in config.mqtt.iter().flat_map(|m| &m.triggers) {
+        if let Some(expression) = &trigger.expression
+            && !config.expressions.contains_key(expression)
+        {
+            report.error(
+                t!("validate.mqtt_expression", trigger.topic, expression),
+                t!("validate.sequence_expression.hint"),
+            );
+        }
+        if let Some(sequence) = &trigger.sequence
+            && !config.sequences.contains_key(sequence)
+        {
+            report.error(
+                t!("validate.mqtt_sequence", trigger.topic, sequence),
+                t!("validate.mqtt_sequence.hint"),
+            );
+        }
+    }
+
+    for (name, frame) in &config.layers {
+        let info = check_image(&config.resolve(&frame.path), config, &mut report);
+        // 9スライスの枠が画像をはみ出すと、伸ばす部分が残らない
+        if let (Some([top, right, bottom, left]), Some(info)) = (frame.slice, info)
+            && (left + right >= info.width || top + bottom >= info.height)
+        {
+            report.warning(
+                t!("validate.slice_too_wide", name, info.width, info.height),
+                t!("validate.slice_too_wide.hint"),
+            );
+        }
+        // 切り抜く元は、切り抜かれていない別のレイヤー
+        if let Some(target) = &frame.clip {
+            let base = config.layers.get(target);
+            if target == name || base.is_none_or(|base| base.clip.is_some()) {
+                report.error(
+                    t!("validate.clip_layer", name, target),
+                    t!("validate.clip_layer.hint"),
+                );
+            }
+        }
+    }
+    for m in &config.mappings {
+        match mapping::parse_parameter(&m.parameter) {
+            None => report.error(
+                t!("validate.mapping_parameter", m.parameter),
+                t!("validate.mapping_parameter.hint"),
+            ),
+            Some(Some(layer)) if !config.layers.contains_key(layer) => report.error(
+                t!("validate.mapping_layer", m.parameter, layer),
+                t!("validate.mapping_layer.hint"),
+            ),
+            Some(_) => {}
+        }
+    }
+
+    for layer in config.eyes.iter().flat_map(|eyes| &eyes.layers) {
+        if !config.layers.contains_key(layer) {
+            report.error(
+                t!("validate.eyes_layer", layer),
+                t!("validate.mapping_layer.hint"),
+            );
+        }
+    }
+
+    for layer in config.typing.iter().flat_map(|typing| &typing.layers) {
+        if !config.layers.contains_key(layer) {
+            report.error(
+                t!("validate.typing_layer", layer),
+                t!("validate.mapping_layer.hint"),
+            );
+        }
+    }
+
+    for (feature, smoothing) in &config.smoothing {
+        if let (Some(min), Some(max)) = (smoothing.min, smoothing.max)
+            && min > max
+        {
+            report.error(
+                t!("validate.smoothing_range", feature.name(), min, max),
+                t!("validate.smoothing_range.hint"),
+            );
+        }
+        if !(smoothing.deadzone >= 0.0 && smoothing.deadzone.is_finite()) {
+            report.error(
+                t!(
+                    "validate.smoothing_deadzone",
+                    feature.name(),
+                    smoothing.deadzone
+                ),
+                t!("validate.smoothing_deadzone.hint"),
+            );
+        }
+    }
+
+    if let Some(emotion) = &config.emotion {
+        let expressions = [
+            ("calm", &emotion.calm),
+            ("excited", &emotion.excited),
+            ("angry", &emotion.angry),
+        ];
+        for (name, expression) in expressions {
+            if let Some(expression) = expression
+                && !config.expressions.contains_key(expression)
+            {
+                report.error(
+                    t!("validate.emotion_expression", name, expression),
+                    t!("validate.sequence_expression.hint"),
+                );
+            }
+        }
+    }
+
+    for (i, source) in config.priority.order.iter().enumerate() {
+        if config.priority.order[..i].contains(source) {
+            report.error(
+                t!("validate.priority_duplicate", source.name()),
+                t!("validate.priority_duplicate.hint"),
+            );
+        }
+    }
+    for (source, timeout) in &config.priority.timeout {
+        if timeout.is_nan() || *timeout < 0.0 {
+            report.error(
+                t!("validate.priority_timeout", source.name(), timeout),
+                t!("validate.priority_timeout.hint"),
+            );
+        }
+    }
+
+    let rate_limit = &config.rate_limit;
+    let cooldowns = std::iter::once((None, &rate_limit.cooldown))
+        .chain(rate_limit.cooldowns.iter().map(|(name, c)| (Some(name), c)));
+    for (name, cooldown) in cooldowns {
+        if let Some(name) = name
+            && !config.sequences.contains_key(name)
+        {
+            report.error(
+                t!("validate.rate_limit_sequence", name),
+                t!("validate.mqtt_sequence.hint"),
+            );
+        }
+        if cooldown.is_nan() || *cooldown < 0.0 {
+            report.error(
+                t!("validate.rate_limit_cooldown", cooldown),
+                t!("validate.rate_limit_cooldown.hint"),
+            );
+        }
+    }
+    if rate_limit.per_minute == Some(0) {
+        report.error(
+            t!("validate.rate_limit_per_minute"),
+            t!("validate.rate_limit_per_minute.hint"),
+        );
+    }
+
+    if let Some(music) = &config.music
+        && !(music.min_bpm > 0.0 && music.min_bpm < music.max_bpm)
+    {
+        report.error(
+            t!("validate.music_bpm", music.min_bpm, music.max_bpm),
+            t!("validate.music_bpm.hint"),
+        );
+    }
+
+    if let Some(mascot) = &config.mascot
+        && !(mascot.speed >= 0.0 && mascot.min_rest >= 0.0 && mascot.min_rest <= mascot.max_rest)
+    {
+        report.error(
+            t!(
+                "validate.mascot",
+                mascot.speed,
+                mascot.min_rest,
+                mascot.max_rest
+            ),
+            t!("validate.mascot.hint"),
+        );
+    }
+
+    if let Some(captions) = &config.captions {
+        for path in [&captions.model, &captions.font] {
+            if !config.resolve(path).exists() {
+                report.error(
+                    t!("validate.missing_file", path.display()),
+                    t!("validate.missing_file.hint"),
+                );
+            }
+        }
+    }
+
+    if let Some(alerts) = &config.alerts {
+        if alerts.source == AlertSource::Twitch && alerts.client_id.is_none() {
+            report.error(
+                t!("alerts.no_client_id"),
+                t!("validate.alerts_client_id.hint"),
+            );
+        }
+        for sequence in [&alerts.follow, &alerts.subscribe, &alerts.raid]
+            .into_iter()
+            .flatten()
+        {
+            if !config.sequences.contains_key(sequence) {
+                report.error(
+                    t!("validate.alert_sequence", sequence),
+                    t!("validate.mqtt_sequence.hint"),
+                );
+            }
+        }
+    }
+
+    for schedule in &config.schedules {
+        if let Err(e) = schedule::check(schedule) {
+            report.error(e.to_string(), t!("validate.schedule.hint"));
+        }
+        if !config.sequences.contains_key(&schedule.sequence) {
+            report.error(
+                t!("validate.schedule_sequence", schedule.sequence),
+                t!("validate.mqtt_sequence.hint"),
+            );
+        }
+    }
+
+    for outfit in &config.outfits {
+        if let Err(e) = schedule::check_outfit(outfit) {
+            report.error(e.to_string(), t!("validate.outfit.hint"));
+        }
+        if let Some(profile) = &outfit.profile
+            && !config.resolve(profile).exists()
+        {
+            report.error(
+                t!("validate.missing_file", profile.display()),
+                t!("validate.missing_file.hint"),
+            );
+        }
+        // 切り替えた先のプロファイルにだけあるパーツかもしれないので警告にとどめる
+        for layer in outfit.show.iter().chain(&outfit.hide) {
+            if !config.layers.contains_key(layer) {
+                report.warning(
+                    t!("validate.outfit_layer", layer),
+                    t!("validate.mapping_layer.hint"),
+                );
+            }
+        }
+    }
+
+    for rule in &config.focus {
+        // どちらも無ければどのアプリにも当てはまらない
+        if rule.app.is_none() && rule.title.is_none() {
+            report.error(t!("validate.focus_match"), t!("validate.focus_match.hint"));
+        }
+        if let Some(expression) = &rule.expression
+            && !config.expressions.contains_key(expression)
+        {
+            report.error(
+                t!("validate.focus_expression", expression),
+                t!("validate.sequence_expression.hint"),
+            );
+        }
+        for layer in rule.show.iter().chain(&rule.hide) {
+            if !config.layers.contains_key(layer) {
+                report.warning(
+                    t!("validate.focus_layer", layer),
+                    t!("validate.mapping_layer.hint"),
+                );
+            }
+        }
+    }
+    if config.focus.iter().any(|rule| rule.expression.is_some())
+        && !config
+            .priority
+            .order
+            .contains(&config::ExpressionSource::Focus)
+    {
+        report.warning(
+            t!("validate.focus_priority"),
+            t!("validate.focus_priority.hint"),
+        );
+    }
+
+    let fonts = [
+        config.talk_time.as_ref().and_then(|t| t.font.as_ref()),
+        config.pomodoro.as_ref().and_then(|p| p.font.as_ref()),
+    ];
+    let text_fonts = config.texts.iter().map(|text| &text.font);
+    for font in fonts.into_iter().flatten().chain(text_fonts) {
+        if !config.resolve(font).exists() {
+            report.error(
+                t!("validate.missing_file", font.display()),
+                t!("validate.missing_file.hint"),
+            );
+        }
+    }
+
+    let data = &config.data;
+    let weather_refresh = data.weather.as_ref().map_or(1.0, |w| w.refresh_minutes);
+    if !(data.refresh_secs > 0.0 && weather_refresh > 0.0) {
+        report.error(
+            t!("validate.data_refresh", data.refresh_secs, weather_refresh),
+            t!("validate.data_refresh.hint"),
+        );
+    }
+
+    for path in &config.themes.files {
+        if let Err(e) = theme::Theme::load(&config.resolve(path)) {
+            report.error(format!("{e:#}"), t!("validate.theme.hint"));
+        }
+    }
+
+    if let Some(remote) = &config.remote {
+        // 短いトークンは総当たりで当てられる
+        let tokens = [
+            remote.token.as_ref(),
+            remote.viewers.as_ref().and_then(|v| v.token.as_ref()),
+        ];
+        if tokens.into_iter().flatten().any(|token| token.len() < 16) {
+            report.error(
+                t!("validate.remote_token"),
+                t!("validate.remote_token.hint"),
+            );
+        }
+        let reactions = remote.viewers.iter().flat_map(|v| &v.reactions);
+        for reaction in reactions {
+            if !config.sequences.contains_key(reaction) {
+                report.error(
+                    t!("validate.viewer_reaction", reaction),
+                    t!("validate.mqtt_sequence.hint"),
+                );
+            }
+        }
+        let tls = remote.tls.iter().flat_map(|tls| [&tls.cert, &tls.key]);
+        for path in tls {
+            if !config.resolve(path).exists() {
+                report.error(
+                    t!("validate.missing_file", path.display()),
+                    t!("validate.missing_file.hint"),
+                );
+            }
+        }
+    }
+
+    if let Some(pomodoro) = &config.pomodoro {
+        let periods = [
+            pomodoro.work_minutes,
+            pomodoro.break_minutes,
+            pomodoro.long_break_minutes,
+        ];
+        if !periods
+            .iter()
+            .all(|&minutes| minutes > 0.0 && minutes.is_finite())
+        {
+            report.error(
+                t!(
+                    "validate.pomodoro_minutes",
+                    periods[0],
+                    periods[1],
+                    periods[2]
+                ),
+                t!("validate.pomodoro_minutes.hint"),
+            );
+        }
+        if let Some(expression) = &pomodoro.expression
+            && !config.expressions.contains_key(expression)
+        {
+            report.error(
+                t!("validate.pomodoro_expression", expression),
+                t!("validate.sequence_expression.hint"),
+            );
+        }
+    }
+
+    if let Some(gacha) = &config.gacha {
+        if !(gacha.duration_secs > 0.0 && gacha.duration_secs.is_finite()) {
+            report.error(
+                t!("validate.gacha_duration", gacha.duration_secs),
+                t!("validate.gacha_duration.hint"),
+            );
+        }
+        for (expression, weight) in &gacha.weights {
+            if !config.expressions.contains_key(expression) {
+                report.error(
+                    t!("validate.gacha_expression", expression),
+                    t!("validate.sequence_expression.hint"),
+                );
+            }
+            if !(*weight >= 0.0 && weight.is_finite()) {
+                report.error(
+                    t!("validate.gacha_weight", expression, weight),
+                    t!("validate.gacha_weight.hint"),
+                );
+            }
+        }
+        // 順位に無い入力は無視されるので、引いても表情が変わらない
+        if !config
+            .priority
+            .order
+            .contains(&config::ExpressionSource::Gacha)
+        {
+            report.warning(
+                t!("validate.gacha_priority"),
+                t!("validate.gacha_priority.hint"),
+            );
+        }
+    }
+
+    let mut hotkeys: HashMap<&str, &str> = HashMap::new();
+    // まとめて取りやめるキーや表示の切り替えは、シーケンスのキーより先に見る
+    if let Some(skip) = config.rate_limit.skip_hotkey.as_deref() {
+        hotkeys.insert(skip, "rate_limit.skip_hotkey");
+    }
+    if let Some(toggle) = config.talk_time.as_ref().and_then(|t| t.hotkey.as_deref()) {
+        hotkeys.insert(toggle, "talk_time.hotkey");
+    }
+    if let Some(dismiss) = config.pomodoro.as_ref().and_then(|p| p.hotkey.as_deref()) {
+        hotkeys.insert(dismiss, "pomodoro.hotkey");
+    }
+    if let Some(roll) = config.gacha.as_ref().and_then(|g| g.hotkey.as_deref()) {
+        hotkeys.insert(roll, "gacha.hotkey");
+    }
+    if let Some(next) = config.themes.hotkey.as_deref() {
+        hotkeys.insert(next, "themes.hotkey");
+    }
+    if let Some(qr) = config.remote.as_ref().and_then(|r| r.qr_hotkey.as_deref()) {
+        hotkeys.insert(qr, "remote.qr_hotkey");
+    }
+    for (name, sequence) in &config.sequences {
+        if sequence.keyframes.is_empty() {
+            report.warning(
+                t!("validate.sequence_empty", name),
+                t!("validate.sequence_empty.hint"),
+            );
+        }
+
+        if let Some(hotkey) = sequence.hotkey.as_deref() {
+            // F・T・Escape はメインウィンドウが使う
+            if matches!(hotkey, "KeyF" | "KeyT" | "Escape") {
+                report.error(
+                    t!("validate.hotkey_reserved", name, hotkey),
+                    t!("validate.hotkey_reserved.hint"),
+                );
+            } else if let Some(other) = hotkeys.insert(hotkey, name) {
+                report.error(
+                    t!("validate.hotkey_duplicate", hotkey, other, name),
+                    t!("validate.hotkey_duplicate.hint"),
+                );
+            }
+        }
+
+        for keyframe in &sequence.keyframes {
+            if let Some(expression) = &keyframe.expression
+                && !config.expressions.contains_key(expression)
+            {
+                report.error(
+                    t!("validate.sequence_expression", name, expression),
+                    t!("validate.sequence_expression.hint"),
+                );
+            }
+            if let Some(sound) = &keyframe.sound
+                && !config.resolve(sound).exists()
+            {
+                report.error(
+                    t!("validate.missing_file", sound.display()),
+                    t!("validate.missing_file.hint"),
+                );
+            }
+            if let Some(particles) = &keyframe.particles {
+                check_sprite(&config.resolve(&particles.image), &mut report);
+            }
+        }
+    }
+
+    report
+}
+
+// パーティクルの画像はキャンバスと比べないので、読めるかだけを見る
+fn check_sprite(path: &Path, report: &mut Report) {
+    if !path.exists() {
+        report.error(
+            t!("validate.missing_file", path.display()),
+            t!("validate.missing_file.hint"),
+        );
+    } else if let Err(e) = open_image(path) {
+        report.error(
+            t!("validate.decode_failed", path.display(), e),
+            t!("validate.decode_failed.hint"),
+        );
+    }
+}
+
+fn check_image(path: &Path, config: &Config, report: &mut Report) -> Option<ImageInfo> {
+    if !path.exists() {
+        report.error(
+            t!("validate.missing_file", path.display()),
+            t!("validate.missing_file.hint"),
+        );
+        return None;
+    }
+
+    let img = match open_image(path) {
+        Ok(img) => img,
+        Err(e) => {
+            report.error(
+                t!("validate.decode_failed", path.display(), e),
+                t!("validate.decode_failed.hint"),
+            );
+            return None;
+        }
+    };
+
+    let (width, height) = img.dimensions();
+    let has_alpha = img.color().has_alpha();
+    let canvas = &config.canvas;
+
+    if width == 0 || height == 0 {
+        report.error(
+            t!("validate.zero_size", path.display()),
+            t!("validate.zero_size.hint"),
+        );
+        return None;
+    }
+
+    if canvas.width > 0 && canvas.height > 0 {
+        let image_aspect = width as f32 / height as f32;
+        let canvas_aspect = canvas.width as f32 / canvas.height as f32;
+        if (image_aspect / canvas_aspect - 1.0).abs() > 0.02 {
+            report.warning(
+                t!(
+                    "validate.stretched",
+                    path.display(),
+                    width,
+                    height,
+                    canvas.width,
+                    canvas.height
+                ),
+                t!("validate.stretched.hint"),
+            );
+        }
+        if width * 2 < canvas.width || height * 2 < canvas.height {
+            report.warning(
+                t!("validate.too_small", path.display(), width, height),
+                t!("validate.too_small.hint"),
+            );
+        }
+    }
+
+    if has_alpha && img.to_rgba8().pixels().all(|p| p[3] == 0) {
+        report.error(
+            t!("validate.transparent", path.display()),
+            t!("validate.transparent.hint"),
+        );
+    }
+
+    Some(ImageInfo {
+        width,
+        height,
+        has_alpha,
+    })
+}
+
+pub fn run(path: &Path) -> Result<()> {
+    let file = config::config_file_path(path);
+    let config = Config::load(path)?;
+    let report = validate(&config);
+
+    for issue in &report.issues {
+        println!("{}", issue);
+    }
+
+    println!(
+        "{}",
+        t!(
+            "validate.summary",
+            file.display(),
+            report.error_count(),
+            report.warning_count()
+        )
+    );
+
+    if report.error_count() > 0 {
+        bail!(t!("validate.failed"));
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use crate::{compose, config::VideoConfig, t};
+use anyhow::{Context, Result};
+use std::{
+    io::Read,
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::{Arc, Mutex},
+};
+
+/// Chroma-keyed video played through an `ffmpeg` child process, decoded to canvas-sized RGBA.
+pub struct VideoSource {
+    child: Child,
+    latest: Arc<Mutex<Option<Vec<u8>>>>,
+    states: Vec<String>,
+}
+
+impl VideoSource {
+    pub fn start(config: &VideoConfig, base_dir: &Path, width: u32, height: u32) -> Result<Self> {
+        let mut command = Command::new("ffmpeg");
+        command.args(["-hide_banner", "-loglevel", "error"]);
+        match &config.format {
+            // キャプチャデバイス（v4l2, dshow, avfoundation など）
+            Some(format) => {
+                command.args(["-f", format, "-i", &config.input]);
+            }
+            None => {
+                let path = base_dir.join(&config.input);
+                if config.looped {
+                    command.args(["-stream_loop", "-1"]);
+                }
+                command.arg("-re").arg("-i").arg(path);
+            }
+        }
+        command
+            .args(["-an", "-vf"])
+            .arg(format!("scale={width}:{height}"))
+            .args(["-pix_fmt", "rgba", "-f", "rawvideo", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped());
+        let mut child = command.spawn().context(t!("video.spawn_failed"))?;
+        let mut stdout = child.stdout.take().context(t!("video.spawn_failed"))?;
+
+        let latest = Arc::new(Mutex::new(None));
+        let latest_clone = latest.clone();
+        let key = Key::new(config);
+        let size = (width * height * 4) as usize;
+        std::thread::spawn(move || {
+            let mut frame = vec![0u8; size];
+            while stdout.read_exact(&mut frame).is_ok() {
+                key.apply(&mut frame);
+                compose::premultiply(&mut frame);
+                *latest_clone.lock().unwrap() = Some(frame.clone());
+            }
+            tracing::info!("{}", t!("video.ended"));
+        });
+
+        tracing::info!(input = %config.input, "{}", t!("video.started", config.input));
+        Ok(Self {
+            child,
+            latest,
+            states: config.states.clone(),
+        })
+    }
+
+    /// Whether the video replaces the image frames in this mouth state.
+    pub fn shown_in(&self, state: &str) -> bool {
+        self.states.iter().any(|s| s == state)
+    }
+
+    /// Copies the most recent decoded frame into `dst`. Returns `false` before the first frame.
+    pub fn copy_latest(&self, dst: &mut Vec<u8>) -> bool {
+        match &*self.latest.lock().unwrap() {
+            Some(frame) => {
+                dst.clear();
+                dst.extend_from_slice(frame);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Drop for VideoSource {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// キー色からの距離でアルファを決める（tolerance 以内は透明、softness の幅でフェード）
+struct Key {
+    color: [f32; 3],
+    tolerance: f32,
+    softness: f32,
+}
+
+impl Key {
+    fn new(config: &VideoConfig) -> Self {
+        Self {
+            color: config.key_color.map(|c| c as f32 / 255.0),
+            tolerance: config.tolerance,
+            softness: config.softness.max(f32::EPSILON),
+        }
+    }
+
+    fn apply(&self, frame: &mut [u8]) {
+        for pixel in frame.chunks_exact_mut(4) {
+            let d = (0..3)
+                .map(|i| {
+                    let c = pixel[i] as f32 / 255.0 - self.color[i];
+                    c * c
+                })
+                .sum::<f32>()
+                .sqrt()
+                / 3f32.sqrt();
+            let alpha = ((d - self.tolerance) / self.softness).clamp(0.0, 1.0);
+            pixel[3] = (pixel[3] as f32 * alpha) as u8;
+        }
+    }
+}
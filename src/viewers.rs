@@ -0,0 +1,125 @@
+// 視聴者からのリアクション（チャットのボットなどが中継する）。リモコンとは別の入口で、
+// 許可したシーケンスだけを、視聴者ごとと全体の回数制限のもとで受け付ける。
+// 受け付けたものは外部サービスのトリガーとして、さらに rate_limit の順番待ちに入る。
+use crate::config::ViewerConfig;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Why a viewer's reaction was turned away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Refusal {
+    /// A moderator turned reactions off.
+    Paused,
+    /// The viewer is on the block list.
+    Blocked,
+    /// Not one of the allowed reactions.
+    Unknown,
+    /// The same viewer reacted too recently.
+    TooSoon,
+    /// Too many reactions from everyone in the last minute.
+    TooMany,
+}
+
+impl Refusal {
+    /// The HTTP status line to answer with.
+    pub fn status(self) -> &'static str {
+        match self {
+            Self::Paused | Self::Blocked => "403 Forbidden",
+            Self::Unknown => "404 Not Found",
+            Self::TooSoon | Self::TooMany => "429 Too Many Requests",
+        }
+    }
+}
+
+#[derive(Default)]
+struct Limits {
+    // 視聴者ごとの、最後に受け付けた時刻
+    last: HashMap<String, Instant>,
+    // 直近1分に受け付けた時刻
+    accepted: VecDeque<Instant>,
+}
+
+/// Admits viewer reactions from the allowed list, within the configured limits.
+pub struct Viewers {
+    config: ViewerConfig,
+    enabled: AtomicBool,
+    limits: Mutex<Limits>,
+}
+
+impl Viewers {
+    pub fn new(config: &ViewerConfig) -> Self {
+        Self {
+            config: config.clone(),
+            enabled: AtomicBool::new(config.enabled),
+            limits: Mutex::default(),
+        }
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.config.token.as_deref()
+    }
+
+    pub fn reactions(&self) -> &[String] {
+        &self.config.reactions
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Counts `name` from `user` against the limits if it may play.
+    pub fn admit(&self, name: &str, user: &str, now: Instant) -> Result<(), Refusal> {
+        if !self.is_enabled() {
+            return Err(Refusal::Paused);
+        }
+        if self
+            .config
+            .blocked
+            .iter()
+            .any(|b| b.eq_ignore_ascii_case(user))
+        {
+            return Err(Refusal::Blocked);
+        }
+        if !self.config.reactions.iter().any(|r| r == name) {
+            return Err(Refusal::Unknown);
+        }
+        // チャットの名前は大文字小文字を区別しない
+        let user = user.to_ascii_lowercase();
+        // 長すぎて Duration に入らない間隔は、二度と受け付けないのと同じ
+        let cooldown = Duration::try_from_secs_f32(self.config.user_cooldown.max(0.0))
+            .unwrap_or(Duration::MAX);
+        let mut limits = self.limits.lock().unwrap();
+        // 古い記録は捨てて、視聴者が増えても大きくならないようにする
+        limits
+            .last
+            .retain(|_, last| now.saturating_duration_since(*last) < cooldown);
+        while limits
+            .accepted
+            .front()
+            .is_some_and(|accepted| now.saturating_duration_since(*accepted) >= WINDOW)
+        {
+            limits.accepted.pop_front();
+        }
+        if limits.last.contains_key(&user) {
+            return Err(Refusal::TooSoon);
+        }
+        if limits.accepted.len() >= self.config.per_minute as usize {
+            return Err(Refusal::TooMany);
+        }
+        limits.last.insert(user, now);
+        limits.accepted.push_back(now);
+        Ok(())
+    }
+}
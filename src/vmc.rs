@@ -0,0 +1,172 @@
+// VMC プロトコル（OSC over UDP）でフェイストラッキングのアプリからブレンドシェイプを受け取る。
+// /VMC/Ext/Blend/Val で名前と値が届き、/VMC/Ext/Blend/Apply でひとまとまりになる
+use crate::{config::VmcConfig, t};
+use anyhow::{Context, Result};
+use std::{
+    io::ErrorKind,
+    net::UdpSocket,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+// これだけ届かなければトラッキングが止まったとみなす
+const STALE: Duration = Duration::from_secs(1);
+
+const BLEND_VALUE: &str = "/VMC/Ext/Blend/Val";
+const BLEND_APPLY: &str = "/VMC/Ext/Blend/Apply";
+
+/// The tracked values of the last applied frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tracked {
+    pub mouth_open: f32,
+    pub blink: f32,
+}
+
+/// Latest tracking received by the listener thread.
+pub struct Tracking {
+    latest: Arc<Mutex<Option<(Tracked, Instant)>>>,
+}
+
+impl Tracking {
+    /// The last applied frame, unless tracking stopped.
+    pub fn latest(&self, now: Instant) -> Option<Tracked> {
+        let latest = *self.latest.lock().unwrap_or_else(|e| e.into_inner());
+        latest
+            .filter(|(_, at)| now.saturating_duration_since(*at) < STALE)
+            .map(|(tracked, _)| tracked)
+    }
+}
+
+// OSC の引数（VMC で使うものだけ）
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Arg<'a> {
+    Str(&'a str),
+    Float(f32),
+    // VMC のブレンドシェイプでは使わない型
+    Other,
+}
+
+// 0 終端で4バイト境界まで詰めた文字列と、その後ろ
+fn osc_string(data: &[u8]) -> Option<(&str, &[u8])> {
+    let end = data.iter().position(|&b| b == 0)?;
+    let text = std::str::from_utf8(&data[..end]).ok()?;
+    let padded = (end / 4 + 1) * 4;
+    Some((text, data.get(padded..)?))
+}
+
+fn osc_word(data: &[u8]) -> Option<([u8; 4], &[u8])> {
+    let (word, rest) = data.split_first_chunk::<4>()?;
+    Some((*word, rest))
+}
+
+// パケット（バンドルなら中身を順に）のメッセージごとに `f` を呼ぶ
+fn parse_packet<'a>(data: &'a [u8], f: &mut impl FnMut(&'a str, &[Arg<'a>])) -> Option<()> {
+    if let Some(mut rest) = data.strip_prefix(b"#bundle\0") {
+        // タイムタグは見ない
+        rest = rest.get(8..)?;
+        while !rest.is_empty() {
+            let (size, tail) = osc_word(rest)?;
+            let size = usize::try_from(i32::from_be_bytes(size)).ok()?;
+            // 読めないメッセージは飛ばして、残りは読む
+            let _ = parse_packet(tail.get(..size)?, f);
+            rest = &tail[size..];
+        }
+        return Some(());
+    }
+    let (address, rest) = osc_string(data)?;
+    let (tags, mut rest) = osc_string(rest)?;
+    let mut args = Vec::new();
+    for tag in tags.strip_prefix(',')?.chars() {
+        let arg = match tag {
+            's' => {
+                let (text, tail) = osc_string(rest)?;
+                rest = tail;
+                Arg::Str(text)
+            }
+            'f' => {
+                let (word, tail) = osc_word(rest)?;
+                rest = tail;
+                Arg::Float(f32::from_be_bytes(word))
+            }
+            'i' | 'c' | 'r' | 'm' => {
+                rest = rest.get(4..)?;
+                Arg::Other
+            }
+            'd' | 'h' | 't' => {
+                rest = rest.get(8..)?;
+                Arg::Other
+            }
+            'T' | 'F' | 'N' | 'I' => Arg::Other,
+            _ => return None,
+        };
+        args.push(arg);
+    }
+    f(address, &args);
+    Some(())
+}
+
+/// Receives VMC blendshapes on `config.listen` from a thread of its own.
+pub fn listen(config: &VmcConfig) -> Result<Tracking> {
+    let socket =
+        UdpSocket::bind(&config.listen).with_context(|| t!("vmc.listen_failed", config.listen))?;
+    socket.set_read_timeout(Some(STALE))?;
+    tracing::info!("{}", t!("vmc.listening", config.listen));
+    let latest = Arc::new(Mutex::new(None));
+    let shared = latest.clone();
+    let config = config.clone();
+    std::thread::spawn(move || {
+        let matches =
+            |names: &[String], name: &str| names.iter().any(|n| n.eq_ignore_ascii_case(name));
+        // Apply までに届いた値
+        let mut pending: Option<Tracked> = None;
+        let mut connected = false;
+        let mut buffer = vec![0u8; 65536];
+        loop {
+            let length = match socket.recv(&mut buffer) {
+                Ok(length) => length,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    if connected {
+                        tracing::info!("{}", t!("vmc.lost"));
+                        connected = false;
+                    }
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!("{}", t!("vmc.receive_failed", e));
+                    std::thread::sleep(STALE);
+                    continue;
+                }
+            };
+            parse_packet(
+                &buffer[..length],
+                &mut |address, args| match (address, args) {
+                    (BLEND_VALUE, [Arg::Str(name), Arg::Float(value)]) => {
+                        let tracked = pending.get_or_insert(Tracked {
+                            mouth_open: 0.0,
+                            blink: 0.0,
+                        });
+                        let value = value.clamp(0.0, 1.0);
+                        if matches(&config.mouth_open, name) {
+                            tracked.mouth_open = tracked.mouth_open.max(value);
+                        }
+                        if matches(&config.blink, name) {
+                            tracked.blink = tracked.blink.max(value);
+                        }
+                    }
+                    (BLEND_APPLY, _) => {
+                        if let Some(tracked) = pending.take() {
+                            if !connected {
+                                tracing::info!("{}", t!("vmc.connected"));
+                                connected = true;
+                            }
+                            *shared.lock().unwrap_or_else(|e| e.into_inner()) =
+                                Some((tracked, Instant::now()));
+                        }
+                    }
+                    _ => {}
+                },
+            );
+        }
+    });
+    Ok(Tracking { latest })
+}
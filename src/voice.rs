@@ -0,0 +1,148 @@
+// 混ざった音声（ループバック）から、マイクと同じ動きをしている部分だけを自分の声とみなす
+use crate::{config::VoiceReferenceConfig, reference::Reference, resample::rms};
+use std::collections::VecDeque;
+
+// 音量の包絡を取る単位（1秒あたり）
+const HOPS_PER_SECOND: u32 = 100;
+// 相関を見る長さ（300ms）と、2つの入力の遅れとして許す範囲（±80ms）
+const WINDOW: usize = 30;
+const MAX_LAG: usize = 8;
+
+// 一定間隔ごとの RMS の列
+struct Envelope {
+    hop: usize,
+    pending: Vec<f32>,
+    values: VecDeque<f32>,
+}
+
+impl Envelope {
+    fn new(hop: usize) -> Self {
+        Self {
+            hop,
+            pending: Vec::new(),
+            values: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, samples: &[f32]) {
+        self.pending.extend_from_slice(samples);
+        let mut start = 0;
+        while self.pending.len() - start >= self.hop {
+            self.values
+                .push_back(rms(&self.pending[start..start + self.hop]));
+            start += self.hop;
+        }
+        self.pending.drain(..start);
+        while self.values.len() > WINDOW + MAX_LAG {
+            self.values.pop_front();
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.values.len() == WINDOW + MAX_LAG
+    }
+}
+
+/// Decides whether the mixed capture currently contains the streamer's own voice by comparing
+/// its loudness envelope with the reference microphone.
+pub struct VoiceGate {
+    config: VoiceReferenceConfig,
+    reference: Reference,
+    samples: Vec<f32>,
+    mix: Envelope,
+    mic: Envelope,
+}
+
+impl VoiceGate {
+    /// `rate` is the analysis rate of both the mix and the reference.
+    pub fn new(config: &VoiceReferenceConfig, reference: Reference, rate: u32) -> Self {
+        let hop = (rate / HOPS_PER_SECOND).max(1) as usize;
+        Self {
+            config: config.clone(),
+            reference,
+            samples: Vec::new(),
+            mix: Envelope::new(hop),
+            mic: Envelope::new(hop),
+        }
+    }
+
+    /// Takes the mix at the analysis rate and returns whether it carries the streamer's voice.
+    pub fn update(&mut self, mix: &[f32]) -> bool {
+        self.samples.clear();
+        self.reference.read(&mut self.samples);
+        self.mic.push(&self.samples);
+        self.mix.push(mix);
+        if !self.mix.is_full() || !self.mic.is_full() {
+            return false;
+        }
+
+        // マイクが黙っていれば、混ざった音声がいくら大きくてもゲームや音楽の音
+        let mic = self.mic.values.make_contiguous();
+        if rms(&mic[MAX_LAG..]) < self.config.threshold {
+            return false;
+        }
+        let mix = self.mix.values.make_contiguous();
+        // 経路による遅れを吸収するため、ずらしながら一番よく合う相関を使う
+        let correlation = (0..=MAX_LAG * 2)
+            .map(|shift| {
+                let (a, b) = if shift <= MAX_LAG {
+                    (&mix[MAX_LAG..], &mic[MAX_LAG - shift..][..WINDOW])
+                } else {
+                    (&mix[shift - MAX_LAG..][..WINDOW], &mic[MAX_LAG..])
+                };
+                pearson(a, b)
+            })
+            .fold(0.0f32, f32::max);
+        correlation >= self.config.correlation
+    }
+}
+
+fn pearson(a: &[f32], b: &[f32]) -> f32 {
+    let n = a.len() as f32;
+    let (mean_a, mean_b) = (a.iter().sum::<f32>() / n, b.iter().sum::<f32>() / n);
+    let mut covariance = 0.0;
+    let mut variance_a = 0.0;
+    let mut variance_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        let (x, y) = (x - mean_a, y - mean_b);
+        covariance += x * y;
+        variance_a += x * x;
+        variance_b += y * y;
+    }
+    let denominator = (variance_a * variance_b).sqrt();
+    if denominator > f32::EPSILON {
+        covariance / denominator
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        // 包絡の値は入力のピークを超えず、ブロックの分け方によらず同じ数だけ作られる
+        #[test]
+        fn envelope_stays_within_the_peak(
+            hop in 1usize..=480,
+            blocks in prop::collection::vec(prop::collection::vec(-1.0f32..=1.0, 0..1024), 0..16),
+        ) {
+            let mut envelope = Envelope::new(hop);
+            let mut peak = 0.0f32;
+            let mut total = 0;
+            for block in &blocks {
+                peak = block.iter().fold(peak, |peak, s| peak.max(s.abs()));
+                total += block.len();
+                envelope.push(block);
+            }
+            prop_assert_eq!(envelope.values.len(), (total / hop).min(WINDOW + MAX_LAG));
+            prop_assert_eq!(envelope.pending.len(), total % hop);
+            for value in &envelope.values {
+                prop_assert!(value.is_finite() && *value >= 0.0);
+                prop_assert!(*value <= peak * (1.0 + 1e-5), "{value} > {peak}");
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{paths, session, t};
+use anyhow::{Result, bail};
+use std::{
+    process::Command,
+    time::{Duration, Instant},
+};
+
+// この時間内に MAX_RESTARTS 回落ちたら諦める
+const CRASH_WINDOW: Duration = Duration::from_secs(60);
+const MAX_RESTARTS: usize = 5;
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Runs Darwin as a child process and restarts it whenever it exits abnormally.
+pub fn supervise() -> Result<()> {
+    let exe = std::env::current_exe()?;
+    let args: Vec<_> = std::env::args_os()
+        .skip(1)
+        .filter(|a| a != "--watchdog")
+        .collect();
+    let state_dir = paths::state_dir();
+    std::fs::create_dir_all(&state_dir)?;
+    let state_file = state_dir.join(format!("darwin-{}.state.toml", std::process::id()));
+
+    let mut crashes: Vec<Instant> = Vec::new();
+    let result = loop {
+        tracing::info!("{}", t!("watchdog.starting"));
+        let status = Command::new(&exe)
+            .args(&args)
+            .env(session::STATE_FILE_ENV, &state_file)
+            .status()?;
+
+        if status.success() {
+            break Ok(());
+        }
+
+        tracing::warn!(status = %status, "{}", t!("watchdog.crashed", status));
+
+        let now = Instant::now();
+        crashes.retain(|t| now.duration_since(*t) < CRASH_WINDOW);
+        crashes.push(now);
+        if crashes.len() > MAX_RESTARTS {
+            break Err(t!(
+                "watchdog.giving_up",
+                MAX_RESTARTS,
+                CRASH_WINDOW.as_secs()
+            ));
+        }
+
+        std::thread::sleep(RESTART_DELAY);
+    };
+
+    let _ = std::fs::remove_file(&state_file);
+    match result {
+        Ok(()) => Ok(()),
+        Err(message) => bail!(message),
+    }
+}
+
+// 監視下ではどのスレッドのパニックでもプロセスごと落として再起動させる
+pub fn install_panic_exit() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        std::process::exit(101);
+    }));
+}
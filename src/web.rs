@@ -0,0 +1,302 @@
+// 描画したフレームをブラウザへ流す HTTP サーバー（OBS のブラウザソースや LAN 内の別の端末で見る）
+//   /         フレームを画面いっぱいに表示するページ（背景は透明）
+//   /stream   multipart/x-mixed-replace で次々に送る画像（PNG、または MJPEG）。
+//             各画像に X-Timestamp（合成した時刻）と X-Audio-Timestamp（音声の位置）をミリ秒で付ける
+//   /frame    最新の1枚
+use crate::{
+    compose,
+    config::StreamFormat,
+    dirty::Rect,
+    http::{self, Request},
+    sink::{FrameSink, Timestamp},
+    t,
+};
+use anyhow::{Context, Result, bail};
+use image::{
+    ExtendedColorType, ImageEncoder,
+    codecs::{
+        jpeg::JpegEncoder,
+        png::{CompressionType, FilterType, PngEncoder},
+    },
+};
+use std::{
+    io::{ErrorKind, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        Arc, Condvar, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+// 止めるときに待たせないよう、この間隔で止める合図を見る
+const POLL: Duration = Duration::from_millis(200);
+// リクエストを送ってこない接続を切るまで
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+// 同時に相手をする接続の数（見ている端末ごとに1つ張りっぱなしになる）
+const MAX_CONNECTIONS: usize = 16;
+
+const PAGE: &str = r#"<!doctype html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Darwin</title>
+<style>
+html, body { margin: 0; height: 100%; background: transparent; overflow: hidden; }
+img { width: 100%; height: 100%; object-fit: contain; }
+</style>
+</head>
+<body><img src="/stream" alt=""></body>
+</html>
+"#;
+
+#[derive(Clone, Default)]
+struct Encoded {
+    sequence: u64,
+    image: Arc<Vec<u8>>,
+    timestamp: Timestamp,
+}
+
+struct Shared {
+    // 最新のフレーム（乗算済み RGBA）と、前回のエンコードから変わったか
+    latest: Mutex<(Vec<u8>, bool, Timestamp)>,
+    // エンコード済みの画像と通し番号（0 はまだない）
+    encoded: Mutex<Encoded>,
+    ready: Condvar,
+    stop: AtomicBool,
+    content_type: &'static str,
+}
+
+impl Shared {
+    // 通し番号が `after` より新しい画像を待つ。止めるときは None
+    fn next(&self, after: u64) -> Option<Encoded> {
+        let mut encoded = self.encoded.lock().unwrap();
+        while encoded.sequence <= after {
+            if self.stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            encoded = self.ready.wait_timeout(encoded, POLL).unwrap().0;
+        }
+        Some(encoded.clone())
+    }
+}
+
+/// Serves the frames over HTTP. Frames are encoded at most `fps` times a second on a
+/// thread of their own and only when they changed; every viewer gets the same images.
+pub struct WebStream {
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl WebStream {
+    pub fn start(
+        bind: &str,
+        fps: u32,
+        format: StreamFormat,
+        quality: u8,
+        width: u32,
+        height: u32,
+    ) -> Result<Self> {
+        if fps == 0 {
+            bail!(t!("offline.bad_fps"));
+        }
+        let listener = TcpListener::bind(bind).with_context(|| t!("web.bind_failed", bind))?;
+        listener.set_nonblocking(true)?;
+        let shared = Arc::new(Shared {
+            latest: Mutex::new((
+                vec![0u8; width as usize * height as usize * 4],
+                true,
+                Timestamp::default(),
+            )),
+            encoded: Mutex::default(),
+            ready: Condvar::new(),
+            stop: AtomicBool::new(false),
+            content_type: match format {
+                StreamFormat::Png => "image/png",
+                StreamFormat::Jpeg => "image/jpeg",
+            },
+        });
+        tracing::info!("{}", t!("web.started", listener.local_addr()?));
+
+        let encoder = {
+            let shared = shared.clone();
+            std::thread::spawn(move || {
+                let interval = Duration::from_secs_f64(1.0 / fps as f64);
+                let mut pixels = Vec::new();
+                while !shared.stop.load(Ordering::Relaxed) {
+                    let started = Instant::now();
+                    let (changed, timestamp) = {
+                        let mut latest = shared.latest.lock().unwrap();
+                        if latest.1 {
+                            pixels.clone_from(&latest.0);
+                        }
+                        (std::mem::take(&mut latest.1), latest.2)
+                    };
+                    if changed {
+                        match encode(&mut pixels, format, quality, width, height) {
+                            Ok(image) => {
+                                let mut encoded = shared.encoded.lock().unwrap();
+                                *encoded = Encoded {
+                                    sequence: encoded.sequence + 1,
+                                    image: Arc::new(image),
+                                    timestamp,
+                                };
+                                shared.ready.notify_all();
+                            }
+                            Err(e) => tracing::warn!("{}", t!("web.encode_failed", e)),
+                        }
+                    }
+                    std::thread::sleep(interval.saturating_sub(started.elapsed()));
+                }
+            })
+        };
+        let server = {
+            let shared = shared.clone();
+            let connections = http::Connections::new(MAX_CONNECTIONS);
+            std::thread::spawn(move || {
+                while !shared.stop.load(Ordering::Relaxed) {
+                    match listener.accept() {
+                        Ok((stream, _)) => {
+                            // 埋まっていれば何も返さずに閉じる
+                            let Some(connection) = connections.enter() else {
+                                tracing::debug!("http: too many connections");
+                                continue;
+                            };
+                            let shared = shared.clone();
+                            // 見ている間ずっと送り続けるので、接続ごとにスレッドを分ける
+                            std::thread::spawn(move || {
+                                let _connection = connection;
+                                if let Err(e) = serve(stream, &shared) {
+                                    tracing::debug!("http: {e}");
+                                }
+                            });
+                        }
+                        Err(e) if e.kind() == ErrorKind::WouldBlock => std::thread::sleep(POLL),
+                        Err(e) => {
+                            tracing::debug!("http: {e}");
+                            std::thread::sleep(POLL);
+                        }
+                    }
+                }
+            })
+        };
+        Ok(Self {
+            shared,
+            threads: vec![encoder, server],
+        })
+    }
+}
+
+// JPEG は透過できないので黒の上に重ねた色（乗算済みの RGB そのもの）にする
+fn encode(
+    pixels: &mut [u8],
+    format: StreamFormat,
+    quality: u8,
+    width: u32,
+    height: u32,
+) -> image::ImageResult<Vec<u8>> {
+    let mut out = Vec::new();
+    match format {
+        StreamFormat::Png => {
+            compose::unpremultiply(pixels);
+            PngEncoder::new_with_quality(&mut out, CompressionType::Fast, FilterType::Sub)
+                .write_image(pixels, width, height, ExtendedColorType::Rgba8)?;
+        }
+        StreamFormat::Jpeg => {
+            let rgb: Vec<u8> = pixels
+                .chunks_exact(4)
+                .flat_map(|p| [p[0], p[1], p[2]])
+                .collect();
+            JpegEncoder::new_with_quality(&mut out, quality.clamp(1, 100)).write_image(
+                &rgb,
+                width,
+                height,
+                ExtendedColorType::Rgb8,
+            )?;
+        }
+    }
+    Ok(out)
+}
+
+fn serve(mut stream: TcpStream, shared: &Shared) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let request = Request::read(&mut stream)?;
+
+    match request.path.as_str() {
+        "/" => http::respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            PAGE.as_bytes(),
+        ),
+        "/frame" => match shared.next(0) {
+            Some(encoded) => {
+                http::respond(&mut stream, "200 OK", shared.content_type, &encoded.image)
+            }
+            None => Ok(()),
+        },
+        "/stream" => {
+            stream.write_all(
+                b"HTTP/1.1 200 OK\r\n\
+                  Content-Type: multipart/x-mixed-replace; boundary=frame\r\n\
+                  Cache-Control: no-cache\r\n\
+                  Access-Control-Allow-Origin: *\r\n\
+                  Connection: close\r\n\r\n",
+            )?;
+            let mut sequence = 0;
+            while let Some(encoded) = shared.next(sequence) {
+                sequence = encoded.sequence;
+                let Timestamp { time, audio } = encoded.timestamp;
+                write!(
+                    stream,
+                    "--frame\r\nContent-Type: {}\r\nContent-Length: {}\r\nX-Timestamp: {:.3}\r\n",
+                    shared.content_type,
+                    encoded.image.len(),
+                    time.as_secs_f64() * 1000.0
+                )?;
+                if let Some(audio) = audio {
+                    write!(
+                        stream,
+                        "X-Audio-Timestamp: {:.3}\r\n",
+                        audio.as_secs_f64() * 1000.0
+                    )?;
+                }
+                stream.write_all(b"\r\n")?;
+                stream.write_all(&encoded.image)?;
+                stream.write_all(b"\r\n")?;
+                stream.flush()?;
+            }
+            Ok(())
+        }
+        _ => http::respond(
+            &mut stream,
+            "404 Not Found",
+            "text/plain; charset=utf-8",
+            b"not found",
+        ),
+    }
+}
+
+impl FrameSink for WebStream {
+    fn present(&mut self, frame: &[u8], _region: Rect, timestamp: Timestamp) {
+        let mut latest = self.shared.latest.lock().unwrap();
+        if latest.0.len() == frame.len() {
+            latest.0.copy_from_slice(frame);
+            latest.1 = true;
+            latest.2 = timestamp;
+        }
+    }
+}
+
+impl Drop for WebStream {
+    fn drop(&mut self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        self.shared.ready.notify_all();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}